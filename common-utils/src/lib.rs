@@ -1,4 +1,6 @@
 use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
 
 use log::trace;
 use thiserror::Error;
@@ -15,6 +17,53 @@ macro_rules! set {
     };
 }
 
+/// `btreeset!` macro works like `set!`, but generates a BTreeSet so the iteration order is deterministic.
+///
+/// ```
+/// use std::collections::BTreeSet;
+/// use common_utils::btreeset;
+///
+/// let s: BTreeSet<i32> = btreeset![3, 1, 2, 1];
+/// assert_eq!(s.into_iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+///
+/// let empty: BTreeSet<String> = btreeset![];
+/// assert!(empty.is_empty());
+/// ```
+#[macro_export]
+macro_rules! btreeset {
+    ( $( $x:expr ),* $(,)? ) => {
+        {
+            let mut temp_set = ::std::collections::BTreeSet::new();
+            $(temp_set.insert($x);)*
+            temp_set
+        }
+    };
+}
+
+/// `map!` macro works like `set!`, but generates a HashMap from `key => value` pairs.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use common_utils::map;
+///
+/// let m: HashMap<&str, i32> = map! { "a" => 1, "b" => 2, };
+/// let expected: HashMap<&str, i32> = [("a", 1), ("b", 2)].into_iter().collect();
+/// assert_eq!(m, expected);
+///
+/// let empty: HashMap<String, String> = map! {};
+/// assert!(empty.is_empty());
+/// ```
+#[macro_export]
+macro_rules! map {
+    ( $( $k:expr => $v:expr ),* $(,)? ) => {
+        {
+            let mut temp_map = ::std::collections::HashMap::new();
+            $(temp_map.insert($k, $v);)*
+            temp_map
+        }
+    };
+}
+
 #[derive(Error, Debug)]
 #[error("{0}")]
 pub struct StringError(String);
@@ -45,6 +94,32 @@ where
     }
 }
 
+/**
+ * Call `f` until it succeeds or `attempts` runs out, the delay between attempts doubles each time.
+ * The last error is returned if all attempts failed.
+ */
+pub async fn retry<F, Fut, T, E>(attempts: usize, base_delay: Duration, mut f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Debug,
+{
+    let mut delay = base_delay;
+    let mut attempt = 1;
+    loop {
+        match f().await.log() {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(_) => {
+                trace!("Attempt {} failed, retrying in {:?}", attempt, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                delay *= 2;
+            }
+        }
+    }
+}
+
 pub trait Appliable
 where
     Self: Sized,
@@ -109,6 +184,12 @@ impl Blank for Option<String> {
     }
 }
 
+impl Blank for str {
+    fn is_blank(&self) -> bool {
+        self.trim().is_empty()
+    }
+}
+
 static LOGGER: std::sync::Once = std::sync::Once::new();
 
 pub fn init_logger() {
@@ -140,3 +221,39 @@ pub fn init_logger() {
         tracing_subscriber::fmt::init();
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::retry;
+
+    #[tokio::test]
+    async fn retry_until_success() {
+        let counter = AtomicUsize::new(0);
+        let ret: Result<usize, String> = retry(5, Duration::from_millis(1), || async {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            if n < 3 {
+                Err(format!("failed {}", n))
+            } else {
+                Ok(n)
+            }
+        })
+        .await;
+        assert_eq!(ret, Ok(3));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_always_fails() {
+        let counter = AtomicUsize::new(0);
+        let ret: Result<(), String> = retry(3, Duration::from_millis(1), || async {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            Err(format!("failed {}", n))
+        })
+        .await;
+        assert_eq!(ret, Err("failed 3".to_string()));
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+}
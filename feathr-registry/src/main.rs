@@ -4,25 +4,30 @@ use std::{
     path::PathBuf,
     pin::Pin,
     process::exit,
+    time::Duration,
     vec,
 };
 
 use clap::Parser;
-use common_utils::Logged;
+use common_utils::{Logged, StringError};
 use futures::{future::join_all, Future};
 use log::{debug, info};
 use poem::{
+    error::NotFoundError,
+    http::{Method, StatusCode},
     listener::TcpListener,
     middleware::{Cors, Tracing},
     web::Json,
-    EndpointExt, Route, Server,
+    Endpoint, EndpointExt, IntoResponse, Route, Server,
 };
 use poem_openapi::OpenApiService;
 use raft_registry::{
-    management_routes, raft_routes, FeathrApiV1, FeathrApiV2, NodeConfig, RaftRegistryApp,
-    RaftSequencer, RbacMiddleware,
+    grpc_service, management_routes, raft_routes, FeathrApiV1, FeathrApiV2, NodeConfig,
+    RaftRegistryApp, RaftSequencer, RbacMiddleware, RequestIdMiddleware, REQUEST_ID_HEADER_NAME,
 };
+use registry_api::ErrorResponse;
 use sql_provider::attach_storage;
+use tokio::sync::watch;
 
 mod spa_endpoint;
 
@@ -41,6 +46,18 @@ pub struct Opt {
     #[clap(long, env = "EXT_SERVER_ADDR")]
     pub ext_http_addr: Option<String>,
 
+    /// Additional addresses the node can be reached at, listed after `ext_http_addr` as servers in the API spec
+    #[clap(
+        long = "extra-server",
+        env = "EXTRA_SERVERS",
+        use_value_delimiter = true
+    )]
+    pub extra_servers: Vec<String>,
+
+    /// gRPC Server Listening Address, gRPC service is disabled if not set
+    #[clap(long, env = "GRPC_ADDR")]
+    pub grpc_addr: Option<String>,
+
     /// Base Path of the API
     #[clap(long, env = "API_BASE", default_value = "/api")]
     pub api_base: String,
@@ -65,6 +82,14 @@ pub struct Opt {
     #[clap(long)]
     pub no_init: bool,
 
+    /// Seconds to wait for in-flight requests to finish on shutdown
+    #[clap(long, env = "SHUTDOWN_TIMEOUT", default_value = "30")]
+    pub shutdown_timeout: u64,
+
+    /// Origins allowed to make cross-origin requests, all origins are allowed if none is set
+    #[clap(long = "cors-origin", env = "CORS_ORIGINS", use_value_delimiter = true)]
+    pub cors_origins: Vec<String>,
+
     #[clap(flatten)]
     pub node_config: NodeConfig,
 }
@@ -108,6 +133,135 @@ fn cleanup_logs(options: &Opt, node_id: u64) -> anyhow::Result<()> {
     Ok(())
 }
 
+/**
+ * Normalize the base path into `/a/b` form, duplicated and trailing slashes are removed
+ * Empty string is returned if the base path is the root
+ */
+fn normalize_api_base(api_base: &str) -> String {
+    api_base
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| format!("/{}", s))
+        .collect()
+}
+
+/**
+ * The external URL of the API, `ext_http_addr` may or may not come with the scheme, `http` is used if it doesn't
+ */
+fn server_url(ext_http_addr: &str, api_base: &str, version: &str) -> String {
+    let (scheme, host) = match ext_http_addr.strip_prefix("https://") {
+        Some(host) => ("https", host),
+        None => (
+            "http",
+            ext_http_addr.trim_start_matches("http://"),  // Devskim: ignore DS137138
+        ),
+    };
+    format!(
+        "{}://{}{}/{}",
+        scheme,
+        host.trim_end_matches('/'),
+        api_base,
+        version
+    )
+}
+
+/**
+ * API services with `ext_http_addr` as the first server, followed by `extra_servers` in the given order
+ */
+fn api_services(
+    ext_http_addr: &str,
+    extra_servers: &[String],
+    api_base: &str,
+) -> (OpenApiService<FeathrApiV1, ()>, OpenApiService<FeathrApiV2, ()>) {
+    let addrs = || std::iter::once(ext_http_addr).chain(extra_servers.iter().map(|s| s.as_str()));
+    let api_service_v1 = addrs().fold(
+        OpenApiService::new(
+            FeathrApiV1,
+            "Feathr Registry API Version 1",
+            option_env!("CARGO_PKG_VERSION").unwrap_or("<unknown>"),
+        ),
+        |service, addr| service.server(server_url(addr, api_base, "v1")),
+    );
+    let api_service_v2 = addrs().fold(
+        OpenApiService::new(
+            FeathrApiV2,
+            "Feathr Registry API Version 2",
+            option_env!("CARGO_PKG_VERSION").unwrap_or("<unknown>"),
+        ),
+        |service, addr| service.server(server_url(addr, api_base, "v2")),
+    );
+    (api_service_v1, api_service_v2)
+}
+
+/**
+ * Unmatched paths under the API base get a JSON 404 instead of falling through to the SPA
+ */
+fn api_not_found<E: Endpoint>(api_route: E) -> impl Endpoint {
+    api_route.catch_error(|e: NotFoundError| async move {
+        Json(ErrorResponse {
+            code: "NOT_FOUND".to_string(),
+            message: e.to_string(),
+        })
+        .with_status(StatusCode::NOT_FOUND)
+    })
+}
+
+/**
+ * CORS middleware only allowing requests from `origins`, fully permissive if `origins` is empty
+ */
+fn cors(origins: &[String]) -> Cors {
+    if origins.is_empty() {
+        Err::<(), _>(StringError::new(
+            "No CORS origin is configured, cross-origin requests from any origin are allowed",
+        ))
+        .log()
+        .ok();
+        return Cors::new();
+    }
+    Cors::new()
+        .allow_origins(origins.iter().map(|o| o.trim_end_matches('/')))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            "authorization",
+            "content-type",
+            "if-match",
+            "x-registry-opt-seq",
+            REQUEST_ID_HEADER_NAME,
+        ])
+        .allow_credentials(true)
+}
+
+/**
+ * Resolves on SIGINT, or SIGTERM on Unix
+ */
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.log().ok();
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut s) => {
+                s.recv().await;
+            }
+            Err(_) => futures::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = futures::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     common_utils::init_logger();
@@ -120,7 +274,19 @@ async fn main() -> Result<(), anyhow::Error> {
         .clone()
         .unwrap_or_else(|| options.http_addr.clone());
 
-    let node_config = options.node_config.clone();
+    if let Err(e) = options.node_config.name_pattern() {
+        println!("ERROR: {}", e);
+        exit(1);
+    }
+
+    if let Err(e) = options.node_config.default_project_tags() {
+        println!("ERROR: {}", e);
+        exit(1);
+    }
+
+    let mut node_config = options.node_config.clone();
+    // Learners don't vote, they only serve reads
+    node_config.read_only |= options.learner;
 
     let app = if options.seeds.is_empty() {
         info!("Starting as cluster leader");
@@ -147,27 +313,11 @@ async fn main() -> Result<(), anyhow::Error> {
         .await
     };
 
-    let api_base = format!("/{}", options.api_base.trim_start_matches('/'));
-    let http_addr = ext_http_addr
-        .trim_start_matches("http://")  // Devskim: ignore DS137138
-        .trim_start_matches("https://")
-        .to_string();
-
-    let api_service_v1 = OpenApiService::new(
-        FeathrApiV1,
-        "Feathr Registry API Version 1",
-        option_env!("CARGO_PKG_VERSION").unwrap_or("<unknown>"),
-    )
-    .server(format!("http://{}{}/v1", http_addr, api_base,));  // Devskim: ignore DS137138
+    let api_base = normalize_api_base(&options.api_base);
+    let (api_service_v1, api_service_v2) =
+        api_services(&ext_http_addr, &options.extra_servers, &api_base);
     let ui_v1 = api_service_v1.swagger_ui();
     let spec_v1 = api_service_v1.spec();
-
-    let api_service_v2 = OpenApiService::new(
-        FeathrApiV2,
-        "Feathr Registry API Version 2",
-        option_env!("CARGO_PKG_VERSION").unwrap_or("<unknown>"),
-    )
-    .server(format!("http://{}{}/v2", http_addr, api_base,));  // Devskim: ignore DS137138
     let ui_v2 = api_service_v2.swagger_ui();
     let spec_v2 = api_service_v2.spec();
 
@@ -176,7 +326,8 @@ async fn main() -> Result<(), anyhow::Error> {
         .nest("/v2", api_service_v2)
         .with(Tracing)
         .with(RaftSequencer::new(app.store.clone()))
-        .with(Cors::new())
+        .with(cors(&options.cors_origins))
+        .with(RequestIdMiddleware)
         .with(RbacMiddleware);
 
     let docs_route = Route::new().nest("/v1", ui_v1).nest("/v2", ui_v2);
@@ -188,7 +339,10 @@ async fn main() -> Result<(), anyhow::Error> {
     let route = management_routes(raft_routes(Route::new()))
         .nest("spec", spec_route)
         .nest("docs", docs_route)
-        .nest(api_base, api_route)
+        .nest(
+            if api_base.is_empty() { "/" } else { api_base.as_str() },
+            api_not_found(api_route),
+        )
         .nest(
             "version",
             poem::endpoint::make_sync(move |_| {
@@ -207,21 +361,57 @@ async fn main() -> Result<(), anyhow::Error> {
         )
         .data(app.clone());
 
+    let drain_timeout = Duration::from_secs(options.shutdown_timeout);
+    // Servers stop accepting new connections once the value turns to true
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let wait_shutdown = |mut rx: watch::Receiver<bool>| async move {
+        while !*rx.borrow() {
+            if rx.changed().await.is_err() {
+                break;
+            }
+        }
+    };
+
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Received shutdown signal");
+        shutdown_tx.send(true).ok();
+    });
     let svc_task = async {
         Server::new(TcpListener::bind(
             options.http_addr.trim_start_matches("http://"),  // Devskim: ignore DS137138
         ))
-        .run(route)
+        .run_with_graceful_shutdown(
+            route,
+            wait_shutdown(shutdown_rx.clone()),
+            Some(drain_timeout),
+        )
         .await
         .log()
         .map_err(anyhow::Error::from)
     };
+    let grpc_task = async {
+        if let Some(addr) = &options.grpc_addr {
+            info!("Starting gRPC service at {}", addr);
+            tonic::transport::Server::builder()
+                .add_service(grpc_service(app.clone()))
+                .serve_with_shutdown(addr.parse()?, wait_shutdown(shutdown_rx.clone()))
+                .await
+                .log()?;
+        }
+        Ok(())
+    };
     let raft_task = async {
         if !options.seeds.is_empty() {
-            debug!("Joining cluster");
-            app.join_or_init(&options.seeds, !options.no_init)
-                .await
-                .log()?
+            if options.learner {
+                debug!("Joining cluster as learner");
+                app.join_cluster(&options.seeds, false).await.log()?
+            } else {
+                debug!("Joining cluster");
+                app.join_or_init(&options.seeds, !options.no_init)
+                    .await
+                    .log()?
+            }
         }
 
         if options.load_db {
@@ -235,10 +425,154 @@ async fn main() -> Result<(), anyhow::Error> {
         Ok(())
     };
     let tasks: Vec<Pin<Box<dyn Future<Output = anyhow::Result<()>>>>> =
-        vec![Box::pin(svc_task), Box::pin(raft_task)];
-    join_all(tasks.into_iter())
+        vec![Box::pin(svc_task), Box::pin(grpc_task), Box::pin(raft_task)];
+    let ret = join_all(tasks.into_iter())
         .await
         .into_iter()
-        .collect::<anyhow::Result<Vec<_>>>()?;
+        .collect::<anyhow::Result<Vec<_>>>();
+    // Servers have stopped, drain requests still being processed and flush before exiting
+    app.shutdown(drain_timeout).await.log()?;
+    ret?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use poem::test::TestClient;
+
+    use super::*;
+
+    #[test]
+    fn nested_api_base() {
+        for base in ["/registry/api", "registry/api/", "//registry//api"] {
+            assert_eq!(normalize_api_base(base), "/registry/api");
+        }
+        assert_eq!(normalize_api_base("/"), "");
+
+        let options = Opt::parse_from([
+            "test",
+            "--api-base",
+            "/registry/api",
+            "--ext-http-addr",
+            "https://example.com/",
+        ]);
+        let (_, api_service_v2) = api_services(
+            options.ext_http_addr.as_deref().unwrap(),
+            &options.extra_servers,
+            &normalize_api_base(&options.api_base),
+        );
+        let spec: serde_json::Value = serde_json::from_str(&api_service_v2.spec()).unwrap();
+        assert_eq!(
+            spec["servers"][0]["url"],
+            "https://example.com/registry/api/v2"
+        );
+    }
+
+    #[test]
+    fn extra_servers() {
+        let options = Opt::parse_from([
+            "test",
+            "--ext-http-addr",
+            "https://example.com",
+            "--extra-server",
+            "registry.internal:8000",
+            "--extra-server",
+            "https://registry.example.org",
+        ]);
+        let (_, api_service_v2) = api_services(
+            options.ext_http_addr.as_deref().unwrap(),
+            &options.extra_servers,
+            &normalize_api_base(&options.api_base),
+        );
+        let spec: serde_json::Value = serde_json::from_str(&api_service_v2.spec()).unwrap();
+        let urls: Vec<&str> = spec["servers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["url"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/api/v2",
+                "http://registry.internal:8000/api/v2",
+                "https://registry.example.org/api/v2",
+            ]
+        );
+    }
+
+    #[test]
+    fn v1_operation_ids() {
+        let (api_service_v1, _) = api_services("localhost:8000", &[], "/api");
+        let spec: serde_json::Value = serde_json::from_str(&api_service_v1.spec()).unwrap();
+        let mut ids = std::collections::HashSet::new();
+        for (path, operations) in spec["paths"].as_object().unwrap() {
+            for (method, operation) in operations.as_object().unwrap() {
+                let id = operation["operationId"].as_str().unwrap_or_default();
+                assert!(!id.is_empty(), "{} {} has no operationId", method, path);
+                assert!(ids.insert(id.to_string()), "Duplicated operationId {}", id);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn api_not_found_is_json() {
+        let static_files = std::env::temp_dir().join(format!("spa-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&static_files).unwrap();
+        std::fs::write(static_files.join("index.html"), "<html></html>").unwrap();
+
+        let cli = TestClient::new(
+            Route::new()
+                .nest(
+                    "/api",
+                    api_not_found(
+                        Route::new().at("/v2/projects", poem::endpoint::make_sync(|_| "[]")),
+                    ),
+                )
+                .nest(
+                    "/",
+                    spa_endpoint::SpaEndpoint::new(&static_files, "index.html"),
+                ),
+        );
+
+        let resp = cli.get("/api/v2/projects").send().await;
+        resp.assert_status_is_ok();
+
+        let resp = cli.get("/api/nonexistent").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_content_type("application/json; charset=utf-8");
+
+        let resp = cli.get("/somepage").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("<html></html>").await;
+
+        remove_dir_all(static_files).ok();
+    }
+
+    #[tokio::test]
+    async fn cors_allowlist() {
+        let options = Opt::parse_from(["test", "--cors-origin", "https://ui.example.com/"]);
+        let cli = TestClient::new(
+            poem::endpoint::make_sync(|_| "ok").with(cors(&options.cors_origins)),
+        );
+
+        let resp = cli
+            .get("/")
+            .header("Origin", "https://ui.example.com")
+            .send()
+            .await;
+        resp.assert_header("access-control-allow-origin", "https://ui.example.com");
+
+        let resp = cli
+            .get("/")
+            .header("Origin", "https://evil.example.com")
+            .send()
+            .await;
+        assert!(resp
+            .0
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+    }
+}
@@ -1,25 +1,33 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::Arc,
+    time::Duration,
 };
 
-use log::{debug, trace};
+use chrono::Utc;
+use common_utils::{Logged, StringError};
+use log::{debug, info, trace, warn};
 use openraft::{
     error::{CheckIsLeaderError, InitializeError},
     raft::ClientWriteRequest,
-    Config, EntryPayload, Node, Raft,
+    Config, EntryPayload, Node, Raft, State,
 };
 use poem::error::Forbidden;
 use registry_api::{
-    ApiError, FeathrApiProvider, FeathrApiRequest, FeathrApiResponse, IntoApiResult,
+    ApiError, CreationResponse, DefinitionLimits, EntityLineage, FeathrApiProvider,
+    FeathrApiRequest, FeathrApiResponse, IntoApiResult, NamePattern, PageLimits,
 };
-use registry_provider::{Credential, Permission, RbacError, RbacProvider};
-use sql_provider::load_content;
-use tokio::net::ToSocketAddrs;
+use registry_provider::{
+    Attributes, Credential, EdgeType, Entity, EntityProperty, Permission, RbacError, RbacProvider,
+    RegistryProvider, ValueType,
+};
+use sql_provider::{load_content, reattach_storage};
+use tokio::{net::ToSocketAddrs, sync::RwLock};
+use uuid::Uuid;
 
 use crate::{
-    ManagementCode, RegistryClient, RegistryNetwork, RegistryNodeId, RegistryRaft, RegistryStore,
-    Restore,
+    current_request_id, current_requestor, EntityCache, FeatureUsage, ManagementCode,
+    RegistryClient, RegistryNetwork, RegistryNodeId, RegistryRaft, RegistryStore, Restore,
 };
 
 // Representation of an application state. This struct can be shared around to share
@@ -32,12 +40,35 @@ pub struct RaftRegistryApp {
     pub store: Arc<RegistryStore>,
     pub config: Arc<Config>,
     pub forwarder: RegistryClient,
+    pub limits: DefinitionLimits,
+    pub page_limits: PageLimits,
+    pub name_pattern: Option<NamePattern>,
+    pub read_only: bool,
+    pub opt_seq_wait: Duration,
+    pub project_entity_warn_threshold: usize,
+    pub check_derived_types: bool,
+    pub default_project_tags: HashMap<String, String>,
+    pub feature_usage: Arc<FeatureUsage>,
+    pub entity_cache: Arc<EntityCache>,
+    // Every request holds the read guard while it's being processed, shutdown takes the write guard to drain them
+    shutting_down: Arc<RwLock<bool>>,
 }
 
 impl RaftRegistryApp {
     pub async fn new(node_id: RegistryNodeId, addr: String, cfg: crate::NodeConfig) -> Self {
         // Create a configuration for the raft instance.
         let config = Arc::new(cfg.raft_config.clone());
+        let limits = cfg.definition_limits();
+        let page_limits = cfg.page_limits();
+        // `main` has validated the pattern before starting the node
+        let name_pattern = cfg.name_pattern().expect("Invalid name pattern");
+        let read_only = cfg.read_only;
+        let opt_seq_wait = cfg.opt_seq_wait();
+        let project_entity_warn_threshold = cfg.project_entity_warn_threshold;
+        let check_derived_types = cfg.check_derived_types;
+        let default_project_tags = cfg
+            .default_project_tags()
+            .expect("Invalid default project tags");
 
         // Create a instance of where the Raft data will be stored.
         let es = RegistryStore::open_create(node_id, cfg.clone());
@@ -55,6 +86,8 @@ impl RaftRegistryApp {
         // Create a local raft instance.
         let raft = Raft::new(node_id, config.clone(), network, store.clone());
 
+        let entity_cache = store.entity_cache.clone();
+
         let forwarder = RegistryClient::new(node_id, addr.clone(), store.get_management_code());
 
         // Create an application that will store all the instances created above, this will
@@ -66,6 +99,17 @@ impl RaftRegistryApp {
             store,
             config,
             forwarder,
+            limits,
+            page_limits,
+            name_pattern,
+            read_only,
+            opt_seq_wait,
+            project_entity_warn_threshold,
+            check_derived_types,
+            default_project_tags,
+            feature_usage: Default::default(),
+            entity_cache,
+            shutting_down: Default::default(),
         }
     }
 
@@ -147,7 +191,95 @@ impl RaftRegistryApp {
         }
     }
 
-    pub async fn request(&self, opt_seq: Option<u64>, req: FeathrApiRequest) -> FeathrApiResponse {
+    /**
+     * Start writing through to the configured database, the same as starting with `--write-db`
+     * Returns false if the storage has already been attached
+     */
+    pub async fn attach_storage(&self) -> poem::Result<bool> {
+        let mut sm = self.store.state_machine.write().await;
+        Ok(reattach_storage(&mut sm.registry).await.map_api_error()?)
+    }
+
+    pub async fn request(
+        &self,
+        opt_seq: Option<u64>,
+        mut req: FeathrApiRequest,
+    ) -> FeathrApiResponse {
+        if !req.is_writing_request() {
+            return self.handle_request(opt_seq, req).await;
+        }
+        // Replicas apply the mutation with the same time instead of their own clocks
+        req.stamp_time(Utc::now());
+        let name = req.name();
+        if self.is_read_only() {
+            // Refuse before touching Raft, the caller should go to the leader instead
+            let leader = self
+                .get_leader_addr()
+                .map(|addr| format!(" at {}", addr))
+                .unwrap_or_default();
+            let resp = FeathrApiResponse::Error(ApiError::MethodNotAllowed(format!(
+                "This node is read-only, updating requests must be submitted to the leader{}",
+                leader
+            )));
+            log_mutation(name, &resp);
+            return resp;
+        }
+        let resp = self.handle_request(opt_seq, req).await;
+        log_mutation(name, &resp);
+        resp
+    }
+
+    async fn handle_request(
+        &self,
+        opt_seq: Option<u64>,
+        mut req: FeathrApiRequest,
+    ) -> FeathrApiResponse {
+        let shutting_down = self.shutting_down.read().await;
+        if *shutting_down {
+            return FeathrApiResponse::Error(ApiError::InternalError(
+                "The registry is shutting down".to_string(),
+            ));
+        }
+        if let FeathrApiRequest::GetEntityAtSeq { seq, .. } = &req {
+            // History is replayed from the local log, no need to forward
+            return match self.store.state_machine_at(*seq).await {
+                Ok(state_machine) => state_machine.registry.query(req).await,
+                Err(e) => FeathrApiResponse::Error(e),
+            };
+        }
+        if let FeathrApiRequest::GetEntityHistory { id } = &req {
+            // History is indexed locally as logs are applied, no need to forward
+            if let Some(seq) = opt_seq {
+                if let Err(e) = self.wait_for_seq(seq).await {
+                    return FeathrApiResponse::Error(e);
+                }
+            }
+            return match Uuid::parse_str(id) {
+                Ok(id) => self
+                    .store
+                    .entity_history(id)
+                    .map(FeathrApiResponse::EntityHistory)
+                    .unwrap_or_else(FeathrApiResponse::Error),
+                Err(_) => {
+                    FeathrApiResponse::Error(ApiError::BadRequest(format!("Invalid id '{}'", id)))
+                }
+            };
+        }
+        if let FeathrApiRequest::CreateProject { definition, .. } = &mut req {
+            // Merged before checking limits so the defaults count as well
+            for (k, v) in &self.default_project_tags {
+                definition.tags.entry(k.clone()).or_insert_with(|| v.clone());
+            }
+        }
+        if let Err(e) = req.check_limits(&self.limits) {
+            return FeathrApiResponse::Error(e);
+        }
+        if let Some(pattern) = &self.name_pattern {
+            if let Err(e) = req.check_name_pattern(pattern) {
+                return FeathrApiResponse::Error(e);
+            }
+        }
+        req.apply_page_limits(&self.page_limits);
         let mut is_leader = true;
         let should_forward = match self.raft.is_leader().await {
             Ok(_) => {
@@ -181,6 +313,14 @@ impl RaftRegistryApp {
                 // return FeathrApiResponse::Error(ApiError::InternalError("Raft cluster error".to_string()));
             }
         };
+        if let Some(seq) = opt_seq.filter(|_| !should_forward && !req.is_writing_request()) {
+            // Read-your-writes, the caller has seen the result of the log at `seq`
+            // Lagging followers have forwarded to the leader already, so only the leader itself or
+            // a node without known leader waits here
+            if let Err(e) = self.wait_for_seq(seq).await {
+                return FeathrApiResponse::Error(e);
+            }
+        }
         if should_forward {
             debug!("The request is being forwarded to the leader");
             match self.forwarder.consistent_request(&req).await {
@@ -206,15 +346,228 @@ impl RaftRegistryApp {
                     ))
                 }
             } else {
-                self.store
-                    .state_machine
-                    .write()
-                    .await
-                    .registry
-                    .request(req)
-                    .await
+                let cached_id = match &req {
+                    FeathrApiRequest::GetFeature { id_or_name } => {
+                        Uuid::parse_str(id_or_name).ok()
+                    }
+                    _ => None,
+                };
+                if let Some(entity) = cached_id.and_then(|id| self.entity_cache.get(id)) {
+                    return FeathrApiResponse::Entity(entity);
+                }
+                let is_get_feature = matches!(req, FeathrApiRequest::GetFeature { .. });
+                // Locking discipline: writes only happen in `apply_to_state_machine` under the write guard,
+                // reads only need the shared guard so they don't block each other
+                let state_machine = self.store.state_machine.read().await;
+                let resp = state_machine.registry.query(req).await;
+                if is_get_feature {
+                    if let FeathrApiResponse::Entity(entity) = &resp {
+                        // Still under the read guard, so no mutation to the entity can be applied
+                        // between the query and caching its result
+                        self.entity_cache.put(entity);
+                    }
+                }
+                resp
+            }
+        }
+    }
+
+    /**
+     * Count a read of the feature in the usage of this node, every transport serving features
+     * calls it after a successful read
+     */
+    pub fn record_feature_hit(&self, entity: &registry_api::Entity) {
+        if let Ok(id) = Uuid::parse_str(&entity.guid) {
+            self.feature_usage.hit(id);
+        }
+    }
+
+    /**
+     * Count a read of the feature the lineage is fetched for
+     */
+    pub fn record_lineage_hit(&self, lineage: &EntityLineage, id_or_name: &str) {
+        if let Some(root) = lineage.guid_entity_map.values().find(|e| {
+            e.guid.eq_ignore_ascii_case(id_or_name)
+                || e.qualified_name.eq_ignore_ascii_case(id_or_name)
+        }) {
+            self.record_feature_hit(root);
+        }
+    }
+
+    /**
+     * Apply the creation request and attach the warnings about the created entity, every transport
+     * creating entities goes through here so they all get the same warnings
+     */
+    pub async fn create(
+        &self,
+        opt_seq: Option<u64>,
+        req: FeathrApiRequest,
+    ) -> poem::Result<CreationResponse> {
+        let project = match &req {
+            FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name, ..
+            }
+            | FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name, ..
+            }
+            | FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name, ..
+            }
+            | FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name, ..
+            } => Some(project_id_or_name.clone()),
+            _ => None,
+        };
+        let is_derived = matches!(req, FeathrApiRequest::CreateProjectDerivedFeature { .. });
+        let created = self.request(opt_seq, req).await.into_uuid_and_version()?;
+        let mut resp = CreationResponse::from(created);
+        if let Some(project) = project {
+            if let Err(e) = self.check_project_size(&project).await.log() {
+                resp.warnings.push(e.to_string());
             }
         }
+        if is_derived {
+            resp.warnings.extend(self.derived_type_warnings(created.0).await);
+        }
+        Ok(resp)
+    }
+
+    /**
+     * The project has grown over the threshold, the creation still succeeds but gets a warning
+     */
+    async fn check_project_size(&self, project: &str) -> Result<(), StringError> {
+        if self.project_entity_warn_threshold == 0 {
+            return Ok(());
+        }
+        let count = {
+            let sm = self.store.state_machine.read().await;
+            sm.registry
+                .get_entity_id(project)
+                .and_then(|id| sm.registry.get_neighbors(id, EdgeType::Contains))
+                .map(|entities| entities.len())
+                .unwrap_or_default()
+        };
+        if count <= self.project_entity_warn_threshold {
+            return Ok(());
+        }
+        Err(StringError::new(format!(
+            "Project '{}' contains {} entities, more than the threshold {}, \
+            lineage and search may be slow",
+            project, count, self.project_entity_warn_threshold
+        )))
+    }
+
+    /**
+     * Warnings to return along with the creation if the value type of the derived feature
+     * differs from the ones of its inputs, it's often a bug in the transformation
+     */
+    async fn derived_type_warnings(&self, id: Uuid) -> Vec<String> {
+        if !self.check_derived_types {
+            return vec![];
+        }
+        let sm = self.store.state_machine.read().await;
+        let feature = match sm.registry.get_entity(id) {
+            Ok(e) => e,
+            Err(_) => return vec![],
+        };
+        let val_type = match feature_val_type(&feature) {
+            Some(t) => t,
+            None => return vec![],
+        };
+        let warnings: Vec<String> = sm
+            .registry
+            .get_neighbors(id, EdgeType::DerivesFrom)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|input| {
+                feature_val_type(&input)
+                    .filter(|&t| t != val_type)
+                    .map(|t| {
+                        format!(
+                            "Input '{}' has value type {:?}, the derived feature '{}' has {:?}",
+                            input.qualified_name, t, feature.qualified_name, val_type
+                        )
+                    })
+            })
+            .collect();
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        warnings
+    }
+
+    /**
+     * Wait until the local state machine has applied the log at `seq`, up to `opt_seq_wait`
+     */
+    async fn wait_for_seq(&self, seq: u64) -> Result<(), ApiError> {
+        let mut metrics = self.raft.metrics();
+        let wait = async {
+            loop {
+                let applied = self.store.state_machine.read().await.last_applied_log;
+                if applied.map(|l| l.index >= seq).unwrap_or(false) {
+                    return Ok(());
+                }
+                // Metrics are updated after each apply
+                if metrics.changed().await.is_err() {
+                    return Err(ApiError::ServiceUnavailable(
+                        "The registry is shutting down".to_string(),
+                    ));
+                }
+            }
+        };
+        match tokio::time::timeout(self.opt_seq_wait, wait).await {
+            Ok(ret) => ret,
+            Err(_) => Err(ApiError::ServiceUnavailable(format!(
+                "Log {} has not been applied on this node in {:?}, retry later",
+                seq, self.opt_seq_wait
+            ))),
+        }
+    }
+
+    /**
+     * Read-only nodes and learners only serve reads, learners can't commit anything by themselves
+     */
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.raft.metrics().borrow().state == State::Learner
+    }
+
+    /**
+     * Address of the current leader if it's known
+     */
+    pub fn get_leader_addr(&self) -> Option<String> {
+        let metrics = self.raft.metrics().borrow().clone();
+        metrics
+            .current_leader
+            .and_then(|id| metrics.membership_config.get_node(&id))
+            .map(|node| node.addr.to_owned())
+    }
+
+    /**
+     * Stop taking new requests, wait for in-flight requests to finish, then flush external storages and stop Raft
+     */
+    pub async fn shutdown(&self, drain_timeout: Duration) -> anyhow::Result<()> {
+        info!("Shutting down, waiting for in-flight requests");
+        match tokio::time::timeout(drain_timeout, self.shutting_down.write()).await {
+            Ok(mut shutting_down) => *shutting_down = true,
+            Err(_) => warn!(
+                "In-flight requests didn't finish in {:?}, shutting down anyway",
+                drain_timeout
+            ),
+        }
+        debug!("Flushing external storages");
+        self.store
+            .state_machine
+            .read()
+            .await
+            .registry
+            .flush_external_storage()
+            .await?;
+        self.raft
+            .shutdown()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to shutdown Raft, error: {:?}", e))?;
+        info!("Shutdown completed");
+        Ok(())
     }
 
     pub async fn join_cluster(&self, seeds: &[String], promote: bool) -> anyhow::Result<()> {
@@ -317,6 +670,38 @@ where
     Ok(ret)
 }
 
+/**
+ * Log the outcome of a mutating request along with the request id, so it can be correlated across nodes
+ */
+pub(crate) fn log_mutation(name: &str, resp: &FeathrApiResponse) {
+    let request_id = current_request_id().unwrap_or_default();
+    let requestor = current_requestor().unwrap_or_default();
+    match resp {
+        FeathrApiResponse::Error(e) => tracing::info!(
+            request_id = %request_id,
+            request = name,
+            requestor = %requestor,
+            status = e.code(),
+            "Mutating request failed"
+        ),
+        _ => tracing::info!(
+            request_id = %request_id,
+            request = name,
+            requestor = %requestor,
+            status = "OK",
+            "Mutating request succeeded"
+        ),
+    }
+}
+
+fn feature_val_type(e: &Entity<EntityProperty>) -> Option<ValueType> {
+    match &e.properties.attributes {
+        Attributes::AnchorFeature(attr) => Some(attr.type_.val_type),
+        Attributes::DerivedFeature(attr) => Some(attr.type_.val_type),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::expand_seeds;
@@ -0,0 +1,113 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, PoisonError},
+};
+
+use registry_api::Entity;
+use uuid::Uuid;
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    // Entity id -> (last access tick, serialized entity)
+    entries: HashMap<Uuid, (u64, String)>,
+    // Last access tick -> entity id, the first one is the least recently used
+    order: BTreeMap<u64, Uuid>,
+    tick: u64,
+}
+
+impl LruEntries {
+    fn touch(&mut self, id: Uuid) -> Option<&String> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (last, json) = self.entries.get_mut(&id)?;
+        self.order.remove(last);
+        self.order.insert(tick, id);
+        *last = tick;
+        Some(json)
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        if let Some((last, _)) = self.entries.remove(&id) {
+            self.order.remove(&last);
+        }
+    }
+}
+
+/**
+ * LRU cache of entities served by `GetFeature`, keyed by the entity id
+ * Entries are invalidated when the state machine applies mutations to them, size 0 disables it
+ */
+#[derive(Debug, Default)]
+pub struct EntityCache {
+    capacity: usize,
+    lru: Mutex<LruEntries>,
+}
+
+impl EntityCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Default::default(),
+        }
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<Entity> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut lru = self.lru.lock().unwrap_or_else(PoisonError::into_inner);
+        lru.touch(id).and_then(|json| serde_json::from_str(json).ok())
+    }
+
+    pub fn put(&self, entity: &Entity) {
+        if self.capacity == 0 {
+            return;
+        }
+        let (id, json) = match (
+            Uuid::parse_str(&entity.guid),
+            serde_json::to_string(entity),
+        ) {
+            (Ok(id), Ok(json)) => (id, json),
+            _ => return,
+        };
+        let mut lru = self.lru.lock().unwrap_or_else(PoisonError::into_inner);
+        lru.remove(id);
+        while lru.entries.len() >= self.capacity {
+            match lru.order.iter().next().map(|(_, id)| *id) {
+                Some(evicted) => lru.remove(evicted),
+                None => break,
+            }
+        }
+        lru.tick += 1;
+        let tick = lru.tick;
+        lru.order.insert(tick, id);
+        lru.entries.insert(id, (tick, json));
+    }
+
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.lru
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .contains_key(&id)
+    }
+
+    pub fn invalidate<I>(&self, ids: I)
+    where
+        I: IntoIterator<Item = Uuid>,
+    {
+        let mut lru = self.lru.lock().unwrap_or_else(PoisonError::into_inner);
+        for id in ids {
+            lru.remove(id);
+        }
+    }
+
+    /**
+     * Drop all entries, the state machine has been replaced as a whole
+     */
+    pub fn clear(&self) {
+        let mut lru = self.lru.lock().unwrap_or_else(PoisonError::into_inner);
+        lru.entries.clear();
+        lru.order.clear();
+    }
+}
@@ -23,10 +23,12 @@ use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::current_request_id;
 use crate::FeathrApiRequest;
 use crate::RegistryNodeId;
 use crate::RegistryTypeConfig;
 use crate::MANAGEMENT_CODE_HEADER_NAME;
+use crate::REQUEST_ID_HEADER_NAME;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Empty {}
@@ -169,6 +171,11 @@ impl RegistryClient {
             Some(c) => r.header(MANAGEMENT_CODE_HEADER_NAME, c),
             None => r,
         })
+        // Keep the request id so the leader logs the forwarded request under the same id
+        .apply(|r| match current_request_id() {
+            Some(id) => r.header(REQUEST_ID_HEADER_NAME, id),
+            None => r,
+        })
         .send()
         .await
         .map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
@@ -9,6 +9,8 @@ mod network;
 mod app;
 mod client;
 mod rbac_middleware;
+mod usage;
+mod cache;
 
 pub type RegistryNodeId = u64;
 
@@ -34,3 +36,5 @@ pub use network::*;
 pub use app::*;
 pub use client::RegistryClient;
 pub use rbac_middleware::RbacMiddleware;
+pub use usage::FeatureUsage;
+pub use cache::EntityCache;
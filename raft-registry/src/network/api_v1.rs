@@ -31,7 +31,12 @@ pub struct FeathrApiV1;
 
 #[OpenApi]
 impl FeathrApiV1 {
-    #[oai(path = "/projects", method = "get", tag = "ApiTags::Project")]
+    #[oai(
+        path = "/projects",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "list_projects"
+    )]
     async fn get_projects(
         &self,
         credential: Data<&Credential>,
@@ -44,13 +49,14 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some("global"), Permission::Read)
             .await?;
+        let size = data.0.page_limits.size(limit.0);
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetProjects {
                     keyword: keyword.0,
-                    size: limit.0,
-                    offset: page.map(|page| (page - 1) * limit.unwrap_or(10)),
+                    size: Some(size),
+                    offset: page.map(|page| (page - 1) * size),
                 },
             )
             .await
@@ -58,7 +64,12 @@ impl FeathrApiV1 {
             .map(Json)
     }
 
-    #[oai(path = "/projects", method = "post", tag = "ApiTags::Project")]
+    #[oai(
+        path = "/projects",
+        method = "post",
+        tag = "ApiTags::Project",
+        operation_id = "new_project"
+    )]
     async fn new_project(
         &self,
         credential: Data<&Credential>,
@@ -78,7 +89,13 @@ impl FeathrApiV1 {
         }
         let ret = data
             .0
-            .request(None, FeathrApiRequest::CreateProject { definition })
+            .request(
+                None,
+                FeathrApiRequest::CreateProject {
+                    definition,
+                    time: None,
+                },
+            )
             .await
             .into_uuid_and_version();
         // Grant project admin permission to the creator of the project.
@@ -103,7 +120,12 @@ impl FeathrApiV1 {
         ret.map(|v| Json(v.into()))
     }
 
-    #[oai(path = "/projects/:project", method = "get", tag = "ApiTags::Project")]
+    #[oai(
+        path = "/projects/:project",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "get_project_lineage"
+    )]
     async fn get_project_lineage(
         &self,
         credential: Data<&Credential>,
@@ -119,6 +141,8 @@ impl FeathrApiV1 {
                 opt_seq.0,
                 FeathrApiRequest::GetProjectLineage {
                     id_or_name: project.0,
+                    size: None,
+                    offset: None,
                 },
             )
             .await
@@ -129,7 +153,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/projects/:project/features",
         method = "get",
-        tag = "ApiTags::Project"
+        tag = "ApiTags::Project",
+        operation_id = "get_project_features"
     )]
     async fn get_project_features(
         &self,
@@ -144,14 +169,15 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
+        let size = data.0.page_limits.size(limit.0);
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetProjectFeatures {
                     project_id_or_name: project.0,
                     keyword: keyword.0,
-                    size: limit.0,
-                    offset: page.map(|page| (page - 1) * limit.unwrap_or(10)),
+                    size: Some(size),
+                    offset: page.map(|page| (page - 1) * size),
                 },
             )
             .await
@@ -163,7 +189,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/projects/:project/datasources",
         method = "get",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::DataSource",
+        operation_id = "get_project_datasources"
     )]
     async fn get_project_datasources(
         &self,
@@ -178,14 +205,15 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
+        let size = data.0.page_limits.size(limit.0);
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetProjectDataSources {
                     project_id_or_name: project.0,
                     keyword: keyword.0,
-                    size: limit.0,
-                    offset: page.map(|page| (page - 1) * limit.unwrap_or(10)),
+                    size: Some(size),
+                    offset: page.map(|page| (page - 1) * size),
                 },
             )
             .await
@@ -197,7 +225,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/projects/:project/datasources",
         method = "post",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::DataSource",
+        operation_id = "new_datasource"
     )]
     async fn new_datasource(
         &self,
@@ -218,22 +247,23 @@ impl FeathrApiV1 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectDataSource {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     #[oai(
         path = "/projects/:project/derivedfeatures",
         method = "post",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::DerivedFeature",
+        operation_id = "new_derived_feature"
     )]
     async fn new_derived_feature(
         &self,
@@ -254,22 +284,23 @@ impl FeathrApiV1 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectDerivedFeature {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     #[oai(
         path = "/projects/:project/anchors",
         method = "get",
-        tag = "ApiTags::Anchor"
+        tag = "ApiTags::Anchor",
+        operation_id = "get_project_anchors"
     )]
     async fn get_project_anchors(
         &self,
@@ -284,14 +315,15 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
+        let size = data.0.page_limits.size(limit.0);
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetProjectAnchors {
                     project_id_or_name: project.0,
                     keyword: keyword.0,
-                    size: limit.0,
-                    offset: page.map(|page| (page - 1) * limit.unwrap_or(10)),
+                    size: Some(size),
+                    offset: page.map(|page| (page - 1) * size),
                 },
             )
             .await
@@ -303,7 +335,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/projects/:project/anchors",
         method = "post",
-        tag = "ApiTags::Anchor"
+        tag = "ApiTags::Anchor",
+        operation_id = "new_anchor"
     )]
     async fn new_anchor(
         &self,
@@ -324,22 +357,23 @@ impl FeathrApiV1 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectAnchor {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     #[oai(
         path = "/projects/:project/anchors/:anchor/features",
         method = "post",
-        tag = "ApiTags::AnchorFeature"
+        tag = "ApiTags::AnchorFeature",
+        operation_id = "new_anchor_feature"
     )]
     async fn new_anchor_feature(
         &self,
@@ -361,20 +395,25 @@ impl FeathrApiV1 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateAnchorFeature {
                     project_id_or_name: project.0,
                     anchor_id_or_name: anchor.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
-    #[oai(path = "/features/:feature", method = "get", tag = "ApiTags::Feature")]
+    #[oai(
+        path = "/features/:feature",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature"
+    )]
     async fn get_feature(
         &self,
         credential: Data<&Credential>,
@@ -385,7 +424,8 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some(&feature), Permission::Read)
             .await?;
-        data.0
+        let entity = data
+            .0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetFeature {
@@ -393,14 +433,16 @@ impl FeathrApiV1 {
                 },
             )
             .await
-            .into_entity()
-            .map(Json)
+            .into_entity()?;
+        data.0.record_feature_hit(&entity);
+        Ok(Json(entity))
     }
 
     #[oai(
         path = "/features/:feature/lineage",
         method = "get",
-        tag = "ApiTags::Feature"
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature_lineage"
     )]
     async fn get_feature_lineage(
         &self,
@@ -412,22 +454,27 @@ impl FeathrApiV1 {
         data.0
             .check_permission(credential.0, Some(&feature), Permission::Read)
             .await?;
-        data.0
+        let lineage = data
+            .0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetFeatureLineage {
-                    id_or_name: feature.0,
+                    id_or_name: feature.0.clone(),
+                    size: None,
+                    offset: None,
                 },
             )
             .await
-            .into_lineage()
-            .map(Json)
+            .into_lineage()?;
+        data.0.record_lineage_hit(&lineage, &feature);
+        Ok(Json(lineage))
     }
 
     #[oai(
         path = "/features/:feature/project",
         method = "get",
-        tag = "ApiTags::Feature"
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature_project"
     )]
     async fn get_feature_project(
         &self,
@@ -451,7 +498,12 @@ impl FeathrApiV1 {
             .map(Json)
     }
 
-    #[oai(path = "/userroles", method = "get", tag = "ApiTags::Rbac")]
+    #[oai(
+        path = "/userroles",
+        method = "get",
+        tag = "ApiTags::Rbac",
+        operation_id = "get_user_roles"
+    )]
     async fn get_user_roles(
         &self,
         credential: Data<&Credential>,
@@ -462,7 +514,15 @@ impl FeathrApiV1 {
             .check_permission(credential.0, Some("global"), Permission::Admin)
             .await?;
         data.0
-            .request(opt_seq.0, FeathrApiRequest::GetUserRoles)
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetUserRoles {
+                    user: None,
+                    scope: None,
+                    size: None,
+                    offset: None,
+                },
+            )
             .await
             .into_user_roles()
             .map(Json)
@@ -471,7 +531,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/users/:user/userroles/add",
         method = "post",
-        tag = "ApiTags::Rbac"
+        tag = "ApiTags::Rbac",
+        operation_id = "add_user_role"
     )]
     async fn add_user_role(
         &self,
@@ -521,7 +582,8 @@ impl FeathrApiV1 {
     #[oai(
         path = "/users/:user/userroles/delete",
         method = "delete",
-        tag = "ApiTags::Rbac"
+        tag = "ApiTags::Rbac",
+        operation_id = "delete_user_role"
     )]
     async fn delete_user_role(
         &self,
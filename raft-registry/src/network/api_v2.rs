@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use common_utils::StringError;
 use poem::{
     error::{BadRequest, InternalServerError},
@@ -5,14 +7,18 @@ use poem::{
 };
 use poem_openapi::{
     param::{Header, Path, Query},
-    payload::Json,
-    OpenApi, Tags,
+    payload::{Json, PlainText},
+    ApiResponse, OpenApi, Tags,
 };
 use registry_api::{
-    AnchorDef, AnchorFeatureDef, ApiError, CreationResponse, DerivedFeatureDef, Entities, Entity,
-    EntityLineage, FeathrApiRequest, ProjectDef, RbacResponse, SourceDef,
+    AnchorDef, AnchorFeatureDef, ApiError, AtlasEntities, BulkTagUpdate, BundleError,
+    CreationResponse, DerivedFeatureDef, DerivedFeatureInputs, EdgeType, Entities, Entity,
+    EntityHistory, EntityLineage, EntityLink, EntityType, FeathrApiRequest, FeatureAnchor,
+    FeatureSchema, FieldDiff, ProjectBundle, ProjectDef, ProjectSummary, RbacResponse,
+    Relationship, ResolvedFeature, SourceDef, UserRoleDef, UserRoleResult,
 };
 use registry_provider::{Credential, Permission};
+use sql_provider::SqlDialect;
 use uuid::Uuid;
 
 use crate::RaftRegistryApp;
@@ -25,14 +31,55 @@ enum ApiTags {
     AnchorFeature,
     DerivedFeature,
     Feature,
+    Entity,
     Rbac,
+    Admin,
+}
+
+#[derive(ApiResponse)]
+enum CsvResponse {
+    #[oai(status = 200, content_type = "text/csv")]
+    Ok(
+        PlainText<String>,
+        #[oai(header = "Content-Disposition")] String,
+    ),
+}
+
+#[derive(ApiResponse)]
+enum ProjectsResponse {
+    /// Names of the projects
+    #[oai(status = 200)]
+    Names(Json<Vec<String>>),
+    /// Full project entities, returned if `detailed` is set
+    #[oai(status = 200)]
+    Detailed(Json<Entities>),
+}
+
+#[derive(ApiResponse)]
+enum EntityPageResponse {
+    /// A page of entities, the cursor of the next page is set if there are more
+    #[oai(status = 200)]
+    Ok(
+        Json<Entities>,
+        #[oai(header = "x-registry-next-cursor")] Option<String>,
+    ),
+}
+
+#[derive(ApiResponse)]
+enum LineageResponse {
+    /// The lineage and its etag
+    #[oai(status = 200)]
+    Ok(Json<EntityLineage>, #[oai(header = "ETag")] String),
+    /// The lineage hasn't changed since the etag in `If-None-Match`
+    #[oai(status = 304)]
+    NotModified,
 }
 
 pub struct FeathrApiV2;
 
 #[OpenApi]
 impl FeathrApiV2 {
-    /// List or search names of all projects
+    /// List or search all projects, returns names unless `detailed` is set
     #[oai(
         path = "/projects",
         method = "get",
@@ -46,26 +93,43 @@ impl FeathrApiV2 {
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         /// Search keywords
         keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
         size: Query<Option<usize>>,
         /// Starting offset of returned list
         offset: Query<Option<usize>>,
-    ) -> poem::Result<Json<Vec<String>>> {
+        /// Return full project entities instead of names
+        detailed: Query<Option<bool>>,
+    ) -> poem::Result<ProjectsResponse> {
         data.0
             .check_permission(credential.0, Some("global"), Permission::Read)
             .await?;
-        data.0
-            .request(
-                opt_seq.0,
-                FeathrApiRequest::GetProjects {
-                    keyword: keyword.0,
-                    size: size.0,
-                    offset: offset.0,
-                },
-            )
-            .await
-            .into_entity_names()
-            .map(Json)
+        if detailed.0.unwrap_or_default() {
+            data.0
+                .request(
+                    opt_seq.0,
+                    FeathrApiRequest::GetProjectsDetailed {
+                        keyword: keyword.0,
+                        size: size.0,
+                        offset: offset.0,
+                    },
+                )
+                .await
+                .into_entities()
+                .map(|v| ProjectsResponse::Detailed(Json(v)))
+        } else {
+            data.0
+                .request(
+                    opt_seq.0,
+                    FeathrApiRequest::GetProjects {
+                        keyword: keyword.0,
+                        size: size.0,
+                        offset: offset.0,
+                    },
+                )
+                .await
+                .into_entity_names()
+                .map(|v| ProjectsResponse::Names(Json(v)))
+        }
     }
 
     /// Create new project
@@ -82,12 +146,26 @@ impl FeathrApiV2 {
         /// Creator of the project
         #[oai(name = "x-registry-requestor")]
         creator: Header<Option<String>>,
+        /// Return the existing entity with the same qualified name instead of creating a new one
+        #[oai(name = "x-if-not-exists")]
+        if_not_exists: Header<Option<bool>>,
         /// Project definition
         def: Json<ProjectDef>,
     ) -> poem::Result<Json<CreationResponse>> {
         data.0
             .check_permission(credential.0, Some("global"), Permission::Write)
             .await?;
+        if let Some(existing) = find_existing(
+            data.0,
+            if_not_exists.0,
+            FeathrApiRequest::GetProject {
+                id_or_name: def.0.name.clone(),
+            },
+        )
+        .await
+        {
+            return Ok(Json(existing));
+        }
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -97,7 +175,13 @@ impl FeathrApiV2 {
         }
         let ret = data
             .0
-            .request(None, FeathrApiRequest::CreateProject { definition })
+            .request(
+                None,
+                FeathrApiRequest::CreateProject {
+                    definition,
+                    time: None,
+                },
+            )
             .await
             .into_uuid_and_version();
         // Grant project admin permission to the creator of the project.
@@ -116,7 +200,7 @@ impl FeathrApiV2 {
                 )
                 .await;
             if let registry_api::FeathrApiResponse::Error(e) = ret {
-                return Err(e.into())
+                return Err(e.into());
             }
         }
 
@@ -153,6 +237,93 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
+    /// Copy the project and all its contents into a new project with fresh ids
+    #[oai(
+        path = "/projects/:project/clone",
+        method = "post",
+        tag = "ApiTags::Project",
+        operation_id = "clone_project"
+    )]
+    async fn clone_project(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        /// Project name or id
+        project: Path<String>,
+        /// Name of the new project
+        name: Query<String>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Write)
+            .await?;
+        let ret = data
+            .0
+            .request(
+                None,
+                FeathrApiRequest::CloneProject {
+                    id_or_name: project.0,
+                    new_name: name.0,
+                    id_seed: Uuid::new_v4(),
+                },
+            )
+            .await
+            .into_uuid_and_version();
+        // Grant project admin permission to the creator of the new project.
+        if let Ok((uuid, _)) = &ret {
+            let ret = data
+                .0
+                .request(
+                    None,
+                    FeathrApiRequest::AddUserRole {
+                        project_id_or_name: uuid.to_string(),
+                        user: credential.0.clone(),
+                        role: Permission::Admin,
+                        requestor: credential.0.clone(),
+                        reason: "Cloned project".to_string(),
+                    },
+                )
+                .await;
+            if let registry_api::FeathrApiResponse::Error(e) = ret {
+                return Err(e.into());
+            }
+        }
+
+        ret.map(|v| Json(v.into()))
+    }
+
+    /// Get number of entities of each type in the project
+    #[oai(
+        path = "/projects/:project/summary",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "get_project_summary"
+    )]
+    async fn get_project_summary(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+    ) -> poem::Result<Json<ProjectSummary>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectSummary {
+                    id_or_name: project.0,
+                },
+            )
+            .await
+            .into_project_summary()
+            .map(Json)
+    }
+
     /// Get project lineage
     #[oai(
         path = "/projects/:project/lineage",
@@ -165,9 +336,16 @@ impl FeathrApiV2 {
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Etag of the lineage fetched before, 304 is returned if the lineage hasn't changed
+        #[oai(name = "If-None-Match")]
+        if_none_match: Header<Option<String>>,
         /// Project name or id
         project: Path<String>,
-    ) -> poem::Result<Json<EntityLineage>> {
+        /// Max number of relationships in the page, only used if `offset` is set
+        size: Query<Option<usize>>,
+        /// Starting offset of relationships, the lineage is paged if set
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<LineageResponse> {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
@@ -176,10 +354,42 @@ impl FeathrApiV2 {
                 opt_seq.0,
                 FeathrApiRequest::GetProjectLineage {
                     id_or_name: project.0,
+                    size: size.0,
+                    offset: offset.0,
                 },
             )
             .await
             .into_lineage()
+            .map(|lineage| lineage_response(lineage, if_none_match.0))
+    }
+
+    /// Export entities of the project in the Atlas compatible format, relationships are listed separately
+    #[oai(
+        path = "/projects/:project/atlas",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "get_project_atlas_entities"
+    )]
+    async fn get_project_atlas_entities(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+    ) -> poem::Result<Json<AtlasEntities>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectAtlasEntities {
+                    id_or_name: project.0,
+                },
+            )
+            .await
+            .into_atlas_entities()
             .map(Json)
     }
 
@@ -199,7 +409,7 @@ impl FeathrApiV2 {
         project: Path<String>,
         /// Search keywords
         keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
         size: Query<Option<usize>>,
         /// Starting offset of returned list
         offset: Query<Option<usize>>,
@@ -222,6 +432,79 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
+    /// Get all features in the project, every feature comes after all its input features
+    #[oai(
+        path = "/projects/:project/features/order",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "get_project_features_in_order"
+    )]
+    async fn get_project_features_in_order(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectFeaturesInOrder {
+                    project_id_or_name: project.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /// Export all features in the project as CSV
+    #[oai(
+        path = "/projects/:project/features.csv",
+        method = "get",
+        tag = "ApiTags::Project",
+        operation_id = "export_project_features"
+    )]
+    async fn export_project_features(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+    ) -> poem::Result<CsvResponse> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        let features = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectFeatures {
+                    project_id_or_name: project.0.clone(),
+                    keyword: None,
+                    size: None,
+                    offset: None,
+                },
+            )
+            .await
+            .into_entities()?;
+        Ok(CsvResponse::Ok(
+            PlainText(features.to_csv()),
+            format!(
+                "attachment; filename=\"{}-features.csv\"",
+                project.0.replace(
+                    |c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-',
+                    "_"
+                )
+            ),
+        ))
+    }
+
     /// Get or search data sources in the project
     #[oai(
         path = "/projects/:project/datasources",
@@ -237,7 +520,7 @@ impl FeathrApiV2 {
         project: Path<String>,
         /// Search keywords
         keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
         size: Query<Option<usize>>,
         /// Starting offset of returned list
         offset: Query<Option<usize>>,
@@ -271,6 +554,9 @@ impl FeathrApiV2 {
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        /// Return the existing entity with the same qualified name instead of creating a new one
+        #[oai(name = "x-if-not-exists")]
+        if_not_exists: Header<Option<bool>>,
         /// Project name or id
         project: Path<String>,
         /// Data source definition
@@ -279,6 +565,18 @@ impl FeathrApiV2 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Write)
             .await?;
+        if let Some(existing) = find_existing(
+            data.0,
+            if_not_exists.0,
+            FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: project.0.clone(),
+                id_or_name: def.0.name.clone(),
+            },
+        )
+        .await
+        {
+            return Ok(Json(existing));
+        }
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -287,16 +585,16 @@ impl FeathrApiV2 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectDataSource {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     /// Get data source with specified name in a project
@@ -331,13 +629,14 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
-    /// Get all versions of a data source in a project
+    /// Delete data source with specified name in a project
     #[oai(
-        path = "/projects/:project/datasources/:source/versions",
-        method = "get",
-        tag = "ApiTags::DataSource"
+        path = "/projects/:project/datasources/:source",
+        method = "delete",
+        tag = "ApiTags::DataSource",
+        operation_id = "delete_datasource"
     )]
-    async fn get_datasource_versions(
+    async fn delete_datasource(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
@@ -346,30 +645,41 @@ impl FeathrApiV2 {
         project: Path<String>,
         /// Source name or id
         source: Path<String>,
-    ) -> poem::Result<Json<Entities>> {
+        /// Detach anchors consuming the source first, otherwise deleting a source in use fails
+        force: Query<Option<bool>>,
+    ) -> poem::Result<Json<String>> {
         data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
+            .check_permission(credential.0, Some(&project), Permission::Write)
             .await?;
-        data.0
+        let resp = data
+            .0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSourceVersions {
+                FeathrApiRequest::DeleteProjectDataSource {
                     project_id_or_name: project.0,
                     id_or_name: source.0,
+                    force: force.0.unwrap_or_default(),
+                    time: None,
                 },
             )
-            .await
-            .into_entities()
-            .map(Json)
+            .await;
+        match resp {
+            registry_api::FeathrApiResponse::Unit => Ok(Json("OK".to_string())),
+            registry_api::FeathrApiResponse::Error(e) => Err(e.into()),
+            _ => Err(InternalServerError(StringError::new(
+                "Internal Server Error",
+            ))),
+        }
     }
 
-    /// Get specific version of a data source in a project
+    /// Get anchors consuming the data source, useful before changing or deleting it
     #[oai(
-        path = "/projects/:project/datasources/:source/versions/:version",
+        path = "/projects/:project/datasources/:source/consumers",
         method = "get",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::DataSource",
+        operation_id = "get_datasource_consumers"
     )]
-    async fn get_datasource_version(
+    async fn get_datasource_consumers(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
@@ -378,83 +688,205 @@ impl FeathrApiV2 {
         project: Path<String>,
         /// Source name or id
         source: Path<String>,
-        /// Version number
-        version: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
+    ) -> poem::Result<Json<Entities>> {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSourceVersion {
+                FeathrApiRequest::GetSourceConsumers {
                     project_id_or_name: project.0,
-                    id_or_name: source.0,
-                    version: parse_version(version.0)?,
+                    source_id_or_name: source.0,
                 },
             )
             .await
-            .into_entity()
+            .into_entities()
             .map(Json)
     }
 
-    /// Get or search derived features in a project
+    /// Get lineage of a data source, i.e. all features built on it directly or via other features
     #[oai(
-        path = "/projects/:project/derivedfeatures",
+        path = "/projects/:project/datasources/:source/lineage",
         method = "get",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::DataSource",
+        operation_id = "get_datasource_lineage"
     )]
-    async fn get_project_derived_features(
+    async fn get_datasource_lineage(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Etag of the lineage fetched before, 304 is returned if the lineage hasn't changed
+        #[oai(name = "If-None-Match")]
+        if_none_match: Header<Option<String>>,
         /// Project name or id
         project: Path<String>,
-        /// Search keywords
-        keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Source name or id
+        source: Path<String>,
+        /// Max number of entities, the lineage is marked as truncated if there are more,
+        /// or the page size if `offset` is set
         size: Query<Option<usize>>,
-        /// Starting offset of returned list
+        /// Starting offset of relationships, the lineage is paged if set
         offset: Query<Option<usize>>,
-    ) -> poem::Result<Json<Entities>> {
+    ) -> poem::Result<LineageResponse> {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Read)
             .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDerivedFeatures {
+                FeathrApiRequest::GetSourceLineage {
                     project_id_or_name: project.0,
-                    keyword: keyword.0,
+                    source_id_or_name: source.0,
                     size: size.0,
                     offset: offset.0,
                 },
             )
             .await
-            .into_entities()
-            .map(Json)
+            .into_lineage()
+            .map(|lineage| lineage_response(lineage, if_none_match.0))
     }
 
-    /// Create a new derived feature in the project
+    /// Get all versions of a data source in a project
     #[oai(
-        path = "/projects/:project/derivedfeatures",
-        method = "post",
-        tag = "ApiTags::DerivedFeature"
+        path = "/projects/:project/datasources/:source/versions",
+        method = "get",
+        tag = "ApiTags::DataSource"
     )]
-    async fn new_derived_feature(
+    async fn get_datasource_versions(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         /// Project name or id
         project: Path<String>,
-        /// Derived feature definition
-        def: Json<DerivedFeatureDef>,
-    ) -> poem::Result<Json<CreationResponse>> {
+        /// Source name or id
+        source: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
         data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSourceVersions {
+                    project_id_or_name: project.0,
+                    id_or_name: source.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /// Get specific version of a data source in a project
+    #[oai(
+        path = "/projects/:project/datasources/:source/versions/:version",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_datasource_version(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Source name or id
+        source: Path<String>,
+        /// Version number
+        version: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSourceVersion {
+                    project_id_or_name: project.0,
+                    id_or_name: source.0,
+                    version: parse_version(version.0)?,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Get or search derived features in a project
+    #[oai(
+        path = "/projects/:project/derivedfeatures",
+        method = "get",
+        tag = "ApiTags::DerivedFeature"
+    )]
+    async fn get_project_derived_features(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Search keywords
+        keyword: Query<Option<String>>,
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
+        size: Query<Option<usize>>,
+        /// Starting offset of returned list
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDerivedFeatures {
+                    project_id_or_name: project.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /// Create a new derived feature in the project
+    #[oai(
+        path = "/projects/:project/derivedfeatures",
+        method = "post",
+        tag = "ApiTags::DerivedFeature"
+    )]
+    async fn new_derived_feature(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        /// Return the existing entity with the same qualified name instead of creating a new one
+        #[oai(name = "x-if-not-exists")]
+        if_not_exists: Header<Option<bool>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Derived feature definition
+        def: Json<DerivedFeatureDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Write)
             .await?;
+        if let Some(existing) = find_existing(
+            data.0,
+            if_not_exists.0,
+            FeathrApiRequest::GetProjectDerivedFeature {
+                project_id_or_name: project.0.clone(),
+                id_or_name: def.0.name.clone(),
+            },
+        )
+        .await
+        {
+            return Ok(Json(existing));
+        }
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -463,16 +895,16 @@ impl FeathrApiV2 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectDerivedFeature {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     /// Get a derived feature in a project
@@ -589,7 +1021,7 @@ impl FeathrApiV2 {
         project: Path<String>,
         /// Search keywords
         keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
         size: Query<Option<usize>>,
         /// Starting offset of returned list
         offset: Query<Option<usize>>,
@@ -623,6 +1055,9 @@ impl FeathrApiV2 {
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        /// Return the existing entity with the same qualified name instead of creating a new one
+        #[oai(name = "x-if-not-exists")]
+        if_not_exists: Header<Option<bool>>,
         /// Project name or id
         project: Path<String>,
         /// Anchor definition
@@ -631,6 +1066,18 @@ impl FeathrApiV2 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Write)
             .await?;
+        if let Some(existing) = find_existing(
+            data.0,
+            if_not_exists.0,
+            FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: project.0.clone(),
+                id_or_name: def.0.name.clone(),
+            },
+        )
+        .await
+        {
+            return Ok(Json(existing));
+        }
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -639,16 +1086,16 @@ impl FeathrApiV2 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateProjectAnchor {
                     project_id_or_name: project.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     /// Get an anchor in a project
@@ -767,7 +1214,7 @@ impl FeathrApiV2 {
         anchor: Path<String>,
         /// Search keywords
         keyword: Query<Option<String>>,
-        /// Limit size of returned list
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
         size: Query<Option<usize>>,
         /// Starting offset of returned list
         offset: Query<Option<usize>>,
@@ -802,6 +1249,9 @@ impl FeathrApiV2 {
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        /// Return the existing entity with the same qualified name instead of creating a new one
+        #[oai(name = "x-if-not-exists")]
+        if_not_exists: Header<Option<bool>>,
         /// Project name or id
         project: Path<String>,
         /// Anchor name or id
@@ -812,6 +1262,19 @@ impl FeathrApiV2 {
         data.0
             .check_permission(credential.0, Some(&project), Permission::Write)
             .await?;
+        if let Some(existing) = find_existing(
+            data.0,
+            if_not_exists.0,
+            FeathrApiRequest::GetAnchorFeature {
+                project_id_or_name: project.0.clone(),
+                anchor_id_or_name: anchor.0.clone(),
+                id_or_name: def.0.name.clone(),
+            },
+        )
+        .await
+        {
+            return Ok(Json(existing));
+        }
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -820,17 +1283,17 @@ impl FeathrApiV2 {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
-            .request(
+            .create(
                 None,
                 FeathrApiRequest::CreateAnchorFeature {
                     project_id_or_name: project.0,
                     anchor_id_or_name: anchor.0,
                     definition,
+                    time: None,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .map(Json)
     }
 
     /// Get a feature in an anchor
@@ -941,6 +1404,42 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
+    /// Search features in all projects, features the caller cannot read are left out
+    #[oai(
+        path = "/features",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "search_all_features"
+    )]
+    async fn search_all_features(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Search keywords
+        keyword: Query<Option<String>>,
+        /// Limit size of returned list, clamped to the max page size, 0 or missing means the default page size
+        size: Query<Option<usize>>,
+        /// Starting offset of returned list
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        // Features the caller cannot read are left out before paging
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::SearchAllFeatures {
+                    keyword: keyword.0,
+                    types: Default::default(),
+                    size: size.0,
+                    offset: offset.0,
+                    credential: credential.0.to_owned(),
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
     /// Get a feature
     #[oai(path = "/features/:feature", method = "get", tag = "ApiTags::Feature")]
     async fn get_feature(
@@ -950,11 +1449,47 @@ impl FeathrApiV2 {
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         /// Feature name or id
         feature: Path<String>,
+        /// Raft log index, returns the feature as it was right after the log entry had been applied
+        as_of: Query<Option<u64>>,
     ) -> poem::Result<Json<Entity>> {
         data.0
             .check_permission(credential.0, Some(&feature), Permission::Read)
             .await?;
+        let request = match as_of.0 {
+            Some(seq) => FeathrApiRequest::GetEntityAtSeq {
+                id_or_name: feature.0,
+                seq,
+            },
+            None => FeathrApiRequest::GetFeature {
+                id_or_name: feature.0,
+            },
+        };
+        let entity = data.0.request(opt_seq.0, request).await.into_entity()?;
+        data.0.record_feature_hit(&entity);
+        Ok(Json(entity))
+    }
+
+    /// Number of times the feature has been read via this node since the node started,
+    /// counters are per node and not persisted, other nodes in the cluster keep their own counts
+    #[oai(
+        path = "/features/:feature/usage",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature_usage"
+    )]
+    async fn get_feature_usage(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Feature name or id
+        feature: Path<String>,
+    ) -> poem::Result<Json<u64>> {
         data.0
+            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .await?;
+        let entity = data
+            .0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetFeature {
@@ -962,61 +1497,70 @@ impl FeathrApiV2 {
                 },
             )
             .await
-            .into_entity()
-            .map(Json)
+            .into_entity()?;
+        let id = Uuid::parse_str(&entity.guid).map_err(InternalServerError)?;
+        Ok(Json(data.0.feature_usage.get(id)))
     }
 
-    /// Get lineage of a feature
+    /// Add or remove input features of a derived feature, the feature keeps its id
     #[oai(
-        path = "/features/:feature/lineage",
-        method = "get",
-        tag = "ApiTags::Feature"
+        path = "/features/:feature/inputs",
+        method = "patch",
+        tag = "ApiTags::Feature",
+        operation_id = "update_derived_feature_inputs"
     )]
-    async fn get_feature_lineage(
+    async fn update_derived_feature_inputs(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        /// Feature name or id
+        /// Derived feature name or id
         feature: Path<String>,
-    ) -> poem::Result<Json<EntityLineage>> {
+        /// Input features to be added or removed
+        inputs: Json<DerivedFeatureInputs>,
+    ) -> poem::Result<Json<Entity>> {
         data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .check_permission(credential.0, Some(&feature), Permission::Write)
             .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetFeatureLineage {
+                FeathrApiRequest::UpdateDerivedFeatureInputs {
                     id_or_name: feature.0,
+                    inputs: inputs.0,
                 },
             )
             .await
-            .into_lineage()
+            .into_entity()
             .map(Json)
     }
 
-    /// Get the project the feature is in
+    /// Move an anchor feature under another anchor in the same project, the feature keeps its id
     #[oai(
-        path = "/features/:feature/project",
-        method = "get",
-        tag = "ApiTags::Feature"
+        path = "/features/:feature/anchor",
+        method = "patch",
+        tag = "ApiTags::Feature",
+        operation_id = "move_anchor_feature"
     )]
-    async fn get_feature_project(
+    async fn move_anchor_feature(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        /// Feature name or id
+        /// Anchor feature name or id
         feature: Path<String>,
+        /// The anchor to move the feature to
+        anchor: Json<FeatureAnchor>,
     ) -> poem::Result<Json<Entity>> {
         data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .check_permission(credential.0, Some(&feature), Permission::Write)
             .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetEntityProject {
+                FeathrApiRequest::MoveAnchorFeature {
                     id_or_name: feature.0,
+                    anchor: anchor.0,
                 },
             )
             .await
@@ -1024,98 +1568,764 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
-    /// Get all user role mappings
-    #[oai(path = "/userroles", method = "get", tag = "ApiTags::Rbac")]
-    async fn get_user_roles(
+    /// Get key columns and value type of a feature
+    #[oai(
+        path = "/features/:feature/schema",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature_schema"
+    )]
+    async fn get_feature_schema(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-    ) -> poem::Result<Json<Vec<RbacResponse>>> {
+        /// Feature name or id
+        feature: Path<String>,
+    ) -> poem::Result<Json<FeatureSchema>> {
         data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .check_permission(credential.0, Some(&feature), Permission::Read)
             .await?;
         data.0
-            .request(opt_seq.0, FeathrApiRequest::GetUserRoles)
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeatureSchema {
+                    id_or_name: feature.0,
+                },
+            )
             .await
-            .into_user_roles()
+            .into_feature_schema()
             .map(Json)
     }
 
-    /// Create an user role mapping
+    /// Get a feature along with the full entities of its inputs, deleted inputs are listed in `deletedInputs`
     #[oai(
-        path = "/users/:user/userroles/add",
-        method = "post",
-        tag = "ApiTags::Rbac"
+        path = "/features/:feature/resolved",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "get_feature_with_inputs"
     )]
-    async fn add_user_role(
+    async fn get_feature_with_inputs(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        /// User name
-        user: Path<String>,
-        /// Scope of the role, can be a project name or "global"
-        project: Query<String>,
-        /// Role name
-        role: Query<String>,
-        /// Reason for the role mapping creation
-        reason: Query<String>,
-    ) -> poem::Result<Json<String>> {
+        /// Feature name or id
+        feature: Path<String>,
+    ) -> poem::Result<Json<ResolvedFeature>> {
         data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .check_permission(credential.0, Some(&feature), Permission::Read)
             .await?;
-        let resp = data
-            .0
+        data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::AddUserRole {
-                    user: user.0.parse().map_err(BadRequest)?,
-                    project_id_or_name: project.0,
-                    role: match role.0.to_lowercase().as_str() {
-                        "admin" => Permission::Admin,
-                        "consumer" => Permission::Read,
-                        "producer" => Permission::Write,
-                        _ => {
-                            return Err(BadRequest(StringError::new(format!(
-                                "invalid role {}",
-                                role.0
-                            ))))
-                        }
-                    },
-                    requestor: credential.0.to_owned(),
-                    reason: reason.0,
+                FeathrApiRequest::GetFeatureWithInputs {
+                    id_or_name: feature.0,
                 },
             )
-            .await;
-        match resp {
-            registry_api::FeathrApiResponse::Unit => Ok(Json("OK".to_string())),
-            registry_api::FeathrApiResponse::Error(e) => Err(e.into()),
-            _ => Err(InternalServerError(StringError::new(
-                "Internal Server Error",
-            ))),
-        }
+            .await
+            .into_resolved_feature()
+            .map(Json)
     }
 
-    /// Delete an user role mapping
+    /// Compare two versions of a feature, returns fields with different values
     #[oai(
-        path = "/users/:user/userroles/delete",
-        method = "delete",
-        tag = "ApiTags::Rbac"
+        path = "/features/:feature/diff",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "diff_feature_versions"
     )]
-    async fn delete_user_role(
+    async fn diff_feature_versions(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        /// User name
-        user: Path<String>,
-        /// Scope of the role, can be a project name or "global"
-        project: Query<String>,
-        /// Role name
-        role: Query<String>,
-        /// Reason for the role mapping deletion
-        reason: Query<String>,
+        /// Feature name or id
+        feature: Path<String>,
+        /// Version to compare from
+        from: Query<u64>,
+        /// Version to compare to
+        to: Query<u64>,
+    ) -> poem::Result<Json<Vec<FieldDiff>>> {
+        data.0
+            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::DiffEntityVersions {
+                    id_or_name: feature.0,
+                    from_version: from.0,
+                    to_version: to.0,
+                },
+            )
+            .await
+            .into_field_diffs()
+            .map(Json)
+    }
+
+    /// Get lineage of a feature
+    #[oai(
+        path = "/features/:feature/lineage",
+        method = "get",
+        tag = "ApiTags::Feature"
+    )]
+    async fn get_feature_lineage(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Etag of the lineage fetched before, 304 is returned if the lineage hasn't changed
+        #[oai(name = "If-None-Match")]
+        if_none_match: Header<Option<String>>,
+        /// Feature name or id
+        feature: Path<String>,
+        /// Max number of entities in each direction, the lineage is marked as truncated if there are more,
+        /// or the page size if `offset` is set
+        size: Query<Option<usize>>,
+        /// Starting offset of relationships, upstream ones first, the lineage is paged if set
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<LineageResponse> {
+        data.0
+            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeatureLineage {
+                    id_or_name: feature.0.clone(),
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_lineage()
+            .map(|lineage| {
+                data.0.record_lineage_hit(&lineage, &feature);
+                lineage_response(lineage, if_none_match.0)
+            })
+    }
+
+    /// Get the project the feature is in
+    #[oai(
+        path = "/features/:feature/project",
+        method = "get",
+        tag = "ApiTags::Feature"
+    )]
+    async fn get_feature_project(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Feature name or id
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .check_permission(credential.0, Some(&feature), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntityProject {
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Get the project containing the entity, 404 if the entity is a project itself
+    #[oai(
+        path = "/entities/:entity/project",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "get_entity_project"
+    )]
+    async fn get_entity_project(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity name or id
+        entity: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .check_permission(credential.0, Some(&entity), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntityProject {
+                    id_or_name: entity.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Get the entities containing the entity, e.g. the project and the anchor of an anchor feature
+    #[oai(
+        path = "/entities/:entity/containers",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "get_entity_containers"
+    )]
+    async fn get_entity_containers(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity name or id
+        entity: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .check_permission(credential.0, Some(&entity), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntityContainers {
+                    id_or_name: entity.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /// List entities of the type across all projects ordered by id, entities the caller cannot read are left out
+    #[oai(
+        path = "/entities",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "list_entities_by_type"
+    )]
+    async fn list_entities_by_type(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity type, e.g. `feathr_source_v1`
+        #[oai(name = "type")]
+        entity_type: Query<EntityType>,
+        /// `x-registry-next-cursor` of the previous page, starts from the beginning if not set
+        cursor: Query<Option<String>>,
+        /// Page size
+        size: Query<Option<usize>>,
+    ) -> poem::Result<EntityPageResponse> {
+        let (page, next) = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::ListEntitiesByType {
+                    entity_type: entity_type.0.into(),
+                    cursor: cursor.0,
+                    size: size.0,
+                    credential: credential.0.to_owned(),
+                },
+            )
+            .await
+            .into_entity_page()?;
+        Ok(EntityPageResponse::Ok(Json(page), next))
+    }
+
+    /// Get multiple entities by their ids, ids not matching any entity are listed in `missing`
+    #[oai(
+        path = "/entities/batch",
+        method = "post",
+        tag = "ApiTags::Entity",
+        operation_id = "get_entities_batch"
+    )]
+    async fn get_entities_batch(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity ids
+        ids: Json<Vec<String>>,
+    ) -> poem::Result<Json<Entities>> {
+        let Entities {
+            entities,
+            mut missing,
+        } = data
+            .0
+            .request(opt_seq.0, FeathrApiRequest::GetEntities { ids: ids.0 })
+            .await
+            .into_entities()?;
+        // Entities the caller cannot read are reported as missing
+        let mut readable = vec![];
+        for e in entities {
+            if data
+                .0
+                .check_permission(credential.0, Some(&e.guid), Permission::Read)
+                .await
+                .is_ok()
+            {
+                readable.push(e);
+            } else {
+                missing.push(e.guid);
+            }
+        }
+        Ok(Json(Entities {
+            entities: readable,
+            missing,
+        }))
+    }
+
+    /// Get features having a key with the alias or full name, features the caller cannot read are left out
+    #[oai(
+        path = "/keys/:key/features",
+        method = "get",
+        tag = "ApiTags::Feature",
+        operation_id = "find_features_by_key"
+    )]
+    async fn find_features_by_key(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Key alias or full name
+        key: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        let features = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::FindFeaturesByKey { key: key.0 },
+            )
+            .await
+            .into_entities()?;
+        let mut readable = vec![];
+        for e in features.entities {
+            if data
+                .0
+                .check_permission(credential.0, Some(&e.guid), Permission::Read)
+                .await
+                .is_ok()
+            {
+                readable.push(e);
+            }
+        }
+        Ok(Json(Entities {
+            entities: readable,
+            missing: vec![],
+        }))
+    }
+
+    /// Suggest qualified names of entities whose name starts with the input
+    #[oai(
+        path = "/suggest",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "suggest"
+    )]
+    async fn suggest(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Name prefix
+        q: Query<String>,
+        /// Max number of suggestions
+        limit: Query<Option<usize>>,
+    ) -> poem::Result<Json<Vec<String>>> {
+        // Suggestions are filtered by the permissions of the caller, no need to check here
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetSuggestions {
+                    prefix: q.0,
+                    limit: limit.0,
+                    credential: credential.0.to_owned(),
+                },
+            )
+            .await
+            .into_entity_names()
+            .map(Json)
+    }
+
+    /// Update tags of an entity, tags with empty value are removed
+    #[oai(
+        path = "/entities/:entity/tags",
+        method = "patch",
+        tag = "ApiTags::Entity",
+        operation_id = "update_entity_tags"
+    )]
+    async fn update_entity_tags(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Expected etag of the entity, the update fails if the entity has been changed
+        #[oai(name = "If-Match")]
+        if_match: Header<Option<String>>,
+        /// Entity name or id
+        entity: Path<String>,
+        /// Tags to be updated
+        tags: Json<HashMap<String, String>>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .check_permission(credential.0, Some(&entity), Permission::Write)
+            .await?;
+        let if_match = parse_etag(if_match.0)?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::UpdateEntityTags {
+                    id_or_name: entity.0,
+                    tags: tags.0,
+                    if_match,
+                    new_etag: Uuid::new_v4(),
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Add and remove tags of the project and all entities in it, returns the number of updated entities
+    #[oai(
+        path = "/projects/:project/tags",
+        method = "patch",
+        tag = "ApiTags::Project",
+        operation_id = "bulk_update_project_tags"
+    )]
+    async fn bulk_update_project_tags(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Tags to be added and keys of tags to be removed
+        update: Json<BulkTagUpdate>,
+    ) -> poem::Result<Json<usize>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Write)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::BulkTagProject {
+                    project_id_or_name: project.0,
+                    add_tags: update.0.add_tags,
+                    remove_keys: update.0.remove_keys,
+                    new_etag: Uuid::new_v4(),
+                },
+            )
+            .await
+            .into_count()
+            .map(Json)
+    }
+
+    /// Get edges of the type starting from an entity
+    #[oai(
+        path = "/entities/:entity/edges",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "get_entity_edges"
+    )]
+    async fn get_entity_edges(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity name or id
+        entity: Path<String>,
+        /// Edge type, one of `BelongsTo`, `Contains`, `Consumes`, `Produces`, `DerivesFrom` and `DerivedBy`
+        #[oai(name = "type")]
+        edge_type: Query<EdgeType>,
+    ) -> poem::Result<Json<Vec<Relationship>>> {
+        data.0
+            .check_permission(credential.0, Some(&entity), Permission::Read)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntityEdges {
+                    id_or_name: entity.0,
+                    edge_type: edge_type.0.into(),
+                },
+            )
+            .await
+            .into_relationships()
+            .map(Json)
+    }
+
+    /// Changes applied to an entity in the log order, changes compacted into a snapshot are
+    /// not listed, `earliestSeq` is set in that case
+    #[oai(
+        path = "/entities/:id/history",
+        method = "get",
+        tag = "ApiTags::Entity",
+        operation_id = "get_entity_history"
+    )]
+    async fn get_entity_history(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity id
+        id: Path<String>,
+    ) -> poem::Result<Json<EntityHistory>> {
+        data.0
+            .check_permission(credential.0, Some(&id), Permission::Read)
+            .await?;
+        data.0
+            .request(opt_seq.0, FeathrApiRequest::GetEntityHistory { id: id.0 })
+            .await
+            .into_entity_history()
+            .map(Json)
+    }
+
+    /// Replace links to external resources of an entity, e.g. dashboards or documents
+    #[oai(
+        path = "/entities/:entity/links",
+        method = "put",
+        tag = "ApiTags::Entity",
+        operation_id = "update_entity_links"
+    )]
+    async fn update_entity_links(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Entity name or id
+        entity: Path<String>,
+        /// Links of the entity, URLs must be absolute http or https ones
+        links: Json<Vec<EntityLink>>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .check_permission(credential.0, Some(&entity), Permission::Write)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::UpdateEntityLinks {
+                    id_or_name: entity.0,
+                    links: links.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Get user role mappings
+    #[oai(path = "/userroles", method = "get", tag = "ApiTags::Rbac")]
+    async fn get_user_roles(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Only return mappings of this user
+        user: Query<Option<String>>,
+        /// Only return mappings in this scope, can be a project name or "global"
+        scope: Query<Option<String>>,
+        /// Limit size of returned list
+        size: Query<Option<usize>>,
+        /// Starting offset of returned list
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Vec<RbacResponse>>> {
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .await?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetUserRoles {
+                    user: user.0,
+                    scope: scope.0,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_user_roles()
+            .map(Json)
+    }
+
+    /// Create an user role mapping
+    #[oai(
+        path = "/users/:user/userroles/add",
+        method = "post",
+        tag = "ApiTags::Rbac"
+    )]
+    async fn add_user_role(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// User name
+        user: Path<String>,
+        /// Scope of the role, can be a project name or "global"
+        project: Query<String>,
+        /// Role name
+        role: Query<String>,
+        /// Reason for the role mapping creation
+        reason: Query<String>,
+    ) -> poem::Result<Json<String>> {
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .await?;
+        let resp = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::AddUserRole {
+                    user: user.0.parse().map_err(BadRequest)?,
+                    project_id_or_name: project.0,
+                    role: match role.0.to_lowercase().as_str() {
+                        "admin" => Permission::Admin,
+                        "consumer" => Permission::Read,
+                        "producer" => Permission::Write,
+                        _ => {
+                            return Err(BadRequest(StringError::new(format!(
+                                "invalid role {}",
+                                role.0
+                            ))))
+                        }
+                    },
+                    requestor: credential.0.to_owned(),
+                    reason: reason.0,
+                },
+            )
+            .await;
+        match resp {
+            registry_api::FeathrApiResponse::Unit => Ok(Json("OK".to_string())),
+            registry_api::FeathrApiResponse::Error(e) => Err(e.into()),
+            _ => Err(InternalServerError(StringError::new(
+                "Internal Server Error",
+            ))),
+        }
+    }
+
+    /// Add many user role mappings to a project at once, returns the result of each mapping
+    #[oai(
+        path = "/projects/:project/userroles/bulk",
+        method = "post",
+        tag = "ApiTags::Rbac"
+    )]
+    async fn add_user_roles(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Reason for the role mapping creation
+        reason: Query<String>,
+        /// User names and roles
+        roles: Json<Vec<UserRoleDef>>,
+    ) -> poem::Result<Json<Vec<UserRoleResult>>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Admin)
+            .await?;
+        let users = roles
+            .0
+            .into_iter()
+            .map(|r| Ok((r.user_name, parse_role(&r.role_name)?)))
+            .collect::<Result<Vec<_>, ApiError>>()?;
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::AddUserRoles {
+                    project_id_or_name: project.0,
+                    users,
+                    requestor: credential.0.to_owned(),
+                    reason: reason.0,
+                },
+            )
+            .await
+            .into_user_role_results()
+            .map(Json)
+    }
+
+    /// Export user role mappings of a project, the result can be imported into another project
+    #[oai(
+        path = "/projects/:project/userroles/export",
+        method = "get",
+        tag = "ApiTags::Rbac",
+        operation_id = "export_user_roles"
+    )]
+    async fn export_user_roles(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+    ) -> poem::Result<Json<Vec<UserRoleDef>>> {
+        data.0
+            .check_permission(credential.0, Some(&project), Permission::Admin)
+            .await?;
+        let roles = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetUserRoles {
+                    user: None,
+                    scope: Some(project.0),
+                    size: None,
+                    offset: None,
+                },
+            )
+            .await
+            .into_user_roles()?;
+        Ok(Json(roles.into_iter().map(UserRoleDef::from).collect()))
+    }
+
+    /// Import user role mappings exported from another project, returns the result of each mapping
+    #[oai(
+        path = "/projects/:project/userroles/import",
+        method = "post",
+        tag = "ApiTags::Rbac",
+        operation_id = "import_user_roles"
+    )]
+    async fn import_user_roles(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// Project name or id
+        project: Path<String>,
+        /// Reason for the role mapping creation, defaults to "Imported"
+        reason: Query<Option<String>>,
+        /// Exported user role mappings
+        roles: Json<Vec<UserRoleDef>>,
+    ) -> poem::Result<Json<Vec<UserRoleResult>>> {
+        self.add_user_roles(
+            credential,
+            data,
+            opt_seq,
+            project,
+            Query(reason.0.unwrap_or_else(|| "Imported".to_string())),
+            roles,
+        )
+        .await
+    }
+
+    /// Delete an user role mapping
+    #[oai(
+        path = "/users/:user/userroles/delete",
+        method = "delete",
+        tag = "ApiTags::Rbac"
+    )]
+    async fn delete_user_role(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        /// User name
+        user: Path<String>,
+        /// Scope of the role, can be a project name or "global"
+        project: Query<String>,
+        /// Role name
+        role: Query<String>,
+        /// Reason for the role mapping deletion
+        reason: Query<String>,
     ) -> poem::Result<Json<String>> {
         data.0
             .check_permission(credential.0, Some("global"), Permission::Admin)
@@ -1151,6 +2361,99 @@ impl FeathrApiV2 {
             ))),
         }
     }
+
+    /// Validate a complete project definition without changing anything in the registry,
+    /// returns all problems found, the definition is valid if the list is empty
+    #[oai(
+        path = "/validate/project",
+        method = "post",
+        tag = "ApiTags::Project",
+        operation_id = "validate_project"
+    )]
+    async fn validate_project(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        /// Project and all entities in it
+        bundle: Json<ProjectBundle>,
+    ) -> poem::Result<Json<Vec<BundleError>>> {
+        data.0
+            .check_permission(credential.0, None, Permission::Read)
+            .await?;
+        Ok(Json(
+            bundle
+                .0
+                .validate(&data.0.limits, data.0.name_pattern.as_ref()),
+        ))
+    }
+
+    /// Rebuild the full text search index of this node, returns the number of indexed entities
+    #[oai(path = "/admin/reindex", method = "post", tag = "ApiTags::Admin")]
+    async fn reindex(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+    ) -> poem::Result<Json<u64>> {
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .await?;
+        let docs = data
+            .0
+            .store
+            .state_machine
+            .write()
+            .await
+            .registry
+            .rebuild_fts()
+            .map_err(ApiError::from)?;
+        Ok(Json(docs))
+    }
+
+    /// Dump all live entities and edges as SQL insert statements, can be used to restore the database
+    #[oai(path = "/admin/dump.sql", method = "get", tag = "ApiTags::Admin")]
+    async fn dump_sql(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        /// SQL dialect of the statements, `ansi` for PostgreSQL and SQLite, `mysql` or `mssql`,
+        /// defaults to `ansi`
+        dialect: Query<Option<String>>,
+    ) -> poem::Result<PlainText<String>> {
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .await?;
+        let dialect: SqlDialect = match dialect.0 {
+            Some(d) => d.parse().map_err(ApiError::BadRequest)?,
+            None => Default::default(),
+        };
+        let sql = data
+            .0
+            .store
+            .state_machine
+            .read()
+            .await
+            .registry
+            .dump_sql(dialect);
+        Ok(PlainText(sql))
+    }
+
+    /// List entities that have been deleted
+    #[oai(path = "/admin/deleted", method = "get", tag = "ApiTags::Admin")]
+    async fn list_deleted(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .check_permission(credential.0, Some("global"), Permission::Admin)
+            .await?;
+        data.0
+            .request(opt_seq.0, FeathrApiRequest::GetDeletedEntities)
+            .await
+            .into_entities()
+            .map(Json)
+    }
 }
 
 fn parse_version<T>(v: T) -> Result<Option<u64>, ApiError>
@@ -1165,9 +2468,108 @@ where
     })?))
 }
 
+fn parse_role(role: &str) -> Result<Permission, ApiError> {
+    match role.to_lowercase().as_str() {
+        "admin" => Ok(Permission::Admin),
+        "consumer" => Ok(Permission::Read),
+        "producer" => Ok(Permission::Write),
+        _ => Err(ApiError::BadRequest(format!("invalid role {}", role))),
+    }
+}
+
+/**
+ * The existing entity if creating only if not exists is requested and the lookup finds one,
+ * it's a read so nothing goes into the Raft log
+ */
+async fn find_existing(
+    app: &RaftRegistryApp,
+    if_not_exists: Option<bool>,
+    lookup: FeathrApiRequest,
+) -> Option<CreationResponse> {
+    if !if_not_exists.unwrap_or_default() {
+        return None;
+    }
+    let entity = app.request(None, lookup).await.into_entity().ok()?;
+    Some(CreationResponse {
+        guid: entity.guid,
+        version: entity.version,
+        warnings: vec![],
+    })
+}
+
+/**
+ * 304 if any of the etags in `If-None-Match` matches the lineage, otherwise the lineage with its
+ * etag, a truncated lineage may miss entities the client has so it's always returned in full
+ */
+fn lineage_response(lineage: EntityLineage, if_none_match: Option<String>) -> LineageResponse {
+    let etag = lineage.etag();
+    let matched = !lineage.truncated
+        && if_none_match
+            .as_deref()
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().trim_start_matches("W/").trim_matches('"'))
+                    .any(|s| s == "*" || s == etag)
+            })
+            .unwrap_or(false);
+    if matched {
+        LineageResponse::NotModified
+    } else {
+        LineageResponse::Ok(Json(lineage), format!("\"{}\"", etag))
+    }
+}
+
+fn parse_etag(v: Option<String>) -> Result<Option<Uuid>, ApiError> {
+    match v.as_deref().map(|s| s.trim().trim_matches('"')) {
+        None | Some("*") => Ok(None),
+        Some(s) => {
+            Ok(Some(Uuid::parse_str(s).map_err(|_| {
+                ApiError::BadRequest(format!("Invalid etag {}", s))
+            })?))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_version;
+    use uuid::Uuid;
+
+    use chrono::{TimeZone, Utc};
+    use registry_api::{Entity, EntityAttributes, EntityLineage, EntityLink, ProjectAttributes};
+
+    use super::{lineage_response, parse_etag, parse_version, LineageResponse};
+
+    fn project(name: &str) -> Entity {
+        let id = Uuid::new_v4();
+        Entity {
+            guid: id.to_string(),
+            name: name.to_string(),
+            qualified_name: name.to_string(),
+            version: 0,
+            entity_type: registry_provider::EntityType::Project.into(),
+            status: "Active".to_string(),
+            display_text: name.to_string(),
+            labels: vec![],
+            attributes: EntityAttributes::Project(ProjectAttributes {
+                qualified_name: name.to_string(),
+                name: name.to_string(),
+                anchors: vec![],
+                sources: vec![],
+                anchor_features: vec![],
+                derived_features: vec![],
+                tags: [("a", "1"), ("b", "2"), ("c", "3")]
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            }),
+            created_by: Default::default(),
+            created_on: Utc::now(),
+            last_modified_by: Default::default(),
+            last_modified_ts: Utc::now().timestamp().to_string(),
+            etag: id.to_string(),
+            links: vec![],
+        }
+    }
 
     #[test]
     fn test_parse_version() {
@@ -1178,4 +2580,50 @@ mod tests {
         assert_eq!(parse_version("1").unwrap(), Some(1));
         assert_eq!(parse_version("42").unwrap(), Some(42));
     }
+
+    #[test]
+    fn test_parse_etag() {
+        let id = Uuid::new_v4();
+        assert!(parse_etag(None).unwrap().is_none());
+        assert!(parse_etag(Some("*".to_string())).unwrap().is_none());
+        assert!(parse_etag(Some("xyz".to_string())).is_err());
+        assert_eq!(parse_etag(Some(id.to_string())).unwrap(), Some(id));
+        assert_eq!(parse_etag(Some(format!("\"{}\"", id))).unwrap(), Some(id));
+    }
+
+    #[test]
+    fn test_lineage_etag() {
+        let entity = project("p1");
+        let lineage: EntityLineage = (vec![entity.clone()], vec![]).into();
+        let etag = lineage.etag();
+        assert_eq!(etag, lineage.clone().etag());
+
+        // Timestamps differ between replicas, they don't change the lineage
+        let mut replica = entity.clone();
+        replica.created_on = Utc.timestamp(1_600_000_000, 0);
+        replica.last_modified_ts = "1600000000".to_string();
+        let replica: EntityLineage = (vec![replica], vec![]).into();
+        assert_eq!(replica.etag(), etag);
+
+        // Links don't bump the entity etag but still change the lineage
+        let mut linked = entity;
+        linked.links.push(EntityLink {
+            title: "doc".to_string(),
+            url: "https://example.com".to_string(),
+        });
+        let changed: EntityLineage = (vec![linked], vec![]).into();
+        assert_ne!(changed.etag(), etag);
+
+        assert!(matches!(
+            lineage_response(lineage.clone(), Some(format!("\"{}\"", etag))),
+            LineageResponse::NotModified
+        ));
+        let mut truncated = lineage;
+        truncated.truncated = true;
+        assert_eq!(truncated.etag(), etag);
+        assert!(matches!(
+            lineage_response(truncated, Some(etag)),
+            LineageResponse::Ok(..)
+        ));
+    }
 }
@@ -0,0 +1,209 @@
+use poem::http::StatusCode;
+use poem_openapi::types::ToJSON;
+use registry_api::{ApiError, FeathrApiRequest, FeathrApiResponse};
+use registry_provider::{Credential, Permission};
+use tonic::{Request, Response, Status};
+
+use crate::RaftRegistryApp;
+
+pub mod pb {
+    tonic::include_proto!("feathr.registry.v1");
+}
+
+use pb::feathr_registry_server::{FeathrRegistry, FeathrRegistryServer};
+
+/**
+ * gRPC service exposing the core read APIs, requests are handled by the same `RaftRegistryApp::request` as REST API
+ */
+pub struct GrpcRegistryService {
+    app: RaftRegistryApp,
+}
+
+pub fn grpc_service(app: RaftRegistryApp) -> FeathrRegistryServer<GrpcRegistryService> {
+    FeathrRegistryServer::new(GrpcRegistryService { app })
+}
+
+impl GrpcRegistryService {
+    async fn check_permission(&self, resource: &str) -> Result<(), Status> {
+        // gRPC requests don't go through `RbacMiddleware`, so there is no credential to check
+        if !std::env::var("ENABLE_RBAC").unwrap_or_default().is_empty() {
+            return Err(Status::permission_denied(
+                "gRPC API is not available when RBAC is enabled",
+            ));
+        }
+        self.app
+            .check_permission(&Credential::RbacDisabled, Some(resource), Permission::Read)
+            .await
+            .map_err(|e| Status::new(http_status_to_code(e.status()), e.to_string()))
+    }
+
+    async fn request(
+        &self,
+        opt_seq: Option<u64>,
+        req: FeathrApiRequest,
+    ) -> Result<FeathrApiResponse, Status> {
+        match self.app.request(opt_seq, req).await {
+            FeathrApiResponse::Error(e) => Err(api_error_to_status(e)),
+            resp => Ok(resp),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl FeathrRegistry for GrpcRegistryService {
+    async fn get_project(
+        &self,
+        request: Request<pb::GetProjectRequest>,
+    ) -> Result<Response<pb::Entity>, Status> {
+        let request = request.into_inner();
+        self.check_permission(&request.project).await?;
+        match self
+            .request(
+                request.opt_seq,
+                FeathrApiRequest::GetProject {
+                    id_or_name: request.project,
+                },
+            )
+            .await?
+        {
+            FeathrApiResponse::Entity(e) => Ok(Response::new(e.into())),
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+
+    async fn get_feature(
+        &self,
+        request: Request<pb::GetFeatureRequest>,
+    ) -> Result<Response<pb::Entity>, Status> {
+        let request = request.into_inner();
+        self.check_permission(&request.feature).await?;
+        match self
+            .request(
+                request.opt_seq,
+                FeathrApiRequest::GetFeature {
+                    id_or_name: request.feature,
+                },
+            )
+            .await?
+        {
+            FeathrApiResponse::Entity(e) => {
+                self.app.record_feature_hit(&e);
+                Ok(Response::new(e.into()))
+            }
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+
+    async fn get_feature_lineage(
+        &self,
+        request: Request<pb::GetFeatureLineageRequest>,
+    ) -> Result<Response<pb::EntityLineage>, Status> {
+        let request = request.into_inner();
+        self.check_permission(&request.feature).await?;
+        match self
+            .request(
+                request.opt_seq,
+                FeathrApiRequest::GetFeatureLineage {
+                    id_or_name: request.feature.clone(),
+                    size: request.size.map(|s| s as usize),
+                    offset: None,
+                },
+            )
+            .await?
+        {
+            FeathrApiResponse::EntityLineage(l) => {
+                self.app.record_lineage_hit(&l, &request.feature);
+                Ok(Response::new(l.into()))
+            }
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+}
+
+impl From<registry_api::Entity> for pb::Entity {
+    fn from(v: registry_api::Entity) -> Self {
+        Self {
+            guid: v.guid,
+            name: v.name,
+            qualified_name: v.qualified_name,
+            version: v.version,
+            type_name: to_json_string(&v.entity_type),
+            status: v.status,
+            display_text: v.display_text,
+            labels: v.labels,
+            attributes_json: v
+                .attributes
+                .to_json()
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            created_by: v.created_by,
+            created_on: v.created_on.to_rfc3339(),
+            last_modified_by: v.last_modified_by,
+            last_modified_ts: v.last_modified().to_rfc3339(),
+            etag: v.etag,
+        }
+    }
+}
+
+impl From<registry_api::EntityLineage> for pb::EntityLineage {
+    fn from(v: registry_api::EntityLineage) -> Self {
+        Self {
+            guid_entity_map: v
+                .guid_entity_map
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+            relations: v
+                .relations
+                .into_iter()
+                .map(|r| pb::Relationship {
+                    relationship_type: to_json_string(&r.edge_type),
+                    from_entity_id: r.from,
+                    to_entity_id: r.to,
+                    attributes: r.attributes,
+                })
+                .collect(),
+            truncated: v.truncated,
+        }
+    }
+}
+
+/**
+ * Enums are rendered as the same strings as in REST API
+ */
+fn to_json_string<T: ToJSON>(v: &T) -> String {
+    v.to_json()
+        .and_then(|v| v.as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+fn api_error_to_status(e: ApiError) -> Status {
+    match e {
+        ApiError::NotFoundError(_) => Status::not_found(e.to_string()),
+        ApiError::Conflict(_) | ApiError::NameExists(_) => Status::already_exists(e.to_string()),
+        ApiError::DeleteInUse(_) | ApiError::CyclicDependency(_) => {
+            Status::failed_precondition(e.to_string())
+        }
+        ApiError::BadRequest(_) | ApiError::WrongEntityType(_) => {
+            Status::invalid_argument(e.to_string())
+        }
+        ApiError::Forbidden(_) => Status::permission_denied(e.to_string()),
+        ApiError::PreconditionFailed(_) => Status::failed_precondition(e.to_string()),
+        ApiError::MethodNotAllowed(_) => Status::unimplemented(e.to_string()),
+        ApiError::InternalError(_) => Status::internal(e.to_string()),
+        ApiError::ServiceUnavailable(_) => Status::unavailable(e.to_string()),
+    }
+}
+
+fn http_status_to_code(status: StatusCode) -> tonic::Code {
+    match status {
+        StatusCode::NOT_FOUND => tonic::Code::NotFound,
+        StatusCode::CONFLICT => tonic::Code::AlreadyExists,
+        StatusCode::BAD_REQUEST => tonic::Code::InvalidArgument,
+        StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::PRECONDITION_FAILED => tonic::Code::FailedPrecondition,
+        StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    }
+}
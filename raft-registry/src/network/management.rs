@@ -1,4 +1,7 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
 
 use openraft::{
     error::{CheckIsLeaderError, Infallible},
@@ -6,15 +9,21 @@ use openraft::{
     EntryPayload, Node, RaftMetrics,
 };
 use poem::{
-    get, handler, post,
+    get, handler,
+    http::HeaderMap,
+    post,
     web::{Data, Json, TypedHeader},
     IntoResponse, Route,
 };
 use poem_openapi::payload::PlainText;
 use registry_api::{ApiError, FeathrApiProvider, FeathrApiRequest, FeathrApiResponse};
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 
-use crate::{ManagementCode, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig};
+use crate::{
+    log_mutation, with_request_id, ManagementCode, RaftRegistryApp, RegistryNodeId,
+    RegistryTypeConfig, REQUEST_ID_HEADER_NAME,
+};
 
 #[handler]
 pub async fn add_learner(
@@ -63,7 +72,63 @@ pub async fn init(
     Ok(Json(res))
 }
 
-/// Get the latest metrics of the cluster
+/**
+ * Replication state of one node as seen by the leader
+ */
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationLag {
+    pub node_id: RegistryNodeId,
+    pub matched_index: Option<u64>,
+    pub lag: u64,
+}
+
+/**
+ * Raft metrics extended with the replication lag of each node
+ * The extra fields are ignored by clients only expecting `RaftMetrics`
+ */
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterMetrics {
+    #[serde(flatten)]
+    pub raft: RaftMetrics<RegistryTypeConfig>,
+    pub commit_index: Option<u64>,
+    pub replication_lag: Vec<ReplicationLag>,
+}
+
+impl From<RaftMetrics<RegistryTypeConfig>> for ClusterMetrics {
+    fn from(raft: RaftMetrics<RegistryTypeConfig>) -> Self {
+        // Log entries are applied right after being committed on the leader
+        let commit_index = raft.last_applied.map(|l| l.index);
+        let commit = commit_index.unwrap_or_default();
+        // Only the leader tracks replication, followers report nothing
+        let replication_lag = match &raft.leader_metrics {
+            Some(leader) => std::iter::once((raft.id, raft.last_log_index))
+                .chain(
+                    leader
+                        .data()
+                        .replication
+                        .iter()
+                        .filter(|(id, _)| **id != raft.id)
+                        .map(|(id, r)| (*id, r.matched().map(|l| l.index))),
+                )
+                .map(|(node_id, matched_index)| ReplicationLag {
+                    node_id,
+                    matched_index,
+                    lag: commit.saturating_sub(matched_index.unwrap_or_default()),
+                })
+                .collect(),
+            None => vec![],
+        };
+        Self {
+            raft,
+            commit_index,
+            replication_lag,
+        }
+    }
+}
+
+/// Get the latest metrics of the cluster, including the replication lag of each node
 #[handler]
 pub async fn metrics(
     app: Data<&RaftRegistryApp>,
@@ -72,7 +137,7 @@ pub async fn metrics(
     app.check_code(code.map(|c| c.0)).await?;
     let metrics = app.raft.metrics().borrow().clone();
 
-    let res: Result<RaftMetrics<RegistryTypeConfig>, Infallible> = Ok(metrics);
+    let res: Result<ClusterMetrics, Infallible> = Ok(metrics.into());
     Ok(Json(res))
 }
 
@@ -96,10 +161,10 @@ pub async fn handle_request(
     let value = app
         .store
         .state_machine
-        .write()
+        .read()
         .await
         .registry
-        .request(req.0)
+        .query(req.0)
         .await;
     let res: Result<FeathrApiResponse, Infallible> = Ok(value);
     Ok(Json(res))
@@ -112,28 +177,46 @@ pub async fn handle_request(
 pub async fn handle_leader_request(
     app: Data<&RaftRegistryApp>,
     code: Option<TypedHeader<ManagementCode>>,
+    headers: &HeaderMap,
     req: Json<FeathrApiRequest>,
 ) -> poem::Result<impl IntoResponse> {
     app.check_code(code.map(|c| c.0)).await?;
 
+    // The request id assigned by the node forwarding the request
+    let request_id = headers
+        .get(REQUEST_ID_HEADER_NAME)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    with_request_id(request_id, do_handle_leader_request(app.0, req.0)).await
+}
+
+async fn do_handle_leader_request(
+    app: &RaftRegistryApp,
+    req: FeathrApiRequest,
+) -> poem::Result<Json<Result<FeathrApiResponse, CheckIsLeaderError<RegistryNodeId>>>> {
     let ret = app.raft.is_leader().await;
     match ret {
         Ok(_) => {
             // Only writing requests need to go to raft state machine
-            let value = if req.0.is_writing_request() {
-                let request = ClientWriteRequest::new(EntryPayload::Normal(req.0));
-                app.raft
+            let value = if req.is_writing_request() {
+                let name = req.name();
+                let request = ClientWriteRequest::new(EntryPayload::Normal(req));
+                let value = app
+                    .raft
                     .client_write(request)
                     .await
                     .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?
-                    .data
+                    .data;
+                log_mutation(name, &value);
+                value
             } else {
                 app.store
                     .state_machine
-                    .write()
+                    .read()
                     .await
                     .registry
-                    .request(req.0)
+                    .query(req)
                     .await
             };
             let res: Result<FeathrApiResponse, CheckIsLeaderError<RegistryNodeId>> = Ok(value);
@@ -143,6 +226,96 @@ pub async fn handle_leader_request(
     }
 }
 
+/**
+ * Get counts of entities, edges and FTS docs on this node
+ */
+#[handler]
+pub async fn stats(
+    app: Data<&RaftRegistryApp>,
+    code: Option<TypedHeader<ManagementCode>>,
+) -> poem::Result<impl IntoResponse> {
+    app.check_code(code.map(|c| c.0)).await?;
+    let stats = app.store.state_machine.read().await.registry.get_stats();
+    Ok(Json(stats))
+}
+
+/**
+ * Log position covered by a snapshot
+ */
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResult {
+    pub snapshot_id: String,
+    pub index: u64,
+    pub term: u64,
+}
+
+/**
+ * Max time to wait for the triggered snapshot to be built
+ */
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/**
+ * Build a snapshot on this node now, e.g. before maintenance
+ * Raft builds one snapshot at a time, nothing is built if the current snapshot already covers
+ * the last applied log, the current snapshot is returned in both cases
+ */
+#[handler]
+pub async fn trigger_snapshot(
+    app: Data<&RaftRegistryApp>,
+    code: Option<TypedHeader<ManagementCode>>,
+) -> poem::Result<impl IntoResponse> {
+    app.check_code(code.map(|c| c.0)).await?;
+    let metrics = app.raft.metrics().borrow().clone();
+    let last_applied = metrics
+        .last_applied
+        .ok_or_else(|| ApiError::BadRequest("Nothing has been applied yet".to_string()))?;
+    if metrics.snapshot.map(|s| s.index) < Some(last_applied.index) {
+        app.raft
+            .trigger_snapshot()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+        app.raft
+            .wait(Some(SNAPSHOT_TIMEOUT))
+            .metrics(
+                |m| m.snapshot.map(|s| s.index) >= Some(last_applied.index),
+                "manual snapshot",
+            )
+            .await
+            .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    }
+    let meta = app
+        .store
+        .current_snapshot_meta()
+        .await
+        .ok_or_else(|| ApiError::InternalError("No snapshot has been built".to_string()))?;
+    Ok(Json(SnapshotResult {
+        snapshot_id: meta.snapshot_id,
+        index: meta.last_log_id.index,
+        term: meta.last_log_id.leader_id.term,
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachStorageResult {
+    pub attached: bool,
+}
+
+/**
+ * Attach the configured database to this node and write the current content into it
+ * `attached` is false if the database has already been attached
+ */
+#[handler]
+pub async fn attach_storage(
+    app: Data<&RaftRegistryApp>,
+    code: Option<TypedHeader<ManagementCode>>,
+) -> poem::Result<impl IntoResponse> {
+    app.check_code(code.map(|c| c.0)).await?;
+    let attached = app.attach_storage().await?;
+    Ok(Json(AttachStorageResult { attached }))
+}
+
 /**
  * Check if the program is still alive
  */
@@ -175,6 +348,9 @@ pub fn management_routes(route: Route) -> Route {
         .at("/change-membership", post(change_membership))
         .at("/init", post(init))
         .at("/metrics", get(metrics))
+        .at("/admin/stats", get(stats))
+        .at("/admin/attach-storage", post(attach_storage))
+        .at("/cluster/snapshot", post(trigger_snapshot))
         .at("/handle-request", post(handle_request))
         .at("/handle-leader-request", post(handle_leader_request))
         .at("/ping", get(liveness))
@@ -1,20 +1,26 @@
 mod sequencer;
 mod api_v2;
 mod api_v1;
+mod grpc;
 mod management;
 mod raft;
 mod raft_network_impl;
+mod request_id;
 
 pub use sequencer::RaftSequencer;
 pub use api_v1::FeathrApiV1;
 pub use api_v2::FeathrApiV2;
-pub use management::management_routes;
+pub use grpc::{grpc_service, pb as grpc_pb, GrpcRegistryService};
+pub use management::{management_routes, ClusterMetrics, ReplicationLag, SnapshotResult};
 use poem::{
     http::HeaderValue,
     web::headers::{Error, Header},
 };
 pub use raft::raft_routes;
 pub use raft_network_impl::RegistryNetwork;
+pub use request_id::{
+    current_request_id, current_requestor, with_request_id, RequestIdMiddleware,
+};
 use reqwest::header::HeaderName;
 
 /// The `Host` header.
@@ -23,6 +29,7 @@ pub struct ManagementCode(String);
 
 pub const MANAGEMENT_CODE_HEADER_NAME: &str = "x-registry-management-code";
 pub const OPT_SEQ_HEADER_NAME: &str = "x-registry-opt-seq";
+pub const REQUEST_ID_HEADER_NAME: &str = "x-request-id";
 
 static MANAGEMENT_CODE_HEADER: HeaderName = HeaderName::from_static(MANAGEMENT_CODE_HEADER_NAME);
 
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response};
+use registry_provider::Credential;
+use uuid::Uuid;
+
+use crate::REQUEST_ID_HEADER_NAME;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+    static REQUESTOR: String;
+}
+
+/**
+ * The id of the request being handled in the current task, if any
+ */
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.to_owned()).ok()
+}
+
+/**
+ * The caller of the request being handled in the current task, if any
+ */
+pub fn current_requestor() -> Option<String> {
+    REQUESTOR.try_with(|r| r.to_owned()).ok()
+}
+
+/**
+ * Run `f` with the request id, so it can be picked up by logging and forwarding
+ */
+pub async fn with_request_id<F>(id: String, f: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    REQUEST_ID.scope(id, f).await
+}
+
+/**
+ * Assigns an id to each request, or uses the one in the `x-request-id` header, and echoes it in the response
+ */
+pub struct RequestIdMiddleware;
+
+impl<E: Endpoint> Middleware<E> for RequestIdMiddleware {
+    type Output = RequestIdMiddlewareImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequestIdMiddlewareImpl { ep }
+    }
+}
+
+pub struct RequestIdMiddlewareImpl<E> {
+    ep: E,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for RequestIdMiddlewareImpl<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER_NAME)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let requestor = req
+            .extensions()
+            .get::<Credential>()
+            .map(|c| c.to_string())
+            .unwrap_or_default();
+        // Mutating requests are logged along with the id and the requestor by `log_mutation`
+        let resp = match REQUESTOR
+            .scope(requestor, with_request_id(id.clone(), self.ep.call(req)))
+            .await
+        {
+            Ok(resp) => resp.into_response(),
+            Err(e) => e.into_response(),
+        };
+        Ok(resp.with_header(REQUEST_ID_HEADER_NAME, id).into_response())
+    }
+}
@@ -1,6 +1,15 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
 use clap::Parser;
+use registry_api::{DefinitionLimits, NamePattern, PageLimits};
 use serde::Deserialize;
 use serde::Serialize;
+use sql_provider::TokenizerConfig;
+
+use super::SnapshotFormat;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Parser)]
 pub struct NodeConfig {
@@ -28,6 +37,104 @@ pub struct NodeConfig {
     )]
     pub journal_path: String,
 
+    /// Persist only the changes since the last full snapshot if less than this fraction of entities changed
+    #[clap(
+        long,
+        hide = true,
+        env = "RAFT_SNAPSHOT_DELTA_THRESHOLD",
+        default_value = "0.1"
+    )]
+    pub snapshot_delta_threshold: f64,
+
+    /// Format of the persisted snapshots, `json` or `bincode`, snapshots in either format can be restored
+    #[clap(long, env = "RAFT_SNAPSHOT_FORMAT", default_value = "json")]
+    #[serde(default)]
+    pub snapshot_format: SnapshotFormat,
+
+    /// Tokenizer used by full text search, `default` or `identifier`
+    #[clap(long, env = "FTS_TOKENIZER", default_value = "default")]
+    #[serde(default)]
+    pub fts_tokenizer: TokenizerConfig,
+
+    /// Max number of input features of a derived feature
+    #[clap(long, env = "MAX_FEATURE_INPUTS", default_value = "1000")]
+    #[serde(default = "default_max_feature_inputs")]
+    pub max_feature_inputs: usize,
+
+    /// Max number of tags of an entity
+    #[clap(long, env = "MAX_TAGS", default_value = "100")]
+    #[serde(default = "default_max_tags")]
+    pub max_tags: usize,
+
+    /// Max length in bytes of a transformation expression
+    #[clap(long, env = "MAX_EXPRESSION_LENGTH", default_value = "65536")]
+    #[serde(default = "default_max_expression_length")]
+    pub max_expression_length: usize,
+
+    /// Page size of search and list requests if it's not specified
+    #[clap(long, env = "DEFAULT_PAGE_SIZE", default_value = "100")]
+    #[serde(default = "default_page_size")]
+    pub default_page_size: usize,
+
+    /// Requested page size larger than this is clamped to it
+    #[clap(long, env = "MAX_PAGE_SIZE", default_value = "10000")]
+    #[serde(default = "default_max_page_size")]
+    pub max_page_size: usize,
+
+    /// Offset of paged lineage larger than this is clamped to it
+    #[clap(long, env = "MAX_LINEAGE_OFFSET", default_value = "100000")]
+    #[serde(default = "default_max_lineage_offset")]
+    pub max_lineage_offset: usize,
+
+    /// Regex the names of new entities must fully match, e.g. `[a-z][a-z0-9_]*`, no check if unset
+    #[clap(long, env = "NAME_PATTERN")]
+    #[serde(default)]
+    pub name_pattern: Option<String>,
+
+    /// Warn when creating entities in a project containing more entities than this, 0 means no warning
+    #[clap(long, env = "PROJECT_ENTITY_WARN_THRESHOLD", default_value = "0")]
+    #[serde(default)]
+    pub project_entity_warn_threshold: usize,
+
+    /// Warn when the value type of a new derived feature differs from the ones of its inputs
+    #[clap(long, env = "CHECK_DERIVED_TYPES")]
+    #[serde(default)]
+    pub check_derived_types: bool,
+
+    /// Tag keys that can't be changed or removed once they have a value, e.g. `cost_center`
+    #[clap(long = "protected-tag", env = "PROTECTED_TAGS", use_value_delimiter = true)]
+    #[serde(default)]
+    pub protected_tags: Vec<String>,
+
+    /// Tags added to every new project unless the key is already set, e.g. `env=prod`
+    #[clap(
+        long = "default-project-tag",
+        env = "DEFAULT_PROJECT_TAGS",
+        use_value_delimiter = true
+    )]
+    #[serde(default)]
+    pub default_project_tags: Vec<String>,
+
+    /// Lineage traversals running longer than this many milliseconds return partial results, 0 means no limit
+    #[clap(long, env = "TRAVERSAL_TIMEOUT_MS", default_value = "5000")]
+    #[serde(default = "default_traversal_timeout_ms")]
+    pub traversal_timeout_ms: u64,
+
+    /// Reads carrying `x-registry-opt-seq` wait this many milliseconds at most for the node to catch up
+    #[clap(long, env = "OPT_SEQ_WAIT_MS", default_value = "3000")]
+    #[serde(default = "default_opt_seq_wait_ms")]
+    pub opt_seq_wait_ms: u64,
+
+    /// Max number of entities kept in the read cache, 0 disables the cache
+    #[clap(long, env = "ENTITY_CACHE_SIZE", default_value = "1000")]
+    #[serde(default = "default_entity_cache_size")]
+    pub entity_cache_size: usize,
+
+    /// Refuse all updating requests, the node only serves reads
+    #[clap(long, env = "READ_ONLY")]
+    #[serde(default)]
+    pub read_only: bool,
+
     /// The secret to protect Raft management functions
     #[clap(long, hide = true, env = "RAFT_MANAGEMENT_CODE")]
     pub management_code: Option<String>,
@@ -36,3 +143,92 @@ pub struct NodeConfig {
     #[clap(flatten)]
     pub raft_config: openraft::Config,
 }
+
+impl NodeConfig {
+    pub fn definition_limits(&self) -> DefinitionLimits {
+        DefinitionLimits {
+            max_inputs: self.max_feature_inputs,
+            max_tags: self.max_tags,
+            max_expression_length: self.max_expression_length,
+        }
+    }
+
+    /**
+     * Compiled `name_pattern`, error if the pattern is not a valid regex
+     */
+    pub fn name_pattern(&self) -> Result<Option<NamePattern>, String> {
+        self.name_pattern.as_deref().map(str::parse).transpose()
+    }
+
+    pub fn default_project_tags(&self) -> Result<HashMap<String, String>, String> {
+        self.default_project_tags
+            .iter()
+            .map(|tag| {
+                tag.split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .ok_or_else(|| {
+                        format!("Invalid default project tag '{}', expected `key=value`", tag)
+                    })
+            })
+            .collect()
+    }
+
+    pub fn page_limits(&self) -> PageLimits {
+        PageLimits {
+            default_size: self.default_page_size,
+            max_size: self.max_page_size,
+            max_offset: self.max_lineage_offset,
+        }
+    }
+
+    pub fn protected_tag_keys(&self) -> HashSet<String> {
+        self.protected_tags.iter().cloned().collect()
+    }
+
+    pub fn traversal_timeout(&self) -> Option<Duration> {
+        match self.traversal_timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    pub fn opt_seq_wait(&self) -> Duration {
+        Duration::from_millis(self.opt_seq_wait_ms)
+    }
+}
+
+fn default_max_feature_inputs() -> usize {
+    DefinitionLimits::default().max_inputs
+}
+
+fn default_max_tags() -> usize {
+    DefinitionLimits::default().max_tags
+}
+
+fn default_max_expression_length() -> usize {
+    DefinitionLimits::default().max_expression_length
+}
+
+fn default_page_size() -> usize {
+    PageLimits::default().default_size
+}
+
+fn default_max_page_size() -> usize {
+    PageLimits::default().max_size
+}
+
+fn default_max_lineage_offset() -> usize {
+    PageLimits::default().max_offset
+}
+
+fn default_entity_cache_size() -> usize {
+    1000
+}
+
+fn default_traversal_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_opt_seq_wait_ms() -> u64 {
+    3000
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use openraft::{Entry, EntryPayload};
+use registry_api::{ApiError, Entity, EntityHistory, EntityHistoryEntry};
+use registry_provider::{EntityProperty, EntityType, RegistryProvider};
+use serde::{Deserialize, Serialize};
+use sql_provider::Registry;
+use uuid::Uuid;
+
+use crate::store::RegistryStore;
+use crate::RegistryTypeConfig;
+
+/// Log index of the last entry indexed into the history
+const HISTORY_APPLIED_KEY: &[u8] = b"applied";
+
+/// Earliest log index in the history, set when entries have been skipped by installing a snapshot
+const HISTORY_EARLIEST_KEY: &[u8] = b"earliest";
+
+/// The entity right after a change, keyed by the entity id and the log index in the history index
+#[derive(Serialize, Deserialize, Debug)]
+struct HistoryRecord {
+    /// Name of the request making the change, empty for the baseline taken from a snapshot
+    request: String,
+
+    /// Time of the request, entries written before requests carried the time don't have it
+    time: Option<DateTime<Utc>>,
+
+    /// None if the entity has been deleted
+    entity: Option<Entity>,
+}
+
+fn history_key(id: Uuid, seq: u64) -> Vec<u8> {
+    [id.as_bytes().as_slice(), &seq.to_be_bytes()].concat()
+}
+
+impl RegistryStore {
+    fn read_history_seq(&self, key: &[u8]) -> anyhow::Result<Option<u64>> {
+        Ok(match self.history.get(key)? {
+            Some(v) => Some(u64::from_be_bytes(v.as_ref().try_into()?)),
+            None => None,
+        })
+    }
+
+    /**
+     * Record entities changed by the log entry just applied, entries indexed already are skipped
+     * so replaying the log on restart doesn't duplicate them
+     */
+    pub(crate) fn index_history(
+        &self,
+        registry: &Registry<EntityProperty>,
+        entry: &Entry<RegistryTypeConfig>,
+    ) -> anyhow::Result<()> {
+        let seq = entry.log_id.index;
+        if self
+            .read_history_seq(HISTORY_APPLIED_KEY)?
+            .map(|applied| seq <= applied)
+            .unwrap_or_default()
+        {
+            return Ok(());
+        }
+        if let EntryPayload::Normal(req) = &entry.payload {
+            for id in registry.changed_ids_at(seq) {
+                let entity: Option<Entity> = registry.get_entity(id).ok().map(Into::into);
+                let key = history_key(id, seq);
+                let last = match self.history.range(history_key(id, 0)..key.clone()).next_back() {
+                    Some(res) => serde_json::from_slice::<HistoryRecord>(&res?.1)?.entity,
+                    None => None,
+                };
+                if last == entity {
+                    continue;
+                }
+                let record = HistoryRecord {
+                    request: req.name().to_string(),
+                    time: req.time(),
+                    entity,
+                };
+                self.history.insert(key, serde_json::to_vec(&record)?)?;
+            }
+        }
+        self.history
+            .insert(HISTORY_APPLIED_KEY, &seq.to_be_bytes())?;
+        Ok(())
+    }
+
+    /**
+     * Log entries up to `seq` covered by the installed snapshot have never been indexed if the
+     * history is behind, entities in the snapshot become the baseline of later changes
+     */
+    pub(crate) fn seed_history(
+        &self,
+        registry: &Registry<EntityProperty>,
+        seq: u64,
+    ) -> anyhow::Result<()> {
+        let applied = self.read_history_seq(HISTORY_APPLIED_KEY)?.unwrap_or_default();
+        if seq <= applied {
+            return Ok(());
+        }
+        tracing::info!("Log entries up to {} are not in the history, seeding it", seq);
+        let entity_types = [
+            EntityType::Project,
+            EntityType::Source,
+            EntityType::Anchor,
+            EntityType::AnchorFeature,
+            EntityType::DerivedFeature,
+        ];
+        for entity_type in entity_types {
+            let (entities, _) = registry.list_entities_by_type(entity_type, None, usize::MAX);
+            for e in entities {
+                let key = history_key(e.id, seq);
+                let record = HistoryRecord {
+                    request: Default::default(),
+                    time: None,
+                    entity: Some(e.into()),
+                };
+                self.history.insert(key, serde_json::to_vec(&record)?)?;
+            }
+        }
+        self.history
+            .insert(HISTORY_EARLIEST_KEY, &(seq + 1).to_be_bytes())?;
+        self.history
+            .insert(HISTORY_APPLIED_KEY, &seq.to_be_bytes())?;
+        Ok(())
+    }
+
+    /**
+     * Changes to the entity from the history index, `earliest_seq` is set if changes before it
+     * were covered by a snapshot installed before they could be indexed
+     */
+    pub fn entity_history(&self, id: Uuid) -> Result<EntityHistory, ApiError> {
+        let internal = |e: anyhow::Error| ApiError::InternalError(format!("{:?}", e));
+        let mut history = EntityHistory {
+            entries: vec![],
+            earliest_seq: self
+                .read_history_seq(HISTORY_EARLIEST_KEY)
+                .map_err(internal)?,
+        };
+        let mut current: Option<Entity> = None;
+        let mut found = false;
+        for res in self.history.scan_prefix(id.as_bytes()) {
+            let (key, val) = res.map_err(|e| internal(e.into()))?;
+            let seq = <[u8; 8]>::try_from(&key[16..])
+                .map(u64::from_be_bytes)
+                .map_err(|e| internal(e.into()))?;
+            let record: HistoryRecord =
+                serde_json::from_slice(&val).map_err(|e| internal(e.into()))?;
+            found = true;
+            if record.request.is_empty() {
+                current = record.entity;
+                continue;
+            }
+            let (timestamp, change) = match (&current, &record.entity) {
+                (None, None) => continue,
+                (Some(old), Some(new)) if old == new => continue,
+                (None, Some(new)) => (new.last_modified(), "created".to_string()),
+                // Deletion doesn't touch the entity, only the request has the time of it
+                (Some(old), None) => (old.last_modified(), "deleted".to_string()),
+                (Some(old), Some(new)) => {
+                    let fields: Vec<String> = old.diff(new).into_iter().map(|d| d.field).collect();
+                    let change = if fields.is_empty() {
+                        "touched".to_string()
+                    } else {
+                        format!("changed {}", fields.join(", "))
+                    };
+                    (new.last_modified(), change)
+                }
+            };
+            history.entries.push(EntityHistoryEntry {
+                seq,
+                timestamp: record.time.unwrap_or(timestamp),
+                change_summary: format!("{}: {}", record.request, change),
+            });
+            current = record.entity;
+        }
+        if !found {
+            return Err(ApiError::NotFoundError(format!("Entity {} not found", id)));
+        }
+        Ok(history)
+    }
+}
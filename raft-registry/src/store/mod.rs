@@ -1,5 +1,7 @@
 mod config;
+mod history;
 mod registry_store;
+mod snapshot_format;
 
 use std::{
     fmt::Debug,
@@ -16,16 +18,19 @@ use openraft::{
     RaftLogReader, RaftSnapshotBuilder, RaftStorage, SnapshotMeta, StateMachineChanges,
     StorageError, StorageIOError, Vote,
 };
-use registry_api::{FeathrApiProvider, FeathrApiResponse};
+use registry_api::{ApiError, FeathrApiProvider, FeathrApiResponse};
 use registry_provider::EntityProperty;
 use serde::{Deserialize, Serialize};
 use sled::{Db, IVec};
 use sql_provider::Registry;
 use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
 
-use crate::{RegistryNodeId, RegistryTypeConfig};
+use crate::{EntityCache, RegistryNodeId, RegistryTypeConfig};
+use registry_store::SnapshotDelta;
 
 pub use config::NodeConfig;
+pub use snapshot_format::{decode_state_machine, encode_state_machine, SnapshotFormat};
 
 #[derive(Debug)]
 pub struct RegistrySnapshot {
@@ -57,12 +62,24 @@ pub struct RegistryStore {
     /// The current granted vote.
     vote: sled::Tree,
 
+    /// Entities after each change, keyed by entity id and log index, see `history.rs`
+    history: sled::Tree,
+
     snapshot_idx: Arc<Mutex<u64>>,
 
+    /// Held while a snapshot is being built, so automatic and manual snapshots don't overlap
+    snapshot_lock: Mutex<()>,
+
     current_snapshot: RwLock<Option<RegistrySnapshot>>,
 
+    /// Log index of the last snapshot persisted in full, later snapshots can be persisted as deltas
+    last_full_snapshot: Mutex<Option<u64>>,
+
     config: NodeConfig,
 
+    /// Entities cached for reads, entries are invalidated when mutations to them are applied
+    pub entity_cache: Arc<EntityCache>,
+
     pub node_id: RegistryNodeId,
 }
 
@@ -88,23 +105,137 @@ impl RegistryStore {
 
         let vote = db.open_tree(format!("votes_{}", node_id)).unwrap();
 
+        let history = db
+            .open_tree(format!("entity_history_{}", node_id))
+            .unwrap();
+
         let current_snapshot = RwLock::new(None);
 
+        let mut state_machine = RegistryStateMachine::default();
+        if let Err(e) = state_machine
+            .registry
+            .set_fts_tokenizer(config.fts_tokenizer)
+        {
+            tracing::warn!("Failed to set FTS tokenizer, error: {:?}", e);
+        }
+        state_machine
+            .registry
+            .set_traversal_timeout(config.traversal_timeout());
+        state_machine
+            .registry
+            .set_protected_tags(config.protected_tag_keys());
+
+        let entity_cache = Arc::new(EntityCache::new(config.entity_cache_size));
+
         RegistryStore {
             last_purged_log_id: Default::default(),
             config,
+            entity_cache,
             node_id,
             log,
-            state_machine: Default::default(),
+            state_machine: RwLock::new(state_machine),
             vote,
+            history,
             snapshot_idx: Arc::new(Mutex::new(0)),
+            snapshot_lock: Default::default(),
             current_snapshot,
+            last_full_snapshot: Default::default(),
         }
     }
 
     pub fn get_management_code(&self) -> Option<String> {
         self.config.management_code.clone()
     }
+
+    /**
+     * Rebuild the state machine as it was right after the log entry `seq` had been applied,
+     * by replaying log entries on top of the latest snapshot taken at or before `seq`
+     */
+    pub async fn state_machine_at(&self, seq: u64) -> Result<RegistryStateMachine, ApiError> {
+        let last_applied = self
+            .state_machine
+            .read()
+            .await
+            .last_applied_log
+            .map(|l| l.index)
+            .unwrap_or_default();
+        if seq > last_applied {
+            return Err(ApiError::BadRequest(format!(
+                "Log index {} has not been applied yet, the last applied index is {}",
+                seq, last_applied
+            )));
+        }
+
+        let snapshot = match &*self.current_snapshot.read().await {
+            Some(snapshot) => Some(snapshot.data.clone()),
+            None => match self.read_snapshot_file().await {
+                Ok(data) => Some(data),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(ApiError::InternalError(format!("{:?}", e))),
+            },
+        };
+        let mut state_machine: RegistryStateMachine = match snapshot {
+            Some(data) => decode_state_machine(&data)
+                .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?,
+            None => Default::default(),
+        };
+        if state_machine
+            .last_applied_log
+            .map(|l| l.index > seq)
+            .unwrap_or_default()
+        {
+            // The snapshot is newer than `seq`, start over from the very beginning of the log
+            state_machine = Default::default();
+        }
+        // Replayed tag updates are checked the same way as they were applied
+        state_machine
+            .registry
+            .set_protected_tags(self.config.protected_tag_keys());
+        let base = state_machine
+            .last_applied_log
+            .map(|l| l.index)
+            .unwrap_or_default();
+
+        let entries = self
+            .log
+            .range(transform_range_bound(base + 1..=seq))
+            .map(|res| {
+                let (_, val) = res.map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+                serde_json::from_slice::<Entry<RegistryTypeConfig>>(&val)
+                    .map_err(|e| ApiError::InternalError(format!("{:?}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Log entries covered by a snapshot may have been purged
+        if base < seq && entries.first().map(|e| e.log_id.index) != Some(base + 1) {
+            return Err(ApiError::BadRequest(format!(
+                "History before log index {} is not available",
+                seq
+            )));
+        }
+
+        for entry in entries {
+            replay_entry(&mut state_machine, entry).await;
+        }
+        Ok(state_machine)
+    }
+}
+
+/**
+ * Apply the log entry to the state machine rebuilt for history lookups
+ */
+async fn replay_entry(state_machine: &mut RegistryStateMachine, entry: Entry<RegistryTypeConfig>) {
+    state_machine.last_applied_log = Some(entry.log_id);
+    state_machine.registry.set_seq(entry.log_id.index);
+    match entry.payload {
+        EntryPayload::Blank => (),
+        EntryPayload::Normal(req) => {
+            state_machine.registry.set_clock(req.time());
+            state_machine.registry.request(req).await;
+        }
+        EntryPayload::Membership(mem) => {
+            state_machine.last_membership = EffectiveMembership::new(Some(entry.log_id), mem);
+        }
+    };
 }
 
 //Store trait for restore things from snapshot and log
@@ -221,24 +352,37 @@ fn serialize_bound(v: &Bound<&u64>) -> Bound<IVec> {
     }
 }
 
-#[async_trait]
-impl RaftSnapshotBuilder<RegistryTypeConfig, Cursor<Vec<u8>>> for Arc<RegistryStore> {
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn build_snapshot(
-        &mut self,
+impl RegistryStore {
+    /**
+     * Meta of the latest snapshot built or installed on this node
+     */
+    pub async fn current_snapshot_meta(&self) -> Option<SnapshotMeta<RegistryNodeId>> {
+        self.current_snapshot
+            .read()
+            .await
+            .as_ref()
+            .map(|s| s.meta.clone())
+    }
+
+    /**
+     * Caller must hold `snapshot_lock`
+     */
+    async fn do_build_snapshot(
+        &self,
     ) -> Result<Snapshot<RegistryNodeId, Cursor<Vec<u8>>>, StorageError<RegistryNodeId>> {
         let (data, last_applied_log);
 
         {
             // Serialize the data of the state machine.
             let state_machine = self.state_machine.read().await;
-            data = serde_json::to_vec(&*state_machine).map_err(|e| {
-                StorageIOError::new(
-                    ErrorSubject::StateMachine,
-                    ErrorVerb::Read,
-                    AnyError::new(&e),
-                )
-            })?;
+            data = encode_state_machine(&state_machine, self.config.snapshot_format)
+                .map_err(|e| {
+                    StorageIOError::new(
+                        ErrorSubject::StateMachine,
+                        ErrorVerb::Read,
+                        AnyError::new(&e),
+                    )
+                })?;
 
             last_applied_log = state_machine.last_applied_log;
         }
@@ -277,7 +421,44 @@ impl RaftSnapshotBuilder<RegistryTypeConfig, Cursor<Vec<u8>>> for Arc<RegistrySt
             *current_snapshot = Some(snapshot);
         }
 
-        self.write_snapshot().await.unwrap();
+        // Persist only the delta if few entities changed since the last full snapshot
+        let delta = match *self.last_full_snapshot.lock().await {
+            Some(base) => {
+                let state_machine = self.state_machine.read().await;
+                match state_machine.registry.changed_fraction(base) {
+                    Some(f) if f < self.config.snapshot_delta_threshold => state_machine
+                        .registry
+                        .take_delta_since(base)
+                        .map(|delta| SnapshotDelta {
+                            last_applied_log: state_machine.last_applied_log,
+                            last_membership: state_machine.last_membership.clone(),
+                            delta,
+                        }),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        match delta {
+            Some(delta) => {
+                self.write_snapshot_delta(&meta.snapshot_id, &delta)
+                    .await
+                    .unwrap();
+            }
+            None => {
+                self.write_snapshot().await.unwrap();
+                // Deltas are taken against the previous full snapshot, they are useless now
+                if let Err(e) = self.prune_snapshot_deltas(None).await {
+                    tracing::warn!("Failed to remove snapshot deltas: {}", e);
+                }
+                *self.last_full_snapshot.lock().await = Some(last_applied_log.index);
+                self.state_machine
+                    .write()
+                    .await
+                    .registry
+                    .trim_changelog(last_applied_log.index);
+            }
+        }
 
         Ok(Snapshot {
             meta,
@@ -286,6 +467,17 @@ impl RaftSnapshotBuilder<RegistryTypeConfig, Cursor<Vec<u8>>> for Arc<RegistrySt
     }
 }
 
+#[async_trait]
+impl RaftSnapshotBuilder<RegistryTypeConfig, Cursor<Vec<u8>>> for Arc<RegistryStore> {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn build_snapshot(
+        &mut self,
+    ) -> Result<Snapshot<RegistryNodeId, Cursor<Vec<u8>>>, StorageError<RegistryNodeId>> {
+        let _guard = self.snapshot_lock.lock().await;
+        self.do_build_snapshot().await
+    }
+}
+
 #[async_trait]
 impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
     type SnapshotData = Cursor<Vec<u8>>;
@@ -411,17 +603,25 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
             tracing::debug!(%entry.log_id, "replicate to sm");
 
             sm.last_applied_log = Some(entry.log_id);
+            sm.registry.set_seq(entry.log_id.index);
 
             match entry.payload {
                 EntryPayload::Blank => res.push(FeathrApiResponse::Unit),
                 EntryPayload::Normal(ref req) => {
-                    res.push(sm.registry.request(req.to_owned()).await)
+                    sm.registry.set_clock(req.time());
+                    res.push(sm.registry.request(req.to_owned()).await);
+                    self.entity_cache
+                        .invalidate(sm.registry.changed_ids_at(entry.log_id.index));
                 }
                 EntryPayload::Membership(ref mem) => {
                     sm.last_membership = EffectiveMembership::new(Some(entry.log_id), mem.clone());
                     res.push(FeathrApiResponse::Unit)
                 }
             };
+            // The history is auxiliary, failing to index it must not stop the state machine
+            if let Err(e) = self.index_history(&sm.registry, entry) {
+                tracing::error!("Failed to index history of log {}, error: {:?}", entry.log_id, e);
+            }
         }
         Ok(res)
     }
@@ -456,15 +656,41 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
         // Update the state machine.
         {
             let updated_state_machine: RegistryStateMachine =
-                serde_json::from_slice(&new_snapshot.data).map_err(|e| {
+                decode_state_machine(&new_snapshot.data).map_err(|e| {
                     StorageIOError::new(
                         ErrorSubject::Snapshot(new_snapshot.meta.clone()),
                         ErrorVerb::Read,
                         AnyError::new(&e),
                     )
                 })?;
+            let mut updated_state_machine = updated_state_machine;
+            // FTS index is rebuilt on load, make sure it uses the configured tokenizer
+            updated_state_machine
+                .registry
+                .set_fts_tokenizer(self.config.fts_tokenizer)
+                .map_err(|e| {
+                    StorageIOError::new(
+                        ErrorSubject::Snapshot(new_snapshot.meta.clone()),
+                        ErrorVerb::Read,
+                        AnyError::new(&e),
+                    )
+                })?;
+            updated_state_machine
+                .registry
+                .set_traversal_timeout(self.config.traversal_timeout());
+            updated_state_machine
+                .registry
+                .set_protected_tags(self.config.protected_tag_keys());
+            if let Some(log_id) = updated_state_machine.last_applied_log {
+                if let Err(e) = self.seed_history(&updated_state_machine.registry, log_id.index) {
+                    tracing::error!("Failed to seed history at log {}, error: {:?}", log_id, e);
+                }
+            }
             let mut state_machine = self.state_machine.write().await;
             *state_machine = updated_state_machine;
+            self.entity_cache.clear();
+            // The changelog of the new state machine is empty, next snapshot must be a full one
+            *self.last_full_snapshot.lock().await = None;
         }
 
         // Update current snapshot.
@@ -497,10 +723,25 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
 
                 let data = match data {
                     Ok(c) => c,
-                    Err(_e) => return Ok(None),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                    // Starting without the snapshot would lose the changes purged from the log
+                    Err(e) => {
+                        return Err(StorageIOError::new(
+                            ErrorSubject::StateMachine,
+                            ErrorVerb::Read,
+                            AnyError::new(&e),
+                        )
+                        .into())
+                    }
                 };
 
-                let content: RegistryStateMachine = serde_json::from_slice(&data).unwrap();
+                let content: RegistryStateMachine = match decode_state_machine(&data) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        tracing::error!("Failed to decode the latest snapshot: {}", e);
+                        return Ok(None);
+                    }
+                };
 
                 let last_applied_log = content.last_applied_log.unwrap();
                 tracing::debug!(
@@ -8,13 +8,32 @@ use std::io::ErrorKind;
 use walkdir::WalkDir;
 
 use openraft::storage::Snapshot;
+use openraft::AnyError;
+use openraft::EffectiveMembership;
+use openraft::ErrorSubject;
+use openraft::ErrorVerb;
+use openraft::LogId;
 use openraft::SnapshotMeta;
 use openraft::StorageError;
+use openraft::StorageIOError;
+use registry_provider::EntityProperty;
+use serde::{Deserialize, Serialize};
+use sql_provider::RegistryDelta;
 
-use crate::store::RegistryStateMachine;
-use crate::store::RegistryStore;
+use crate::store::snapshot_format::{decode_snapshot_delta, encode_snapshot_delta};
+use crate::store::{decode_state_machine, encode_state_machine, RegistryStore};
 use crate::RegistryNodeId;
 
+/// Changes since the last full snapshot, persisted instead of the full state machine
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SnapshotDelta {
+    pub last_applied_log: Option<LogId<RegistryNodeId>>,
+
+    pub last_membership: EffectiveMembership<RegistryNodeId>,
+
+    pub delta: RegistryDelta<EntityProperty>,
+}
+
 #[derive(Debug)]
 pub struct RegistrySnapshot {
     pub meta: SnapshotMeta<RegistryNodeId>,
@@ -53,28 +72,98 @@ impl RegistryStore {
         Ok(())
     }
 
+    #[tracing::instrument(level = "debug", skip(self, delta))]
+    pub async fn write_snapshot_delta(
+        &self,
+        snapshot_id: &str,
+        delta: &SnapshotDelta,
+    ) -> io::Result<()> {
+        let file_name = format!(
+            "{}/{}+{}+{}.delta",
+            self.config.snapshot_path, self.config.instance_prefix, self.node_id, snapshot_id
+        );
+        tracing::debug!("write_snapshot_delta: [{:?}, +oo)", file_name);
+        let data = encode_snapshot_delta(delta, self.config.snapshot_format)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(file_name)
+            .await?;
+        file.write_all(data.as_slice()).await?;
+        // Only the latest delta is ever read, the older ones have been superseded
+        self.prune_snapshot_deltas(Some(snapshot_id)).await
+    }
+
+    /// Remove delta files of this node except the one of `keep`, e.g. after a full snapshot
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn prune_snapshot_deltas(&self, keep: Option<&str>) -> io::Result<()> {
+        let node_prefix = format!("{}+{}+", self.config.instance_prefix, self.node_id);
+        for entry in WalkDir::new(&self.config.snapshot_path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| !e.file_type().is_dir())
+        {
+            let f_name = entry.file_name().to_string_lossy().to_string();
+            let snapshot_id = match f_name
+                .strip_prefix(&node_prefix)
+                .and_then(|s| s.strip_suffix(".delta"))
+            {
+                Some(id) => id,
+                None => continue,
+            };
+            if Some(snapshot_id) != keep {
+                tracing::debug!("prune_snapshot_deltas: removing {:?}", f_name);
+                tokio::fs::remove_file(entry.path()).await?;
+            }
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn read_snapshot_file(&self) -> io::Result<Vec<u8>> {
-        let latest_file = match self.latest_snapshot_file().await {
+        let (latest_file, index) = match self.latest_file("bin", 0).await {
             Ok(file) => file,
             _ => return Err(Error::new(ErrorKind::NotFound, "No snapshot files")),
         };
         tracing::debug!("read_file: {}", latest_file);
 
-        let file = File::open(&latest_file).await;
-        let mut file = match file {
-            Ok(file) => file,
-            Err(e) => return Err(e),
-        };
-        let mut data = Vec::new();
-        file.read_to_end(&mut data).await?;
+        let data = read_file(&latest_file).await?;
 
-        Ok(data)
+        // Fold in the latest delta taken after the full snapshot, if any
+        let delta_file = match self.latest_file("delta", index + 1).await {
+            Ok((file, _)) => file,
+            _ => return Ok(data),
+        };
+        tracing::debug!("read_file: {}", delta_file);
+        let delta = decode_snapshot_delta(&read_file(&delta_file).await?)?;
+        if delta.delta.since != index {
+            tracing::warn!("Snapshot delta '{}' doesn't match the full snapshot", delta_file);
+            return Ok(data);
+        }
+        let mut state_machine = decode_state_machine(&data)?;
+        if let Err(e) = state_machine.registry.apply_delta(delta.delta).await {
+            // The log has been purged up to the delta, the full snapshot alone would lose changes
+            tracing::error!("Failed to apply snapshot delta '{}': {}", delta_file, e);
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to apply snapshot delta '{}': {}", delta_file, e),
+            ));
+        }
+        state_machine.last_applied_log = delta.last_applied_log;
+        state_machine.last_membership = delta.last_membership;
+        encode_state_machine(&state_machine, self.config.snapshot_format)
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn latest_snapshot_file(&self) -> Result<String, ()> {
+        self.latest_file("bin", 0).await.map(|(file, _)| file)
+    }
+
+    /// Find the file with the specified extension and the largest log index, which is at least `min_index`
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn latest_file(&self, extension: &str, min_index: u64) -> Result<(String, u64), ()> {
         let mut max_index: u64 = 0;
+        let mut found = false;
         let mut latest_snapshot_file: String = String::from("");
 
         for entry in WalkDir::new(&self.config.snapshot_path)
@@ -86,7 +175,7 @@ impl RegistryStore {
             let mut s1 = f_name.split('.');
             let file = s1.next();
             let ext = s1.next();
-            if ext.unwrap() == "bin" {
+            if ext == Some(extension) {
                 tracing::trace!("file: {:?}", file);
                 let mut s3 = file.unwrap().split('+');
                 let prefix = s3.next();
@@ -114,16 +203,17 @@ impl RegistryStore {
                 let _snapshot_id = s2.next();
 
                 let index = index.unwrap().parse().unwrap();
-                if index > max_index {
+                if index >= min_index && (!found || index > max_index) {
+                    found = true;
                     max_index = index;
                     latest_snapshot_file = f_name;
                 }
             }
         }
-        if !latest_snapshot_file.is_empty() {
-            Ok(format!(
-                "{}/{}",
-                self.config.snapshot_path, latest_snapshot_file
+        if found {
+            Ok((
+                format!("{}/{}", self.config.snapshot_path, latest_snapshot_file),
+                max_index,
             ))
         } else {
             Err(())
@@ -151,10 +241,25 @@ impl RegistryStore {
 
                 let data = match data {
                     Ok(c) => c,
-                    Err(_e) => return Ok(None),
+                    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+                    // Starting without the snapshot would lose the changes purged from the log
+                    Err(e) => {
+                        return Err(StorageIOError::new(
+                            ErrorSubject::StateMachine,
+                            ErrorVerb::Read,
+                            AnyError::new(&e),
+                        )
+                        .into())
+                    }
                 };
 
-                let content: RegistryStateMachine = serde_json::from_slice(&data).unwrap();
+                let content = match decode_state_machine(&data) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        tracing::error!("Failed to decode the latest snapshot: {}", e);
+                        return Ok(None);
+                    }
+                };
 
                 let last_applied_log = content.last_applied_log.unwrap();
                 tracing::debug!(
@@ -191,3 +296,10 @@ impl RegistryStore {
         }
     }
 }
+
+async fn read_file(path: &str) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path).await?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).await?;
+    Ok(data)
+}
@@ -0,0 +1,228 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
+
+use registry_provider::EntityProperty;
+use serde::{Deserialize, Serialize};
+use sql_provider::{Registry, RegistryDelta};
+
+use super::registry_store::SnapshotDelta;
+use crate::store::RegistryStateMachine;
+
+/**
+ * Leading bytes of a bincode snapshot, JSON snapshots always start with `{` so both can be told apart on restore
+ */
+const BINCODE_MAGIC: &[u8] = b"FRB\x01";
+
+/**
+ * Format of the state machine in snapshots
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotFormat {
+    Json,
+    /**
+     * Compact binary format, much smaller and faster for big registries
+     */
+    Bincode,
+}
+
+impl Default for SnapshotFormat {
+    fn default() -> Self {
+        SnapshotFormat::Json
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(SnapshotFormat::Json),
+            "bincode" => Ok(SnapshotFormat::Bincode),
+            _ => Err(format!(
+                "unknown snapshot format '{}', allowed values are json, bincode",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryStateMachine {
+    // Last applied log and membership in JSON, they're tiny compared to the registry
+    meta: Vec<u8>,
+    registry: Vec<u8>,
+}
+
+fn invalid_data(e: bincode::Error) -> Error {
+    Error::new(ErrorKind::InvalidData, e)
+}
+
+pub fn encode_state_machine(
+    state_machine: &RegistryStateMachine,
+    format: SnapshotFormat,
+) -> Result<Vec<u8>> {
+    match format {
+        SnapshotFormat::Json => Ok(serde_json::to_vec(state_machine)?),
+        SnapshotFormat::Bincode => {
+            let content = BinaryStateMachine {
+                meta: serde_json::to_vec(&(
+                    &state_machine.last_applied_log,
+                    &state_machine.last_membership,
+                ))?,
+                registry: state_machine.registry.to_bincode().map_err(invalid_data)?,
+            };
+            let mut data = BINCODE_MAGIC.to_vec();
+            data.extend(bincode::serialize(&content).map_err(invalid_data)?);
+            Ok(data)
+        }
+    }
+}
+
+/**
+ * Decode the state machine from a snapshot in either format
+ */
+pub fn decode_state_machine(data: &[u8]) -> Result<RegistryStateMachine> {
+    match data.strip_prefix(BINCODE_MAGIC) {
+        Some(data) => {
+            let content: BinaryStateMachine = bincode::deserialize(data).map_err(invalid_data)?;
+            let (last_applied_log, last_membership) = serde_json::from_slice(&content.meta)?;
+            Ok(RegistryStateMachine {
+                last_applied_log,
+                last_membership,
+                registry: Registry::<EntityProperty>::from_bincode(&content.registry)
+                    .map_err(invalid_data)?,
+            })
+        }
+        None => Ok(serde_json::from_slice(data)?),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinarySnapshotDelta {
+    meta: Vec<u8>,
+    delta: Vec<u8>,
+}
+
+pub fn encode_snapshot_delta(delta: &SnapshotDelta, format: SnapshotFormat) -> Result<Vec<u8>> {
+    match format {
+        SnapshotFormat::Json => Ok(serde_json::to_vec(delta)?),
+        SnapshotFormat::Bincode => {
+            let content = BinarySnapshotDelta {
+                meta: serde_json::to_vec(&(&delta.last_applied_log, &delta.last_membership))?,
+                delta: delta.delta.to_bincode().map_err(invalid_data)?,
+            };
+            let mut data = BINCODE_MAGIC.to_vec();
+            data.extend(bincode::serialize(&content).map_err(invalid_data)?);
+            Ok(data)
+        }
+    }
+}
+
+/**
+ * Decode the snapshot delta in either format
+ */
+pub fn decode_snapshot_delta(data: &[u8]) -> Result<SnapshotDelta> {
+    match data.strip_prefix(BINCODE_MAGIC) {
+        Some(data) => {
+            let content: BinarySnapshotDelta = bincode::deserialize(data).map_err(invalid_data)?;
+            let (last_applied_log, last_membership) = serde_json::from_slice(&content.meta)?;
+            Ok(SnapshotDelta {
+                last_applied_log,
+                last_membership,
+                delta: RegistryDelta::<EntityProperty>::from_bincode(&content.delta)
+                    .map_err(invalid_data)?,
+            })
+        }
+        None => Ok(serde_json::from_slice(data)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use registry_api::{FeathrApiProvider, FeathrApiRequest, ProjectDef};
+    use registry_provider::RegistryProvider;
+    use uuid::Uuid;
+
+    use super::*;
+
+    async fn state_machine() -> RegistryStateMachine {
+        let mut state_machine = RegistryStateMachine::default();
+        // Changes at seq 0 are not in deltas
+        state_machine.registry.set_seq(1);
+        for name in ["p1", "p2"] {
+            state_machine
+                .registry
+                .request(FeathrApiRequest::CreateProject {
+                    definition: ProjectDef {
+                        id: Uuid::new_v4().to_string(),
+                        name: name.to_string(),
+                        qualified_name: name.to_string(),
+                        tags: Default::default(),
+                        created_by: Default::default(),
+                    },
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+        }
+        state_machine
+    }
+
+    fn project_names(state_machine: &RegistryStateMachine) -> Vec<String> {
+        let mut names: Vec<String> = state_machine
+            .registry
+            .get_entry_points()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn snapshot_formats() {
+        let state_machine = state_machine().await;
+        for format in [SnapshotFormat::Json, SnapshotFormat::Bincode] {
+            let data = encode_state_machine(&state_machine, format).unwrap();
+            assert_eq!(
+                data.starts_with(BINCODE_MAGIC),
+                format == SnapshotFormat::Bincode
+            );
+            let restored = decode_state_machine(&data).unwrap();
+            assert_eq!(project_names(&restored), vec!["p1", "p2"]);
+            assert_eq!(
+                serde_json::to_value(&restored.registry).unwrap(),
+                serde_json::to_value(&state_machine.registry).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_delta_formats() {
+        let state_machine = state_machine().await;
+        for format in [SnapshotFormat::Json, SnapshotFormat::Bincode] {
+            let delta = SnapshotDelta {
+                last_applied_log: state_machine.last_applied_log,
+                last_membership: state_machine.last_membership.clone(),
+                delta: state_machine.registry.take_delta_since(0).unwrap(),
+            };
+            let data = encode_snapshot_delta(&delta, format).unwrap();
+            assert_eq!(
+                data.starts_with(BINCODE_MAGIC),
+                format == SnapshotFormat::Bincode
+            );
+            let mut restored = RegistryStateMachine::default();
+            restored
+                .registry
+                .apply_delta(decode_snapshot_delta(&data).unwrap().delta)
+                .await
+                .unwrap();
+            assert_eq!(project_names(&restored), vec!["p1", "p2"]);
+        }
+    }
+}
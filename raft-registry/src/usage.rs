@@ -0,0 +1,48 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+use uuid::Uuid;
+
+/**
+ * Read hits of features served by this node since it started
+ * Counters are per node and kept in memory only, they are not replicated and reset on restart
+ */
+#[derive(Debug, Default)]
+pub struct FeatureUsage {
+    hits: RwLock<HashMap<Uuid, AtomicU64>>,
+}
+
+impl FeatureUsage {
+    pub fn hit(&self, id: Uuid) {
+        // Existing counters only need the read guard
+        if let Some(counter) = self
+            .hits
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+        {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.hits
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(id)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, id: Uuid) -> u64 {
+        self.hits
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+}
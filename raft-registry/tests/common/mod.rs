@@ -0,0 +1,45 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use clap::Parser;
+use raft_registry::{NodeConfig, RaftRegistryApp};
+use uuid::Uuid;
+
+/**
+ * Config of a test node keeping its data in a new temp dir named after `name`, `args` are appended
+ * to the command line
+ */
+pub fn node_config(name: &str, args: &[&str]) -> NodeConfig {
+    let dir = std::env::temp_dir().join(format!("feathr-registry-{}-{}", name, Uuid::new_v4()));
+    let dir = dir.to_string_lossy().to_string();
+    let snapshot_path = format!("{}/snapshot", dir);
+    let journal_path = format!("{}/journal", dir);
+    NodeConfig::parse_from(
+        [
+            "test",
+            "--snapshot-path",
+            &snapshot_path,
+            "--journal-path",
+            &journal_path,
+        ]
+        .into_iter()
+        .chain(args.iter().copied()),
+    )
+}
+
+/**
+ * Start a single node cluster with the config of `node_config`
+ */
+pub async fn start_app(name: &str, args: &[&str]) -> RaftRegistryApp {
+    let app = RaftRegistryApp::new(1, "127.0.0.1:0".to_string(), node_config(name, args)).await;
+    app.init().await.unwrap();
+    // Wait until the single node cluster elected the leader
+    for _ in 0..100 {
+        if app.raft.is_leader().await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    app
+}
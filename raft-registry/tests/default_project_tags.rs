@@ -0,0 +1,60 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn default_tags_on_new_projects() {
+    let app = common::start_app(
+        "default-project-tags",
+        &[
+            "--default-project-tag",
+            "env=prod",
+            "--default-project-tag",
+            "team=registry",
+        ],
+    )
+    .await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli.get("/api/v2/projects/p1").send().await;
+    resp.assert_status_is_ok();
+    let project: serde_json::Value = resp.json().await.value().deserialize();
+    assert_eq!(project["attributes"]["tags"]["env"], "prod");
+    assert_eq!(project["attributes"]["tags"]["team"], "registry");
+
+    // Explicitly set keys are kept
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p2", "tags": { "env": "dev" } }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli.get("/api/v2/projects/p2").send().await;
+    resp.assert_status_is_ok();
+    let project: serde_json::Value = resp.json().await.value().deserialize();
+    assert_eq!(project["attributes"]["tags"]["env"], "dev");
+    assert_eq!(project["attributes"]["tags"]["team"], "registry");
+
+    // Other entities don't get the defaults
+    cli.post("/api/v2/projects/p1/anchors")
+        .body_json(&serde_json::json!({ "name": "a1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli.get("/api/v2/projects/p1/anchors/a1").send().await;
+    resp.assert_status_is_ok();
+    let anchor: serde_json::Value = resp.json().await.value().deserialize();
+    assert!(anchor["attributes"]["tags"].get("env").is_none());
+}
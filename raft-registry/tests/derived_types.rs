@@ -0,0 +1,81 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+fn feature_def(name: &str, val_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": name,
+        "featureType": {
+            "type": "TENSOR",
+            "tensorCategory": "DENSE",
+            "dimensionType": [],
+            "valType": val_type,
+        },
+        "transformation": {
+            "transformExpr": "x + 1",
+        },
+        "key": [{
+            "keyColumn": "user_id",
+            "keyColumnType": "LONG",
+        }],
+    })
+}
+
+#[tokio::test]
+async fn warn_on_mismatched_input_types() {
+    let app = common::start_app("derived-types", &["--check-derived-types"]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    cli.post("/api/v2/projects/p1/anchors")
+        .body_json(&serde_json::json!({ "name": "a1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli
+        .post("/api/v2/projects/p1/anchors/a1/features")
+        .body_json(&feature_def("f1", "FLOAT"))
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let created: serde_json::Value = resp.json().await.value().deserialize();
+    let f1 = created["guid"].as_str().unwrap().to_string();
+
+    // Same type as the input, no warning
+    let mut df1 = feature_def("df1", "FLOAT");
+    df1["inputAnchorFeatures"] = serde_json::json!([f1]);
+    let resp = cli
+        .post("/api/v2/projects/p1/derivedfeatures")
+        .body_json(&df1)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let created: serde_json::Value = resp.json().await.value().deserialize();
+    assert!(created.get("warnings").is_none());
+
+    // INT output from a FLOAT input is created with a warning
+    let mut df2 = feature_def("df2", "INT");
+    df2["inputAnchorFeatures"] = serde_json::json!([f1]);
+    let resp = cli
+        .post("/api/v2/projects/p1/derivedfeatures")
+        .body_json(&df2)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let created: serde_json::Value = resp.json().await.value().deserialize();
+    let warnings = created["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("p1__a1__f1"));
+}
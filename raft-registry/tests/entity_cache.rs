@@ -0,0 +1,165 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RaftRegistryApp, RbacMiddleware};
+use registry_api::{AnchorDef, EntityAttributes, FeathrApiRequest, ProjectDef};
+use registry_provider::RegistryProvider;
+use uuid::Uuid;
+
+async fn register_feature(app: &RaftRegistryApp) -> Uuid {
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: "p1__a1".to_string(),
+                source_id: Default::default(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let definition = serde_json::from_value(serde_json::json!({
+        "id": Uuid::new_v4().to_string(),
+        "name": "f1",
+        "qualified_name": "p1__a1__f1",
+        "feature_type": {
+            "type_": "TENSOR",
+            "tensor_category": "DENSE",
+            "dimension_type": [],
+            "val_type": "INT",
+        },
+        "transformation": {
+            "transform_expr": "x + 1",
+        },
+        "key": [{
+            "key_column": "user_id",
+            "key_column_type": "LONG",
+        }],
+        "tags": {},
+        "created_by": "alice",
+    }))
+    .unwrap();
+    let (id, _) = app
+        .request(
+            None,
+            FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition,
+                time: None,
+            },
+        )
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    id
+}
+
+#[tokio::test]
+async fn cached_feature_is_served() {
+    let app = common::start_app("cache", &[]).await;
+    let id = register_feature(&app).await;
+    assert!(!app.entity_cache.contains(id));
+
+    let get = FeathrApiRequest::GetFeature {
+        id_or_name: id.to_string(),
+    };
+    let entity = app.request(None, get.clone()).await.into_entity().unwrap();
+    assert!(app.entity_cache.contains(id));
+    let cached = app.request(None, get).await.into_entity().unwrap();
+    assert_eq!(cached, entity);
+}
+
+#[tokio::test]
+async fn update_invalidates_cached_feature() {
+    let app = common::start_app("cache", &[]).await;
+    let id = register_feature(&app).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.get(format!("/api/v2/features/{}", id))
+        .send()
+        .await
+        .assert_status_is_ok();
+    assert!(app.entity_cache.contains(id));
+
+    cli.patch(format!("/api/v2/entities/{}/tags", id))
+        .body_json(&serde_json::json!({ "team": "t1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    assert!(!app.entity_cache.contains(id));
+
+    let entity = app
+        .request(
+            None,
+            FeathrApiRequest::GetFeature {
+                id_or_name: id.to_string(),
+            },
+        )
+        .await
+        .into_entity()
+        .unwrap();
+    match entity.attributes {
+        EntityAttributes::AnchorFeature(attr) => assert_eq!(attr.tags["team"], "t1"),
+        _ => panic!("Not an anchor feature"),
+    }
+}
+
+#[tokio::test]
+async fn delete_invalidates_cached_feature() {
+    let app = common::start_app("cache", &[]).await;
+    let id = register_feature(&app).await;
+    let get = FeathrApiRequest::GetFeature {
+        id_or_name: id.to_string(),
+    };
+    app.request(None, get.clone()).await.into_entity().unwrap();
+    assert!(app.entity_cache.contains(id));
+
+    {
+        // Delete the way the state machine applies a mutation at the next log index
+        let mut sm = app.store.state_machine.write().await;
+        let seq = sm.last_applied_log.unwrap().index + 1;
+        sm.registry.set_seq(seq);
+        sm.registry.delete_entity_by_id(id).await.unwrap();
+        let changed = sm.registry.changed_ids_at(seq);
+        // The containers lost a member, their cached copies are stale as well
+        for name in ["p1", "p1__a1"] {
+            assert!(changed.contains(&sm.registry.get_entity_id(name).unwrap()));
+        }
+        app.entity_cache.invalidate(changed);
+    }
+    assert!(!app.entity_cache.contains(id));
+    assert!(app.request(None, get).await.into_entity().is_err());
+    assert!(!app.entity_cache.contains(id));
+}
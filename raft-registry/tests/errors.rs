@@ -0,0 +1,42 @@
+mod common;
+
+use poem::{http::StatusCode, test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn duplicate_name_is_conflict() {
+    let app = common::start_app("errors", &[]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    for name in ["p1", "p2"] {
+        cli.post("/api/v2/projects")
+            .body_json(&serde_json::json!({ "name": name }))
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+
+    // Cloning into an existing project name
+    let resp = cli
+        .post("/api/v2/projects/p1/clone")
+        .query("name", &"p2")
+        .send()
+        .await;
+    resp.assert_status(StatusCode::CONFLICT);
+    let body: serde_json::Value = resp.0.into_body().into_json().await.unwrap();
+    assert_eq!(body["code"], "NAME_EXISTS");
+    assert!(!body["message"].as_str().unwrap_or_default().is_empty());
+
+    // Missing entities are reported as 404
+    let resp = cli.get("/api/v2/projects/p3").send().await;
+    resp.assert_status(StatusCode::NOT_FOUND);
+    let body: serde_json::Value = resp.0.into_body().into_json().await.unwrap();
+    assert_eq!(body["code"], "ENTITY_NOT_FOUND");
+}
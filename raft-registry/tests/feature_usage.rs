@@ -0,0 +1,122 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV1, FeathrApiV2, RaftRegistryApp, RbacMiddleware};
+use registry_api::{AnchorDef, FeathrApiRequest, ProjectDef};
+use uuid::Uuid;
+
+async fn register_feature(app: &RaftRegistryApp) {
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: "p1__a1".to_string(),
+                source_id: Default::default(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let definition = serde_json::from_value(serde_json::json!({
+        "id": Uuid::new_v4().to_string(),
+        "name": "f1",
+        "qualified_name": "p1__a1__f1",
+        "feature_type": {
+            "type_": "TENSOR",
+            "tensor_category": "DENSE",
+            "dimension_type": [],
+            "val_type": "INT",
+        },
+        "transformation": {
+            "transform_expr": "x + 1",
+        },
+        "key": [{
+            "key_column": "user_id",
+            "key_column_type": "LONG",
+        }],
+        "tags": {},
+        "created_by": "alice",
+    }))
+    .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition,
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+}
+
+#[tokio::test]
+async fn feature_reads_are_counted() {
+    let app = common::start_app("usage", &[]).await;
+    register_feature(&app).await;
+    let api_v1 = OpenApiService::new(FeathrApiV1, "test", "test");
+    let api_v2 = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v1", api_v1)
+            .nest("/api/v2", api_v2)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    let usage = || async {
+        let resp = cli.get("/api/v2/features/p1__a1__f1/usage").send().await;
+        resp.assert_status_is_ok();
+        resp.0.into_body().into_json::<u64>().await.unwrap()
+    };
+    assert_eq!(usage().await, 0);
+
+    let resp = cli.get("/api/v2/features/p1__a1__f1").send().await;
+    resp.assert_status_is_ok();
+    let entity: serde_json::Value = resp.0.into_body().into_json().await.unwrap();
+    let id = entity["guid"].as_str().unwrap().to_string();
+    // Reading by id and by name counts for the same feature
+    cli.get(format!("/api/v2/features/{}", id))
+        .send()
+        .await
+        .assert_status_is_ok();
+    assert_eq!(usage().await, 2);
+
+    // Reads through API v1 count as well
+    cli.get("/api/v1/features/p1__a1__f1")
+        .send()
+        .await
+        .assert_status_is_ok();
+    cli.get("/api/v1/features/p1__a1__f1/lineage")
+        .send()
+        .await
+        .assert_status_is_ok();
+    assert_eq!(usage().await, 4);
+}
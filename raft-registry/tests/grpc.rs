@@ -0,0 +1,139 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{
+    grpc_pb::{feathr_registry_client::FeathrRegistryClient, GetFeatureRequest},
+    grpc_service, FeathrApiV2, RaftRegistryApp, RbacMiddleware,
+};
+use registry_api::{AnchorDef, FeathrApiRequest, ProjectDef};
+use tokio_stream::wrappers::TcpListenerStream;
+use uuid::Uuid;
+
+async fn register_feature(app: &RaftRegistryApp) {
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: "p1__a1".to_string(),
+                source_id: Default::default(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let definition = serde_json::from_value(serde_json::json!({
+        "id": Uuid::new_v4().to_string(),
+        "name": "f1",
+        "qualified_name": "p1__a1__f1",
+        "feature_type": {
+            "type_": "TENSOR",
+            "tensor_category": "DENSE",
+            "dimension_type": [],
+            "val_type": "INT",
+        },
+        "transformation": {
+            "transform_expr": "x + 1",
+        },
+        "key": [{
+            "key_column": "user_id",
+            "key_column_type": "LONG",
+        }],
+        "tags": {},
+        "created_by": "alice",
+    }))
+    .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition,
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+}
+
+#[tokio::test]
+async fn grpc_matches_rest() {
+    let app = common::start_app("grpc", &[]).await;
+    register_feature(&app).await;
+
+    // Fetch the feature via REST API
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let rest = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+    let resp = rest.get("/api/v2/features/p1__a1__f1").send().await;
+    resp.assert_status_is_ok();
+    let rest_feature: serde_json::Value = resp.0.into_body().into_json().await.unwrap();
+
+    // Fetch the same feature via gRPC
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(grpc_service(app.clone()))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    let mut client = FeathrRegistryClient::connect(format!("http://{}", addr))  // Devskim: ignore DS137138
+        .await
+        .unwrap();
+    let grpc_feature = client
+        .get_feature(GetFeatureRequest {
+            feature: "p1__a1__f1".to_string(),
+            opt_seq: None,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(rest_feature["guid"], grpc_feature.guid);
+    assert_eq!(rest_feature["qualifiedName"], grpc_feature.qualified_name);
+    assert_eq!(rest_feature["typeName"], grpc_feature.type_name);
+    assert_eq!(rest_feature["version"], grpc_feature.version);
+    assert_eq!(rest_feature["etag"], grpc_feature.etag);
+    let attributes: serde_json::Value =
+        serde_json::from_str(&grpc_feature.attributes_json).unwrap();
+    assert_eq!(rest_feature["attributes"], attributes);
+
+    // Missing entities are reported with the gRPC status code
+    let err = client
+        .get_feature(GetFeatureRequest {
+            feature: "p1__a1__missing".to_string(),
+            opt_seq: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::NotFound);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RaftRegistryApp, RbacMiddleware};
+
+async fn entity_count(app: &RaftRegistryApp) -> usize {
+    app.store
+        .state_machine
+        .read()
+        .await
+        .registry
+        .get_stats()
+        .entities
+        .values()
+        .sum()
+}
+
+async fn last_applied(app: &RaftRegistryApp) -> u64 {
+    app.store
+        .state_machine
+        .read()
+        .await
+        .last_applied_log
+        .map(|l| l.index)
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn create_if_not_exists() {
+    let app = common::start_app("if-not-exists", &[]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    let create = |path: &'static str, name: &'static str| {
+        cli.post(path)
+            .header("x-if-not-exists", "true")
+            .body_json(&serde_json::json!({ "name": name }))
+            .send()
+    };
+
+    let mut guids = vec![];
+    for (path, name) in [("/api/v2/projects", "p1"), ("/api/v2/projects/p1/anchors", "a1")] {
+        let resp = create(path, name).await;
+        resp.assert_status_is_ok();
+        let created: serde_json::Value = resp.json().await.value().deserialize();
+        let guid = created["guid"].as_str().unwrap().to_string();
+
+        let count = entity_count(&app).await;
+        let seq = last_applied(&app).await;
+        // The existing entity is returned without going through Raft
+        let resp = create(path, name).await;
+        resp.assert_status_is_ok();
+        let existing: serde_json::Value = resp.json().await.value().deserialize();
+        assert_eq!(existing["guid"].as_str().unwrap(), guid);
+        assert_eq!(entity_count(&app).await, count);
+        assert_eq!(last_applied(&app).await, seq);
+        guids.push(guid);
+    }
+    assert_ne!(guids[0], guids[1]);
+}
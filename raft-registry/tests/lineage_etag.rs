@@ -0,0 +1,55 @@
+mod common;
+
+use poem::{http::StatusCode, test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn unchanged_lineage_is_not_modified() {
+    let app = common::start_app("lineage", &[]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    let resp = cli.get("/api/v2/projects/p1/lineage").send().await;
+    resp.assert_status_is_ok();
+    let etag = resp
+        .0
+        .headers()
+        .get("etag")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let resp = cli
+        .get("/api/v2/projects/p1/lineage")
+        .header("If-None-Match", &etag)
+        .send()
+        .await;
+    resp.assert_status(StatusCode::NOT_MODIFIED);
+    resp.assert_text("").await;
+
+    // Updating tags changes the etag of the project, so as the lineage
+    cli.patch("/api/v2/projects/p1/tags")
+        .body_json(&serde_json::json!({ "addTags": { "team": "t1" } }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli
+        .get("/api/v2/projects/p1/lineage")
+        .header("If-None-Match", &etag)
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use raft_registry::RaftRegistryApp;
+use registry_api::{AnchorDef, FeathrApiRequest, FeathrApiResponse, ProjectDef, SourceDef};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn deletion_uses_request_time() {
+    let app = common::start_app("mutation-time", &[]).await;
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let (source_id, _) = app
+        .request(
+            None,
+            FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: SourceDef {
+                    id: Uuid::new_v4().to_string(),
+                    name: "s1".to_string(),
+                    qualified_name: Default::default(),
+                    source_type: "hdfs".to_string(),
+                    options: HashMap::new(),
+                    event_timestamp_column: None,
+                    timestamp_format: None,
+                    connection_id: None,
+                    table: None,
+                    preprocessing: None,
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            },
+        )
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    app.request(
+        None,
+        FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: Default::default(),
+                source_id: source_id.to_string(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+
+    // The anchor is detached from the source at the time carried by the request
+    let time = Utc.timestamp(1_600_000_000, 0);
+    let resp = app
+        .request(
+            None,
+            FeathrApiRequest::DeleteProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
+                force: true,
+                time: Some(time),
+            },
+        )
+        .await;
+    assert!(matches!(resp, FeathrApiResponse::Unit));
+    let anchor = app
+        .request(
+            None,
+            FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            },
+        )
+        .await
+        .into_entity()
+        .unwrap();
+    assert_eq!(anchor.last_modified(), time);
+}
+
+#[tokio::test]
+async fn creation_uses_request_time() {
+    let app = common::start_app("creation-time", &[]).await;
+    let time = Utc.timestamp(1_600_000_000, 0);
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: Some(time),
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let project = app
+        .request(
+            None,
+            FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            },
+        )
+        .await
+        .into_entity()
+        .unwrap();
+    assert_eq!(project.created_on, time);
+    assert_eq!(project.last_modified(), time);
+}
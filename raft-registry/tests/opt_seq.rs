@@ -0,0 +1,59 @@
+mod common;
+
+use std::time::Duration;
+
+use poem::{http::StatusCode, test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RaftRegistryApp, RbacMiddleware};
+
+async fn last_applied(app: &RaftRegistryApp) -> u64 {
+    app.store
+        .state_machine
+        .read()
+        .await
+        .last_applied_log
+        .map(|l| l.index)
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn read_waits_for_opt_seq() {
+    let app = common::start_app("opt-seq", &["--opt-seq-wait-ms", "2000"]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    let seq = last_applied(&app).await + 1;
+    let read = async {
+        cli.get("/api/v2/projects")
+            .header("x-registry-opt-seq", seq)
+            .send()
+            .await
+    };
+    let write = async {
+        // The read has to wait for this write
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        cli.post("/api/v2/projects")
+            .body_json(&serde_json::json!({ "name": "p1" }))
+            .send()
+            .await
+    };
+    let (read, write) = tokio::join!(read, write);
+    write.assert_status_is_ok();
+    read.assert_status_is_ok();
+    let projects: serde_json::Value = read.json().await.value().deserialize();
+    assert!(projects.to_string().contains("p1"));
+
+    // Nothing is going to be applied at the seq, gives up after the timeout
+    let seq = last_applied(&app).await + 100;
+    let resp = cli
+        .get("/api/v2/projects")
+        .header("x-registry-opt-seq", seq)
+        .send()
+        .await;
+    resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+}
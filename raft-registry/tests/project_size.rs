@@ -0,0 +1,62 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn warn_on_large_project() {
+    let app = common::start_app("project-size", &["--project-entity-warn-threshold", "2"]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    let resp = cli
+        .post("/api/v2/projects/p1/anchors")
+        .body_json(&serde_json::json!({ "name": "a1" }))
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let created: serde_json::Value = resp.json().await.value().deserialize();
+    assert!(created.get("warnings").is_none());
+
+    let mut warnings = vec![];
+    for name in ["f1", "f2", "f3"] {
+        let resp = cli
+            .post("/api/v2/projects/p1/anchors/a1/features")
+            .body_json(&serde_json::json!({
+                "name": name,
+                "featureType": {
+                    "type": "TENSOR",
+                    "tensorCategory": "DENSE",
+                    "dimensionType": [],
+                    "valType": "INT",
+                },
+                "transformation": {
+                    "transformExpr": "x + 1",
+                },
+                "key": [{
+                    "keyColumn": "user_id",
+                    "keyColumnType": "LONG",
+                }],
+            }))
+            .send()
+            .await;
+        // Creations over the threshold still succeed
+        resp.assert_status_is_ok();
+        let created: serde_json::Value = resp.json().await.value().deserialize();
+        warnings.push(created.get("warnings").cloned());
+    }
+    let last = warnings.pop().unwrap().unwrap();
+    assert_eq!(last.as_array().unwrap().len(), 1);
+    assert!(last[0].as_str().unwrap().contains("p1"));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use poem::{http::StatusCode, test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn protected_tag_is_set_once() {
+    let app = common::start_app("protected-tags", &["--protected-tag", "cost_center"]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    cli.post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    // Setting the protected tag for the first time is allowed
+    cli.patch("/api/v2/entities/p1/tags")
+        .body_json(&serde_json::json!({ "cost_center": "c1", "team": "t1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+    // Setting the same value again changes nothing
+    cli.patch("/api/v2/entities/p1/tags")
+        .body_json(&serde_json::json!({ "cost_center": "c1" }))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    // Neither changing nor removing it is allowed
+    cli.patch("/api/v2/entities/p1/tags")
+        .body_json(&serde_json::json!({ "cost_center": "c2" }))
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    cli.patch("/api/v2/entities/p1/tags")
+        .body_json(&serde_json::json!({ "cost_center": "" }))
+        .send()
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Unprotected tags can be edited freely
+    let resp = cli
+        .patch("/api/v2/entities/p1/tags")
+        .body_json(&serde_json::json!({ "team": "t2" }))
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let entity: serde_json::Value = resp.json().await.value().deserialize();
+    assert_eq!(entity["attributes"]["tags"]["team"], "t2");
+    assert_eq!(entity["attributes"]["tags"]["cost_center"], "c1");
+}
@@ -0,0 +1,27 @@
+mod common;
+
+use poem::{http::StatusCode, test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn read_only_refuses_updates() {
+    let app = common::start_app("read-only", &["--read-only"]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    let resp = cli
+        .post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await;
+    resp.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+
+    let resp = cli.get("/api/v2/projects").send().await;
+    resp.assert_status_is_ok();
+}
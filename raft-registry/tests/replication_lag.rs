@@ -0,0 +1,67 @@
+mod common;
+
+use std::{collections::BTreeSet, time::Duration};
+
+use openraft::Node;
+use poem::{listener::TcpListener, test::TestClient, EndpointExt, Route, Server};
+use raft_registry::{management_routes, raft_routes, RaftRegistryApp};
+
+async fn start_node(id: u64) -> RaftRegistryApp {
+    let config = common::node_config("replication-lag", &[]);
+    // Reserve a free port for the Raft RPC server
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let app = RaftRegistryApp::new(id, addr.clone(), config).await;
+    let route = raft_routes(management_routes(Route::new())).data(app.clone());
+    tokio::spawn(Server::new(TcpListener::bind(addr)).run(route));
+    app
+}
+
+#[tokio::test]
+async fn replication_lag_per_node() {
+    let leader = start_node(1).await;
+    let follower = start_node(2).await;
+    leader.init().await.unwrap();
+    for _ in 0..100 {
+        if leader.raft.is_leader().await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let node = Node {
+        addr: follower.addr.clone(),
+        ..Default::default()
+    };
+    leader.raft.add_learner(2, Some(node), true).await.unwrap();
+    leader
+        .raft
+        .change_membership(BTreeSet::from([1, 2]), true, false)
+        .await
+        .unwrap();
+
+    let cli = TestClient::new(management_routes(Route::new()).data(leader.clone()));
+    let resp = cli.get("/metrics").send().await;
+    resp.assert_status_is_ok();
+    let metrics: serde_json::Value = resp.json().await.value().deserialize();
+    let metrics = &metrics["Ok"];
+    assert!(metrics["commitIndex"].as_u64().is_some());
+    let lags = metrics["replicationLag"].as_array().unwrap();
+    let nodes: BTreeSet<u64> = lags
+        .iter()
+        .map(|l| l["nodeId"].as_u64().unwrap())
+        .collect();
+    assert_eq!(nodes, BTreeSet::from([1, 2]));
+    for l in lags {
+        assert!(l["lag"].as_u64().is_some());
+    }
+
+    // Followers don't track replication
+    let cli = TestClient::new(management_routes(Route::new()).data(follower.clone()));
+    let resp = cli.get("/metrics").send().await;
+    resp.assert_status_is_ok();
+    let metrics: serde_json::Value = resp.json().await.value().deserialize();
+    assert!(metrics["Ok"]["replicationLag"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware, RequestIdMiddleware, REQUEST_ID_HEADER_NAME};
+
+#[tokio::test]
+async fn request_id_is_echoed() {
+    let app = common::start_app("request-id", &[]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RequestIdMiddleware)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    // An id is assigned if the client doesn't supply one
+    let resp = cli
+        .post("/api/v2/projects")
+        .body_json(&serde_json::json!({ "name": "p1" }))
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let id = resp
+        .0
+        .headers()
+        .get(REQUEST_ID_HEADER_NAME)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    assert!(!id.is_empty());
+
+    // The supplied id is echoed unchanged, including on errors
+    for path in ["/api/v2/projects/p1", "/api/v2/projects/p2"] {
+        let resp = cli
+            .get(path)
+            .header(REQUEST_ID_HEADER_NAME, "my-request-1")
+            .send()
+            .await;
+        resp.assert_header(REQUEST_ID_HEADER_NAME, "my-request-1");
+    }
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::time::Duration;
+
+use raft_registry::RaftRegistryApp;
+use registry_api::{FeathrApiRequest, FeathrApiResponse, ProjectDef};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_requests() {
+    let app = common::start_app("shutdown", &[]).await;
+    let (trigger, triggered) = oneshot::channel::<()>();
+    let shutdown = {
+        let app = app.clone();
+        tokio::spawn(async move {
+            triggered.await.ok();
+            app.shutdown(Duration::from_secs(10)).await
+        })
+    };
+    let create = {
+        let app = app.clone();
+        tokio::spawn(async move {
+            app.request(
+                None,
+                FeathrApiRequest::CreateProject {
+                    definition: ProjectDef {
+                        id: Uuid::new_v4().to_string(),
+                        name: "p1".to_string(),
+                        qualified_name: "p1".to_string(),
+                        tags: Default::default(),
+                        created_by: "alice".to_string(),
+                    },
+                    time: None,
+                },
+            )
+            .await
+        })
+    };
+    // Let the creation start before triggering the shutdown
+    tokio::task::yield_now().await;
+    trigger.send(()).unwrap();
+
+    shutdown.await.unwrap().unwrap();
+    assert!(create.is_finished());
+    create.await.unwrap().into_uuid_and_version().unwrap();
+
+    // No more requests are taken after shutdown
+    assert!(matches!(
+        app.request(
+            None,
+            FeathrApiRequest::GetProjects {
+                keyword: None,
+                size: None,
+                offset: None,
+            },
+        )
+        .await,
+        FeathrApiResponse::Error(_)
+    ));
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use raft_registry::{management_routes, RaftRegistryApp, SnapshotResult};
+use registry_api::{FeathrApiRequest, ProjectDef};
+use uuid::Uuid;
+
+async fn create_project(app: &RaftRegistryApp, name: &str) {
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                qualified_name: name.to_string(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+}
+
+#[tokio::test]
+async fn manual_snapshot() {
+    let app = common::start_app("snapshot", &[]).await;
+    let cli = TestClient::new(management_routes(Route::new()).data(app.clone()));
+
+    create_project(&app, "p1").await;
+    let resp = cli.post("/cluster/snapshot").send().await;
+    resp.assert_status_is_ok();
+    let first: SnapshotResult = resp.json().await.value().deserialize();
+    assert!(first.index > 0);
+
+    // Nothing changed, the current snapshot is returned
+    let resp = cli.post("/cluster/snapshot").send().await;
+    resp.assert_status_is_ok();
+    let same: SnapshotResult = resp.json().await.value().deserialize();
+    assert_eq!(same.snapshot_id, first.snapshot_id);
+
+    create_project(&app, "p2").await;
+    let resp = cli.post("/cluster/snapshot").send().await;
+    resp.assert_status_is_ok();
+    let second: SnapshotResult = resp.json().await.value().deserialize();
+    assert!(second.index > first.index);
+    assert_ne!(second.snapshot_id, first.snapshot_id);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use common_utils::map;
+use raft_registry::RaftRegistryApp;
+use registry_api::{
+    EntityAttributes, FeathrApiRequest, FeathrApiResponse, ProjectDef, SourceDef,
+};
+use uuid::Uuid;
+
+fn project_tags(response: FeathrApiResponse) -> HashMap<String, String> {
+    match response.into_entity().unwrap().attributes {
+        EntityAttributes::Project(attr) => attr.tags,
+        _ => panic!("Should be a project"),
+    }
+}
+
+async fn last_applied(app: &RaftRegistryApp) -> u64 {
+    app.store
+        .state_machine
+        .read()
+        .await
+        .last_applied_log
+        .unwrap()
+        .index
+}
+
+#[tokio::test]
+async fn read_entity_at_seq() {
+    let app = common::start_app("time-travel", &[]).await;
+    let (id, _) = app
+        .request(
+            None,
+            FeathrApiRequest::CreateProject {
+                definition: ProjectDef {
+                    id: Uuid::new_v4().to_string(),
+                    name: "p1".to_string(),
+                    qualified_name: "p1".to_string(),
+                    tags: Default::default(),
+                    created_by: "alice".to_string(),
+                },
+                time: None,
+            },
+        )
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    let created_at = last_applied(&app).await;
+
+    app.request(
+        None,
+        FeathrApiRequest::UpdateEntityTags {
+            id_or_name: id.to_string(),
+            tags: map!("team".to_string() => "ads".to_string()),
+            if_match: None,
+            new_etag: Uuid::new_v4(),
+        },
+    )
+    .await
+    .into_entity()
+    .unwrap();
+    let updated_at = last_applied(&app).await;
+    assert!(updated_at > created_at);
+
+    let at = |seq: u64| {
+        app.request(
+            None,
+            FeathrApiRequest::GetEntityAtSeq {
+                id_or_name: id.to_string(),
+                seq,
+            },
+        )
+    };
+
+    // The project before the update has no tags
+    assert!(project_tags(at(created_at).await).is_empty());
+    assert_eq!(
+        project_tags(at(updated_at).await),
+        map!("team".to_string() => "ads".to_string())
+    );
+
+    // The project didn't exist before it was created
+    assert!(matches!(
+        at(created_at - 1).await,
+        FeathrApiResponse::Error(_)
+    ));
+    // Future is not available
+    assert!(matches!(
+        at(updated_at + 100).await,
+        FeathrApiResponse::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn entity_history() {
+    let app = common::start_app("time-travel", &[]).await;
+    let (id, _) = app
+        .request(
+            None,
+            FeathrApiRequest::CreateProject {
+                definition: ProjectDef {
+                    id: Uuid::new_v4().to_string(),
+                    name: "p1".to_string(),
+                    qualified_name: "p1".to_string(),
+                    tags: Default::default(),
+                    created_by: "alice".to_string(),
+                },
+                time: None,
+            },
+        )
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    let created_at = last_applied(&app).await;
+    app.request(
+        None,
+        FeathrApiRequest::UpdateEntityTags {
+            id_or_name: id.to_string(),
+            tags: map!("team".to_string() => "ads".to_string()),
+            if_match: None,
+            new_etag: Uuid::new_v4(),
+        },
+    )
+    .await
+    .into_entity()
+    .unwrap();
+    let updated_at = last_applied(&app).await;
+
+    let history = app
+        .request(
+            None,
+            FeathrApiRequest::GetEntityHistory { id: id.to_string() },
+        )
+        .await
+        .into_entity_history()
+        .unwrap();
+    // Nothing has been compacted
+    assert_eq!(history.earliest_seq, None);
+    let entries: Vec<(u64, &str)> = history
+        .entries
+        .iter()
+        .map(|e| (e.seq, e.change_summary.as_str()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            (created_at, "CreateProject: created"),
+            (updated_at, "UpdateEntityTags: changed tags"),
+        ]
+    );
+    assert!(history.entries[0].timestamp <= history.entries[1].timestamp);
+
+    // Unknown entity
+    assert!(matches!(
+        app.request(
+            None,
+            FeathrApiRequest::GetEntityHistory {
+                id: Uuid::new_v4().to_string()
+            },
+        )
+        .await,
+        FeathrApiResponse::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn deletion_in_history() {
+    let app = common::start_app("time-travel", &[]).await;
+    app.request(
+        None,
+        FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: "p1".to_string(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        },
+    )
+    .await
+    .into_uuid_and_version()
+    .unwrap();
+    let (source_id, _) = app
+        .request(
+            None,
+            FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: SourceDef {
+                    id: Uuid::new_v4().to_string(),
+                    name: "s1".to_string(),
+                    qualified_name: Default::default(),
+                    source_type: "hdfs".to_string(),
+                    options: HashMap::new(),
+                    event_timestamp_column: None,
+                    timestamp_format: None,
+                    connection_id: None,
+                    table: None,
+                    preprocessing: None,
+                    tags: Default::default(),
+                    created_by: "alice".to_string(),
+                },
+                time: None,
+            },
+        )
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    let time = Utc.timestamp(1_600_000_000, 0);
+    let resp = app
+        .request(
+            None,
+            FeathrApiRequest::DeleteProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
+                force: false,
+                time: Some(time),
+            },
+        )
+        .await;
+    assert!(matches!(resp, FeathrApiResponse::Unit));
+    let deleted_at = last_applied(&app).await;
+
+    // The history is read after the deletion has been applied
+    let history = app
+        .request(
+            Some(deleted_at),
+            FeathrApiRequest::GetEntityHistory {
+                id: source_id.to_string(),
+            },
+        )
+        .await
+        .into_entity_history()
+        .unwrap();
+    let last = history.entries.last().unwrap();
+    assert_eq!(last.seq, deleted_at);
+    assert_eq!(last.change_summary, "DeleteProjectDataSource: deleted");
+    // Deletion is stamped with the time of the request
+    assert_eq!(last.timestamp, time);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use poem::{test::TestClient, EndpointExt, Route};
+use poem_openapi::OpenApiService;
+use raft_registry::{FeathrApiV2, RbacMiddleware};
+
+#[tokio::test]
+async fn export_and_import_user_roles() {
+    let app = common::start_app("roles", &[]).await;
+    let api = OpenApiService::new(FeathrApiV2, "test", "test");
+    let cli = TestClient::new(
+        Route::new()
+            .nest("/api/v2", api)
+            .with(RbacMiddleware)
+            .data(app.clone()),
+    );
+
+    for name in ["p1", "p2"] {
+        cli.post("/api/v2/projects")
+            .body_json(&serde_json::json!({ "name": name }))
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+    cli.post("/api/v2/projects/p1/userroles/bulk")
+        .query("reason", &"test")
+        .body_json(&serde_json::json!([
+            { "userName": "alice", "roleName": "consumer" },
+            { "userName": "bob", "roleName": "producer" },
+        ]))
+        .send()
+        .await
+        .assert_status_is_ok();
+
+    let export = |project: &'static str| {
+        let cli = &cli;
+        async move {
+            let resp = cli
+                .get(format!("/api/v2/projects/{}/userroles/export", project))
+                .send()
+                .await;
+            resp.assert_status_is_ok();
+            resp.0
+                .into_body()
+                .into_json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let roles = |exported: &serde_json::Value| -> BTreeSet<(String, String)> {
+        exported
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["userName"].as_str().unwrap().to_string(),
+                    r["roleName"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+
+    let exported = export("p1").await;
+    assert!(roles(&exported).contains(&("alice".to_string(), "consumer".to_string())));
+    assert!(roles(&exported).contains(&("bob".to_string(), "producer".to_string())));
+
+    cli.post("/api/v2/projects/p2/userroles/import")
+        .body_json(&exported)
+        .send()
+        .await
+        .assert_status_is_ok();
+    assert_eq!(roles(&export("p2").await), roles(&exported));
+
+    // User names are validated on import
+    let resp = cli
+        .post("/api/v2/projects/p2/userroles/import")
+        .body_json(&serde_json::json!([{ "userName": "bad name", "roleName": "consumer" }]))
+        .send()
+        .await;
+    resp.assert_status_is_ok();
+    let results: serde_json::Value = resp.0.into_body().into_json().await.unwrap();
+    assert_eq!(results[0]["success"], false);
+}
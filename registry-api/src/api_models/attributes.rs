@@ -133,6 +133,10 @@ pub struct SourceAttributes {
     pub event_timestamp_column: Option<String>,
     #[oai(skip_serializing_if = "Option::is_none")]
     pub timestamp_format: Option<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
     #[oai(rename = "type")]
     pub type_: String,
     pub tags: HashMap<String, String>,
@@ -144,8 +148,12 @@ pub struct AnchorAttributes {
     pub qualified_name: String,
     pub name: String,
     pub features: Vec<EntityRef>,
+    /// Deprecated, the first one in `sources`
     #[oai(skip_serializing_if = "Option::is_none")]
     pub source: Option<EntityRef>,
+    #[oai(default)]
+    #[serde(default)]
+    pub sources: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
 }
 
@@ -158,6 +166,12 @@ pub struct AnchorFeatureAttributes {
     pub type_: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_value: Option<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
     pub tags: HashMap<String, String>,
 }
 
@@ -171,6 +185,12 @@ pub struct DerivedFeatureAttributes {
     pub type_: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_value: Option<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
     pub input_anchor_features: Vec<EntityRef>,
     pub input_derived_features: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
@@ -196,6 +216,8 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                     type_: attr.type_.into(),
                     transformation: attr.transformation.into(),
                     key: attr.key.into_iter().map(|e| e.into()).collect(),
+                    sample_value: attr.sample_value,
+                    default_value: attr.default_value,
                 })
             }
             registry_provider::Attributes::DerivedFeature(attr) => {
@@ -206,6 +228,8 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                     type_: attr.type_.into(),
                     transformation: attr.transformation.into(),
                     key: attr.key.into_iter().map(|e| e.into()).collect(),
+                    sample_value: attr.sample_value,
+                    default_value: attr.default_value,
                     input_anchor_features: Default::default(),
                     input_derived_features: Default::default(),
                 })
@@ -216,6 +240,7 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                 tags: v.tags,
                 features: Default::default(),
                 source: None,
+                sources: Default::default(),
             }),
             registry_provider::Attributes::Source(attr) => Self::Source(SourceAttributes {
                 qualified_name: v.qualified_name,
@@ -225,7 +250,9 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                 preprocessing: attr.preprocessing,
                 event_timestamp_column: attr.event_timestamp_column,
                 timestamp_format: attr.timestamp_format,
-                type_: attr.type_,
+                connection_id: attr.connection_id,
+                table: attr.table,
+                type_: attr.type_.to_string(),
             }),
             registry_provider::Attributes::Project => Self::Project(ProjectAttributes {
                 qualified_name: v.qualified_name,
@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use poem_openapi::Object;
+use registry_provider::child_qualified_name;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{
+    validate_name, AnchorDef, AnchorFeatureDef, DefinitionLimits, DerivedFeatureDef, NamePattern,
+    ProjectDef, SourceDef,
+};
+
+/**
+ * Anchor and the features in it
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorBundle {
+    pub anchor: AnchorDef,
+    #[oai(default)]
+    #[serde(default)]
+    pub features: Vec<AnchorFeatureDef>,
+}
+
+/**
+ * Complete definition of a project, entities in it refer to each other by name or qualified name
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBundle {
+    pub project: ProjectDef,
+    #[oai(default)]
+    #[serde(default)]
+    pub sources: Vec<SourceDef>,
+    #[oai(default)]
+    #[serde(default)]
+    pub anchors: Vec<AnchorBundle>,
+    #[oai(default)]
+    #[serde(default)]
+    pub derived_features: Vec<DerivedFeatureDef>,
+}
+
+/**
+ * Problem found in a bundle
+ * `field` is the path to the offending field, e.g. `anchors[0].features[1].name`
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct BundleError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Checker<'a> {
+    name_pattern: Option<&'a NamePattern>,
+    errors: Vec<BundleError>,
+    // Qualified names of all entities in the bundle
+    names: HashSet<String>,
+}
+
+impl<'a> Checker<'a> {
+    fn push(&mut self, field: impl Into<String>, message: impl ToString) {
+        self.errors.push(BundleError {
+            field: field.into(),
+            message: message.to_string(),
+        });
+    }
+
+    fn check(&mut self, field: &str, result: Result<(), impl ToString>) {
+        if let Err(e) = result {
+            self.push(field, e);
+        }
+    }
+
+    fn check_name(&mut self, field: &str, name: &str, qualified_name: &str) {
+        let field = format!("{}.name", field);
+        if let Err(e) = validate_name(name) {
+            self.push(field, e);
+        } else if let Some(Err(e)) = self.name_pattern.map(|p| p.check(name)) {
+            self.push(field, e);
+        } else if !self.names.insert(qualified_name.to_string()) {
+            self.push(field, format!("Name `{}` is used more than once", qualified_name));
+        }
+    }
+}
+
+impl ProjectBundle {
+    /**
+     * Run the checks done on creation against the bundle in isolation, the registry is not involved
+     * Returns all problems found, the bundle is valid if the list is empty
+     */
+    pub fn validate(
+        &self,
+        limits: &DefinitionLimits,
+        name_pattern: Option<&NamePattern>,
+    ) -> Vec<BundleError> {
+        let mut c = Checker {
+            name_pattern,
+            ..Default::default()
+        };
+        let project = &self.project.name;
+        c.check_name("project", project, project);
+        c.check("project", limits.check_project(&self.project));
+
+        let mut sources: HashSet<String> = HashSet::new();
+        for (i, source) in self.sources.iter().enumerate() {
+            let field = format!("sources[{}]", i);
+            let qualified_name = child_qualified_name(project, &source.name);
+            c.check_name(&field, &source.name, &qualified_name);
+            c.check(&field, limits.check_source(source));
+            let mut def = source.clone();
+            def.id = Uuid::nil().to_string();
+            c.check(
+                &field,
+                TryInto::<registry_provider::SourceDef>::try_into(def).map(|_| ()),
+            );
+            sources.insert(source.name.clone());
+            sources.insert(qualified_name);
+        }
+
+        let mut anchor_features: HashSet<String> = HashSet::new();
+        for (i, anchor) in self.anchors.iter().enumerate() {
+            let field = format!("anchors[{}].anchor", i);
+            let anchor_name = child_qualified_name(project, &anchor.anchor.name);
+            c.check_name(&field, &anchor.anchor.name, &anchor_name);
+            c.check(&field, limits.check_anchor(&anchor.anchor));
+            if !anchor.anchor.source_id.is_empty() && !sources.contains(&anchor.anchor.source_id) {
+                c.push(
+                    format!("{}.sourceId", field),
+                    format!("Source `{}` is not in the bundle", anchor.anchor.source_id),
+                );
+            }
+            for (j, source) in anchor.anchor.source_ids.iter().enumerate() {
+                if !sources.contains(source) {
+                    c.push(
+                        format!("{}.sourceIds[{}]", field, j),
+                        format!("Source `{}` is not in the bundle", source),
+                    );
+                }
+            }
+            for (j, feature) in anchor.features.iter().enumerate() {
+                let field = format!("anchors[{}].features[{}]", i, j);
+                let qualified_name = child_qualified_name(&anchor_name, &feature.name);
+                c.check_name(&field, &feature.name, &qualified_name);
+                c.check(&field, limits.check_anchor_feature(feature));
+                let mut def = feature.clone();
+                def.id = Uuid::nil().to_string();
+                c.check(
+                    &field,
+                    TryInto::<registry_provider::AnchorFeatureDef>::try_into(def).map(|_| ()),
+                );
+                anchor_features.insert(feature.name.clone());
+                anchor_features.insert(qualified_name);
+            }
+        }
+
+        // Derived features can refer to ones defined after them
+        let mut derived_features: HashMap<String, usize> = HashMap::new();
+        for (i, feature) in self.derived_features.iter().enumerate() {
+            derived_features.insert(feature.name.clone(), i);
+            derived_features.insert(child_qualified_name(project, &feature.name), i);
+        }
+        let mut inputs: Vec<Vec<usize>> = vec![vec![]; self.derived_features.len()];
+        for (i, feature) in self.derived_features.iter().enumerate() {
+            let field = format!("derivedFeatures[{}]", i);
+            let qualified_name = child_qualified_name(project, &feature.name);
+            c.check_name(&field, &feature.name, &qualified_name);
+            c.check(&field, limits.check_derived_feature(feature));
+            let mut def = feature.clone();
+            def.id = Uuid::nil().to_string();
+            def.input_anchor_features.clear();
+            def.input_derived_features.clear();
+            c.check(
+                &field,
+                TryInto::<registry_provider::DerivedFeatureDef>::try_into(def).map(|_| ()),
+            );
+            for (j, input) in feature.input_anchor_features.iter().enumerate() {
+                if !anchor_features.contains(input) {
+                    c.push(
+                        format!("{}.inputAnchorFeatures[{}]", field, j),
+                        format!("Anchor feature `{}` is not in the bundle", input),
+                    );
+                }
+            }
+            for (j, input) in feature.input_derived_features.iter().enumerate() {
+                let field = format!("{}.inputDerivedFeatures[{}]", field, j);
+                match derived_features.get(input) {
+                    Some(&k) if k == i => c.push(
+                        field,
+                        format!("Derived feature `{}` cannot consume itself", input),
+                    ),
+                    Some(&k) => inputs[i].push(k),
+                    None => c.push(
+                        field,
+                        format!("Derived feature `{}` is not in the bundle", input),
+                    ),
+                }
+            }
+        }
+
+        // A derived feature is in a cycle if it can be reached from its own inputs
+        for (i, feature) in self.derived_features.iter().enumerate() {
+            let mut visited: HashSet<usize> = HashSet::new();
+            let mut stack = inputs[i].clone();
+            while let Some(k) = stack.pop() {
+                if k == i {
+                    c.push(
+                        format!("derivedFeatures[{}].inputDerivedFeatures", i),
+                        format!("Derived feature `{}` depends on itself", feature.name),
+                    );
+                    break;
+                }
+                if visited.insert(k) {
+                    stack.extend(inputs[k].iter().copied());
+                }
+            }
+        }
+        c.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AnchorDef, AnchorFeatureDef, DefinitionLimits, DerivedFeatureDef, ProjectDef};
+
+    use super::{AnchorBundle, ProjectBundle};
+
+    fn feature_type() -> crate::FeatureType {
+        registry_provider::FeatureType {
+            type_: registry_provider::VectorType::TENSOR,
+            tensor_category: registry_provider::TensorCategory::DENSE,
+            dimension_type: vec![],
+            dimension_sizes: vec![],
+            val_type: registry_provider::ValueType::FLOAT,
+        }
+        .into()
+    }
+
+    fn anchor_feature_def(name: &str) -> AnchorFeatureDef {
+        AnchorFeatureDef {
+            id: Default::default(),
+            name: name.to_string(),
+            qualified_name: Default::default(),
+            feature_type: feature_type(),
+            transformation: registry_provider::FeatureTransformation::Expression {
+                transform_expr: "x".to_string(),
+            }
+            .into(),
+            key: vec![],
+            sample_value: None,
+            default_value: None,
+            tags: Default::default(),
+            created_by: Default::default(),
+        }
+    }
+
+    fn derived_feature_def(
+        name: &str,
+        anchor_inputs: &[&str],
+        derived_inputs: &[&str],
+    ) -> DerivedFeatureDef {
+        DerivedFeatureDef {
+            id: Default::default(),
+            name: name.to_string(),
+            qualified_name: Default::default(),
+            feature_type: feature_type(),
+            transformation: registry_provider::FeatureTransformation::Expression {
+                transform_expr: "f1 + 1".to_string(),
+            }
+            .into(),
+            key: vec![],
+            input_anchor_features: anchor_inputs.iter().map(|s| s.to_string()).collect(),
+            input_derived_features: derived_inputs.iter().map(|s| s.to_string()).collect(),
+            sample_value: None,
+            default_value: None,
+            tags: Default::default(),
+            created_by: Default::default(),
+        }
+    }
+
+    fn bundle(derived_features: Vec<DerivedFeatureDef>) -> ProjectBundle {
+        ProjectBundle {
+            project: ProjectDef {
+                id: Default::default(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            sources: vec![],
+            anchors: vec![AnchorBundle {
+                anchor: AnchorDef {
+                    id: Default::default(),
+                    name: "a1".to_string(),
+                    qualified_name: Default::default(),
+                    source_id: Default::default(),
+                    source_ids: vec![],
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                features: vec![anchor_feature_def("f1")],
+            }],
+            derived_features,
+        }
+    }
+
+    #[test]
+    fn valid_bundle() {
+        let b = bundle(vec![
+            derived_feature_def("df2", &[], &["df1"]),
+            derived_feature_def("df1", &["p1__a1__f1"], &[]),
+        ]);
+        assert!(b.validate(&DefinitionLimits::default(), None).is_empty());
+    }
+
+    #[test]
+    fn dangling_input() {
+        let b = bundle(vec![derived_feature_def("df1", &["p1__a1__f2"], &[])]);
+        let errors = b.validate(&DefinitionLimits::default(), None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "derivedFeatures[0].inputAnchorFeatures[0]");
+    }
+
+    #[test]
+    fn cyclic_inputs() {
+        let b = bundle(vec![
+            derived_feature_def("df1", &[], &["df2"]),
+            derived_feature_def("df2", &[], &["p1__df1"]),
+        ]);
+        let fields: Vec<String> = b
+            .validate(&DefinitionLimits::default(), None)
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "derivedFeatures[0].inputDerivedFeatures",
+                "derivedFeatures[1].inputDerivedFeatures",
+            ]
+        );
+    }
+}
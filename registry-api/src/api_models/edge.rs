@@ -1,13 +1,20 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
 use poem_openapi::{Enum, Object};
 use registry_provider::Edge;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Enum)]
 pub enum EdgeType {
     BelongsTo,
     Contains,
     Consumes,
     Produces,
+    DerivesFrom,
+    DerivedBy,
 }
 
 impl From<registry_provider::EdgeType> for EdgeType {
@@ -17,6 +24,8 @@ impl From<registry_provider::EdgeType> for EdgeType {
             registry_provider::EdgeType::Contains => EdgeType::Contains,
             registry_provider::EdgeType::Consumes => EdgeType::Consumes,
             registry_provider::EdgeType::Produces => EdgeType::Produces,
+            registry_provider::EdgeType::DerivesFrom => EdgeType::DerivesFrom,
+            registry_provider::EdgeType::DerivedBy => EdgeType::DerivedBy,
         }
     }
 }
@@ -28,11 +37,13 @@ impl From<EdgeType> for registry_provider::EdgeType {
             EdgeType::Contains => registry_provider::EdgeType::Contains,
             EdgeType::Consumes => registry_provider::EdgeType::Consumes,
             EdgeType::Produces => registry_provider::EdgeType::Produces,
+            EdgeType::DerivesFrom => registry_provider::EdgeType::DerivesFrom,
+            EdgeType::DerivedBy => registry_provider::EdgeType::DerivedBy,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Object)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 pub struct Relationship {
     #[oai(rename = "relationshipType")]
     pub edge_type: EdgeType,
@@ -40,6 +51,17 @@ pub struct Relationship {
     pub from: String,
     #[oai(rename = "toEntityId")]
     pub to: String,
+    #[oai(default)]
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Hash for Relationship {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.edge_type.hash(state);
+        self.from.hash(state);
+        self.to.hash(state);
+    }
 }
 
 impl From<Edge> for Relationship {
@@ -48,6 +70,7 @@ impl From<Edge> for Relationship {
             edge_type: v.edge_type.into(),
             from: v.from.to_string(),
             to: v.to.to_string(),
+            attributes: v.attributes,
         }
     }
 }
@@ -1,12 +1,13 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
-use chrono::{Utc, DateTime};
-use poem_openapi::{Enum, Object};
+use chrono::{DateTime, TimeZone, Utc};
+use poem_openapi::{types::ToJSON, Enum, Object};
 use registry_provider::EntityProperty;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use super::{EntityAttributes, Relationship};
+use super::{EntityAttributes, EntityLink, Relationship};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Enum)]
 pub enum EntityType {
@@ -38,6 +39,19 @@ impl From<registry_provider::EntityType> for EntityType {
     }
 }
 
+impl From<EntityType> for registry_provider::EntityType {
+    fn from(v: EntityType) -> Self {
+        match v {
+            EntityType::Unknown => registry_provider::EntityType::Unknown,
+            EntityType::Project => registry_provider::EntityType::Project,
+            EntityType::Source => registry_provider::EntityType::Source,
+            EntityType::Anchor => registry_provider::EntityType::Anchor,
+            EntityType::AnchorFeature => registry_provider::EntityType::AnchorFeature,
+            EntityType::DerivedFeature => registry_provider::EntityType::DerivedFeature,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct Entity {
@@ -53,6 +67,29 @@ pub struct Entity {
     pub attributes: EntityAttributes,
     pub created_by: String,
     pub created_on: DateTime<Utc>,
+    pub last_modified_by: String,
+    /// Unix seconds, as a string
+    pub last_modified_ts: String,
+    pub etag: String,
+    #[oai(skip_serializing_if_is_empty, default)]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub links: Vec<EntityLink>,
+}
+
+impl Entity {
+    /**
+     * `last_modified_ts` as a timestamp, entities stored in history before it became unix seconds
+     * still have RFC 3339 strings
+     */
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        match self.last_modified_ts.parse::<i64>() {
+            Ok(secs) => Utc.timestamp_opt(secs, 0).single(),
+            Err(_) => DateTime::parse_from_rfc3339(&self.last_modified_ts)
+                .ok()
+                .map(|ts| ts.with_timezone(&Utc)),
+        }
+        .unwrap_or_else(|| Utc.timestamp(0, 0))
+    }
 }
 
 impl From<registry_provider::Entity<EntityProperty>> for Entity {
@@ -68,14 +105,86 @@ impl From<registry_provider::Entity<EntityProperty>> for Entity {
             labels: v.properties.labels.clone(),
             created_by: v.properties.created_by.clone(),
             created_on: v.properties.created_on,
+            last_modified_by: v.properties.last_modified_by.clone(),
+            last_modified_ts: v.properties.last_modified_ts.timestamp().to_string(),
+            etag: v.properties.etag.to_string(),
+            links: v.properties.links.iter().cloned().map(|l| l.into()).collect(),
             attributes: v.properties.into(),
         }
     }
 }
 
+/**
+ * A field changed between 2 versions of an entity
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Object)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+impl Entity {
+    /**
+     * Fields compared between versions, features have their definitions compared as well as tags
+     */
+    fn versioned_fields(&self) -> Vec<(&'static str, serde_json::Value)> {
+        fn json<T: Serialize>(v: &T) -> serde_json::Value {
+            serde_json::to_value(v).unwrap_or_default()
+        }
+        match &self.attributes {
+            EntityAttributes::AnchorFeature(attr) => vec![
+                ("type", json(&attr.type_)),
+                ("transformation", json(&attr.transformation)),
+                ("key", json(&attr.key)),
+                ("sampleValue", json(&attr.sample_value)),
+                ("defaultValue", json(&attr.default_value)),
+                ("tags", json(&attr.tags)),
+            ],
+            EntityAttributes::DerivedFeature(attr) => vec![
+                ("type", json(&attr.type_)),
+                ("transformation", json(&attr.transformation)),
+                ("key", json(&attr.key)),
+                ("sampleValue", json(&attr.sample_value)),
+                ("defaultValue", json(&attr.default_value)),
+                ("tags", json(&attr.tags)),
+            ],
+            EntityAttributes::Project(attr) => vec![("tags", json(&attr.tags))],
+            EntityAttributes::Source(attr) => vec![("tags", json(&attr.tags))],
+            EntityAttributes::Anchor(attr) => vec![("tags", json(&attr.tags))],
+        }
+    }
+
+    /**
+     * Compare field by field, returns fields with different values in this and the `newer` one
+     */
+    pub fn diff(&self, newer: &Entity) -> Vec<FieldDiff> {
+        let mut newer: BTreeMap<&str, serde_json::Value> =
+            newer.versioned_fields().into_iter().collect();
+        self.versioned_fields()
+            .into_iter()
+            .filter_map(|(field, old)| {
+                let new = newer.remove(field).unwrap_or_default();
+                if old == new {
+                    return None;
+                }
+                Some(FieldDiff {
+                    field: field.to_string(),
+                    old,
+                    new,
+                })
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 pub struct Entities {
     pub entities: Vec<Entity>,
+    /// Requested ids that don't match any entity, only used by batch lookups
+    #[oai(skip_serializing_if_is_empty, default)]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub missing: Vec<String>,
 }
 
 impl FromIterator<registry_provider::Entity<EntityProperty>> for Entities {
@@ -84,6 +193,7 @@ impl FromIterator<registry_provider::Entity<EntityProperty>> for Entities {
     ) -> Self {
         Self {
             entities: iter.into_iter().map(|e| e.into()).collect(),
+            missing: vec![],
         }
     }
 }
@@ -94,6 +204,54 @@ impl From<Vec<registry_provider::Entity<EntityProperty>>> for Entities {
     }
 }
 
+impl Entities {
+    /**
+     * Render features as CSV with columns `qualified_name, name, type, entity_type, tags`
+     * Tags are serialized as `k=v;k=v`, entities other than features are skipped
+     */
+    pub fn to_csv(&self) -> String {
+        let mut csv = "qualified_name,name,type,entity_type,tags\n".to_string();
+        for e in &self.entities {
+            let (type_, tags) = match &e.attributes {
+                EntityAttributes::AnchorFeature(attr) => (&attr.type_, &attr.tags),
+                EntityAttributes::DerivedFeature(attr) => (&attr.type_, &attr.tags),
+                _ => continue,
+            };
+            let mut tags: Vec<String> = tags.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            tags.sort();
+            let row = [
+                e.qualified_name.to_owned(),
+                e.name.to_owned(),
+                to_json_string(&type_.val_type),
+                to_json_string(&e.entity_type),
+                tags.join(";"),
+            ];
+            csv.push_str(
+                &row.iter()
+                    .map(|f| csv_field(f))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+fn to_json_string<T: ToJSON>(v: &T) -> String {
+    v.to_json()
+        .and_then(|v| v.as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct EntityUniqueAttributes {
@@ -125,12 +283,136 @@ impl EntityRef {
     }
 }
 
+/**
+ * Feature along with the full entities of its inputs
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedFeature {
+    pub feature: Entity,
+    /// Input entities keyed by their ids
+    pub inputs: BTreeMap<String, Entity>,
+    /// Ids of the inputs that have been deleted, they're not in `inputs`
+    #[oai(skip_serializing_if_is_empty, default)]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub deleted_inputs: Vec<String>,
+}
+
+/**
+ * A change applied to an entity, found by replaying the Raft log
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct EntityHistoryEntry {
+    /// Index of the Raft log entry making the change
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub change_summary: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct EntityHistory {
+    /// Changes in the log order
+    pub entries: Vec<EntityHistoryEntry>,
+    /// Earliest log index in the history, changes before it have been compacted into a snapshot
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub earliest_seq: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSummary {
+    pub sources: u64,
+    pub anchors: u64,
+    pub anchor_features: u64,
+    pub derived_features: u64,
+}
+
+impl<Prop> FromIterator<registry_provider::Entity<Prop>> for ProjectSummary
+where
+    Prop: Clone + Debug + PartialEq + Eq,
+{
+    fn from_iter<T: IntoIterator<Item = registry_provider::Entity<Prop>>>(iter: T) -> Self {
+        let mut summary = Self::default();
+        for e in iter {
+            match e.entity_type {
+                registry_provider::EntityType::Source => summary.sources += 1,
+                registry_provider::EntityType::Anchor => summary.anchors += 1,
+                registry_provider::EntityType::AnchorFeature => summary.anchor_features += 1,
+                registry_provider::EntityType::DerivedFeature => summary.derived_features += 1,
+                _ => (),
+            }
+        }
+        summary
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct EntityLineage {
     #[serde(rename = "guidEntityMap")]
-    pub guid_entity_map: HashMap<String, Entity>,
+    pub guid_entity_map: BTreeMap<String, Entity>,
     pub relations: Vec<Relationship>,
+    /**
+     * Set if the lineage is larger than the requested size and some entities are left out
+     */
+    #[serde(default)]
+    pub truncated: bool,
+    /**
+     * Set if the lineage is paged and there are more relationships after this page
+     */
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+impl EntityLineage {
+    /**
+     * Sort relations by `(from, to, edge_type)` so the output is deterministic
+     */
+    pub fn ordered(mut self) -> Self {
+        self.relations.sort_by(|a, b| {
+            (&a.from, &a.to, &a.edge_type).cmp(&(&b.from, &b.to, &b.edge_type))
+        });
+        self
+    }
+
+    /**
+     * Hash of the entity etags, versions and links and the relationships, changes whenever any
+     * entity or relationship in the lineage changes and stays the same across nodes otherwise.
+     * Links are included as setting them doesn't bump the entity etag.
+     * Timestamps differ between replicas and `truncated` depends on the traversal deadline,
+     * so neither is part of the hash
+     */
+    pub fn etag(&self) -> String {
+        let mut relations: Vec<String> = self
+            .relations
+            .iter()
+            .map(|r| format!("{}>{}:{:?}", r.from, r.to, r.edge_type))
+            .collect();
+        relations.sort();
+        let content = self
+            .guid_entity_map
+            .iter()
+            .map(|(id, e)| {
+                let links: Vec<String> = e
+                    .links
+                    .iter()
+                    .map(|l| format!("{}={}", l.title, l.url))
+                    .collect();
+                format!("{}:{}:{}:{}", id, e.version, e.etag, links.join(","))
+            })
+            .chain(relations)
+            .chain([self.has_more.to_string()])
+            .collect::<Vec<_>>()
+            .join(";");
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, content.as_bytes()).to_string()
+    }
 }
 
 impl
@@ -145,24 +427,62 @@ impl
             Vec<registry_provider::Edge>,
         ),
     ) -> Self {
-        let guid_entity_map: HashMap<String, Entity> = entities
+        let guid_entity_map: BTreeMap<String, Entity> = entities
             .into_iter()
             .map(|e| (e.id.to_string(), e.into()))
             .collect();
         Self {
             guid_entity_map,
             relations: edges.into_iter().map(|e| e.into()).collect(),
+            truncated: false,
+            has_more: false,
         }
+        .ordered()
     }
 }
 
 impl From<(Vec<Entity>, Vec<registry_provider::Edge>)> for EntityLineage {
     fn from((entities, edges): (Vec<Entity>, Vec<registry_provider::Edge>)) -> Self {
-        let guid_entity_map: HashMap<String, Entity> =
+        let guid_entity_map: BTreeMap<String, Entity> =
             entities.into_iter().map(|e| (e.guid.clone(), e)).collect();
         Self {
             guid_entity_map,
             relations: edges.into_iter().map(|e| e.into()).collect(),
+            truncated: false,
+            has_more: false,
         }
+        .ordered()
+    }
+}
+
+/**
+ * Entities of a project in the persisted format, which mirrors Apache Atlas entity fields,
+ * and the relationships between them
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+pub struct AtlasEntities {
+    pub entities: Vec<serde_json::Value>,
+    pub relationships: Vec<Relationship>,
+}
+
+impl AtlasEntities {
+    pub fn new(
+        mut entities: Vec<registry_provider::Entity<EntityProperty>>,
+        edges: Vec<registry_provider::Edge>,
+    ) -> Result<Self, serde_json::Error> {
+        entities.sort_by(|a, b| {
+            (&a.qualified_name, a.version).cmp(&(&b.qualified_name, b.version))
+        });
+        let mut relationships: Vec<Relationship> = edges.into_iter().map(|e| e.into()).collect();
+        relationships.sort_by(|a, b| {
+            (&a.from, &a.to, &a.edge_type).cmp(&(&b.from, &b.to, &b.edge_type))
+        });
+        Ok(Self {
+            entities: entities
+                .into_iter()
+                .map(|e| serde_json::to_value(e.properties))
+                .collect::<Result<_, _>>()?,
+            relationships,
+        })
     }
 }
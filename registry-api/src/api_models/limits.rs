@@ -0,0 +1,246 @@
+use std::{collections::HashMap, str::FromStr};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+use super::{
+    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, FeatureTransformation, ProjectDef, SourceDef,
+};
+
+/**
+ * Upper bounds of the size of entity definitions, oversized definitions are rejected before going into the Raft log
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefinitionLimits {
+    pub max_inputs: usize,
+    pub max_tags: usize,
+    pub max_expression_length: usize,
+}
+
+impl Default for DefinitionLimits {
+    fn default() -> Self {
+        Self {
+            max_inputs: 1000,
+            max_tags: 100,
+            max_expression_length: 65536,
+        }
+    }
+}
+
+impl DefinitionLimits {
+    pub fn check_tags(&self, tags: &HashMap<String, String>) -> Result<(), ApiError> {
+        if tags.len() > self.max_tags {
+            return Err(ApiError::BadRequest(format!(
+                "Field `tags` has {} entries, the limit is {}",
+                tags.len(),
+                self.max_tags
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_inputs(&self, field: &str, count: usize) -> Result<(), ApiError> {
+        if count > self.max_inputs {
+            return Err(ApiError::BadRequest(format!(
+                "Field `{}` has {} entries, the limit is {}",
+                field, count, self.max_inputs
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_expression(&self, field: &str, expr: &Option<String>) -> Result<(), ApiError> {
+        match expr {
+            Some(e) if e.len() > self.max_expression_length => {
+                Err(ApiError::BadRequest(format!(
+                    "Field `{}` is {} bytes long, the limit is {}",
+                    field,
+                    e.len(),
+                    self.max_expression_length
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_transformation(&self, t: &FeatureTransformation) -> Result<(), ApiError> {
+        self.check_expression("transformation.defExpr", &t.def_expr)?;
+        self.check_expression("transformation.transformExpr", &t.transform_expr)?;
+        self.check_expression("transformation.filter", &t.filter)?;
+        self.check_expression("transformation.groupBy", &t.group_by)
+    }
+
+    pub fn check_project(&self, def: &ProjectDef) -> Result<(), ApiError> {
+        self.check_tags(&def.tags)
+    }
+
+    pub fn check_source(&self, def: &SourceDef) -> Result<(), ApiError> {
+        self.check_tags(&def.tags)?;
+        self.check_expression("preprocessing", &def.preprocessing)
+    }
+
+    pub fn check_anchor(&self, def: &AnchorDef) -> Result<(), ApiError> {
+        self.check_tags(&def.tags)?;
+        self.check_inputs("sourceIds", def.source_ids.len())
+    }
+
+    pub fn check_anchor_feature(&self, def: &AnchorFeatureDef) -> Result<(), ApiError> {
+        self.check_tags(&def.tags)?;
+        self.check_transformation(&def.transformation)
+    }
+
+    pub fn check_derived_feature(&self, def: &DerivedFeatureDef) -> Result<(), ApiError> {
+        self.check_tags(&def.tags)?;
+        self.check_inputs(
+            "inputAnchorFeatures + inputDerivedFeatures",
+            def.input_anchor_features.len() + def.input_derived_features.len(),
+        )?;
+        self.check_transformation(&def.transformation)
+    }
+}
+
+/**
+ * Naming convention of entities, names of new entities must fully match the pattern
+ */
+#[derive(Clone, Debug)]
+pub struct NamePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl FromStr for NamePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Anchored so a partial match doesn't count
+        let regex = Regex::new(&format!("^(?:{})$", s))
+            .map_err(|e| format!("invalid name pattern '{}', {}", s, e))?;
+        Ok(Self {
+            pattern: s.to_string(),
+            regex,
+        })
+    }
+}
+
+impl NamePattern {
+    pub fn check(&self, name: &str) -> Result<(), ApiError> {
+        if !self.regex.is_match(name) {
+            return Err(ApiError::BadRequest(format!(
+                "Name `{}` doesn't match the pattern `{}`",
+                name, self.pattern
+            )));
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Page size of search and list requests, the requested size is clamped to `[1, max_size]`,
+ * missing or zero size falls back to `default_size`
+ * Offsets of paged lineage are clamped to `max_offset` as the traversal has to go through them
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageLimits {
+    pub default_size: usize,
+    pub max_size: usize,
+    pub max_offset: usize,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        Self {
+            default_size: 100,
+            max_size: 10000,
+            max_offset: 100000,
+        }
+    }
+}
+
+impl PageLimits {
+    pub fn size(&self, size: Option<usize>) -> usize {
+        let max_size = self.max_size.max(1);
+        match size {
+            None | Some(0) => self.default_size.clamp(1, max_size),
+            Some(s) => s.min(max_size),
+        }
+    }
+
+    pub fn offset(&self, offset: usize) -> usize {
+        offset.min(self.max_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DerivedFeatureDef;
+
+    use super::{DefinitionLimits, NamePattern, PageLimits};
+
+    fn derived_feature_def(inputs: usize) -> DerivedFeatureDef {
+        DerivedFeatureDef {
+            id: Default::default(),
+            name: "df1".to_string(),
+            qualified_name: Default::default(),
+            feature_type: registry_provider::FeatureType {
+                type_: registry_provider::VectorType::TENSOR,
+                tensor_category: registry_provider::TensorCategory::DENSE,
+                dimension_type: vec![],
+                dimension_sizes: vec![],
+                val_type: registry_provider::ValueType::FLOAT,
+            }
+            .into(),
+            transformation: registry_provider::FeatureTransformation::Expression {
+                transform_expr: "f0 + 1".to_string(),
+            }
+            .into(),
+            key: vec![],
+            input_anchor_features: (0..inputs).map(|i| format!("f{}", i)).collect(),
+            input_derived_features: vec![],
+            sample_value: None,
+            default_value: None,
+            tags: Default::default(),
+            created_by: Default::default(),
+        }
+    }
+
+    #[test]
+    fn input_count_limit() {
+        let limits = DefinitionLimits {
+            max_inputs: 10,
+            ..Default::default()
+        };
+        assert!(limits
+            .check_derived_feature(&derived_feature_def(10))
+            .is_ok());
+        assert!(limits
+            .check_derived_feature(&derived_feature_def(11))
+            .is_err());
+    }
+
+    #[test]
+    fn name_pattern() {
+        let pattern: NamePattern = "[a-z][a-z0-9_]*".parse().unwrap();
+        assert!(pattern.check("f_trip").is_ok());
+        assert!(pattern.check("F_Trip").is_err());
+        // The whole name must match
+        assert!(pattern.check("f_trip-2").is_err());
+        assert!("[a-z".parse::<NamePattern>().is_err());
+    }
+
+    #[test]
+    fn page_size_is_clamped() {
+        let limits = PageLimits {
+            default_size: 20,
+            max_size: 500,
+            max_offset: 1000,
+        };
+        assert_eq!(limits.size(Some(100000)), 500);
+        assert_eq!(limits.size(Some(0)), 20);
+        assert_eq!(limits.size(None), 20);
+        assert_eq!(limits.size(Some(42)), 42);
+        assert_eq!(limits.offset(42), 42);
+        assert_eq!(limits.offset(usize::MAX), 1000);
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use common_utils::Blank;
 use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -7,19 +8,135 @@ use uuid::Uuid;
 use crate::error::ApiError;
 
 mod attributes;
+mod bundle;
 mod edge;
 mod entity;
+mod limits;
 mod rbac;
 
 pub use attributes::*;
+pub use bundle::*;
 pub use edge::*;
 pub use entity::*;
+pub use limits::*;
 pub use rbac::*;
 
 fn parse_uuid(s: &str) -> Result<Uuid, ApiError> {
     Uuid::parse_str(s).map_err(|_| ApiError::BadRequest(format!("Invalid GUID `{}`", s)))
 }
 
+/**
+ * Window must be a number followed by a unit, e.g. `90d`, units are `s`, `m`, `h`, `d` and `w`
+ */
+fn validate_window(window: Option<String>) -> Result<Option<String>, ApiError> {
+    if let Some(w) = &window {
+        let valid = match w.char_indices().last() {
+            Some((i, unit)) => {
+                i > 0
+                    && matches!(unit, 's' | 'm' | 'h' | 'd' | 'w')
+                    && w[..i].chars().all(|c| c.is_ascii_digit())
+            }
+            None => false,
+        };
+        if !valid {
+            return Err(ApiError::BadRequest(format!("Invalid window `{}`", w)));
+        }
+    }
+    Ok(window)
+}
+
+/**
+ * Names are used to build qualified names, so they cannot contain the separator or leading/trailing whitespaces
+ */
+pub fn validate_name(name: &str) -> Result<(), ApiError> {
+    if name.is_blank() {
+        return Err(ApiError::BadRequest("Name cannot be empty or blank".to_string()));
+    }
+    if name.contains(registry_provider::QUALIFIED_NAME_SEPARATOR) {
+        return Err(ApiError::BadRequest(format!(
+            "Name `{}` cannot contain `{}`",
+            name,
+            registry_provider::QUALIFIED_NAME_SEPARATOR
+        )));
+    }
+    if name.trim() != name {
+        return Err(ApiError::BadRequest(format!(
+            "Name `{}` cannot have leading or trailing whitespaces",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Sample and default values of scalar features must be parsable as the value type,
+ * values of tensors with dimensions are kept as is
+ */
+fn validate_value(
+    field: &str,
+    value: Option<String>,
+    feature_type: &registry_provider::FeatureType,
+) -> Result<Option<String>, ApiError> {
+    if let Some(v) = &value {
+        if !feature_type.dimension_type.is_empty() {
+            return Ok(value);
+        }
+        let t = v.trim();
+        let valid = match feature_type.val_type {
+            registry_provider::ValueType::BOOL => {
+                t.eq_ignore_ascii_case("true") || t.eq_ignore_ascii_case("false")
+            }
+            registry_provider::ValueType::INT32 => t.parse::<i32>().is_ok(),
+            registry_provider::ValueType::INT64 => t.parse::<i64>().is_ok(),
+            registry_provider::ValueType::FLOAT => t.parse::<f32>().is_ok(),
+            registry_provider::ValueType::DOUBLE => t.parse::<f64>().is_ok(),
+            registry_provider::ValueType::UNSPECIFIED
+            | registry_provider::ValueType::STRING
+            | registry_provider::ValueType::BYTES => true,
+        };
+        if !valid {
+            return Err(ApiError::BadRequest(format!(
+                "Field `{}` has value `{}` which is not a valid {:?}",
+                field, v, feature_type.val_type
+            )));
+        }
+    }
+    Ok(value)
+}
+
+/**
+ * Timestamp format is either `epoch`, `epoch_millis`, or a date pattern with at least year, month and day,
+ * pattern letters must be known ones, other text must be separators or quoted
+ */
+fn validate_timestamp_format(format: Option<String>) -> Result<Option<String>, ApiError> {
+    const PATTERN_LETTERS: &str = "yMdHhmsSaZXzE";
+    const SEPARATORS: &str = " -/:.,T_";
+    if let Some(f) = &format {
+        let valid = match f.as_str() {
+            "epoch" | "epoch_millis" => true,
+            _ => {
+                let mut quoted = false;
+                let mut letters_valid = true;
+                for c in f.chars() {
+                    if c == '\'' {
+                        quoted = !quoted;
+                    } else if !quoted
+                        && !PATTERN_LETTERS.contains(c)
+                        && !SEPARATORS.contains(c)
+                    {
+                        letters_valid = false;
+                    }
+                }
+                letters_valid && !quoted && ['y', 'M', 'd'].iter().all(|&c| f.contains(c))
+            }
+        };
+        if !valid {
+            return Err(ApiError::BadRequest(format!("Invalid timestamp format `{}`", f)));
+        }
+    }
+    Ok(format)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[serde(rename_all = "camelCase")]
 #[oai(rename_all = "camelCase")]
@@ -68,6 +185,14 @@ pub struct SourceDef {
     #[oai(default)]
     #[serde(default)]
     pub timestamp_format: Option<String>,
+    /// Connection the table is read from, required if `type` is `jdbc`, ignored for file types
+    #[oai(default)]
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    /// Table name in the connection, required if `type` is `jdbc`, ignored for file types
+    #[oai(default)]
+    #[serde(default)]
+    pub table: Option<String>,
     #[oai(default)]
     #[serde(default)]
     pub preprocessing: Option<String>,
@@ -89,7 +214,9 @@ impl TryInto<registry_provider::SourceDef> for SourceDef {
             source_type: self.source_type,
             options: self.options,
             event_timestamp_column: self.event_timestamp_column,
-            timestamp_format: self.timestamp_format,
+            timestamp_format: validate_timestamp_format(self.timestamp_format)?,
+            connection_id: self.connection_id,
+            table: self.table,
             preprocessing: self.preprocessing,
             tags: self.tags,
             created_by: self.created_by,
@@ -106,7 +233,14 @@ pub struct AnchorDef {
     pub name: String,
     #[oai(skip)]
     pub qualified_name: String,
+    /// Deprecated, use `sourceIds` instead
+    #[oai(default)]
+    #[serde(default)]
     pub source_id: String,
+    /// Empty for anchors without source, e.g. passthrough features
+    #[oai(default)]
+    #[serde(default)]
+    pub source_ids: Vec<String>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
     #[oai(skip)]
@@ -121,7 +255,12 @@ impl TryInto<registry_provider::AnchorDef> for AnchorDef {
             id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
             name: self.name,
             qualified_name: self.qualified_name,
-            source_id: parse_uuid(&self.source_id)?,
+            source_ids: self
+                .source_ids
+                .iter()
+                .chain(Some(&self.source_id).filter(|s| !s.is_empty()))
+                .map(|s| parse_uuid(s))
+                .collect::<Result<_, _>>()?,
             tags: self.tags,
             created_by: self.created_by,
         })
@@ -137,6 +276,11 @@ pub struct FeatureType {
     pub tensor_category: TensorCategory,
     #[oai(default)]
     pub dimension_type: Vec<ValueType>,
+    /// Size of each dimension, aligned with `dimensionType`, null for variable size,
+    /// e.g. `[128]` for a 128-dim embedding, sizes are unspecified if it's empty
+    #[oai(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dimension_sizes: Vec<Option<u64>>,
     pub val_type: ValueType,
 }
 
@@ -144,10 +288,36 @@ impl TryInto<registry_provider::FeatureType> for FeatureType {
     type Error = ApiError;
 
     fn try_into(self) -> Result<registry_provider::FeatureType, Self::Error> {
+        match self.tensor_category {
+            // Sparse tensors are indexed by their dimensions, there must be at least one
+            TensorCategory::SPARSE if self.dimension_type.is_empty() => {
+                return Err(ApiError::BadRequest(
+                    "Field `dimensionType` cannot be empty for SPARSE tensors".to_string(),
+                ));
+            }
+            // Dense tensors are scalars, they have no dimension
+            TensorCategory::DENSE if !self.dimension_type.is_empty() => {
+                return Err(ApiError::BadRequest(format!(
+                    "Field `dimensionType` must be empty for DENSE tensors, got {:?}",
+                    self.dimension_type
+                )));
+            }
+            _ => {}
+        }
+        if !self.dimension_sizes.is_empty()
+            && self.dimension_sizes.len() != self.dimension_type.len()
+        {
+            return Err(ApiError::BadRequest(format!(
+                "Field `dimensionSizes` has {} entries, `dimensionType` has {}",
+                self.dimension_sizes.len(),
+                self.dimension_type.len()
+            )));
+        }
         Ok(registry_provider::FeatureType {
             type_: self.type_.into(),
             tensor_category: self.tensor_category.into(),
             dimension_type: self.dimension_type.into_iter().map(|e| e.into()).collect(),
+            dimension_sizes: self.dimension_sizes,
             val_type: self.val_type.into(),
         })
     }
@@ -159,6 +329,7 @@ impl From<registry_provider::FeatureType> for FeatureType {
             type_: v.type_.into(),
             tensor_category: v.tensor_category.into(),
             dimension_type: v.dimension_type.into_iter().map(|e| e.into()).collect(),
+            dimension_sizes: v.dimension_sizes,
             val_type: v.val_type.into(),
         }
     }
@@ -292,7 +463,7 @@ impl TryInto<registry_provider::FeatureTransformation> for FeatureTransformation
                     Some(s) => registry_provider::FeatureTransformation::WindowAgg {
                         def_expr: s,
                         agg_func: self.agg_func.map(|a| a.into()),
-                        window: self.window,
+                        window: validate_window(self.window)?,
                         group_by: self.group_by,
                         filter: self.filter,
                         limit: self.limit,
@@ -351,6 +522,12 @@ pub struct AnchorFeatureDef {
     pub feature_type: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    /// Sample value of the feature, must be valid for `featureType.valType`
+    #[serde(default)]
+    pub sample_value: Option<String>,
+    /// Value used when the feature is missing, must be valid for `featureType.valType`
+    #[serde(default)]
+    pub default_value: Option<String>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
     #[oai(skip)]
@@ -361,11 +538,14 @@ impl TryInto<registry_provider::AnchorFeatureDef> for AnchorFeatureDef {
     type Error = ApiError;
 
     fn try_into(self) -> Result<registry_provider::AnchorFeatureDef, Self::Error> {
+        let feature_type: registry_provider::FeatureType = self.feature_type.try_into()?;
         Ok(registry_provider::AnchorFeatureDef {
             id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
             name: self.name,
             qualified_name: self.qualified_name,
-            feature_type: self.feature_type.try_into()?,
+            sample_value: validate_value("sampleValue", self.sample_value, &feature_type)?,
+            default_value: validate_value("defaultValue", self.default_value, &feature_type)?,
+            feature_type,
             transformation: self.transformation.try_into()?,
             key: self
                 .key
@@ -394,6 +574,12 @@ pub struct DerivedFeatureDef {
     pub input_anchor_features: Vec<String>,
     #[oai(validator(unique_items), default)]
     pub input_derived_features: Vec<String>,
+    /// Sample value of the feature, must be valid for `featureType.valType`
+    #[serde(default)]
+    pub sample_value: Option<String>,
+    /// Value used when the feature is missing, must be valid for `featureType.valType`
+    #[serde(default)]
+    pub default_value: Option<String>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
     #[oai(skip)]
@@ -404,11 +590,14 @@ impl TryInto<registry_provider::DerivedFeatureDef> for DerivedFeatureDef {
     type Error = ApiError;
 
     fn try_into(self) -> Result<registry_provider::DerivedFeatureDef, Self::Error> {
+        let feature_type: registry_provider::FeatureType = self.feature_type.try_into()?;
         Ok(registry_provider::DerivedFeatureDef {
             id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
             name: self.name,
             qualified_name: self.qualified_name,
-            feature_type: self.feature_type.try_into()?,
+            sample_value: validate_value("sampleValue", self.sample_value, &feature_type)?,
+            default_value: validate_value("defaultValue", self.default_value, &feature_type)?,
+            feature_type,
             transformation: self.transformation.try_into()?,
             key: self
                 .key
@@ -431,10 +620,113 @@ impl TryInto<registry_provider::DerivedFeatureDef> for DerivedFeatureDef {
     }
 }
 
+/**
+ * Input features to be added to or removed from a derived feature, by name or id
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct DerivedFeatureInputs {
+    #[oai(validator(unique_items), default)]
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[oai(validator(unique_items), default)]
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/**
+ * New anchor of an anchor feature, by name or id
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct FeatureAnchor {
+    pub anchor: String,
+}
+
+/**
+ * Key columns and value type of a feature
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureSchema {
+    pub keys: Vec<TypedKey>,
+    pub feature_type: FeatureType,
+}
+
+/**
+ * Tags to be added to and removed from the project and all entities in it
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct BulkTagUpdate {
+    #[oai(default)]
+    #[serde(default)]
+    pub add_tags: HashMap<String, String>,
+    #[oai(validator(unique_items), default)]
+    #[serde(default)]
+    pub remove_keys: Vec<String>,
+}
+
+/**
+ * URL must be absolute with `http` or `https` scheme and a host, whitespaces are not allowed
+ */
+fn validate_url(url: &str) -> Result<(), ApiError> {
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .map(|rest| rest.split(['/', '?', '#']).next().unwrap_or_default());
+    match host {
+        Some(host)
+            if !host.is_empty()
+                && !url.contains(|c: char| c.is_whitespace() || c.is_control()) =>
+        {
+            Ok(())
+        }
+        _ => Err(ApiError::BadRequest(format!(
+            "Link `{}` is not a valid http or https URL",
+            url
+        ))),
+    }
+}
+
+/**
+ * Link to an external resource like a dashboard, a document or the lineage in another system
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+pub struct EntityLink {
+    pub title: String,
+    pub url: String,
+}
+
+impl From<registry_provider::EntityLink> for EntityLink {
+    fn from(v: registry_provider::EntityLink) -> Self {
+        Self {
+            title: v.title,
+            url: v.url,
+        }
+    }
+}
+
+impl TryInto<registry_provider::EntityLink> for EntityLink {
+    type Error = ApiError;
+
+    fn try_into(self) -> Result<registry_provider::EntityLink, Self::Error> {
+        validate_url(&self.url)?;
+        Ok(registry_provider::EntityLink {
+            title: self.title,
+            url: self.url,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Object)]
 pub struct CreationResponse {
     pub guid: String,
     pub version: u64,
+    /// Problems worth attention, the entity has been created anyway
+    #[oai(skip_serializing_if_is_empty, default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl TryInto<Uuid> for CreationResponse {
@@ -450,13 +742,112 @@ impl From<(Uuid, u64)> for CreationResponse {
         Self {
             guid: id.to_string(),
             version,
+            warnings: vec![],
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::SourceDef;
+    use super::{validate_name, validate_timestamp_format, validate_url, validate_window};
+    use crate::{FeatureType, SourceDef, TensorCategory, ValueType, VectorType};
+
+    #[test]
+    fn window() {
+        assert!(validate_window(Some("90d".to_string())).is_ok());
+        assert!(validate_window(Some("1w".to_string())).is_ok());
+        assert!(validate_window(None).is_ok());
+        assert!(validate_window(Some("90x".to_string())).is_err());
+        assert!(validate_window(Some("d".to_string())).is_err());
+        assert!(validate_window(Some("".to_string())).is_err());
+        assert!(validate_window(Some("9 0d".to_string())).is_err());
+    }
+
+    #[test]
+    fn name() {
+        assert!(validate_name("user_clicks").is_ok());
+        assert!(validate_name("user__clicks").is_err());
+        assert!(validate_name("__clicks").is_err());
+        assert!(validate_name(" user_clicks").is_err());
+        assert!(validate_name("user_clicks\t").is_err());
+    }
+
+    #[test]
+    fn url() {
+        assert!(validate_url("https://grafana.example.com/d/abc?from=now-1d").is_ok());
+        assert!(validate_url("http://localhost:8080").is_ok());
+        assert!(validate_url("ftp://example.com").is_err());
+        assert!(validate_url("https://").is_err());
+        assert!(validate_url("https:///path").is_err());
+        assert!(validate_url("example.com/docs").is_err());
+        assert!(validate_url("https://example.com/my docs").is_err());
+    }
+
+    #[test]
+    fn timestamp_format() {
+        assert!(validate_timestamp_format(None).is_ok());
+        assert!(validate_timestamp_format(Some("epoch".to_string())).is_ok());
+        assert!(validate_timestamp_format(Some("yyyy-MM-dd HH:mm:ss".to_string())).is_ok());
+        assert!(validate_timestamp_format(Some("yyyy-MM-dd'T'HH:mm:ss'Z'".to_string())).is_ok());
+        assert!(validate_timestamp_format(Some("yyyy-MM-dd HH:mm:ss.SSS".to_string())).is_ok());
+        assert!(validate_timestamp_format(Some("HH:mm:ss".to_string())).is_err());
+        assert!(validate_timestamp_format(Some("yyyy-MM-dd %H".to_string())).is_err());
+        assert!(validate_timestamp_format(Some("yyyy-MM-dd'T".to_string())).is_err());
+    }
+
+    fn feature_type(
+        tensor_category: TensorCategory,
+        dimension_type: Vec<ValueType>,
+    ) -> Result<registry_provider::FeatureType, crate::ApiError> {
+        FeatureType {
+            type_: VectorType::TENSOR,
+            tensor_category,
+            dimension_type,
+            dimension_sizes: vec![],
+            val_type: ValueType::FLOAT,
+        }
+        .try_into()
+    }
+
+    #[test]
+    fn feature_type_dimensions() {
+        // Dense scalar
+        assert!(feature_type(TensorCategory::DENSE, vec![]).is_ok());
+        // Sparse vector
+        assert!(feature_type(TensorCategory::SPARSE, vec![ValueType::STRING]).is_ok());
+        assert!(feature_type(TensorCategory::SPARSE, vec![]).is_err());
+        // Dense with dimensions
+        assert!(feature_type(TensorCategory::DENSE, vec![ValueType::INT32]).is_err());
+        assert!(feature_type(TensorCategory::DENSE, vec![ValueType::STRING]).is_err());
+    }
+
+    #[test]
+    fn feature_type_dimension_sizes() {
+        let embedding = FeatureType {
+            type_: VectorType::TENSOR,
+            tensor_category: TensorCategory::SPARSE,
+            dimension_type: vec![ValueType::INT32],
+            dimension_sizes: vec![Some(128)],
+            val_type: ValueType::FLOAT,
+        };
+        let converted: registry_provider::FeatureType = embedding.clone().try_into().unwrap();
+        assert_eq!(converted.dimension_sizes, vec![Some(128)]);
+        let json = serde_json::to_string(&converted).unwrap();
+        let restored: registry_provider::FeatureType = serde_json::from_str(&json).unwrap();
+        assert_eq!(FeatureType::from(restored), embedding);
+
+        // Sizes are optional for existing definitions
+        let json =
+            r#"{"type":"TENSOR","tensorCategory":"DENSE","dimensionType":[],"valType":"FLOAT"}"#;
+        let scalar: registry_provider::FeatureType = serde_json::from_str(json).unwrap();
+        assert!(scalar.dimension_sizes.is_empty());
+
+        let mismatched = FeatureType {
+            dimension_sizes: vec![Some(128), None],
+            ..embedding
+        };
+        assert!(TryInto::<registry_provider::FeatureType>::try_into(mismatched).is_err());
+    }
 
     #[test]
     fn des_source() {
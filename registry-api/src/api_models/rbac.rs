@@ -19,6 +19,35 @@ pub struct RbacResponse {
     pub access: Vec<String>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct UserRoleDef {
+    pub user_name: String,
+    pub role_name: String,
+}
+
+/**
+ * Portable part of a mapping, scope and audit fields are dropped so it applies to any project
+ */
+impl From<RbacResponse> for UserRoleDef {
+    fn from(r: RbacResponse) -> Self {
+        Self {
+            user_name: r.user_name,
+            role_name: r.role_name,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub struct UserRoleResult {
+    pub user_name: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
 pub fn into_user_roles(permissions: impl IntoIterator<Item = RbacRecord>) -> Vec<RbacResponse> {
     permissions
         .into_iter()
@@ -1,19 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use common_utils::{set, Blank};
 use log::debug;
 use registry_provider::{
-    Credential, Edge, EdgeType, EntityProperty, EntityType, Permission, RbacProvider, RbacRecord,
-    RegistryError, RegistryProvider,
+    child_qualified_name, Credential, Edge, EdgeType, EntityProperty, EntityType, Permission,
+    RbacProvider, RbacRecord, RegistryError, RegistryProvider, Resource,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    into_user_roles, AnchorDef, AnchorFeatureDef, ApiError, DerivedFeatureDef, Entities, Entity,
-    EntityAttributes, EntityLineage, EntityRef, IntoApiResult, ProjectDef, RbacResponse, SourceDef,
+    into_user_roles, validate_name, AnchorDef, AnchorFeatureDef, ApiError, AtlasEntities,
+    DefinitionLimits, DerivedFeatureDef, DerivedFeatureInputs, Entities, Entity, EntityAttributes,
+    EntityHistory, EntityLineage, EntityLink, EntityRef, FeatureAnchor, FeatureSchema, FieldDiff,
+    IntoApiResult, NamePattern, PageLimits, ProjectDef, ProjectSummary, RbacResponse, Relationship,
+    ResolvedFeature, SourceDef, UserRoleResult,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -23,11 +26,30 @@ pub enum FeathrApiRequest {
         size: Option<usize>,
         offset: Option<usize>,
     },
+    GetProjectsDetailed {
+        keyword: Option<String>,
+        size: Option<usize>,
+        offset: Option<usize>,
+    },
     GetProject {
         id_or_name: String,
     },
     GetProjectLineage {
         id_or_name: String,
+        /**
+         * Max number of relationships in the page, only used if `offset` is set
+         */
+        size: Option<usize>,
+        /**
+         * The lineage is paged by relationships if set
+         */
+        offset: Option<usize>,
+    },
+    GetProjectAtlasEntities {
+        id_or_name: String,
+    },
+    GetProjectSummary {
+        id_or_name: String,
     },
     GetProjectFeatures {
         project_id_or_name: String,
@@ -35,8 +57,28 @@ pub enum FeathrApiRequest {
         size: Option<usize>,
         offset: Option<usize>,
     },
+    GetProjectFeaturesInOrder {
+        project_id_or_name: String,
+    },
+    SearchAllFeatures {
+        keyword: Option<String>,
+        types: HashSet<EntityType>,
+        size: Option<usize>,
+        offset: Option<usize>,
+        credential: Credential,
+    },
     CreateProject {
         definition: ProjectDef,
+        /**
+         * Time of the creation, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
+    },
+    CloneProject {
+        id_or_name: String,
+        new_name: String,
+        id_seed: Uuid,
     },
     GetProjectDataSources {
         project_id_or_name: String,
@@ -48,6 +90,19 @@ pub enum FeathrApiRequest {
         project_id_or_name: String,
         id_or_name: String,
     },
+    GetSourceConsumers {
+        project_id_or_name: String,
+        source_id_or_name: String,
+    },
+    GetSourceLineage {
+        project_id_or_name: String,
+        source_id_or_name: String,
+        size: Option<usize>,
+        /**
+         * The lineage is paged by relationships if set, `size` is the page size
+         */
+        offset: Option<usize>,
+    },
     GetProjectDataSourceVersions {
         project_id_or_name: String,
         id_or_name: String,
@@ -60,6 +115,24 @@ pub enum FeathrApiRequest {
     CreateProjectDataSource {
         project_id_or_name: String,
         definition: SourceDef,
+        /**
+         * Time of the creation, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
+    },
+    DeleteProjectDataSource {
+        project_id_or_name: String,
+        id_or_name: String,
+        /**
+         * Detach anchors consuming the source instead of refusing the deletion
+         */
+        force: bool,
+        /**
+         * Time of the deletion, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
     },
     GetProjectAnchors {
         project_id_or_name: String,
@@ -83,6 +156,11 @@ pub enum FeathrApiRequest {
     CreateProjectAnchor {
         project_id_or_name: String,
         definition: AnchorDef,
+        /**
+         * Time of the creation, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
     },
     GetProjectDerivedFeatures {
         project_id_or_name: String,
@@ -106,6 +184,11 @@ pub enum FeathrApiRequest {
     CreateProjectDerivedFeature {
         project_id_or_name: String,
         definition: DerivedFeatureDef,
+        /**
+         * Time of the creation, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
     },
     GetAnchorFeatures {
         project_id_or_name: String,
@@ -134,16 +217,97 @@ pub enum FeathrApiRequest {
         project_id_or_name: String,
         anchor_id_or_name: String,
         definition: AnchorFeatureDef,
+        /**
+         * Time of the creation, set by the node accepting the request so all replicas agree
+         */
+        #[serde(default)]
+        time: Option<DateTime<Utc>>,
     },
     GetFeature {
         id_or_name: String,
     },
+    GetFeatureSchema {
+        id_or_name: String,
+    },
+    GetFeatureWithInputs {
+        id_or_name: String,
+    },
+    GetEntityEdges {
+        id_or_name: String,
+        edge_type: EdgeType,
+    },
+    DiffEntityVersions {
+        id_or_name: String,
+        from_version: u64,
+        to_version: u64,
+    },
+    UpdateDerivedFeatureInputs {
+        id_or_name: String,
+        inputs: DerivedFeatureInputs,
+    },
+    MoveAnchorFeature {
+        id_or_name: String,
+        anchor: FeatureAnchor,
+    },
+    GetEntities {
+        ids: Vec<String>,
+    },
     GetFeatureLineage {
         id_or_name: String,
+        size: Option<usize>,
+        /**
+         * The lineage is paged by relationships if set, `size` is the page size,
+         * upstream relationships come before downstream ones
+         */
+        offset: Option<usize>,
+    },
+    FindFeaturesByKey {
+        key: String,
+    },
+    GetDeletedEntities,
+    // Paged by id, `cursor` is the id the previous page ended with
+    ListEntitiesByType {
+        entity_type: EntityType,
+        cursor: Option<String>,
+        size: Option<usize>,
+        credential: Credential,
+    },
+    // Served on the registry replayed up to `seq`
+    GetEntityAtSeq {
+        id_or_name: String,
+        seq: u64,
+    },
+    // Served by replaying the Raft log
+    GetEntityHistory {
+        id: String,
     },
     GetEntityProject {
         id_or_name: String,
     },
+    GetEntityContainers {
+        id_or_name: String,
+    },
+    GetSuggestions {
+        prefix: String,
+        limit: Option<usize>,
+        credential: Credential,
+    },
+    UpdateEntityTags {
+        id_or_name: String,
+        tags: HashMap<String, String>,
+        if_match: Option<Uuid>,
+        new_etag: Uuid,
+    },
+    UpdateEntityLinks {
+        id_or_name: String,
+        links: Vec<EntityLink>,
+    },
+    BulkTagProject {
+        project_id_or_name: String,
+        add_tags: HashMap<String, String>,
+        remove_keys: Vec<String>,
+        new_etag: Uuid,
+    },
     // Raft specific
     BatchLoad {
         entities: Vec<registry_provider::Entity<EntityProperty>>,
@@ -151,7 +315,12 @@ pub enum FeathrApiRequest {
         permissions: Vec<RbacRecord>,
     },
     // RBAC
-    GetUserRoles,
+    GetUserRoles {
+        user: Option<String>,
+        scope: Option<String>,
+        size: Option<usize>,
+        offset: Option<usize>,
+    },
     AddUserRole {
         project_id_or_name: String,
         user: Credential,
@@ -159,6 +328,12 @@ pub enum FeathrApiRequest {
         requestor: Credential,
         reason: String,
     },
+    AddUserRoles {
+        project_id_or_name: String,
+        users: Vec<(String, Permission)>,
+        requestor: Credential,
+        reason: String,
+    },
     DeleteUserRole {
         project_id_or_name: String,
         user: Credential,
@@ -169,19 +344,187 @@ pub enum FeathrApiRequest {
 }
 
 impl FeathrApiRequest {
+    /**
+     * Time the mutation is applied at, if the request carries one
+     */
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::CreateProject { time, .. }
+            | Self::CreateProjectDataSource { time, .. }
+            | Self::DeleteProjectDataSource { time, .. }
+            | Self::CreateProjectAnchor { time, .. }
+            | Self::CreateAnchorFeature { time, .. }
+            | Self::CreateProjectDerivedFeature { time, .. } => *time,
+            _ => None,
+        }
+    }
+
+    /**
+     * Set the time of the mutation before it goes into the Raft log, unless it's already set
+     */
+    pub fn stamp_time(&mut self, now: DateTime<Utc>) {
+        match self {
+            Self::CreateProject { time, .. }
+            | Self::CreateProjectDataSource { time, .. }
+            | Self::DeleteProjectDataSource { time, .. }
+            | Self::CreateProjectAnchor { time, .. }
+            | Self::CreateAnchorFeature { time, .. }
+            | Self::CreateProjectDerivedFeature { time, .. } => {
+                time.get_or_insert(now);
+            }
+            _ => (),
+        }
+    }
+
     pub fn is_writing_request(&self) -> bool {
         matches!(
             &self,
             Self::CreateProject { .. }
+                | Self::CloneProject { .. }
                 | Self::CreateProjectDataSource { .. }
+                | Self::DeleteProjectDataSource { .. }
                 | Self::CreateProjectAnchor { .. }
                 | Self::CreateAnchorFeature { .. }
                 | Self::CreateProjectDerivedFeature { .. }
+                | Self::UpdateEntityTags { .. }
+                | Self::UpdateEntityLinks { .. }
+                | Self::BulkTagProject { .. }
+                | Self::UpdateDerivedFeatureInputs { .. }
+                | Self::MoveAnchorFeature { .. }
                 | Self::BatchLoad { .. }
                 | Self::AddUserRole { .. }
+                | Self::AddUserRoles { .. }
                 | Self::DeleteUserRole { .. }
         )
     }
+
+    /**
+     * Name of the request variant, used in logs
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GetProjects { .. } => "GetProjects",
+            Self::GetProjectsDetailed { .. } => "GetProjectsDetailed",
+            Self::GetProject { .. } => "GetProject",
+            Self::GetProjectLineage { .. } => "GetProjectLineage",
+            Self::GetProjectAtlasEntities { .. } => "GetProjectAtlasEntities",
+            Self::GetProjectSummary { .. } => "GetProjectSummary",
+            Self::GetProjectFeatures { .. } => "GetProjectFeatures",
+            Self::SearchAllFeatures { .. } => "SearchAllFeatures",
+            Self::GetProjectFeaturesInOrder { .. } => "GetProjectFeaturesInOrder",
+            Self::CreateProject { .. } => "CreateProject",
+            Self::CloneProject { .. } => "CloneProject",
+            Self::GetProjectDataSources { .. } => "GetProjectDataSources",
+            Self::GetProjectDataSource { .. } => "GetProjectDataSource",
+            Self::GetSourceConsumers { .. } => "GetSourceConsumers",
+            Self::GetSourceLineage { .. } => "GetSourceLineage",
+            Self::GetProjectDataSourceVersions { .. } => "GetProjectDataSourceVersions",
+            Self::GetProjectDataSourceVersion { .. } => "GetProjectDataSourceVersion",
+            Self::CreateProjectDataSource { .. } => "CreateProjectDataSource",
+            Self::DeleteProjectDataSource { .. } => "DeleteProjectDataSource",
+            Self::GetProjectAnchors { .. } => "GetProjectAnchors",
+            Self::GetProjectAnchor { .. } => "GetProjectAnchor",
+            Self::GetProjectAnchorVersions { .. } => "GetProjectAnchorVersions",
+            Self::GetProjectAnchorVersion { .. } => "GetProjectAnchorVersion",
+            Self::CreateProjectAnchor { .. } => "CreateProjectAnchor",
+            Self::GetProjectDerivedFeatures { .. } => "GetProjectDerivedFeatures",
+            Self::GetProjectDerivedFeature { .. } => "GetProjectDerivedFeature",
+            Self::GetProjectDerivedFeatureVersions { .. } => "GetProjectDerivedFeatureVersions",
+            Self::GetProjectDerivedFeatureVersion { .. } => "GetProjectDerivedFeatureVersion",
+            Self::CreateProjectDerivedFeature { .. } => "CreateProjectDerivedFeature",
+            Self::GetAnchorFeatures { .. } => "GetAnchorFeatures",
+            Self::GetAnchorFeature { .. } => "GetAnchorFeature",
+            Self::GetAnchorFeatureVersions { .. } => "GetAnchorFeatureVersions",
+            Self::GetAnchorFeatureVersion { .. } => "GetAnchorFeatureVersion",
+            Self::CreateAnchorFeature { .. } => "CreateAnchorFeature",
+            Self::GetFeature { .. } => "GetFeature",
+            Self::GetFeatureSchema { .. } => "GetFeatureSchema",
+            Self::GetFeatureWithInputs { .. } => "GetFeatureWithInputs",
+            Self::GetEntityEdges { .. } => "GetEntityEdges",
+            Self::DiffEntityVersions { .. } => "DiffEntityVersions",
+            Self::UpdateDerivedFeatureInputs { .. } => "UpdateDerivedFeatureInputs",
+            Self::MoveAnchorFeature { .. } => "MoveAnchorFeature",
+            Self::GetEntities { .. } => "GetEntities",
+            Self::GetFeatureLineage { .. } => "GetFeatureLineage",
+            Self::FindFeaturesByKey { .. } => "FindFeaturesByKey",
+            Self::GetDeletedEntities => "GetDeletedEntities",
+            Self::ListEntitiesByType { .. } => "ListEntitiesByType",
+            Self::GetEntityAtSeq { .. } => "GetEntityAtSeq",
+            Self::GetEntityHistory { .. } => "GetEntityHistory",
+            Self::GetEntityProject { .. } => "GetEntityProject",
+            Self::GetEntityContainers { .. } => "GetEntityContainers",
+            Self::GetSuggestions { .. } => "GetSuggestions",
+            Self::UpdateEntityTags { .. } => "UpdateEntityTags",
+            Self::UpdateEntityLinks { .. } => "UpdateEntityLinks",
+            Self::BulkTagProject { .. } => "BulkTagProject",
+            Self::BatchLoad { .. } => "BatchLoad",
+            Self::GetUserRoles { .. } => "GetUserRoles",
+            Self::AddUserRole { .. } => "AddUserRole",
+            Self::AddUserRoles { .. } => "AddUserRoles",
+            Self::DeleteUserRole { .. } => "DeleteUserRole",
+        }
+    }
+
+    /**
+     * Reject the request if the definition or tags in it exceed the limits
+     */
+    pub fn check_limits(&self, limits: &DefinitionLimits) -> Result<(), ApiError> {
+        match &self {
+            Self::CreateProject { definition, .. } => limits.check_project(definition),
+            Self::CreateProjectDataSource { definition, .. } => limits.check_source(definition),
+            Self::CreateProjectAnchor { definition, .. } => limits.check_anchor(definition),
+            Self::CreateAnchorFeature { definition, .. } => limits.check_anchor_feature(definition),
+            Self::CreateProjectDerivedFeature { definition, .. } => {
+                limits.check_derived_feature(definition)
+            }
+            Self::UpdateEntityTags { tags, .. } => limits.check_tags(tags),
+            Self::BulkTagProject { add_tags, .. } => limits.check_tags(add_tags),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Reject the request if the name of the entity to be created doesn't match the pattern
+     */
+    pub fn check_name_pattern(&self, pattern: &NamePattern) -> Result<(), ApiError> {
+        match &self {
+            Self::CreateProject { definition, .. } => pattern.check(&definition.name),
+            Self::CreateProjectDataSource { definition, .. } => pattern.check(&definition.name),
+            Self::CreateProjectAnchor { definition, .. } => pattern.check(&definition.name),
+            Self::CreateAnchorFeature { definition, .. } => pattern.check(&definition.name),
+            Self::CreateProjectDerivedFeature { definition, .. } => pattern.check(&definition.name),
+            Self::CloneProject { new_name, .. } => pattern.check(new_name),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Replace the requested page size of search and list requests and the offset of lineage
+     * requests with the clamped ones
+     */
+    pub fn apply_page_limits(&mut self, limits: &PageLimits) {
+        match self {
+            Self::GetProjects { size, .. }
+            | Self::GetProjectsDetailed { size, .. }
+            | Self::GetProjectFeatures { size, .. }
+            | Self::SearchAllFeatures { size, .. }
+            | Self::GetProjectDataSources { size, .. }
+            | Self::GetProjectAnchors { size, .. }
+            | Self::GetProjectDerivedFeatures { size, .. }
+            | Self::GetAnchorFeatures { size, .. }
+            | Self::ListEntitiesByType { size, .. }
+            | Self::GetUserRoles { size, .. } => *size = Some(limits.size(*size)),
+            // Suggestions keep their own shorter default
+            Self::GetSuggestions { limit, .. } => {
+                *limit = Some(limits.size(limit.or(Some(DEFAULT_SUGGESTIONS))))
+            }
+            // Paging lineage traverses everything before the offset
+            Self::GetSourceLineage { offset, .. } | Self::GetFeatureLineage { offset, .. } => {
+                *offset = offset.map(|o| limits.offset(o))
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -195,6 +538,17 @@ pub enum FeathrApiResponse {
     Entities(Entities),
     EntityLineage(EntityLineage),
     UserRoles(Vec<RbacResponse>),
+    UserRoleResults(Vec<UserRoleResult>),
+    ProjectSummary(ProjectSummary),
+    AtlasEntities(AtlasEntities),
+    Count(usize),
+    FieldDiffs(Vec<FieldDiff>),
+    FeatureSchema(FeatureSchema),
+    Relationships(Vec<Relationship>),
+    // A page of entities and the cursor of the next page
+    EntityPage(Entities, Option<String>),
+    ResolvedFeature(ResolvedFeature),
+    EntityHistory(EntityHistory),
 }
 
 impl FeathrApiResponse {
@@ -244,6 +598,86 @@ impl FeathrApiResponse {
             _ => panic!("Shouldn't reach here"),
         }
     }
+
+    pub fn into_project_summary(self) -> poem::Result<ProjectSummary> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::ProjectSummary(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_user_role_results(self) -> poem::Result<Vec<UserRoleResult>> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::UserRoleResults(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_atlas_entities(self) -> poem::Result<AtlasEntities> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::AtlasEntities(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_count(self) -> poem::Result<usize> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::Count(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_field_diffs(self) -> poem::Result<Vec<FieldDiff>> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::FieldDiffs(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_feature_schema(self) -> poem::Result<FeatureSchema> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::FeatureSchema(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_relationships(self) -> poem::Result<Vec<Relationship>> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::Relationships(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_entity_page(self) -> poem::Result<(Entities, Option<String>)> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::EntityPage(v, cursor) => Ok((v, cursor)),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_resolved_feature(self) -> poem::Result<ResolvedFeature> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::ResolvedFeature(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_entity_history(self) -> poem::Result<EntityHistory> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::EntityHistory(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
 }
 
 impl From<RegistryError> for FeathrApiResponse {
@@ -278,7 +712,10 @@ impl From<Entity> for FeathrApiResponse {
 
 impl From<Vec<Entity>> for FeathrApiResponse {
     fn from(v: Vec<Entity>) -> Self {
-        Self::Entities(Entities { entities: v })
+        Self::Entities(Entities {
+            entities: v,
+            missing: vec![],
+        })
     }
 }
 
@@ -306,6 +743,36 @@ impl From<(Vec<Entity>, Vec<Edge>)> for FeathrApiResponse {
     }
 }
 
+impl From<AtlasEntities> for FeathrApiResponse {
+    fn from(v: AtlasEntities) -> Self {
+        Self::AtlasEntities(v)
+    }
+}
+
+impl From<Vec<Relationship>> for FeathrApiResponse {
+    fn from(v: Vec<Relationship>) -> Self {
+        Self::Relationships(v)
+    }
+}
+
+impl From<FeatureSchema> for FeathrApiResponse {
+    fn from(v: FeatureSchema) -> Self {
+        Self::FeatureSchema(v)
+    }
+}
+
+impl From<Vec<FieldDiff>> for FeathrApiResponse {
+    fn from(v: Vec<FieldDiff>) -> Self {
+        Self::FieldDiffs(v)
+    }
+}
+
+impl From<usize> for FeathrApiResponse {
+    fn from(v: usize) -> Self {
+        Self::Count(v)
+    }
+}
+
 impl From<EntityLineage> for FeathrApiResponse {
     fn from(v: EntityLineage) -> Self {
         Self::EntityLineage(v)
@@ -318,6 +785,18 @@ impl From<Vec<RbacRecord>> for FeathrApiResponse {
     }
 }
 
+impl From<ProjectSummary> for FeathrApiResponse {
+    fn from(v: ProjectSummary) -> Self {
+        Self::ProjectSummary(v)
+    }
+}
+
+impl From<Vec<UserRoleResult>> for FeathrApiResponse {
+    fn from(v: Vec<UserRoleResult>) -> Self {
+        Self::UserRoleResults(v)
+    }
+}
+
 impl<T, E> From<Result<T, E>> for FeathrApiResponse
 where
     FeathrApiResponse: From<T> + From<E>,
@@ -332,7 +811,16 @@ where
 
 #[async_trait]
 pub trait FeathrApiProvider: Sync + Send {
+    /**
+     * Handle any request, writing requests need exclusive access to the registry
+     */
     async fn request(&mut self, request: FeathrApiRequest) -> FeathrApiResponse;
+
+    /**
+     * Handle a read-only request with shared access, so multiple readers can run concurrently
+     * Writing requests are rejected with `BadRequest`
+     */
+    async fn query(&self, request: FeathrApiRequest) -> FeathrApiResponse;
 }
 
 #[async_trait]
@@ -341,607 +829,3653 @@ where
     T: RegistryProvider<EntityProperty> + RbacProvider + Sync + Send,
 {
     async fn request(&mut self, request: FeathrApiRequest) -> FeathrApiResponse {
-        fn get_id<T>(t: &T, id_or_name: String) -> Result<Uuid, RegistryError>
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            match Uuid::parse_str(&id_or_name) {
-                Ok(id) => Ok(id),
-                Err(_) => t.get_entity_id(&id_or_name),
-            }
+        match handle_request(self, request).await {
+            Ok(v) => v,
+            Err(e) => FeathrApiResponse::Error(e),
         }
+    }
 
-        fn get_name<T>(t: &T, uuid: Uuid) -> Result<String, RegistryError>
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            t.get_entity_qualified_name(uuid)
+    async fn query(&self, request: FeathrApiRequest) -> FeathrApiResponse {
+        match handle_read_request(self, request).await {
+            Ok(v) => v,
+            Err(e) => FeathrApiResponse::Error(e),
         }
+    }
+}
 
-        fn get_child_id<T>(
-            t: &T,
-            parent_id_or_name: String,
-            child_id_or_name: String,
-        ) -> Result<(Uuid, Uuid), RegistryError>
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            debug!("Parent name: {}", parent_id_or_name);
-            debug!("Child name: {}", child_id_or_name);
-            let parent_id = get_id(t, parent_id_or_name)?;
-            let child_id = match get_id(t, child_id_or_name.clone()) {
-                Ok(id) => id,
-                Err(_) => {
-                    let project_name = get_name(t, parent_id)?;
-                    get_id(t, format!("{}__{}", project_name, child_id_or_name))?
-                }
-            };
-            Ok((parent_id, child_id))
-        }
-
-        fn search_entities<T>(
-            t: &T,
-            keyword: Option<String>,
-            size: Option<usize>,
-            offset: Option<usize>,
-            types: HashSet<registry_provider::EntityType>,
-            scope: Option<Uuid>,
-        ) -> Result<Vec<Entity>, RegistryError>
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            t.search_entity(
-                &keyword.unwrap_or_default(),
-                types,
-                scope,
-                size.unwrap_or(100),
-                offset.unwrap_or(0),
-            )
-            .map(|es| es.into_iter().map(|e| fill_entity(t, e)).collect())
-        }
-
-        fn search_children<T>(
-            t: &T,
-            id_or_name: String,
-            keyword: Option<String>,
-            size: Option<usize>,
-            offset: Option<usize>,
-            types: HashSet<registry_provider::EntityType>,
-        ) -> Result<Vec<Entity>, RegistryError>
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            debug!("Project name: {}", id_or_name);
-            let scope_id = get_id(t, id_or_name)?;
-
-            if keyword.is_blank() {
-                let children = t
-                    .get_children(scope_id, types)
-                    .map(|es| es.into_iter().map(|e| fill_entity(t, e)).collect());
-                children.map(|mut es: Vec<_>| {
-                    es.sort_by_key(|e| e.name.clone());
-                    es
-                })
-            } else {
-                search_entities(t, keyword, size, offset, types, Some(scope_id))
-            }
+fn get_id<T>(t: &T, id_or_name: String) -> Result<Uuid, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    match Uuid::parse_str(&id_or_name) {
+        Ok(id) => Ok(id),
+        Err(_) => t.get_entity_id(&id_or_name),
+    }
+}
+
+fn get_name<T>(t: &T, uuid: Uuid) -> Result<String, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    t.get_entity_qualified_name(uuid)
+}
+
+fn get_child_id<T>(
+    t: &T,
+    parent_id_or_name: String,
+    child_id_or_name: String,
+) -> Result<(Uuid, Uuid), RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    debug!("Parent name: {}", parent_id_or_name);
+    debug!("Child name: {}", child_id_or_name);
+    let parent_id = get_id(t, parent_id_or_name)?;
+    let child_id = match get_id(t, child_id_or_name.clone()) {
+        Ok(id) => id,
+        Err(_) => {
+            let project_name = get_name(t, parent_id)?;
+            get_id(t, child_qualified_name(&project_name, &child_id_or_name))?
         }
+    };
+    Ok((parent_id, child_id))
+}
 
-        fn fill_entity<T>(this: &T, mut e: registry_provider::Entity<EntityProperty>) -> Entity
-        where
-            T: RegistryProvider<EntityProperty>,
-        {
-            match &mut e.properties.attributes {
-                registry_provider::Attributes::Project => {
-                    let project_id = e.id;
-                    let mut project: Entity = e.into();
-                    // Contents
-                    let children = this
-                        .get_neighbors(project_id, EdgeType::Contains)
-                        .expect("Data inconsistency detected");
-                    match &mut project.attributes {
-                        EntityAttributes::Project(attr) => {
-                            attr.sources = children
-                                .iter()
-                                .filter(|&e| e.entity_type == registry_provider::EntityType::Source)
-                                .map(EntityRef::new)
-                                .collect();
-                            attr.anchors = children
-                                .iter()
-                                .filter(|&e| e.entity_type == registry_provider::EntityType::Anchor)
-                                .map(EntityRef::new)
-                                .collect();
-                            attr.anchor_features = children
-                                .iter()
-                                .filter(|&e| {
-                                    e.entity_type == registry_provider::EntityType::AnchorFeature
-                                })
-                                .map(EntityRef::new)
-                                .collect();
-                            attr.derived_features = children
-                                .iter()
-                                .filter(|&e| {
-                                    e.entity_type == registry_provider::EntityType::DerivedFeature
-                                })
-                                .map(EntityRef::new)
-                                .collect();
-                        }
-                        _ => panic!("Data inconsistency detected"),
-                    };
-                    project
-                }
-                registry_provider::Attributes::Anchor => {
-                    let anchor_id = e.id;
-                    let mut anchor: Entity = e.into();
-                    // Source
-                    let source = this
-                        .get_neighbors(anchor_id, EdgeType::Consumes)
-                        .expect("Data inconsistency detected")
-                        .pop()
-                        .expect("Data inconsistency detected");
-                    // Features
-                    let features: Vec<EntityRef> = this
-                        .get_neighbors(anchor_id, EdgeType::Contains)
-                        .expect("Data inconsistency detected")
-                        .into_iter()
-                        .map(|e| EntityRef::new(&e))
-                        .collect();
-                    match &mut anchor.attributes {
-                        EntityAttributes::Anchor(attr) => {
-                            attr.source = Some(EntityRef::new(&source));
-                            attr.features = features;
-                        }
-                        _ => panic!("Data inconsistency detected"),
-                    };
-                    anchor
-                }
-                registry_provider::Attributes::DerivedFeature(_) => {
-                    let feature_id = e.id;
-                    let mut feature: Entity = e.into();
-                    // Contents
-                    let upstream = this
-                        .get_neighbors(feature_id, EdgeType::Consumes)
-                        .expect("Data inconsistency detected");
-                    match &mut feature.attributes {
-                        EntityAttributes::DerivedFeature(attr) => {
-                            attr.input_anchor_features = upstream
-                                .iter()
-                                .filter(|&e| {
-                                    e.entity_type == registry_provider::EntityType::AnchorFeature
-                                })
-                                .map(EntityRef::new)
-                                .collect();
-                            attr.input_derived_features = upstream
-                                .iter()
-                                .filter(|&e| {
-                                    e.entity_type == registry_provider::EntityType::DerivedFeature
-                                })
-                                .map(EntityRef::new)
-                                .collect();
-                        }
-                        _ => panic!("Data inconsistency detected"),
-                    };
+/**
+ * Resolve the child entity in the container, fails if it doesn't have the `expected` type
+ */
+fn get_typed_child<T>(
+    t: &T,
+    parent_id_or_name: String,
+    child_id_or_name: String,
+    expected: EntityType,
+) -> Result<registry_provider::Entity<EntityProperty>, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    let (_, child_id) = get_child_id(t, parent_id_or_name, child_id_or_name)?;
+    t.get_typed_entity(&child_id.to_string(), expected)
+}
 
-                    feature
-                }
-                _ => e.into(),
+/**
+ * Page size of list requests, the size has been clamped by `apply_page_limits` with the configured
+ * limits if the request went through it
+ */
+fn page_size(size: Option<usize>) -> usize {
+    size.unwrap_or_else(|| PageLimits::default().size(None))
+}
+
+fn can_read<T>(t: &T, credential: &Credential, id: Uuid) -> bool
+where
+    T: RbacProvider,
+{
+    t.check_permission(credential, &Resource::Entity(id), Permission::Read)
+        .unwrap_or(false)
+}
+
+fn search_entities<T>(
+    t: &T,
+    keyword: Option<String>,
+    size: Option<usize>,
+    offset: Option<usize>,
+    types: HashSet<registry_provider::EntityType>,
+    scope: Option<Uuid>,
+) -> Result<Vec<Entity>, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    t.search_entity(
+        &keyword.unwrap_or_default(),
+        types,
+        scope,
+        page_size(size),
+        offset.unwrap_or(0),
+    )
+    .map(|es| es.into_iter().map(|e| fill_entity(t, e)).collect())
+}
+
+/**
+ * Same as `search_entities` without scope, but only entities `credential` can read count
+ * towards the page, search results are fetched in batches until the page is filled
+ */
+fn search_readable_entities<T>(
+    t: &T,
+    keyword: Option<String>,
+    size: Option<usize>,
+    offset: Option<usize>,
+    types: HashSet<registry_provider::EntityType>,
+    credential: &Credential,
+) -> Result<Vec<Entity>, RegistryError>
+where
+    T: RegistryProvider<EntityProperty> + RbacProvider,
+{
+    if t.check_permission(credential, &Resource::Global, Permission::Read)? {
+        return search_entities(t, keyword, size, offset, types, None);
+    }
+    let keyword = keyword.unwrap_or_default();
+    let size = page_size(size);
+    let mut skip = offset.unwrap_or(0);
+    let mut page = vec![];
+    let mut batch_offset = 0;
+    loop {
+        let batch = t.search_entity(&keyword, types.clone(), None, size, batch_offset)?;
+        if batch.is_empty() {
+            return Ok(page);
+        }
+        batch_offset += size;
+        for e in batch {
+            if !can_read(t, credential, e.id) {
+                continue;
+            }
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            page.push(fill_entity(t, e));
+            if page.len() == size {
+                return Ok(page);
             }
         }
+    }
+}
 
-        async fn handle_request<T>(
-            this: &mut T,
-            request: FeathrApiRequest,
-        ) -> Result<FeathrApiResponse, ApiError>
-        where
-            T: RegistryProvider<EntityProperty> + RbacProvider,
-        {
-            Ok(match request {
-                FeathrApiRequest::GetProjects {
-                    keyword,
-                    size,
-                    offset,
-                } => if keyword.is_blank() {
-                    let r = this.get_entry_points();
-                    match r {
-                        Ok(entities) => {
-                            let mut es: Vec<Entity> = vec![];
-                            for e in entities {
-                                es.push(fill_entity(this, e))
-                            }
-                            es.sort_by_key(|e| e.name.clone());
-                            Ok(es)
-                        }
-                        Err(e) => Err(e),
-                    }
-                } else {
-                    search_entities(
-                        this,
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::Project],
-                        None,
-                    )
-                }
-                .map(|r| {
-                    r.into_iter()
-                        .map(|e| e.qualified_name)
-                        .collect::<Vec<String>>()
-                })
-                .into(),
-                FeathrApiRequest::GetProject { id_or_name } => {
-                    match this.get_entity_by_id_or_qualified_name(&id_or_name) {
-                        Ok(e) => fill_entity(this, e).into(),
-                        Err(e) => e.into(),
-                    }
-                }
-                FeathrApiRequest::GetProjectLineage { id_or_name } => {
-                    debug!("Project name: {}", id_or_name);
-
-                    this.get_project(&id_or_name)
-                        .map(|(entities, edges)| {
-                            (
-                                entities
-                                    .into_iter()
-                                    .map(|e| fill_entity(this, e))
-                                    .collect::<Vec<_>>(),
-                                edges,
-                            )
-                        })
-                        .into()
-                }
-                FeathrApiRequest::GetProjectFeatures {
-                    project_id_or_name,
-                    keyword,
-                    size,
-                    offset,
-                } => {
-                    debug!("Project name: {}", project_id_or_name);
-                    search_children(
-                        this,
-                        project_id_or_name,
-                        keyword,
-                        size,
-                        offset,
-                        set![
-                            registry_provider::EntityType::AnchorFeature,
-                            registry_provider::EntityType::DerivedFeature
-                        ],
-                    )
-                    .into()
-                }
-                FeathrApiRequest::CreateProject { mut definition } => {
-                    definition.qualified_name = definition.name.clone();
-                    this.new_project(&definition.try_into()?).await.into()
-                }
-                FeathrApiRequest::GetProjectDataSources {
-                    project_id_or_name,
-                    keyword,
-                    size,
-                    offset,
-                } => {
-                    debug!("Project name: {}", project_id_or_name);
-                    search_children(
-                        this,
-                        project_id_or_name,
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::Source],
-                    )
-                    .into()
-                }
-                FeathrApiRequest::GetProjectDataSource {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, source_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    this.get_entity(source_id)
-                        .map(|e| fill_entity(this, e))
-                        .into()
-                }
-                FeathrApiRequest::GetProjectDataSourceVersions {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, source_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let source = this.get_entity(source_id).map(|e| fill_entity(this, e))?;
-                    let mut ret = this.get_all_versions(&source.qualified_name);
-                    ret.sort_by_key(|e| e.version);
-                    ret.into()
+/**
+ * Number of name suggestions returned if the request doesn't specify the limit
+ */
+const DEFAULT_SUGGESTIONS: usize = 10;
+
+/**
+ * Default and max number of entities in each direction of a feature lineage
+ */
+const DEFAULT_LINEAGE_SIZE: usize = 100;
+const MAX_LINEAGE_SIZE: usize = 10000;
+
+/**
+ * Page of `size` edges from `offset`, only the root and the entities referenced by the page are kept
+ * The flag is set if there are more edges after the page
+ */
+fn page_edges(
+    root: Uuid,
+    entities: Vec<registry_provider::Entity<EntityProperty>>,
+    edges: Vec<Edge>,
+    offset: usize,
+    size: usize,
+) -> (
+    Vec<registry_provider::Entity<EntityProperty>>,
+    Vec<Edge>,
+    bool,
+) {
+    let has_more = edges.len() > offset.saturating_add(size);
+    let edges: Vec<Edge> = edges.into_iter().skip(offset).take(size).collect();
+    let ids: HashSet<Uuid> = std::iter::once(root)
+        .chain(edges.iter().flat_map(|e| [e.from, e.to]))
+        .collect();
+    let entities = entities
+        .into_iter()
+        .filter(|e| ids.contains(&e.id))
+        .collect();
+    (entities, edges, has_more)
+}
+
+/**
+ * User role mappings sorted by scope and user, optionally filtered by user name and scope
+ * Scope can be "global", a project name or a project id
+ */
+fn list_user_roles<T>(
+    t: &T,
+    user: Option<String>,
+    scope: Option<String>,
+    size: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<RbacRecord>, ApiError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    let scope = match scope {
+        Some(s) if s.eq_ignore_ascii_case("global") => Some(Resource::Global),
+        Some(s) => match Uuid::parse_str(&s) {
+            Ok(id) => Some(Resource::NamedEntity(get_name(t, id).map_api_error()?)),
+            Err(_) => Some(Resource::NamedEntity(s)),
+        },
+        None => None,
+    };
+    let mut records: Vec<RbacRecord> = t
+        .get_permissions()
+        .map_api_error()?
+        .into_iter()
+        .filter(|r| {
+            user.as_ref()
+                .map(|u| &r.credential.to_string() == u)
+                .unwrap_or(true)
+                && scope.as_ref().map(|s| &r.resource == s).unwrap_or(true)
+        })
+        .collect();
+    records.sort_by(|a, b| {
+        (&a.resource, &a.credential, a.permission).cmp(&(&b.resource, &b.credential, b.permission))
+    });
+    Ok(records
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(page_size(size))
+        .collect())
+}
+
+/**
+ * All projects sorted by name if `keyword` is blank, otherwise projects matching the keyword
+ */
+fn list_projects<T>(
+    t: &T,
+    keyword: Option<String>,
+    size: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<Entity>, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    if keyword.is_blank() {
+        t.get_entry_points().map(|entities| {
+            let mut es: Vec<Entity> = entities.into_iter().map(|e| fill_entity(t, e)).collect();
+            es.sort_by_key(|e| e.name.clone());
+            es
+        })
+    } else {
+        search_entities(
+            t,
+            keyword,
+            size,
+            offset,
+            set![registry_provider::EntityType::Project],
+            None,
+        )
+    }
+}
+
+fn search_children<T>(
+    t: &T,
+    id_or_name: String,
+    keyword: Option<String>,
+    size: Option<usize>,
+    offset: Option<usize>,
+    types: HashSet<registry_provider::EntityType>,
+) -> Result<Vec<Entity>, RegistryError>
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    debug!("Project name: {}", id_or_name);
+    let scope_id = get_id(t, id_or_name)?;
+
+    if keyword.is_blank() {
+        let children = t
+            .get_children(scope_id, types)
+            .map(|es| es.into_iter().map(|e| fill_entity(t, e)).collect());
+        children.map(|mut es: Vec<_>| {
+            es.sort_by_key(|e| e.name.clone());
+            es
+        })
+    } else {
+        search_entities(t, keyword, size, offset, types, Some(scope_id))
+    }
+}
+
+fn fill_entity<T>(this: &T, mut e: registry_provider::Entity<EntityProperty>) -> Entity
+where
+    T: RegistryProvider<EntityProperty>,
+{
+    match &mut e.properties.attributes {
+        registry_provider::Attributes::Project => {
+            let project_id = e.id;
+            let mut project: Entity = e.into();
+            // Contents
+            let children = this
+                .get_neighbors(project_id, EdgeType::Contains)
+                .expect("Data inconsistency detected");
+            match &mut project.attributes {
+                EntityAttributes::Project(attr) => {
+                    attr.sources = children
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::Source)
+                        .map(EntityRef::new)
+                        .collect();
+                    attr.anchors = children
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::Anchor)
+                        .map(EntityRef::new)
+                        .collect();
+                    attr.anchor_features = children
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::AnchorFeature)
+                        .map(EntityRef::new)
+                        .collect();
+                    attr.derived_features = children
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::DerivedFeature)
+                        .map(EntityRef::new)
+                        .collect();
                 }
-                FeathrApiRequest::GetProjectDataSourceVersion {
-                    project_id_or_name,
-                    id_or_name,
-                    version,
-                } => {
-                    let (_, source_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let source = this.get_entity(source_id).map(|e| fill_entity(this, e))?;
-                    this.get_entity_version(&source.qualified_name, version)
-                        .into()
+                _ => panic!("Data inconsistency detected"),
+            };
+            project
+        }
+        registry_provider::Attributes::Anchor => {
+            let anchor_id = e.id;
+            let mut anchor: Entity = e.into();
+            // Sources, can be empty
+            let sources: Vec<EntityRef> = this
+                .get_neighbors(anchor_id, EdgeType::Consumes)
+                .expect("Data inconsistency detected")
+                .into_iter()
+                .map(|e| EntityRef::new(&e))
+                .collect();
+            // Features
+            let features: Vec<EntityRef> = this
+                .get_neighbors(anchor_id, EdgeType::Contains)
+                .expect("Data inconsistency detected")
+                .into_iter()
+                .map(|e| EntityRef::new(&e))
+                .collect();
+            match &mut anchor.attributes {
+                EntityAttributes::Anchor(attr) => {
+                    attr.source = sources.first().cloned();
+                    attr.sources = sources;
+                    attr.features = features;
                 }
-                FeathrApiRequest::CreateProjectDataSource {
-                    project_id_or_name,
-                    mut definition,
-                } => {
-                    debug!(
-                        "Creating Source in project {}: {:?}",
-                        project_id_or_name, definition
-                    );
-                    let project_id = get_id(this, project_id_or_name)?;
-                    let project_name = get_name(this, project_id)?;
-                    definition.qualified_name = format!("{}__{}", project_name, definition.name);
-                    this.new_source(project_id, &definition.try_into()?)
-                        .await
-                        .into()
+                _ => panic!("Data inconsistency detected"),
+            };
+            anchor
+        }
+        registry_provider::Attributes::DerivedFeature(_) => {
+            let feature_id = e.id;
+            let mut feature: Entity = e.into();
+            // Contents
+            let upstream = this
+                .get_neighbors(feature_id, EdgeType::DerivesFrom)
+                .expect("Data inconsistency detected");
+            match &mut feature.attributes {
+                EntityAttributes::DerivedFeature(attr) => {
+                    attr.input_anchor_features = upstream
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::AnchorFeature)
+                        .map(EntityRef::new)
+                        .collect();
+                    attr.input_derived_features = upstream
+                        .iter()
+                        .filter(|&e| e.entity_type == registry_provider::EntityType::DerivedFeature)
+                        .map(EntityRef::new)
+                        .collect();
                 }
-                FeathrApiRequest::GetProjectAnchors {
-                    project_id_or_name,
-                    keyword,
-                    size,
-                    offset,
-                } => {
-                    debug!("Project name: {}", project_id_or_name);
-                    search_children(
-                        this,
-                        project_id_or_name,
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::Anchor],
-                    )
-                    .into()
+                _ => panic!("Data inconsistency detected"),
+            };
+
+            feature
+        }
+        _ => e.into(),
+    }
+}
+
+async fn handle_request<T>(
+    this: &mut T,
+    request: FeathrApiRequest,
+) -> Result<FeathrApiResponse, ApiError>
+where
+    T: RegistryProvider<EntityProperty> + RbacProvider,
+{
+    Ok(match request {
+        FeathrApiRequest::CreateProject { mut definition, .. } => {
+            validate_name(&definition.name)?;
+            definition.qualified_name = definition.name.clone();
+            this.new_project(&definition.try_into()?).await.into()
+        }
+        FeathrApiRequest::CloneProject {
+            id_or_name,
+            new_name,
+            id_seed,
+        } => {
+            validate_name(&new_name)?;
+            let project_id = get_id(this, id_or_name)?;
+            this.clone_project(project_id, &new_name, id_seed)
+                .await
+                .into()
+        }
+        FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name,
+            mut definition,
+            ..
+        } => {
+            debug!(
+                "Creating Source in project {}: {:?}",
+                project_id_or_name, definition
+            );
+            validate_name(&definition.name)?;
+            let project_id = get_id(this, project_id_or_name)?;
+            let project_name = get_name(this, project_id)?;
+            definition.qualified_name = child_qualified_name(&project_name, &definition.name);
+            this.new_source(project_id, &definition.try_into()?)
+                .await
+                .into()
+        }
+        FeathrApiRequest::DeleteProjectDataSource {
+            project_id_or_name,
+            id_or_name,
+            force,
+            ..
+        } => {
+            let source = get_typed_child(this, project_id_or_name, id_or_name, EntityType::Source)?;
+            this.delete_source(source.id, force).await.into()
+        }
+        FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name,
+            mut definition,
+            ..
+        } => {
+            validate_name(&definition.name)?;
+            let project_id = get_id(this, project_id_or_name)?;
+            let project_name = get_name(this, project_id)?;
+            definition.qualified_name = child_qualified_name(&project_name, &definition.name);
+            this.new_anchor(project_id, &definition.try_into()?)
+                .await
+                .into()
+        }
+        FeathrApiRequest::CreateProjectDerivedFeature {
+            project_id_or_name,
+            mut definition,
+            ..
+        } => {
+            validate_name(&definition.name)?;
+            let project_id = get_id(this, project_id_or_name)?;
+            let project_name = get_name(this, project_id)?;
+            definition.qualified_name = child_qualified_name(&project_name, &definition.name);
+            this.new_derived_feature(project_id, &definition.try_into()?)
+                .await
+                .into()
+        }
+        FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name,
+            anchor_id_or_name,
+            mut definition,
+            ..
+        } => {
+            validate_name(&definition.name)?;
+            let (project_id, anchor_id) =
+                get_child_id(this, project_id_or_name, anchor_id_or_name)?;
+            let anchor_name = get_name(this, anchor_id)?;
+            definition.qualified_name = child_qualified_name(&anchor_name, &definition.name);
+            this.new_anchor_feature(project_id, anchor_id, &definition.try_into()?)
+                .await
+                .into()
+        }
+        FeathrApiRequest::BatchLoad {
+            entities,
+            edges,
+            permissions,
+        } => this.load_data(entities, edges, permissions).await.into(),
+        FeathrApiRequest::UpdateEntityTags {
+            id_or_name,
+            tags,
+            if_match,
+            new_etag,
+        } => {
+            let id = get_id(this, id_or_name)?;
+            this.update_tags(id, tags, if_match, new_etag).await?;
+            this.get_entity(id).map(|e| fill_entity(this, e)).into()
+        }
+        FeathrApiRequest::BulkTagProject {
+            project_id_or_name,
+            mut add_tags,
+            remove_keys,
+            new_etag,
+        } => {
+            let project_id = get_id(this, project_id_or_name)?;
+            // Tags with empty value are removed
+            add_tags.extend(remove_keys.into_iter().map(|k| (k, String::new())));
+            this.update_project_tags(project_id, add_tags, new_etag)
+                .await
+                .into()
+        }
+        FeathrApiRequest::UpdateEntityLinks { id_or_name, links } => {
+            let links: Vec<registry_provider::EntityLink> = links
+                .into_iter()
+                .map(|l| l.try_into())
+                .collect::<Result<_, _>>()?;
+            let id = get_id(this, id_or_name)?;
+            this.update_links(id, links).await?;
+            this.get_entity(id).map(|e| fill_entity(this, e)).into()
+        }
+        FeathrApiRequest::UpdateDerivedFeatureInputs { id_or_name, inputs } => {
+            let id = get_id(this, id_or_name)?;
+            let add = inputs
+                .add
+                .into_iter()
+                .map(|s| get_id(this, s))
+                .collect::<Result<_, _>>()?;
+            let remove = inputs
+                .remove
+                .into_iter()
+                .map(|s| get_id(this, s))
+                .collect::<Result<_, _>>()?;
+            this.update_derived_inputs(id, add, remove).await?;
+            this.get_entity(id).map(|e| fill_entity(this, e)).into()
+        }
+        FeathrApiRequest::MoveAnchorFeature { id_or_name, anchor } => {
+            let id = get_id(this, id_or_name)?;
+            let anchor_id = get_id(this, anchor.anchor)?;
+            this.move_anchor_feature(id, anchor_id).await?;
+            this.get_entity(id).map(|e| fill_entity(this, e)).into()
+        }
+        FeathrApiRequest::AddUserRole {
+            project_id_or_name,
+            user,
+            role,
+            requestor,
+            reason,
+        } => {
+            let grant = RbacRecord {
+                credential: user,
+                resource: project_id_or_name.parse()?,
+                permission: role,
+                requestor,
+                reason,
+                time: Utc::now(),
+            };
+            this.grant_permission(&grant).await.into()
+        }
+        FeathrApiRequest::AddUserRoles {
+            project_id_or_name,
+            users,
+            requestor,
+            reason,
+        } => {
+            let resource: Resource = project_id_or_name.parse()?;
+            let time = Utc::now();
+            let mut results: Vec<UserRoleResult> = vec![];
+            for (user_name, role) in users {
+                let ret = match parse_user_name(&user_name) {
+                    Ok(credential) => {
+                        let grant = RbacRecord {
+                            credential,
+                            resource: resource.clone(),
+                            permission: role,
+                            requestor: requestor.clone(),
+                            reason: reason.clone(),
+                            time,
+                        };
+                        this.grant_permission(&grant).await.map_api_error()
+                    }
+                    Err(e) => Err(e),
+                };
+                results.push(UserRoleResult {
+                    user_name,
+                    success: ret.is_ok(),
+                    error: ret.err().map(|e| e.to_string()),
+                });
+            }
+            results.into()
+        }
+        FeathrApiRequest::DeleteUserRole {
+            project_id_or_name,
+            user,
+            role,
+            requestor,
+            reason,
+        } => {
+            let revoke = RbacRecord {
+                credential: user,
+                resource: project_id_or_name.parse()?,
+                permission: role,
+                requestor,
+                reason,
+                time: Utc::now(),
+            };
+            this.revoke_permission(&revoke).await.into()
+        }
+        request => handle_read_request(&*this, request).await?,
+    })
+}
+
+/**
+ * User names must be non-empty and cannot contain whitespaces or control characters
+ */
+fn parse_user_name(user_name: &str) -> Result<Credential, ApiError> {
+    if user_name.is_empty()
+        || user_name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid user name `{}`",
+            user_name
+        )));
+    }
+    user_name.parse::<Credential>().map_api_error()
+}
+
+async fn handle_read_request<T>(
+    this: &T,
+    request: FeathrApiRequest,
+) -> Result<FeathrApiResponse, ApiError>
+where
+    T: RegistryProvider<EntityProperty> + RbacProvider,
+{
+    Ok(match request {
+        FeathrApiRequest::GetProjects {
+            keyword,
+            size,
+            offset,
+        } => list_projects(this, keyword, size, offset)
+            .map(|r| {
+                r.into_iter()
+                    .map(|e| e.qualified_name)
+                    .collect::<Vec<String>>()
+            })
+            .into(),
+        FeathrApiRequest::GetProjectsDetailed {
+            keyword,
+            size,
+            offset,
+        } => list_projects(this, keyword, size, offset).into(),
+        FeathrApiRequest::GetProject { id_or_name } => {
+            match this.get_entity_by_id_or_qualified_name(&id_or_name) {
+                Ok(e) => fill_entity(this, e).into(),
+                Err(e) => e.into(),
+            }
+        }
+        FeathrApiRequest::GetProjectSummary { id_or_name } => {
+            let project_id = get_id(this, id_or_name)?;
+            let project = this.get_entity(project_id).map_api_error()?;
+            if project.entity_type != EntityType::Project {
+                return Err(RegistryError::WrongEntityType(project_id, project.entity_type).into());
+            }
+            // Only direct children are counted, deleted ones are excluded
+            this.get_neighbors(project_id, EdgeType::Contains)
+                .map_api_error()?
+                .into_iter()
+                .filter(|e| this.get_entity(e.id).is_ok())
+                .collect::<ProjectSummary>()
+                .into()
+        }
+        FeathrApiRequest::GetProjectLineage {
+            id_or_name,
+            size,
+            offset,
+        } => {
+            debug!("Project name: {}", id_or_name);
+
+            let (entities, mut edges) = this.get_project(&id_or_name)?;
+            let mut has_more = false;
+            let entities = match offset {
+                Some(offset) => {
+                    let root = get_id(this, id_or_name)?;
+                    let size = size.unwrap_or(DEFAULT_LINEAGE_SIZE).min(MAX_LINEAGE_SIZE);
+                    // Page in the same order as the relationships in the response
+                    edges.sort_by(|a, b| {
+                        (a.from, a.to, a.edge_type).cmp(&(b.from, b.to, b.edge_type))
+                    });
+                    let (entities, page, more) = page_edges(root, entities, edges, offset, size);
+                    edges = page;
+                    has_more = more;
+                    entities
                 }
-                FeathrApiRequest::GetProjectAnchor {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    this.get_entity(anchor_id)
+                None => entities,
+            };
+            let lineage: EntityLineage = (
+                entities
+                    .into_iter()
+                    .map(|e| fill_entity(this, e))
+                    .collect::<Vec<_>>(),
+                edges,
+            )
+                .into();
+            EntityLineage {
+                has_more,
+                ..lineage
+            }
+            .into()
+        }
+        FeathrApiRequest::GetProjectAtlasEntities { id_or_name } => {
+            let project_id = get_id(this, id_or_name)?;
+            let (mut entities, edges) = this.get_project(&get_name(this, project_id)?)?;
+            // Deleted entities are disconnected from the project already, this is only a safeguard
+            let deleted: HashSet<Uuid> = this.list_deleted().into_iter().map(|e| e.id).collect();
+            entities.retain(|e| !deleted.contains(&e.id));
+            AtlasEntities::new(entities, edges)
+                .map_err(|e| ApiError::InternalError(e.to_string()))?
+                .into()
+        }
+        FeathrApiRequest::GetProjectFeatures {
+            project_id_or_name,
+            keyword,
+            size,
+            offset,
+        } => {
+            debug!("Project name: {}", project_id_or_name);
+            search_children(
+                this,
+                project_id_or_name,
+                keyword,
+                size,
+                offset,
+                set![
+                    registry_provider::EntityType::AnchorFeature,
+                    registry_provider::EntityType::DerivedFeature
+                ],
+            )
+            .into()
+        }
+        FeathrApiRequest::SearchAllFeatures {
+            keyword,
+            types,
+            size,
+            offset,
+            credential,
+        } => {
+            let feature_types = set![EntityType::AnchorFeature, EntityType::DerivedFeature];
+            // Only features are returned, empty `types` means all kinds of features
+            let types = if types.is_empty() {
+                feature_types
+            } else {
+                types.intersection(&feature_types).copied().collect()
+            };
+            search_readable_entities(this, keyword, size, offset, types, &credential).into()
+        }
+        FeathrApiRequest::GetProjectFeaturesInOrder { project_id_or_name } => {
+            let project_id = get_id(this, project_id_or_name)?;
+            this.topo_order_features(project_id)
+                .map_api_error()?
+                .into_iter()
+                .map(|id| this.get_entity(id).map(|e| fill_entity(this, e)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_api_error()?
+                .into()
+        }
+        FeathrApiRequest::GetProjectDataSources {
+            project_id_or_name,
+            keyword,
+            size,
+            offset,
+        } => {
+            debug!("Project name: {}", project_id_or_name);
+            search_children(
+                this,
+                project_id_or_name,
+                keyword,
+                size,
+                offset,
+                set![registry_provider::EntityType::Source],
+            )
+            .into()
+        }
+        FeathrApiRequest::GetProjectDataSource {
+            project_id_or_name,
+            id_or_name,
+        } => get_typed_child(this, project_id_or_name, id_or_name, EntityType::Source)
+            .map(|e| fill_entity(this, e))
+            .into(),
+        FeathrApiRequest::GetSourceLineage {
+            project_id_or_name,
+            source_id_or_name,
+            size,
+            offset,
+        } => {
+            let source_id = get_typed_child(
+                this,
+                project_id_or_name,
+                source_id_or_name,
+                EntityType::Source,
+            )?
+            .id;
+            let size = size.unwrap_or(DEFAULT_LINEAGE_SIZE).min(MAX_LINEAGE_SIZE);
+            if let Some(offset) = offset {
+                let (entities, edges, has_more, truncated) =
+                    this.get_source_downstream_page(source_id, offset, size)?;
+                let lineage: EntityLineage = (
+                    entities
+                        .into_iter()
                         .map(|e| fill_entity(this, e))
-                        .into()
-                }
-                FeathrApiRequest::GetProjectAnchorVersions {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let anchor = this.get_entity(anchor_id).map(|e| fill_entity(this, e))?;
-                    let mut ret = this.get_all_versions(&anchor.qualified_name);
-                    ret.sort_by_key(|e| e.version);
-                    ret.into()
+                        .collect::<Vec<_>>(),
+                    edges,
+                )
+                    .into();
+                return Ok(EntityLineage {
+                    truncated,
+                    has_more,
+                    ..lineage
                 }
-                FeathrApiRequest::GetProjectAnchorVersion {
-                    project_id_or_name,
-                    id_or_name,
-                    version,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let anchor = this.get_entity(anchor_id).map(|e| fill_entity(this, e))?;
-                    this.get_entity_version(&anchor.qualified_name, version)
-                        .into()
+                .into());
+            }
+            let (entities, edges, truncated) = this.get_source_downstream(source_id, size)?;
+            let lineage: EntityLineage = (
+                entities
+                    .into_iter()
+                    .map(|e| fill_entity(this, e))
+                    .collect::<Vec<_>>(),
+                edges,
+            )
+                .into();
+            EntityLineage {
+                truncated,
+                ..lineage
+            }
+            .into()
+        }
+        FeathrApiRequest::GetSourceConsumers {
+            project_id_or_name,
+            source_id_or_name,
+        } => {
+            let source_id = get_typed_child(
+                this,
+                project_id_or_name,
+                source_id_or_name,
+                EntityType::Source,
+            )?
+            .id;
+            // Anchors consume the source, i.e. the source produces them
+            let deleted: HashSet<Uuid> = this.list_deleted().into_iter().map(|e| e.id).collect();
+            let mut anchors: Vec<_> = this
+                .get_neighbors(source_id, EdgeType::Produces)?
+                .into_iter()
+                .filter(|e| e.entity_type == EntityType::Anchor && !deleted.contains(&e.id))
+                .collect();
+            anchors.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+            anchors
+                .into_iter()
+                .map(|e| fill_entity(this, e))
+                .collect::<Vec<_>>()
+                .into()
+        }
+        FeathrApiRequest::GetProjectDataSourceVersions {
+            project_id_or_name,
+            id_or_name,
+        } => {
+            let source = get_typed_child(this, project_id_or_name, id_or_name, EntityType::Source)?;
+            let mut ret = this.get_all_versions(&source.qualified_name);
+            ret.sort_by_key(|e| e.version);
+            ret.into()
+        }
+        FeathrApiRequest::GetProjectDataSourceVersion {
+            project_id_or_name,
+            id_or_name,
+            version,
+        } => {
+            let source = get_typed_child(this, project_id_or_name, id_or_name, EntityType::Source)?;
+            this.get_entity_version(&source.qualified_name, version)
+                .into()
+        }
+        FeathrApiRequest::GetProjectAnchors {
+            project_id_or_name,
+            keyword,
+            size,
+            offset,
+        } => {
+            debug!("Project name: {}", project_id_or_name);
+            search_children(
+                this,
+                project_id_or_name,
+                keyword,
+                size,
+                offset,
+                set![registry_provider::EntityType::Anchor],
+            )
+            .into()
+        }
+        FeathrApiRequest::GetProjectAnchor {
+            project_id_or_name,
+            id_or_name,
+        } => get_typed_child(this, project_id_or_name, id_or_name, EntityType::Anchor)
+            .map(|e| fill_entity(this, e))
+            .into(),
+        FeathrApiRequest::GetProjectAnchorVersions {
+            project_id_or_name,
+            id_or_name,
+        } => {
+            let anchor = get_typed_child(this, project_id_or_name, id_or_name, EntityType::Anchor)?;
+            let mut ret = this.get_all_versions(&anchor.qualified_name);
+            ret.sort_by_key(|e| e.version);
+            ret.into()
+        }
+        FeathrApiRequest::GetProjectAnchorVersion {
+            project_id_or_name,
+            id_or_name,
+            version,
+        } => {
+            let anchor = get_typed_child(this, project_id_or_name, id_or_name, EntityType::Anchor)?;
+            this.get_entity_version(&anchor.qualified_name, version)
+                .into()
+        }
+        FeathrApiRequest::GetProjectDerivedFeatures {
+            project_id_or_name,
+            keyword,
+            size,
+            offset,
+        } => {
+            debug!("Project name: {}", project_id_or_name);
+            search_children(
+                this,
+                project_id_or_name,
+                keyword,
+                size,
+                offset,
+                set![registry_provider::EntityType::DerivedFeature],
+            )
+            .into()
+        }
+        FeathrApiRequest::GetProjectDerivedFeature {
+            project_id_or_name,
+            id_or_name,
+        } => get_typed_child(
+            this,
+            project_id_or_name,
+            id_or_name,
+            EntityType::DerivedFeature,
+        )
+        .into(),
+        FeathrApiRequest::GetProjectDerivedFeatureVersions {
+            project_id_or_name,
+            id_or_name,
+        } => {
+            let f = get_typed_child(
+                this,
+                project_id_or_name,
+                id_or_name,
+                EntityType::DerivedFeature,
+            )?;
+            let mut ret = this.get_all_versions(&f.qualified_name);
+            ret.sort_by_key(|e| e.version);
+            ret.into()
+        }
+        FeathrApiRequest::GetProjectDerivedFeatureVersion {
+            project_id_or_name,
+            id_or_name,
+            version,
+        } => {
+            let f = get_typed_child(
+                this,
+                project_id_or_name,
+                id_or_name,
+                EntityType::DerivedFeature,
+            )?;
+            this.get_entity_version(&f.qualified_name, version).into()
+        }
+        FeathrApiRequest::GetAnchorFeatures {
+            project_id_or_name,
+            anchor_id_or_name,
+            keyword,
+            size,
+            offset,
+        } => {
+            let (_, anchor_id) = get_child_id(this, project_id_or_name, anchor_id_or_name)?;
+            search_children(
+                this,
+                anchor_id.to_string(),
+                keyword,
+                size,
+                offset,
+                set![registry_provider::EntityType::AnchorFeature],
+            )
+            .into()
+        }
+        FeathrApiRequest::GetAnchorFeature {
+            project_id_or_name,
+            anchor_id_or_name,
+            id_or_name,
+        } => {
+            let anchor = get_typed_child(
+                this,
+                project_id_or_name,
+                anchor_id_or_name,
+                EntityType::Anchor,
+            )?;
+            get_typed_child(
+                this,
+                anchor.id.to_string(),
+                id_or_name,
+                EntityType::AnchorFeature,
+            )
+            .into()
+        }
+        FeathrApiRequest::GetAnchorFeatureVersions {
+            project_id_or_name,
+            anchor_id_or_name,
+            id_or_name,
+        } => {
+            let anchor = get_typed_child(
+                this,
+                project_id_or_name,
+                anchor_id_or_name,
+                EntityType::Anchor,
+            )?;
+            let f = get_typed_child(
+                this,
+                anchor.id.to_string(),
+                id_or_name,
+                EntityType::AnchorFeature,
+            )?;
+            let mut ret = this.get_all_versions(&f.qualified_name);
+            ret.sort_by_key(|e| e.version);
+            ret.into()
+        }
+        FeathrApiRequest::GetAnchorFeatureVersion {
+            project_id_or_name,
+            anchor_id_or_name,
+            id_or_name,
+            version,
+        } => {
+            let anchor = get_typed_child(
+                this,
+                project_id_or_name,
+                anchor_id_or_name,
+                EntityType::Anchor,
+            )?;
+            let f = get_typed_child(
+                this,
+                anchor.id.to_string(),
+                id_or_name,
+                EntityType::AnchorFeature,
+            )?;
+            this.get_entity_version(&f.qualified_name, version).into()
+        }
+        FeathrApiRequest::GetFeature { id_or_name } => this
+            .get_entity_by_id_or_qualified_name(&id_or_name)
+            .map(|e| fill_entity(this, e))
+            .into(),
+        FeathrApiRequest::GetFeatureSchema { id_or_name } => {
+            let entity: Entity = this.get_entity_by_id_or_qualified_name(&id_or_name)?.into();
+            match entity.attributes {
+                EntityAttributes::AnchorFeature(attr) => FeatureSchema {
+                    keys: attr.key,
+                    feature_type: attr.type_,
+                },
+                EntityAttributes::DerivedFeature(attr) => FeatureSchema {
+                    keys: attr.key,
+                    feature_type: attr.type_,
+                },
+                _ => {
+                    return Err(ApiError::BadRequest(format!(
+                        "Entity '{}' is not a feature",
+                        id_or_name
+                    )))
                 }
-                FeathrApiRequest::CreateProjectAnchor {
-                    project_id_or_name,
-                    mut definition,
-                } => {
-                    let project_id = get_id(this, project_id_or_name)?;
-                    let project_name = get_name(this, project_id)?;
-                    definition.qualified_name = format!("{}__{}", project_name, definition.name);
-                    this.new_anchor(project_id, &definition.try_into()?)
-                        .await
-                        .into()
+            }
+            .into()
+        }
+        FeathrApiRequest::GetFeatureWithInputs { id_or_name } => {
+            let feature = this.get_entity_by_id_or_qualified_name(&id_or_name)?;
+            if !matches!(
+                feature.entity_type,
+                EntityType::AnchorFeature | EntityType::DerivedFeature
+            ) {
+                return Err(ApiError::BadRequest(format!(
+                    "Entity '{}' is not a feature",
+                    id_or_name
+                )));
+            }
+            let deleted: HashSet<Uuid> = this.list_deleted().into_iter().map(|e| e.id).collect();
+            let mut inputs = BTreeMap::new();
+            let mut deleted_inputs = vec![];
+            // Anchor features consume sources, derived features derive from other features
+            let upstream = [EdgeType::Consumes, EdgeType::DerivesFrom]
+                .into_iter()
+                .map(|t| this.get_neighbors(feature.id, t))
+                .collect::<Result<Vec<_>, _>>()?;
+            for input in upstream.into_iter().flatten() {
+                if deleted.contains(&input.id) {
+                    deleted_inputs.push(input.id.to_string());
+                } else {
+                    inputs.insert(input.id.to_string(), fill_entity(this, input));
                 }
-                FeathrApiRequest::GetProjectDerivedFeatures {
-                    project_id_or_name,
-                    keyword,
-                    size,
-                    offset,
-                } => {
-                    debug!("Project name: {}", project_id_or_name);
-                    search_children(
-                        this,
-                        project_id_or_name,
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::DerivedFeature],
+            }
+            FeathrApiResponse::ResolvedFeature(ResolvedFeature {
+                feature: fill_entity(this, feature),
+                inputs,
+                deleted_inputs,
+            })
+        }
+        FeathrApiRequest::GetEntityEdges {
+            id_or_name,
+            edge_type,
+        } => {
+            let id = get_id(this, id_or_name)?;
+            this.get_edges(id, edge_type)?
+                .into_iter()
+                .map(Relationship::from)
+                .collect::<Vec<_>>()
+                .into()
+        }
+        FeathrApiRequest::DiffEntityVersions {
+            id_or_name,
+            from_version,
+            to_version,
+        } => {
+            let qualified_name = this
+                .get_entity_by_id_or_qualified_name(&id_or_name)?
+                .qualified_name;
+            let from: Entity = this
+                .get_entity_version(&qualified_name, Some(from_version))?
+                .into();
+            let to: Entity = this
+                .get_entity_version(&qualified_name, Some(to_version))?
+                .into();
+            from.diff(&to).into()
+        }
+        FeathrApiRequest::GetEntities { ids } => {
+            // De-dup ids and keep the requested order
+            let mut seen: HashSet<String> = HashSet::new();
+            let ids: Vec<String> = ids
+                .into_iter()
+                .filter(|id| {
+                    seen.insert(
+                        Uuid::parse_str(id)
+                            .map(|uuid| uuid.to_string())
+                            .unwrap_or_else(|_| id.clone()),
                     )
-                    .into()
-                }
-                FeathrApiRequest::GetProjectDerivedFeature {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, feature_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    this.get_entity(feature_id).into()
-                }
-                FeathrApiRequest::GetProjectDerivedFeatureVersions {
-                    project_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, feature_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let f = this.get_entity(feature_id)?;
-                    let mut ret = this.get_all_versions(&f.qualified_name);
-                    ret.sort_by_key(|e| e.version);
-                    ret.into()
+                })
+                .collect();
+            let mut found: HashMap<Uuid, registry_provider::Entity<EntityProperty>> = this
+                .get_entities(
+                    ids.iter()
+                        .filter_map(|id| Uuid::parse_str(id).ok())
+                        .collect(),
+                )
+                .map_api_error()?
+                .into_iter()
+                .map(|e| (e.id, e))
+                .collect();
+            let mut entities = vec![];
+            let mut missing = vec![];
+            for id in ids {
+                match Uuid::parse_str(&id)
+                    .ok()
+                    .and_then(|uuid| found.remove(&uuid))
+                {
+                    Some(e) => entities.push(fill_entity(this, e)),
+                    None => missing.push(id),
                 }
-                FeathrApiRequest::GetProjectDerivedFeatureVersion {
-                    project_id_or_name,
-                    id_or_name,
-                    version,
-                } => {
-                    let (_, feature_id) = get_child_id(this, project_id_or_name, id_or_name)?;
-                    let f = this.get_entity(feature_id)?;
-                    this.get_entity_version(&f.qualified_name, version).into()
+            }
+            FeathrApiResponse::Entities(Entities { entities, missing })
+        }
+        // The caller is responsible for passing in the registry as it was at `seq`
+        FeathrApiRequest::GetEntityAtSeq { id_or_name, .. } => this
+            .get_entity_by_id_or_qualified_name(&id_or_name)
+            .map(|e| fill_entity(this, e))
+            .into(),
+        FeathrApiRequest::GetEntityHistory { .. } => {
+            return Err(ApiError::BadRequest(
+                "Entity history is only available on Raft nodes".to_string(),
+            ))
+        }
+        FeathrApiRequest::FindFeaturesByKey { key } => this
+            .get_features_by_key(&key)
+            .into_iter()
+            .map(|e| fill_entity(this, e))
+            .collect::<Vec<_>>()
+            .into(),
+        // Edges of deleted entities are gone, so they're returned as is
+        FeathrApiRequest::GetDeletedEntities => this
+            .list_deleted()
+            .into_iter()
+            .map(Entity::from)
+            .collect::<Vec<_>>()
+            .into(),
+        FeathrApiRequest::ListEntitiesByType {
+            entity_type,
+            cursor,
+            size,
+            credential,
+        } => {
+            let mut after = match cursor {
+                Some(c) => Some(
+                    Uuid::parse_str(&c)
+                        .map_err(|_| ApiError::BadRequest(format!("Invalid cursor `{}`", c)))?,
+                ),
+                None => None,
+            };
+            let size = page_size(size);
+            let all_readable =
+                this.check_permission(&credential, &Resource::Global, Permission::Read)?;
+            // Keep reading until the page is filled with entities the caller can read
+            let mut entities = vec![];
+            let next = 'pages: loop {
+                let (batch, more) = this.list_entities_by_type(entity_type, after, size);
+                let count = batch.len();
+                for (i, e) in batch.into_iter().enumerate() {
+                    let id = e.id;
+                    after = Some(id);
+                    if all_readable || can_read(this, &credential, id) {
+                        entities.push(fill_entity(this, e));
+                    }
+                    if entities.len() == size {
+                        break 'pages Some(id).filter(|_| i + 1 < count || more.is_some());
+                    }
                 }
-                FeathrApiRequest::CreateProjectDerivedFeature {
-                    project_id_or_name,
-                    mut definition,
-                } => {
-                    let project_id = get_id(this, project_id_or_name)?;
-                    let project_name = get_name(this, project_id)?;
-                    definition.qualified_name = format!("{}__{}", project_name, definition.name);
-                    this.new_derived_feature(project_id, &definition.try_into()?)
-                        .await
-                        .into()
+                if more.is_none() {
+                    break None;
                 }
-                FeathrApiRequest::GetAnchorFeatures {
-                    project_id_or_name,
-                    anchor_id_or_name,
-                    keyword,
-                    size,
+            };
+            FeathrApiResponse::EntityPage(
+                Entities {
+                    entities,
+                    missing: vec![],
+                },
+                next.map(|id| id.to_string()),
+            )
+        }
+        FeathrApiRequest::GetFeatureLineage {
+            id_or_name,
+            size,
+            offset,
+        } => {
+            debug!("Feature name: {}", id_or_name);
+            let id = get_id(this, id_or_name)?;
+            let size = size.unwrap_or(DEFAULT_LINEAGE_SIZE).min(MAX_LINEAGE_SIZE);
+            if let Some(offset) = offset {
+                // Enough edges from both directions to fill the page wherever it starts
+                let end = offset.saturating_add(size);
+                let (up_entities, up_edges, up_more, up_truncated) = this
+                    .bfs_page(id, registry_provider::EdgeType::Consumes, 0, end)
+                    .map_api_error()?;
+                let (down_entities, down_edges, down_more, down_truncated) = this
+                    .bfs_page(id, registry_provider::EdgeType::Produces, 0, end)
+                    .map_api_error()?;
+                let (entities, edges, more) = page_edges(
+                    id,
+                    up_entities.into_iter().chain(down_entities).collect(),
+                    up_edges.into_iter().chain(down_edges).collect(),
                     offset,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, anchor_id_or_name)?;
-                    search_children(
-                        this,
-                        anchor_id.to_string(),
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::AnchorFeature],
-                    )
-                    .into()
-                }
-                FeathrApiRequest::GetAnchorFeature {
-                    project_id_or_name,
-                    anchor_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, anchor_id_or_name)?;
-                    let (_, feature_id) = get_child_id(this, anchor_id.to_string(), id_or_name)?;
-                    this.get_entity(feature_id).into()
-                }
-                FeathrApiRequest::GetAnchorFeatureVersions {
-                    project_id_or_name,
-                    anchor_id_or_name,
-                    id_or_name,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, anchor_id_or_name)?;
-                    let (_, feature_id) = get_child_id(this, anchor_id.to_string(), id_or_name)?;
-                    let f = this.get_entity(feature_id)?;
-                    let mut ret = this.get_all_versions(&f.qualified_name);
-                    ret.sort_by_key(|e| e.version);
-                    ret.into()
-                }
-                FeathrApiRequest::GetAnchorFeatureVersion {
-                    project_id_or_name,
-                    anchor_id_or_name,
-                    id_or_name,
-                    version,
-                } => {
-                    let (_, anchor_id) = get_child_id(this, project_id_or_name, anchor_id_or_name)?;
-                    let (_, feature_id) = get_child_id(this, anchor_id.to_string(), id_or_name)?;
-                    let f = this.get_entity(feature_id)?;
-                    this.get_entity_version(&f.qualified_name, version).into()
-                }
-                FeathrApiRequest::CreateAnchorFeature {
-                    project_id_or_name,
-                    anchor_id_or_name,
-                    mut definition,
-                } => {
-                    let (project_id, anchor_id) =
-                        get_child_id(this, project_id_or_name, anchor_id_or_name)?;
-                    let anchor_name = get_name(this, anchor_id)?;
-                    definition.qualified_name = format!("{}__{}", anchor_name, definition.name);
-                    this.new_anchor_feature(project_id, anchor_id, &definition.try_into()?)
-                        .await
-                        .into()
+                    size,
+                );
+                let lineage: EntityLineage = (
+                    entities
+                        .into_iter()
+                        .map(|e| fill_entity(this, e))
+                        .collect::<Vec<_>>(),
+                    edges,
+                )
+                    .into();
+                return Ok(EntityLineage {
+                    truncated: up_truncated || down_truncated,
+                    has_more: more || up_more || down_more,
+                    ..lineage
                 }
-                FeathrApiRequest::GetFeature { id_or_name } => this
-                    .get_entity_by_id_or_qualified_name(&id_or_name)
+                .into());
+            }
+            let (up_entities, up_edges, up_truncated) = this
+                .bfs_with_limit(id, registry_provider::EdgeType::Consumes, size)
+                .map_api_error()?;
+            let (down_entities, down_edges, down_truncated) = this
+                .bfs_with_limit(id, registry_provider::EdgeType::Produces, size)
+                .map_api_error()?;
+            let lineage: EntityLineage = (
+                up_entities
+                    .into_iter()
+                    .chain(down_entities.into_iter())
                     .map(|e| fill_entity(this, e))
-                    .into(),
-                FeathrApiRequest::GetFeatureLineage { id_or_name } => {
-                    debug!("Feature name: {}", id_or_name);
-                    let id = get_id(this, id_or_name)?;
-                    let (up_entities, up_edges) = this
-                        .bfs(id, registry_provider::EdgeType::Consumes, None)
-                        .map_api_error()?;
-                    let (down_entities, down_edges) = this
-                        .bfs(id, registry_provider::EdgeType::Produces, None)
-                        .map_api_error()?;
-                    (
-                        up_entities
-                            .into_iter()
-                            .chain(down_entities.into_iter())
-                            .map(|e| fill_entity(this, e))
-                            .collect::<Vec<_>>(),
-                        up_edges
-                            .into_iter()
-                            .chain(down_edges.into_iter())
-                            .collect::<Vec<_>>(),
-                    )
-                        .into()
+                    .collect::<Vec<_>>(),
+                up_edges
+                    .into_iter()
+                    .chain(down_edges.into_iter())
+                    .collect::<Vec<_>>(),
+            )
+                .into();
+            EntityLineage {
+                truncated: up_truncated || down_truncated,
+                ..lineage
+            }
+            .into()
+        }
+        FeathrApiRequest::GetEntityProject { id_or_name } => {
+            let entity = this.get_entity_by_id_or_qualified_name(&id_or_name)?;
+            if entity.entity_type.is_entry_point() {
+                return Err(ApiError::NotFoundError(format!(
+                    "Entity {} is not contained by any project",
+                    id_or_name
+                )));
+            }
+            // Walk up the containers until reaching the project
+            let (containers, _) = this.bfs(entity.id, EdgeType::BelongsTo)?;
+            containers
+                .into_iter()
+                .find(|c| c.entity_type == EntityType::Project)
+                .map(|c| fill_entity(this, c))
+                .ok_or_else(|| {
+                    RegistryError::EntityNotFound(format!(
+                        "Entity {} doesn't belong to any project",
+                        id_or_name
+                    ))
+                })?
+                .into()
+        }
+        FeathrApiRequest::GetEntityContainers { id_or_name } => {
+            let id = get_id(this, id_or_name)?;
+            this.get_neighbors(id, EdgeType::BelongsTo)?
+                .into_iter()
+                .filter(|c| this.get_entity(c.id).is_ok())
+                .map(|c| fill_entity(this, c))
+                .collect::<Vec<_>>()
+                .into()
+        }
+        FeathrApiRequest::GetSuggestions {
+            prefix,
+            limit,
+            credential,
+        } => {
+            // Only suggest entities in the projects the caller can read
+            let scopes =
+                if this.check_permission(&credential, &Resource::Global, Permission::Read)? {
+                    None
+                } else {
+                    let mut scopes = HashSet::new();
+                    for project in this.get_entry_points()? {
+                        if this.check_permission(
+                            &credential,
+                            &Resource::Entity(project.id),
+                            Permission::Read,
+                        )? {
+                            scopes.insert(project.id);
+                        }
+                    }
+                    Some(scopes)
+                };
+            this.suggest_names(&prefix, scopes, limit.unwrap_or(DEFAULT_SUGGESTIONS))?
+                .into()
+        }
+        FeathrApiRequest::GetUserRoles {
+            user,
+            scope,
+            size,
+            offset,
+        } => list_user_roles(this, user, scope, size, offset)?.into(),
+        _ => {
+            return Err(ApiError::BadRequest(
+                "Updating requests must go through the writing path".to_string(),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use common_utils::map;
+    use sql_provider::Registry;
+
+    use registry_provider::{Credential, EdgeType, EntityType, Permission, RegistryProvider};
+
+    use crate::{
+        AnchorDef, AnchorFeatureDef, ApiError, DerivedFeatureDef, DerivedFeatureInputs,
+        EntityAttributes, EntityLink, EntityRef, FeathrApiProvider, FeathrApiRequest,
+        FeathrApiResponse, FeatureAnchor, PageLimits, ProjectDef, ProjectSummary, SourceDef,
+        TypedKey, ValueType,
+    };
+
+    #[tokio::test]
+    async fn created_by_is_kept() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        r.request(FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: "alice".to_string(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        r.request(FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            definition: SourceDef {
+                created_by: "bob".to_string(),
+                ..source_def("s1")
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let source = r
+            .request(FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(source.created_by, "bob");
+        let project = r
+            .request(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(project.created_by, "alice");
+        assert!(project.created_on <= source.created_on);
+    }
+
+    #[tokio::test]
+    async fn stale_etag_is_rejected() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        r.request(FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let etag: uuid::Uuid = r
+            .request(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap()
+            .etag
+            .parse()
+            .unwrap();
+
+        // Both updates are based on the same etag, only the first one can succeed
+        let first = r
+            .request(FeathrApiRequest::UpdateEntityTags {
+                id_or_name: "p1".to_string(),
+                tags: map! { "owner".to_string() => "alice".to_string() },
+                if_match: Some(etag),
+                new_etag: uuid::Uuid::new_v4(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_ne!(first.etag, etag.to_string());
+        let second = r
+            .request(FeathrApiRequest::UpdateEntityTags {
+                id_or_name: "p1".to_string(),
+                tags: map! { "owner".to_string() => "bob".to_string() },
+                if_match: Some(etag),
+                new_etag: uuid::Uuid::new_v4(),
+            })
+            .await;
+        assert!(matches!(
+            second,
+            FeathrApiResponse::Error(ApiError::PreconditionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sourceless_anchor() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        r.request(FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let (anchor_id, _) = r
+            .request(FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                definition: AnchorDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: "a1".to_string(),
+                    qualified_name: Default::default(),
+                    source_id: Default::default(),
+                    source_ids: Default::default(),
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let anchor = r
+            .request(FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match anchor.attributes {
+            EntityAttributes::Anchor(attr) => assert!(attr.source.is_none()),
+            _ => panic!("Wrong entity type"),
+        }
+        assert!(r
+            .get_neighbors(anchor_id, EdgeType::Consumes)
+            .unwrap()
+            .is_empty());
+
+        // Invalid source id is still rejected
+        let resp = r
+            .request(FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                definition: AnchorDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: "a2".to_string(),
+                    qualified_name: Default::default(),
+                    source_id: Default::default(),
+                    source_ids: vec!["not-a-uuid".to_string()],
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await;
+        assert!(matches!(
+            resp,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn multi_source_anchor() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        r.request(FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let mut source_ids = vec![];
+        for name in ["s1", "s2"] {
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateProjectDataSource {
+                    project_id_or_name: "p1".to_string(),
+                    definition: source_def(name),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            source_ids.push(id.to_string());
+        }
+        let (anchor_id, _) = r
+            .request(FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                definition: AnchorDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: "a1".to_string(),
+                    qualified_name: Default::default(),
+                    source_id: Default::default(),
+                    source_ids: source_ids.clone(),
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        assert_eq!(
+            r.get_neighbors(anchor_id, EdgeType::Consumes)
+                .unwrap()
+                .len(),
+            2
+        );
+        let anchor = r
+            .request(FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match anchor.attributes {
+            EntityAttributes::Anchor(attr) => assert_eq!(attr.sources.len(), 2),
+            _ => panic!("Wrong entity type"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_reads_during_writes() {
+        const SOURCES: usize = 100;
+        let r: Arc<tokio::sync::RwLock<Registry<registry_provider::EntityProperty>>> =
+            Default::default();
+        r.write()
+            .await
+            .request(FeathrApiRequest::CreateProject {
+                definition: ProjectDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: "p1".to_string(),
+                    qualified_name: Default::default(),
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let list_sources = || FeathrApiRequest::GetProjectDataSources {
+            project_id_or_name: "p1".to_string(),
+            keyword: None,
+            size: None,
+            offset: None,
+        };
+
+        let writer = {
+            let r = r.clone();
+            tokio::spawn(async move {
+                for i in 0..SOURCES {
+                    r.write()
+                        .await
+                        .request(FeathrApiRequest::CreateProjectDataSource {
+                            project_id_or_name: "p1".to_string(),
+                            definition: source_def(&format!("s{}", i)),
+                            time: None,
+                        })
+                        .await
+                        .into_uuid_and_version()
+                        .unwrap();
                 }
-                FeathrApiRequest::BatchLoad {
-                    entities,
-                    edges,
-                    permissions,
-                } => this.load_data(entities, edges, permissions).await.into(),
-                FeathrApiRequest::GetEntityProject { id_or_name } => {
-                    let entity = this.get_entity_by_id_or_qualified_name(&id_or_name)?;
-                    if entity.entity_type == EntityType::Project {
-                        fill_entity(this, entity).into()
-                    } else {
-                        let id = get_id(this, id_or_name.clone())?;
-                        let containers = this.get_neighbors(id, EdgeType::BelongsTo)?;
-                        containers
-                            .iter()
-                            .find(|c| c.entity_type == EntityType::Project)
-                            .map(|c| fill_entity(this, c.to_owned()))
-                            .ok_or_else(|| RegistryError::EntityNotFound(format!(
-                                "Entity {} doesn't belong to any project",
-                                id_or_name
-                            )))?
-                            .into()
+            })
+        };
+        let readers: Vec<_> = (0..16)
+            .map(|_| {
+                let r = r.clone();
+                tokio::spawn(async move {
+                    for _ in 0..20 {
+                        let count = r
+                            .read()
+                            .await
+                            .query(list_sources())
+                            .await
+                            .into_entities()
+                            .unwrap()
+                            .entities
+                            .len();
+                        assert!(count <= SOURCES);
                     }
+                })
+            })
+            .collect();
+
+        tokio::time::timeout(Duration::from_secs(60), async {
+            writer.await.unwrap();
+            for reader in readers {
+                reader.await.unwrap();
+            }
+        })
+        .await
+        .expect("Deadlock detected");
+
+        let count = r
+            .read()
+            .await
+            .query(list_sources())
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .len();
+        assert_eq!(count, SOURCES);
+
+        // Writing requests are not allowed in the read path
+        assert!(matches!(
+            r.read().await.query(list_sources()).await,
+            FeathrApiResponse::Entities(_)
+        ));
+        assert!(matches!(
+            r.read()
+                .await
+                .query(FeathrApiRequest::CreateProject {
+                    definition: ProjectDef {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        name: "p2".to_string(),
+                        qualified_name: Default::default(),
+                        tags: Default::default(),
+                        created_by: Default::default(),
+                    },
+                    time: None,
+                })
+                .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    async fn create_project(r: &mut Registry<registry_provider::EntityProperty>, name: &str) {
+        r.request(FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    }
+
+    async fn create_anchor(
+        r: &mut Registry<registry_provider::EntityProperty>,
+        project: &str,
+        name: &str,
+    ) {
+        r.request(FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: project.to_string(),
+            definition: AnchorDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                qualified_name: Default::default(),
+                source_id: Default::default(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+    }
+
+    fn source_def(name: &str) -> SourceDef {
+        SourceDef {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            qualified_name: Default::default(),
+            source_type: "hdfs".to_string(),
+            options: HashMap::new(),
+            event_timestamp_column: None,
+            timestamp_format: None,
+            connection_id: None,
+            table: None,
+            preprocessing: None,
+            tags: Default::default(),
+            created_by: Default::default(),
+        }
+    }
+
+    fn anchor_feature_def(name: &str, tags: HashMap<String, String>) -> AnchorFeatureDef {
+        AnchorFeatureDef {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            qualified_name: Default::default(),
+            feature_type: registry_provider::FeatureType {
+                type_: registry_provider::VectorType::TENSOR,
+                tensor_category: registry_provider::TensorCategory::DENSE,
+                dimension_type: vec![],
+                dimension_sizes: vec![],
+                val_type: registry_provider::ValueType::INT32,
+            }
+            .into(),
+            transformation: registry_provider::FeatureTransformation::Expression {
+                transform_expr: "x + 1".to_string(),
+            }
+            .into(),
+            key: vec![TypedKey {
+                key_column: "user_id".to_string(),
+                key_column_type: ValueType::INT64,
+                full_name: None,
+                description: None,
+                key_column_alias: None,
+            }],
+            sample_value: None,
+            default_value: None,
+            tags,
+            created_by: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn features_csv() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: anchor_feature_def(
+                "f1",
+                map! { "owner".to_string() => "alice".to_string(), "env".to_string() => "prod".to_string() },
+            ),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let csv = r
+            .request(FeathrApiRequest::GetProjectFeatures {
+                project_id_or_name: "p1".to_string(),
+                keyword: None,
+                size: None,
+                offset: None,
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "qualified_name,name,type,entity_type,tags");
+        assert!(lines.contains(&"p1__a1__f1,f1,INT,feathr_anchor_feature_v1,env=prod;owner=alice"));
+    }
+
+    #[tokio::test]
+    async fn bulk_tag_project() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        for name in ["f1", "f2"] {
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def(
+                    name,
+                    map! { "env".to_string() => "prod".to_string() },
+                ),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let count = r
+            .request(FeathrApiRequest::BulkTagProject {
+                project_id_or_name: "p1".to_string(),
+                add_tags: map! { "owner".to_string() => "platform".to_string() },
+                remove_keys: vec!["env".to_string()],
+                new_etag: uuid::Uuid::new_v4(),
+            })
+            .await
+            .into_count()
+            .unwrap();
+        // The project, the anchor and 2 features
+        assert_eq!(count, 4);
+
+        let features = |keyword: Option<&str>| FeathrApiRequest::GetProjectFeatures {
+            project_id_or_name: "p1".to_string(),
+            keyword: keyword.map(ToString::to_string),
+            size: None,
+            offset: None,
+        };
+        let entities = r
+            .query(features(None))
+            .await
+            .into_entities()
+            .unwrap()
+            .entities;
+        assert_eq!(entities.len(), 2);
+        for e in entities {
+            match e.attributes {
+                EntityAttributes::AnchorFeature(attr) => {
+                    assert_eq!(
+                        attr.tags,
+                        map! { "owner".to_string() => "platform".to_string() }
+                    )
                 }
-                FeathrApiRequest::GetUserRoles => this
-                    .get_permissions()
-                    .map_api_error()?
-                    .into(),
-                FeathrApiRequest::AddUserRole {
-                    project_id_or_name,
-                    user,
-                    role,
-                    requestor,
-                    reason,
-                } => {
-                    let grant = RbacRecord{
-                        credential: user,
-                        resource: project_id_or_name.parse()?,
-                        permission: role,
-                        requestor,
-                        reason,
-                        time: Utc::now(),
-                    };
-                    this.grant_permission(&grant).await.into()
+                _ => panic!("Wrong entity type"),
+            }
+        }
+        let mut found: Vec<String> = r
+            .query(features(Some("tags:platform")))
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["p1__a1__f1", "p1__a1__f2"]);
+        assert!(r
+            .query(features(Some("tags:prod")))
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn updated_tags_are_searchable() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: anchor_feature_def("f1", map! { "env".to_string() => "prod".to_string() }),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        r.request(FeathrApiRequest::UpdateEntityTags {
+            id_or_name: "p1__a1__f1".to_string(),
+            tags: map! {
+                "owner".to_string() => "alice".to_string(),
+                "env".to_string() => String::new()
+            },
+            if_match: None,
+            new_etag: uuid::Uuid::new_v4(),
+        })
+        .await
+        .into_entity()
+        .unwrap();
+
+        let features = |keyword: &str| FeathrApiRequest::GetProjectFeatures {
+            project_id_or_name: "p1".to_string(),
+            keyword: Some(keyword.to_string()),
+            size: None,
+            offset: None,
+        };
+        let found: Vec<String> = r
+            .query(features("tags:alice"))
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        assert_eq!(found, vec!["p1__a1__f1"]);
+        assert!(r
+            .query(features("tags:prod"))
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn protected_tags_are_checked_when_applied() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        r.set_protected_tags(["cost_center".to_string()].into_iter().collect());
+        create_project(&mut r, "p1").await;
+        let update = |value: &str| FeathrApiRequest::UpdateEntityTags {
+            id_or_name: "p1".to_string(),
+            tags: map! { "cost_center".to_string() => value.to_string() },
+            if_match: None,
+            new_etag: uuid::Uuid::new_v4(),
+        };
+        r.request(update("c1")).await.into_entity().unwrap();
+        assert!(matches!(
+            r.request(update("c2")).await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+        assert!(matches!(
+            r.request(FeathrApiRequest::BulkTagProject {
+                project_id_or_name: "p1".to_string(),
+                add_tags: Default::default(),
+                remove_keys: vec!["cost_center".to_string()],
+                new_etag: uuid::Uuid::new_v4(),
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn lineage_is_deterministic() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        for name in ["f1", "f2", "f3", "f4"] {
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def(
+                    name,
+                    map! { "owner".to_string() => "alice".to_string(), "env".to_string() => "prod".to_string() },
+                ),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let lineage = || async {
+            let lineage = r
+                .query(FeathrApiRequest::GetProjectLineage {
+                    id_or_name: "p1".to_string(),
+                    size: None,
+                    offset: None,
+                })
+                .await
+                .into_lineage()
+                .unwrap();
+            serde_json::to_string(&lineage).unwrap()
+        };
+        let first = lineage().await;
+        let second = lineage().await;
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn entity_containers() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let (id, _) = r
+            .request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def("f1", Default::default()),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let mut containers: Vec<String> = r
+            .query(FeathrApiRequest::GetEntityContainers {
+                id_or_name: id.to_string(),
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        containers.sort();
+        assert_eq!(containers, vec!["p1".to_string(), "p1__a1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn adding_feature_touches_anchor() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let get_anchor = |r: &Registry<registry_provider::EntityProperty>| {
+            r.get_entity_by_qualified_name("p1__a1").unwrap()
+        };
+        let before = get_anchor(&r).properties.last_modified_ts;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut def = anchor_feature_def("f1", Default::default());
+        def.created_by = "carol".to_string();
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: def,
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let anchor = get_anchor(&r);
+        assert!(anchor.properties.last_modified_ts > before);
+        assert_eq!(anchor.properties.last_modified_by, "carol");
+    }
+
+    #[tokio::test]
+    async fn find_features_by_key() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        for (name, alias) in [("f1", Some("uid")), ("f2", Some("uid")), ("f3", None)] {
+            let mut def = anchor_feature_def(name, Default::default());
+            def.key[0].key_column_alias = alias.map(|a| a.to_string());
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: def,
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let names: Vec<String> = r
+            .query(FeathrApiRequest::FindFeaturesByKey {
+                key: "uid".to_string(),
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["p1__a1__f1".to_string(), "p1__a1__f2".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn atlas_export() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: anchor_feature_def("f1", Default::default()),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let atlas = r
+            .query(FeathrApiRequest::GetProjectAtlasEntities {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_atlas_entities()
+            .unwrap();
+        let names: Vec<String> = atlas
+            .entities
+            .iter()
+            .map(|v| {
+                serde_json::from_str::<registry_provider::EntityProperty>(&v.to_string())
+                    .unwrap()
+                    .qualified_name
+            })
+            .collect();
+        assert_eq!(names, vec!["p1", "p1__a1", "p1__a1__f1"]);
+        assert!(!atlas.relationships.is_empty());
+    }
+
+    fn derived_feature_def(name: &str, inputs: Vec<String>) -> DerivedFeatureDef {
+        let feature = anchor_feature_def(name, Default::default());
+        DerivedFeatureDef {
+            id: feature.id,
+            name: feature.name,
+            qualified_name: Default::default(),
+            feature_type: feature.feature_type,
+            transformation: feature.transformation,
+            key: feature.key,
+            input_anchor_features: vec![],
+            input_derived_features: inputs,
+            sample_value: None,
+            default_value: None,
+            tags: Default::default(),
+            created_by: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn feature_sample_value() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let float_feature = |name: &str, sample_value: &str| {
+            let mut def = anchor_feature_def(name, Default::default());
+            def.feature_type.val_type = ValueType::FLOAT;
+            def.sample_value = Some(sample_value.to_string());
+            def.default_value = Some("0".to_string());
+            FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: def,
+                time: None,
+            }
+        };
+        let (id, _) = r
+            .request(float_feature("f1", "1.5"))
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let f1 = r
+            .query(FeathrApiRequest::GetFeature {
+                id_or_name: id.to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match f1.attributes {
+            EntityAttributes::AnchorFeature(attr) => {
+                assert_eq!(attr.sample_value.as_deref(), Some("1.5"));
+                assert_eq!(attr.default_value.as_deref(), Some("0"));
+            }
+            _ => panic!("Wrong entity type"),
+        }
+        assert!(matches!(
+            r.request(float_feature("f2", "foo")).await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn search_all_features() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        for project in ["p1", "p2"] {
+            create_project(&mut r, project).await;
+            create_anchor(&mut r, project, "a1").await;
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: project.to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def(
+                    "f1",
+                    map! { "team".to_string() => "discovery".to_string() },
+                ),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let mut found: Vec<String> = r
+            .query(FeathrApiRequest::SearchAllFeatures {
+                keyword: Some("tags:discovery".to_string()),
+                types: Default::default(),
+                size: None,
+                offset: None,
+                credential: Credential::RbacDisabled,
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        found.sort();
+        assert_eq!(found, vec!["p1__a1__f1", "p2__a1__f1"]);
+
+        // Malformed queries are the caller's fault
+        assert!(matches!(
+            r.query(FeathrApiRequest::SearchAllFeatures {
+                keyword: Some("(tags:discovery".to_string()),
+                types: Default::default(),
+                size: None,
+                offset: None,
+                credential: Credential::RbacDisabled,
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn search_readable_features() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        for project in ["p1", "p2"] {
+            create_project(&mut r, project).await;
+            create_anchor(&mut r, project, "a1").await;
+            for name in ["f1", "f2", "f3"] {
+                r.request(FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: project.to_string(),
+                    anchor_id_or_name: "a1".to_string(),
+                    definition: anchor_feature_def(
+                        name,
+                        map! { "team".to_string() => "discovery".to_string() },
+                    ),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            }
+        }
+        r.request(FeathrApiRequest::AddUserRoles {
+            project_id_or_name: "p2".to_string(),
+            users: vec![("alice".to_string(), Permission::Read)],
+            requestor: Credential::RbacDisabled,
+            reason: "Onboarding".to_string(),
+        })
+        .await
+        .into_user_role_results()
+        .unwrap();
+
+        // Features in p1 don't take up room in alice's pages
+        let mut found = vec![];
+        for offset in [0, 2, 4] {
+            let page: Vec<String> = r
+                .query(FeathrApiRequest::SearchAllFeatures {
+                    keyword: Some("tags:discovery".to_string()),
+                    types: Default::default(),
+                    size: Some(2),
+                    offset: Some(offset),
+                    credential: Credential::User("alice".to_string()),
+                })
+                .await
+                .into_entities()
+                .unwrap()
+                .entities
+                .into_iter()
+                .map(|e| e.qualified_name)
+                .collect();
+            found.extend(page);
+        }
+        found.sort();
+        assert_eq!(found, vec!["p2__a1__f1", "p2__a1__f2", "p2__a1__f3"]);
+    }
+
+    #[tokio::test]
+    async fn source_lineage() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let (s1, _) = r
+            .request(FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: source_def("s1"),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        r.request(FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: Default::default(),
+                source_id: Default::default(),
+                source_ids: vec![s1.to_string()],
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let (f1, _) = r
+            .request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def("f1", Default::default()),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let mut df1 = derived_feature_def("df1", vec![]);
+        df1.input_anchor_features = vec![f1.to_string()];
+        let (df1, _) = r
+            .request(FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                definition: df1,
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+
+        let lineage = r
+            .query(FeathrApiRequest::GetSourceLineage {
+                project_id_or_name: "p1".to_string(),
+                source_id_or_name: "s1".to_string(),
+                size: None,
+                offset: None,
+            })
+            .await
+            .into_lineage()
+            .unwrap();
+        let mut ids: Vec<String> = lineage.guid_entity_map.into_keys().collect();
+        ids.sort();
+        let mut expected = vec![s1.to_string(), f1.to_string(), df1.to_string()];
+        expected.sort();
+        assert_eq!(ids, expected);
+        assert!(!lineage.truncated);
+    }
+
+    #[tokio::test]
+    async fn blank_names() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        for name in ["", "  "] {
+            let requests = [
+                FeathrApiRequest::CreateProject {
+                    definition: ProjectDef {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        name: name.to_string(),
+                        qualified_name: Default::default(),
+                        tags: Default::default(),
+                        created_by: Default::default(),
+                    },
+                    time: None,
+                },
+                FeathrApiRequest::CreateProjectDataSource {
+                    project_id_or_name: "p1".to_string(),
+                    definition: source_def(name),
+                    time: None,
+                },
+                FeathrApiRequest::CreateProjectAnchor {
+                    project_id_or_name: "p1".to_string(),
+                    definition: AnchorDef {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        name: name.to_string(),
+                        qualified_name: Default::default(),
+                        source_id: Default::default(),
+                        source_ids: Default::default(),
+                        tags: Default::default(),
+                        created_by: Default::default(),
+                    },
+                    time: None,
+                },
+                FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: "p1".to_string(),
+                    anchor_id_or_name: "a1".to_string(),
+                    definition: anchor_feature_def(name, Default::default()),
+                    time: None,
+                },
+                FeathrApiRequest::CreateProjectDerivedFeature {
+                    project_id_or_name: "p1".to_string(),
+                    definition: derived_feature_def(name, vec![]),
+                    time: None,
+                },
+            ];
+            for request in requests {
+                let kind = request.name();
+                assert!(
+                    matches!(
+                        r.request(request).await,
+                        FeathrApiResponse::Error(ApiError::BadRequest(_))
+                    ),
+                    "{} accepted blank name '{}'",
+                    kind,
+                    name
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn entity_edges() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let mut inputs = vec![];
+        for name in ["f1", "f2"] {
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: "p1".to_string(),
+                    anchor_id_or_name: "a1".to_string(),
+                    definition: anchor_feature_def(name, Default::default()),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            inputs.push(id.to_string());
+        }
+        let mut df1 = derived_feature_def("df1", vec![]);
+        df1.input_anchor_features = inputs.clone();
+        let (df1, _) = r
+            .request(FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                definition: df1,
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+
+        let edges = r
+            .query(FeathrApiRequest::GetEntityEdges {
+                id_or_name: "p1__df1".to_string(),
+                edge_type: registry_provider::EdgeType::DerivesFrom,
+            })
+            .await
+            .into_relationships()
+            .unwrap();
+        assert!(edges.iter().all(|e| e.from == df1.to_string()));
+        let mut targets: Vec<String> = edges.into_iter().map(|e| e.to).collect();
+        targets.sort();
+        inputs.sort();
+        assert_eq!(targets, inputs);
+    }
+
+    #[tokio::test]
+    async fn feature_schema() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: anchor_feature_def("f1", Default::default()),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let schema = r
+            .query(FeathrApiRequest::GetFeatureSchema {
+                id_or_name: "p1__a1__f1".to_string(),
+            })
+            .await
+            .into_feature_schema()
+            .unwrap();
+        assert_eq!(schema.keys.len(), 1);
+        assert_eq!(schema.keys[0].key_column, "user_id");
+        assert_eq!(schema.keys[0].key_column_type, ValueType::INT64);
+        assert_eq!(schema.feature_type.val_type, ValueType::INT32);
+        assert!(matches!(
+            r.query(FeathrApiRequest::GetFeatureSchema {
+                id_or_name: "p1__a1".to_string(),
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn diff_versions() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let mut v2 = anchor_feature_def("f1", Default::default());
+        v2.transformation = registry_provider::FeatureTransformation::Expression {
+            transform_expr: "x + 2".to_string(),
+        }
+        .into();
+        for definition in [anchor_feature_def("f1", Default::default()), v2] {
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition,
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let diffs = r
+            .query(FeathrApiRequest::DiffEntityVersions {
+                id_or_name: "p1__a1__f1".to_string(),
+                from_version: 1,
+                to_version: 2,
+            })
+            .await
+            .into_field_diffs()
+            .unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, "transformation");
+        assert!(r
+            .query(FeathrApiRequest::DiffEntityVersions {
+                id_or_name: "p1__a1__f1".to_string(),
+                from_version: 1,
+                to_version: 3,
+            })
+            .await
+            .into_field_diffs()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn update_derived_inputs() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let mut ids = vec![];
+        for name in ["f1", "f2"] {
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: "p1".to_string(),
+                    anchor_id_or_name: "a1".to_string(),
+                    definition: anchor_feature_def(name, Default::default()),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            ids.push(id);
+        }
+        let mut df1 = derived_feature_def("df1", vec![]);
+        df1.input_anchor_features = vec![ids[0].to_string()];
+        let (df1, _) = r
+            .request(FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                definition: df1,
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+
+        let entity = r
+            .request(FeathrApiRequest::UpdateDerivedFeatureInputs {
+                id_or_name: df1.to_string(),
+                inputs: DerivedFeatureInputs {
+                    add: vec!["p1__a1__f2".to_string()],
+                    remove: vec![ids[0].to_string()],
+                },
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(entity.guid, df1.to_string());
+        let inputs = match entity.attributes {
+            EntityAttributes::DerivedFeature(attr) => attr.input_anchor_features,
+            _ => panic!("Should be a derived feature"),
+        };
+        assert_eq!(inputs, vec![EntityRef::new(&r.get_entity(ids[1]).unwrap())]);
+        let consumed: Vec<uuid::Uuid> = r
+            .get_neighbors(df1, EdgeType::DerivesFrom)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(consumed, vec![ids[1]]);
+
+        // df2 consumes df1, so df1 cannot consume df2
+        let (df2, _) = r
+            .request(FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                definition: derived_feature_def("df2", vec![df1.to_string()]),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let err = match r
+            .request(FeathrApiRequest::UpdateDerivedFeatureInputs {
+                id_or_name: df1.to_string(),
+                inputs: DerivedFeatureInputs {
+                    add: vec![df2.to_string()],
+                    remove: vec![],
+                },
+            })
+            .await
+        {
+            FeathrApiResponse::Error(e) => e,
+            _ => panic!("Should fail"),
+        };
+        assert!(matches!(err, ApiError::CyclicDependency(_)));
+        assert_eq!(err.code(), "CYCLIC_DEPENDENCY");
+    }
+
+    #[tokio::test]
+    async fn move_anchor_feature() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_project(&mut r, "p2").await;
+        let mut source_ids = vec![];
+        for name in ["s1", "s2"] {
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateProjectDataSource {
+                    project_id_or_name: "p1".to_string(),
+                    definition: source_def(name),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            source_ids.push(id);
+        }
+        for (name, source_id) in [("a1", source_ids[0]), ("a2", source_ids[1])] {
+            r.request(FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                definition: AnchorDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: name.to_string(),
+                    qualified_name: Default::default(),
+                    source_id: source_id.to_string(),
+                    source_ids: Default::default(),
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        create_anchor(&mut r, "p2", "a3").await;
+        let (id, _) = r
+            .request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def("f1", Default::default()),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+
+        let entity = r
+            .request(FeathrApiRequest::MoveAnchorFeature {
+                id_or_name: id.to_string(),
+                anchor: FeatureAnchor {
+                    anchor: "p1__a2".to_string(),
+                },
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(entity.guid, id.to_string());
+        // The feature is renamed under the new anchor
+        assert_eq!(entity.qualified_name, "p1__a2__f1");
+        assert!(r.get_entity_id("p1__a1__f1").is_err());
+        assert_eq!(r.get_entity_id("p1__a2__f1").unwrap(), id);
+        let mut containers: Vec<String> = r
+            .get_neighbors(id, EdgeType::BelongsTo)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        containers.sort();
+        assert_eq!(containers, vec!["p1".to_string(), "p1__a2".to_string()]);
+        let sources: Vec<uuid::Uuid> = r
+            .get_neighbors(id, EdgeType::Consumes)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(sources, vec![source_ids[1]]);
+
+        // Anchors in other projects are refused
+        assert!(r
+            .request(FeathrApiRequest::MoveAnchorFeature {
+                id_or_name: id.to_string(),
+                anchor: FeatureAnchor {
+                    anchor: "p2__a3".to_string(),
+                },
+            })
+            .await
+            .into_entity()
+            .is_err());
+        // Moving into an anchor having a feature with the same name is refused
+        r.request(FeathrApiRequest::CreateAnchorFeature {
+            project_id_or_name: "p1".to_string(),
+            anchor_id_or_name: "a1".to_string(),
+            definition: anchor_feature_def("f1", Default::default()),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        assert!(r
+            .request(FeathrApiRequest::MoveAnchorFeature {
+                id_or_name: id.to_string(),
+                anchor: FeatureAnchor {
+                    anchor: "p1__a1".to_string(),
+                },
+            })
+            .await
+            .into_entity()
+            .is_err());
+        assert_eq!(r.get_entity_id("p1__a2__f1").unwrap(), id);
+    }
+
+    #[test]
+    fn page_limits_are_applied() {
+        let limits = PageLimits {
+            default_size: 20,
+            max_size: 500,
+            max_offset: 1000,
+        };
+        for (requested, applied) in [(Some(100000), 500), (Some(0), 20), (None, 20)] {
+            let mut req = FeathrApiRequest::GetProjects {
+                keyword: None,
+                size: requested,
+                offset: None,
+            };
+            req.apply_page_limits(&limits);
+            assert!(matches!(
+                req,
+                FeathrApiRequest::GetProjects { size: Some(s), .. } if s == applied
+            ));
+            let mut req = FeathrApiRequest::GetUserRoles {
+                user: None,
+                scope: None,
+                size: requested,
+                offset: None,
+            };
+            req.apply_page_limits(&limits);
+            assert!(matches!(
+                req,
+                FeathrApiRequest::GetUserRoles { size: Some(s), .. } if s == applied
+            ));
+        }
+        for (requested, applied) in [(Some(100000), 500), (None, 10)] {
+            let mut req = FeathrApiRequest::GetSuggestions {
+                prefix: "p".to_string(),
+                limit: requested,
+                credential: Credential::RbacDisabled,
+            };
+            req.apply_page_limits(&limits);
+            assert!(matches!(
+                req,
+                FeathrApiRequest::GetSuggestions { limit: Some(l), .. } if l == applied
+            ));
+        }
+        let mut req = FeathrApiRequest::GetFeatureLineage {
+            id_or_name: "p1__f1".to_string(),
+            size: None,
+            offset: Some(usize::MAX),
+        };
+        req.apply_page_limits(&limits);
+        assert!(matches!(
+            req,
+            FeathrApiRequest::GetFeatureLineage {
+                offset: Some(1000),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn entity_project() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let (source_id, _) = r
+            .request(FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: source_def("s1"),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        let anchor_id = r.get_entity_id("p1__a1").unwrap();
+        for id in [source_id, anchor_id] {
+            let project = r
+                .query(FeathrApiRequest::GetEntityProject {
+                    id_or_name: id.to_string(),
+                })
+                .await
+                .into_entity()
+                .unwrap();
+            assert_eq!(project.qualified_name, "p1");
+        }
+        assert!(matches!(
+            r.query(FeathrApiRequest::GetEntityProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::NotFoundError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn entity_links() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let links = vec![
+            EntityLink {
+                title: "Dashboard".to_string(),
+                url: "https://grafana.example.com/d/p1".to_string(),
+            },
+            EntityLink {
+                title: "Docs".to_string(),
+                url: "https://wiki.example.com/p1".to_string(),
+            },
+        ];
+        let updated = r
+            .request(FeathrApiRequest::UpdateEntityLinks {
+                id_or_name: "p1".to_string(),
+                links: links.clone(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(updated.links, links);
+        let project = r
+            .query(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(project.links, links);
+
+        assert!(matches!(
+            r.request(FeathrApiRequest::UpdateEntityLinks {
+                id_or_name: "p1".to_string(),
+                links: vec![EntityLink {
+                    title: "Broken".to_string(),
+                    url: "not a url".to_string(),
+                }],
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn wrong_entity_type() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let anchor = r
+            .query(FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_eq!(anchor.qualified_name, "p1__a1");
+        for id_or_name in ["a1".to_string(), anchor.guid] {
+            match r
+                .query(FeathrApiRequest::GetProjectDataSource {
+                    project_id_or_name: "p1".to_string(),
+                    id_or_name,
+                })
+                .await
+            {
+                FeathrApiResponse::Error(e @ ApiError::WrongEntityType(_)) => {
+                    assert_eq!(e.code(), "WRONG_ENTITY_TYPE");
+                    assert!(e.to_string().contains("incorrect type Anchor"), "{}", e)
                 }
-                FeathrApiRequest::DeleteUserRole {
-                    project_id_or_name,
-                    user,
-                    role,
-                    requestor,
-                    reason,
-                } => {
-                    let revoke = RbacRecord{
-                        credential: user,
-                        resource: project_id_or_name.parse()?,
-                        permission: role,
-                        requestor,
-                        reason,
-                        time: Utc::now(),
-                    };
-                    this.revoke_permission(&revoke).await.into()
+                resp => panic!("Unexpected response {:?}", resp),
+            }
+        }
+        assert!(matches!(
+            r.query(FeathrApiRequest::GetProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::WrongEntityType(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn source_consumers() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let (source_id, _) = r
+            .request(FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: source_def("s1"),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        for name in ["a1", "a2", "a3"] {
+            let source_ids = if name == "a3" {
+                vec![]
+            } else {
+                vec![source_id.to_string()]
+            };
+            r.request(FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                definition: AnchorDef {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    name: name.to_string(),
+                    qualified_name: Default::default(),
+                    source_id: Default::default(),
+                    source_ids,
+                    tags: Default::default(),
+                    created_by: Default::default(),
+                },
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let consumers: Vec<String> = r
+            .query(FeathrApiRequest::GetSourceConsumers {
+                project_id_or_name: "p1".to_string(),
+                source_id_or_name: "s1".to_string(),
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .into_iter()
+            .map(|e| e.qualified_name)
+            .collect();
+        assert_eq!(consumers, vec!["p1__a1", "p1__a2"]);
+    }
+
+    #[tokio::test]
+    async fn bulk_user_roles() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let results = r
+            .request(FeathrApiRequest::AddUserRoles {
+                project_id_or_name: "p1".to_string(),
+                users: vec![
+                    ("alice".to_string(), Permission::Read),
+                    ("bad user".to_string(), Permission::Write),
+                    ("bob".to_string(), Permission::Admin),
+                ],
+                requestor: Credential::RbacDisabled,
+                reason: "Onboarding".to_string(),
+            })
+            .await
+            .into_user_role_results()
+            .unwrap();
+        let status: Vec<(&str, bool)> = results
+            .iter()
+            .map(|r| (r.user_name.as_str(), r.success))
+            .collect();
+        assert_eq!(
+            status,
+            vec![("alice", true), ("bad user", false), ("bob", true)]
+        );
+        assert!(results[1].error.is_some());
+
+        let mut users: Vec<String> = r
+            .query(FeathrApiRequest::GetUserRoles {
+                user: None,
+                scope: None,
+                size: None,
+                offset: None,
+            })
+            .await
+            .into_user_roles()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.user_name)
+            .collect();
+        users.sort();
+        assert_eq!(users, vec!["alice".to_string(), "bob".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn user_roles_filter_and_pagination() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_project(&mut r, "p2").await;
+        for project in ["p1", "p2"] {
+            r.request(FeathrApiRequest::AddUserRoles {
+                project_id_or_name: project.to_string(),
+                users: vec![
+                    ("alice".to_string(), Permission::Read),
+                    ("bob".to_string(), Permission::Write),
+                    ("carol".to_string(), Permission::Admin),
+                ],
+                requestor: Credential::RbacDisabled,
+                reason: "Onboarding".to_string(),
+            })
+            .await
+            .into_user_role_results()
+            .unwrap();
+        }
+        let get = |user: Option<&str>, scope: Option<&str>, size, offset| {
+            r.query(FeathrApiRequest::GetUserRoles {
+                user: user.map(ToString::to_string),
+                scope: scope.map(ToString::to_string),
+                size,
+                offset,
+            })
+        };
+
+        assert_eq!(
+            get(None, None, None, None)
+                .await
+                .into_user_roles()
+                .unwrap()
+                .len(),
+            6
+        );
+
+        let alice = get(Some("alice"), None, None, None)
+            .await
+            .into_user_roles()
+            .unwrap();
+        assert_eq!(alice.len(), 2);
+        assert!(alice.iter().all(|r| r.user_name == "alice"));
+
+        let p2 = get(None, Some("p2"), None, None)
+            .await
+            .into_user_roles()
+            .unwrap();
+        assert_eq!(p2.len(), 3);
+        assert!(p2.iter().all(|r| r.scope == "p2"));
+
+        let page: Vec<String> = get(None, Some("p1"), Some(2), Some(1))
+            .await
+            .into_user_roles()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.user_name)
+            .collect();
+        assert_eq!(page, vec!["bob".to_string(), "carol".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn batch_get_entities() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let p1 = r
+            .query(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap()
+            .guid;
+        let a1 = r
+            .query(FeathrApiRequest::GetFeature {
+                id_or_name: "p1__a1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap()
+            .guid;
+        let unknown = uuid::Uuid::new_v4().to_string();
+        let result = r
+            .query(FeathrApiRequest::GetEntities {
+                ids: vec![
+                    a1.clone(),
+                    unknown.clone(),
+                    p1.clone(),
+                    "not-a-guid".to_string(),
+                    a1.clone(),
+                ],
+            })
+            .await
+            .into_entities()
+            .unwrap();
+        let found: Vec<String> = result.entities.into_iter().map(|e| e.guid).collect();
+        assert_eq!(found, vec![a1, p1]);
+        assert_eq!(result.missing, vec![unknown, "not-a-guid".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn project_summary() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        for name in ["f1", "f2"] {
+            r.request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def(name, Default::default()),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        }
+        let summary = r
+            .query(FeathrApiRequest::GetProjectSummary {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_project_summary()
+            .unwrap();
+        assert_eq!(
+            summary,
+            ProjectSummary {
+                sources: 0,
+                anchors: 1,
+                anchor_features: 2,
+                derived_features: 0,
+            }
+        );
+
+        // Only projects have summaries
+        assert!(r
+            .query(FeathrApiRequest::GetProjectSummary {
+                id_or_name: "p1__a1".to_string(),
+            })
+            .await
+            .into_project_summary()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn detailed_project_list() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        let mut ids = vec![];
+        for name in ["p2", "p1"] {
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateProject {
+                    definition: ProjectDef {
+                        id: uuid::Uuid::new_v4().to_string(),
+                        name: name.to_string(),
+                        qualified_name: Default::default(),
+                        tags: map!("owner".to_string() => format!("{}_owner", name)),
+                        created_by: Default::default(),
+                    },
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            ids.push(id);
+        }
+        let projects = r
+            .query(FeathrApiRequest::GetProjectsDetailed {
+                keyword: None,
+                size: None,
+                offset: None,
+            })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities;
+        // Sorted by name
+        let names: Vec<&str> = projects.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["p1", "p2"]);
+        assert_eq!(projects[0].guid, ids[1].to_string());
+        assert_eq!(projects[1].guid, ids[0].to_string());
+        for p in projects {
+            match p.attributes {
+                EntityAttributes::Project(attr) => {
+                    assert_eq!(attr.tags["owner"], format!("{}_owner", p.name))
                 }
+                _ => panic!("Should be a project"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn idempotent_creation_by_id() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let id = uuid::Uuid::new_v4();
+        let source_def = |source_type: &str| SourceDef {
+            id: id.to_string(),
+            source_type: source_type.to_string(),
+            ..source_def("s1")
+        };
+        let create = |definition: SourceDef| FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            definition,
+            time: None,
+        };
+
+        let first = r
+            .request(create(source_def("hdfs")))
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        assert_eq!(first, (id, 1));
+
+        // Replaying the identical creation is a no-op
+        let second = r
+            .request(create(source_def("hdfs")))
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        assert_eq!(second, first);
+        assert_eq!(
+            r.query(FeathrApiRequest::GetProjectDataSourceVersions {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
             })
+            .await
+            .into_entities()
+            .unwrap()
+            .entities
+            .len(),
+            1
+        );
+
+        // A different definition under the same id conflicts
+        match r.request(create(source_def("csv"))).await {
+            FeathrApiResponse::Error(ApiError::Conflict(_)) => (),
+            _ => panic!("Should conflict"),
         }
 
-        match handle_request(self, request).await {
-            Ok(v) => v,
-            Err(e) => FeathrApiResponse::Error(e),
+        // Tags are part of the definition
+        let tagged = SourceDef {
+            tags: map! { "team".to_string() => "discovery".to_string() },
+            ..source_def("hdfs")
+        };
+        match r.request(create(tagged)).await {
+            FeathrApiResponse::Error(ApiError::Conflict(_)) => (),
+            _ => panic!("Should conflict"),
+        }
+    }
+
+    #[tokio::test]
+    async fn jdbc_source() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let source_def = |name: &str, connection_id: Option<&str>, table: Option<&str>| SourceDef {
+            source_type: "jdbc".to_string(),
+            connection_id: connection_id.map(ToString::to_string),
+            table: table.map(ToString::to_string),
+            ..source_def(name)
+        };
+
+        match r
+            .request(FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: source_def("s1", None, Some("trips")),
+                time: None,
+            })
+            .await
+        {
+            FeathrApiResponse::Error(ApiError::BadRequest(_)) => (),
+            _ => panic!("JDBC source without connection id should be rejected"),
+        }
+
+        r.request(FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            definition: source_def("s2", Some("warehouse"), Some("trips")),
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let source = r
+            .query(FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s2".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match source.attributes {
+            EntityAttributes::Source(attr) => {
+                assert_eq!(attr.connection_id.as_deref(), Some("warehouse"));
+                assert_eq!(attr.table.as_deref(), Some("trips"));
+            }
+            _ => panic!("Should be a source"),
+        }
+
+        // File sources don't keep the connection and the table
+        r.request(FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            definition: SourceDef {
+                source_type: "parquet".to_string(),
+                ..source_def("s3", Some("warehouse"), Some("trips"))
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let source = r
+            .query(FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s3".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match source.attributes {
+            EntityAttributes::Source(attr) => {
+                assert_eq!(attr.connection_id, None);
+                assert_eq!(attr.table, None);
+            }
+            _ => panic!("Should be a source"),
+        }
+    }
+
+    #[tokio::test]
+    async fn recreate_deleted_project() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let old = r
+            .query(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        r.delete_entity_by_id(uuid::Uuid::parse_str(&old.guid).unwrap())
+            .await
+            .unwrap();
+
+        create_project(&mut r, "p1").await;
+        let new = r
+            .query(FeathrApiRequest::GetProject {
+                id_or_name: "p1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        assert_ne!(new.guid, old.guid);
+        assert_eq!(new.version, 1);
+    }
+
+    #[tokio::test]
+    async fn duplicated_inputs() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let (f1, _) = r
+            .request(FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name: "p1".to_string(),
+                anchor_id_or_name: "a1".to_string(),
+                definition: anchor_feature_def("f1", Default::default()),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+
+        // Same input listed twice, in different forms
+        let mut df1 = derived_feature_def("df1", vec![f1.to_string().to_uppercase()]);
+        df1.input_anchor_features = vec![f1.to_string()];
+        r.request(FeathrApiRequest::CreateProjectDerivedFeature {
+            project_id_or_name: "p1".to_string(),
+            definition: df1,
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let edges = r
+            .query(FeathrApiRequest::GetEntityEdges {
+                id_or_name: "p1__df1".to_string(),
+                edge_type: registry_provider::EdgeType::DerivesFrom,
+            })
+            .await
+            .into_relationships()
+            .unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].to, f1.to_string());
+
+        // A feature cannot consume itself
+        let mut df2 = derived_feature_def("df2", vec![]);
+        df2.input_derived_features = vec![df2.id.clone()];
+        assert!(matches!(
+            r.request(FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name: "p1".to_string(),
+                definition: df2,
+                time: None,
+            })
+            .await,
+            FeathrApiResponse::Error(ApiError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn delete_source() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        let (source_id, _) = r
+            .request(FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                definition: source_def("s1"),
+                time: None,
+            })
+            .await
+            .into_uuid_and_version()
+            .unwrap();
+        r.request(FeathrApiRequest::CreateProjectAnchor {
+            project_id_or_name: "p1".to_string(),
+            definition: AnchorDef {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "a1".to_string(),
+                qualified_name: Default::default(),
+                source_id: source_id.to_string(),
+                source_ids: Default::default(),
+                tags: Default::default(),
+                created_by: Default::default(),
+            },
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+        let delete = |force| FeathrApiRequest::DeleteProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            id_or_name: "s1".to_string(),
+            force,
+            time: None,
+        };
+
+        // a1 consumes s1
+        assert!(matches!(
+            r.request(delete(false)).await,
+            FeathrApiResponse::Error(ApiError::DeleteInUse(_))
+        ));
+        assert!(r
+            .query(FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
+            })
+            .await
+            .into_entity()
+            .is_ok());
+
+        assert!(matches!(
+            r.request(delete(true)).await,
+            FeathrApiResponse::Unit
+        ));
+        assert!(r
+            .query(FeathrApiRequest::GetProjectDataSource {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "s1".to_string(),
+            })
+            .await
+            .into_entity()
+            .is_err());
+        let anchor = r
+            .query(FeathrApiRequest::GetProjectAnchor {
+                project_id_or_name: "p1".to_string(),
+                id_or_name: "a1".to_string(),
+            })
+            .await
+            .into_entity()
+            .unwrap();
+        match anchor.attributes {
+            EntityAttributes::Anchor(attr) => {
+                assert_eq!(attr.source, None);
+                assert!(attr.sources.is_empty());
+            }
+            _ => panic!("Should be an anchor"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_entities_by_type() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        let mut expected = HashSet::new();
+        for project in ["p1", "p2"] {
+            create_project(&mut r, project).await;
+            create_anchor(&mut r, project, "a1").await;
+            for name in ["s1", "s2", "s3"] {
+                r.request(FeathrApiRequest::CreateProjectDataSource {
+                    project_id_or_name: project.to_string(),
+                    definition: source_def(name),
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+                expected.insert(format!("{}__{}", project, name));
+            }
+        }
+
+        r.request(FeathrApiRequest::AddUserRoles {
+            project_id_or_name: "p2".to_string(),
+            users: vec![("alice".to_string(), Permission::Read)],
+            requestor: Credential::RbacDisabled,
+            reason: "Onboarding".to_string(),
+        })
+        .await
+        .into_user_role_results()
+        .unwrap();
+        let readable: HashSet<String> = expected
+            .iter()
+            .filter(|n| n.starts_with("p2"))
+            .cloned()
+            .collect();
+
+        for (credential, expected) in [
+            (Credential::RbacDisabled, expected),
+            (Credential::User("alice".to_string()), readable),
+        ] {
+            let mut names = vec![];
+            let mut cursor = None;
+            loop {
+                let (page, next) = r
+                    .query(FeathrApiRequest::ListEntitiesByType {
+                        entity_type: EntityType::Source,
+                        cursor,
+                        size: Some(2),
+                        credential: credential.clone(),
+                    })
+                    .await
+                    .into_entity_page()
+                    .unwrap();
+                // Only the last page can be short
+                assert!(page.entities.len() == 2 || next.is_none());
+                names.extend(page.entities.into_iter().map(|e| e.qualified_name));
+                match next {
+                    Some(c) => cursor = Some(c),
+                    None => break,
+                }
+            }
+            assert_eq!(names.len(), expected.len());
+            assert_eq!(names.into_iter().collect::<HashSet<_>>(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn feature_with_inputs() {
+        let mut r: Registry<registry_provider::EntityProperty> = Default::default();
+        create_project(&mut r, "p1").await;
+        create_anchor(&mut r, "p1", "a1").await;
+        let mut input_ids = vec![];
+        for (name, val_type) in [("f1", ValueType::INT32), ("f2", ValueType::FLOAT)] {
+            let mut def = anchor_feature_def(name, Default::default());
+            def.feature_type.val_type = val_type;
+            let (id, _) = r
+                .request(FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: "p1".to_string(),
+                    anchor_id_or_name: "a1".to_string(),
+                    definition: def,
+                    time: None,
+                })
+                .await
+                .into_uuid_and_version()
+                .unwrap();
+            input_ids.push((id.to_string(), val_type));
+        }
+        let mut df1 = derived_feature_def("df1", vec![]);
+        df1.input_anchor_features = input_ids.iter().map(|(id, _)| id.clone()).collect();
+        r.request(FeathrApiRequest::CreateProjectDerivedFeature {
+            project_id_or_name: "p1".to_string(),
+            definition: df1,
+            time: None,
+        })
+        .await
+        .into_uuid_and_version()
+        .unwrap();
+
+        let resolved = r
+            .query(FeathrApiRequest::GetFeatureWithInputs {
+                id_or_name: "p1__df1".to_string(),
+            })
+            .await
+            .into_resolved_feature()
+            .unwrap();
+        assert_eq!(resolved.feature.qualified_name, "p1__df1");
+        assert_eq!(resolved.inputs.len(), 2);
+        assert!(resolved.deleted_inputs.is_empty());
+        for (id, val_type) in input_ids {
+            match &resolved.inputs[&id].attributes {
+                EntityAttributes::AnchorFeature(attr) => assert_eq!(attr.type_.val_type, val_type),
+                _ => panic!("Input is not an anchor feature"),
+            }
         }
     }
 }
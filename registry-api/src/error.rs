@@ -1,5 +1,6 @@
 use common_utils::Logged;
-use poem::{error::ResponseError, http::StatusCode};
+use poem::{error::ResponseError, http::StatusCode, IntoResponse, Response};
+use poem_openapi::Object;
 use registry_provider::RegistryError;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
@@ -10,42 +11,123 @@ pub enum ApiError {
     #[error("Entity('{0}') is not found")]
     NotFoundError(String),
 
+    #[error("{0}")]
+    WrongEntityType(String),
+
     #[error("{0}")]
     Conflict(String),
 
+    #[error("{0}")]
+    NameExists(String),
+
+    #[error("{0}")]
+    DeleteInUse(String),
+
+    #[error("{0}")]
+    CyclicDependency(String),
+
     #[error("{0}")]
     BadRequest(String),
 
     #[error("{0}")]
     Forbidden(String),
 
+    #[error("{0}")]
+    PreconditionFailed(String),
+
+    #[error("{0}")]
+    MethodNotAllowed(String),
+
     #[error("{0}")]
     InternalError(String),
+
+    #[error("{0}")]
+    ServiceUnavailable(String),
+}
+
+impl ApiError {
+    /**
+     * Machine-readable error code, returned along with the message in the error body
+     */
+    pub fn code(&self) -> &'static str {
+        match &self {
+            ApiError::NotFoundError(_) => "ENTITY_NOT_FOUND",
+            ApiError::WrongEntityType(_) => "WRONG_ENTITY_TYPE",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::NameExists(_) => "NAME_EXISTS",
+            ApiError::DeleteInUse(_) => "DELETE_IN_USE",
+            ApiError::CyclicDependency(_) => "CYCLIC_DEPENDENCY",
+            ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::PreconditionFailed(_) => "PRECONDITION_FAILED",
+            ApiError::MethodNotAllowed(_) => "METHOD_NOT_ALLOWED",
+            ApiError::InternalError(_) => "INTERNAL_ERROR",
+            ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
+        }
+    }
+}
+
+/**
+ * JSON body of error responses
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+}
+
+impl From<&ApiError> for ErrorResponse {
+    fn from(e: &ApiError) -> Self {
+        Self {
+            code: e.code().to_string(),
+            message: e.to_string(),
+        }
+    }
 }
 
 impl ResponseError for ApiError {
     fn status(&self) -> poem::http::StatusCode {
         match &self {
             ApiError::NotFoundError(_) => StatusCode::NOT_FOUND,
+            ApiError::WrongEntityType(_) => StatusCode::BAD_REQUEST,
             ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::NameExists(_) => StatusCode::CONFLICT,
+            ApiError::DeleteInUse(_) => StatusCode::CONFLICT,
+            ApiError::CyclicDependency(_) => StatusCode::CONFLICT,
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
             ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ApiError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
             ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
+
+    fn as_response(&self) -> Response {
+        let mut resp = poem::web::Json(ErrorResponse::from(self)).into_response();
+        resp.set_status(self.status());
+        resp
+    }
 }
 
 impl From<RegistryError> for ApiError {
     fn from(e: RegistryError) -> Self {
         match e {
-            RegistryError::WrongEntityType(id, _) => ApiError::NotFoundError(id.to_string()),
+            RegistryError::WrongEntityType(_, _) => ApiError::WrongEntityType(format!("{}", e)),
             RegistryError::EntityNotFound(e) => ApiError::NotFoundError(e),
             RegistryError::InvalidEntity(id) => ApiError::NotFoundError(id.to_string()),
-            RegistryError::InvalidEdge(_, _) => ApiError::InternalError(format!("{:?}", e)),
-            RegistryError::EntityNameExists(_) => ApiError::Conflict(format!("{:?}", e)),
+            RegistryError::InvalidEntities(_) => ApiError::NotFoundError(format!("{}", e)),
+            RegistryError::InvalidEdge(_, _) => ApiError::BadRequest(format!("{}", e)),
+            RegistryError::EntityNameExists(_) => ApiError::NameExists(format!("{}", e)),
             RegistryError::EntityIdExists(_) => ApiError::Conflict(format!("{:?}", e)),
-            RegistryError::DeleteInUsed(_) => ApiError::BadRequest(format!("{:?}", e)),
+            RegistryError::AmbiguousName(_, _) => ApiError::BadRequest(format!("{}", e)),
+            RegistryError::DeleteInUsed(_, _) => ApiError::DeleteInUse(format!("{}", e)),
+            RegistryError::EtagMismatch(_) => ApiError::PreconditionFailed(format!("{:?}", e)),
+            RegistryError::CyclicDependency(_) => ApiError::CyclicDependency(format!("{}", e)),
+            RegistryError::InvalidDefinition(_) => ApiError::BadRequest(format!("{}", e)),
+            RegistryError::ProtectedTag(_, _) => ApiError::BadRequest(format!("{}", e)),
             RegistryError::FtsError(_) => ApiError::InternalError(format!("{:?}", e)),
+            RegistryError::InvalidQuery(_) => ApiError::BadRequest(format!("{}", e)),
             RegistryError::ExternalStorageError(_) => ApiError::InternalError(format!("{:?}", e)),
             RegistryError::RbacError(e) => match e {
                 registry_provider::RbacError::CredentialNotFound(_) => ApiError::BadRequest(format!("{:?}", e)),
@@ -17,21 +17,42 @@ pub enum RegistryError {
     #[error("Entity with name {0} already exists")]
     EntityNameExists(String),
 
+    #[error("Name {0} is ambiguous, it matches {1:?}")]
+    AmbiguousName(String, Vec<String>),
+
     #[error("Entity[{0}] already exists")]
     EntityIdExists(Uuid),
 
     #[error("Entity[{0}] doesn't exist")]
     InvalidEntity(Uuid),
 
+    #[error("Entities {0:?} don't exist")]
+    InvalidEntities(Vec<Uuid>),
+
     #[error("Invalid edge from [{0:?}] to [{1:?}]")]
     InvalidEdge(EntityType, EntityType),
 
-    #[error("Cannot delete [{0}] when it still has dependents")]
-    DeleteInUsed(Uuid),
+    #[error("Cannot delete [{0}] when it still has dependents {1:?}")]
+    DeleteInUsed(Uuid, Vec<Uuid>),
+
+    #[error("Entity[{0}] has been modified by others")]
+    EtagMismatch(Uuid),
+
+    #[error("Entities {0:?} have cyclic dependencies")]
+    CyclicDependency(Vec<Uuid>),
+
+    #[error("Invalid definition: {0}")]
+    InvalidDefinition(String),
+
+    #[error("Tag '{0}' of entity '{1}' is protected and can't be changed")]
+    ProtectedTag(String, String),
 
     #[error("{0}")]
     FtsError(String),
 
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
     #[error("{0}")]
     ExternalStorageError(String),
 
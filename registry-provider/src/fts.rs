@@ -10,6 +10,23 @@ pub trait ToDoc {
     fn get_id(&self) -> String;
     fn get_type(&self) -> String;
     fn get_body(&self) -> String;
+    /**
+     * Names matched by prefix when suggesting
+     */
+    fn get_suggest_names(&self) -> Vec<String>;
+    /**
+     * The name returned as the suggestion
+     */
+    fn get_qualified_name(&self) -> String;
+    /**
+     * Tags as `key=value` lines
+     */
+    fn get_tags(&self) -> String {
+        Default::default()
+    }
+    fn get_description(&self) -> String {
+        Default::default()
+    }
 }
 
 /**
@@ -17,6 +34,14 @@ pub trait ToDoc {
  */
 pub trait ToDocString {
     fn to_doc_string(&self) -> String;
+
+    fn to_tags_string(&self) -> String {
+        Default::default()
+    }
+
+    fn to_description(&self) -> String {
+        Default::default()
+    }
 }
 
 impl<T> ToDoc for Entity<T>
@@ -38,6 +63,22 @@ where
     fn get_body(&self) -> String {
         self.properties.to_doc_string()
     }
+
+    fn get_suggest_names(&self) -> Vec<String> {
+        vec![self.name.to_owned(), self.qualified_name.to_owned()]
+    }
+
+    fn get_qualified_name(&self) -> String {
+        self.qualified_name.to_owned()
+    }
+
+    fn get_tags(&self) -> String {
+        self.properties.to_tags_string()
+    }
+
+    fn get_description(&self) -> String {
+        self.properties.to_description()
+    }
 }
 
 impl ToDocString for EntityProperty {
@@ -50,6 +91,20 @@ impl ToDocString for EntityProperty {
         v.extend(self.labels.iter().cloned());
         v.join("\n")
     }
+
+    fn to_tags_string(&self) -> String {
+        self.tags
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn to_description(&self) -> String {
+        let mut v = vec![self.display_text.to_owned()];
+        v.extend(self.tags.get("description").cloned());
+        v.join("\n")
+    }
 }
 
 /**
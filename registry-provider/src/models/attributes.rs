@@ -1,7 +1,7 @@
-use std::{fmt::Debug, collections::HashMap};
+use std::{fmt::Debug, fmt::Display, collections::HashMap};
 use std::hash::Hash;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -60,6 +60,12 @@ pub struct FeatureType {
     pub type_: VectorType,
     pub tensor_category: TensorCategory,
     pub dimension_type: Vec<ValueType>,
+    /**
+     * Size of each dimension, aligned with `dimension_type`, `None` for variable size
+     * Empty if sizes are not specified
+     */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dimension_sizes: Vec<Option<u64>>,
     pub val_type: ValueType,
 }
 
@@ -125,6 +131,10 @@ pub struct AnchorFeatureAttributes {
     pub type_: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -133,18 +143,128 @@ pub struct DerivedFeatureAttributes {
     pub type_: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+}
+
+/**
+ * Source types other than the formats below that readers understand, e.g. storage schemes
+ */
+const KNOWN_OTHER_SOURCE_TYPES: &[&str] = &[
+    "hdfs",
+    "wasb",
+    "wasbs",
+    "abfs",
+    "abfss",
+    "s3",
+    "dbfs",
+    "passthrough",
+    "kafka",
+    "sparksql",
+    "snowflake",
+    "cosmosdb",
+    "elasticsearch",
+    "aerospike",
+    "redis",
+    "generic",
+];
+
+/**
+ * Format of the data source, names are case-insensitive, unrecognized ones are kept in `Other`
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SourceFormat {
+    Parquet,
+    Csv,
+    Json,
+    Delta,
+    Jdbc,
+    Other(String),
+}
+
+impl SourceFormat {
+    /**
+     * Formats of files read by path, sources of them have no connection or table
+     */
+    pub fn is_file(&self) -> bool {
+        matches!(
+            self,
+            SourceFormat::Parquet | SourceFormat::Csv | SourceFormat::Json
+        )
+    }
+
+    /**
+     * False for types no reader understands, most likely typos like "parqet"
+     */
+    pub fn is_known(&self) -> bool {
+        match self {
+            SourceFormat::Other(s) => KNOWN_OTHER_SOURCE_TYPES.contains(&s.to_lowercase().as_str()),
+            _ => true,
+        }
+    }
+}
+
+impl From<&str> for SourceFormat {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "parquet" => SourceFormat::Parquet,
+            "csv" => SourceFormat::Csv,
+            "json" => SourceFormat::Json,
+            "delta" => SourceFormat::Delta,
+            "jdbc" => SourceFormat::Jdbc,
+            _ => SourceFormat::Other(s.to_string()),
+        }
+    }
+}
+
+impl Display for SourceFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceFormat::Parquet => write!(f, "parquet"),
+            SourceFormat::Csv => write!(f, "csv"),
+            SourceFormat::Json => write!(f, "json"),
+            SourceFormat::Delta => write!(f, "delta"),
+            SourceFormat::Jdbc => write!(f, "jdbc"),
+            SourceFormat::Other(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl Serialize for SourceFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(SourceFormat::from(s.as_str()))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SourceAttributes {
     #[serde(rename = "type")]
-    pub type_: String,
+    pub type_: SourceFormat,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub preprocessing: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub event_timestamp_column: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub timestamp_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub connection_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub table: Option<String>,
     #[serde(default)]
     pub options: HashMap<String, serde_json::Value>,
 }
@@ -1,21 +1,30 @@
-use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::EntityType;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum EdgeType {
     // Feature/Source/AnchorGroup belongs to project
     BelongsTo,
     // Project Contains Feature/Source/AnchorGroup, AnchorGroup contains AnchorFeatures
     Contains,
 
-    // AnchorGroup uses Source, DerivedFeature used Anchor/DerivedFeatures
+    // AnchorGroup/AnchorFeature uses Source
     Consumes,
-    // Source used by AnchorGroup, Anchor/DerivedFeatures derives DerivedFeature
+    // Source used by AnchorGroup/AnchorFeature
     Produces,
+
+    // DerivedFeature is derived from Anchor/DerivedFeatures
+    DerivesFrom,
+    // Anchor/DerivedFeatures derives DerivedFeature
+    DerivedBy,
 }
 
 impl Default for EdgeType {
@@ -31,15 +40,54 @@ impl EdgeType {
             EdgeType::Contains => EdgeType::BelongsTo,
             EdgeType::Consumes => EdgeType::Produces,
             EdgeType::Produces => EdgeType::Consumes,
+            EdgeType::DerivesFrom => EdgeType::DerivedBy,
+            EdgeType::DerivedBy => EdgeType::DerivesFrom,
         }
     }
 
     pub fn is_downstream(self) -> bool {
-        matches!(self, EdgeType::Contains | EdgeType::Produces)
+        matches!(
+            self,
+            EdgeType::Contains | EdgeType::Produces | EdgeType::DerivedBy
+        )
     }
 
     pub fn is_upstream(self) -> bool {
-        matches!(self, EdgeType::BelongsTo | EdgeType::Consumes)
+        matches!(
+            self,
+            EdgeType::BelongsTo | EdgeType::Consumes | EdgeType::DerivesFrom
+        )
+    }
+
+    /**
+     * Lineage traversals following `Consumes` also follow `DerivesFrom`, and `Produces` also
+     * follows `DerivedBy`, so the whole lineage is covered while feature-to-feature
+     * dependencies can still be queried alone
+     */
+    pub fn covers(self, other: EdgeType) -> bool {
+        self == other
+            || matches!(
+                (self, other),
+                (EdgeType::Consumes, EdgeType::DerivesFrom)
+                    | (EdgeType::Produces, EdgeType::DerivedBy)
+            )
+    }
+
+    /**
+     * Feature-to-feature `Consumes` and `Produces` edges created by older versions are
+     * `DerivesFrom` and `DerivedBy`
+     */
+    pub fn normalize(self, from: EntityType, to: EntityType) -> Self {
+        let is_feature = |t| matches!(t, EntityType::AnchorFeature | EntityType::DerivedFeature);
+        match self {
+            EdgeType::Consumes if from == EntityType::DerivedFeature && is_feature(to) => {
+                EdgeType::DerivesFrom
+            }
+            EdgeType::Produces if is_feature(from) && to == EntityType::DerivedFeature => {
+                EdgeType::DerivedBy
+            }
+            _ => self,
+        }
     }
 
     pub fn validate(&self, from: EntityType, to: EntityType) -> bool {
@@ -89,7 +137,7 @@ impl EdgeType {
                 | (
                     EntityType::AnchorFeature,
                     EntityType::DerivedFeature,
-                    EdgeType::Produces
+                    EdgeType::DerivedBy
                 )
                 | (
                     EntityType::DerivedFeature,
@@ -99,23 +147,23 @@ impl EdgeType {
                 | (
                     EntityType::DerivedFeature,
                     EntityType::AnchorFeature,
-                    EdgeType::Consumes
+                    EdgeType::DerivesFrom
                 )
                 | (
                     EntityType::DerivedFeature,
                     EntityType::DerivedFeature,
-                    EdgeType::Produces
+                    EdgeType::DerivedBy
                 )
                 | (
                     EntityType::DerivedFeature,
                     EntityType::DerivedFeature,
-                    EdgeType::Consumes
+                    EdgeType::DerivesFrom
                 )
         )
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Edge
 {
     #[serde(rename = "relationshipType")]
@@ -124,6 +172,20 @@ pub struct Edge
     pub from: Uuid,
     #[serde(rename = "toEntityId")]
     pub to: Uuid,
+    /**
+     * Arbitrary metadata attached to the relationship, e.g. join key
+     */
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl Hash for Edge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // `HashMap` is not hashable, equal edges always have same endpoints and type
+        self.edge_type.hash(state);
+        self.from.hash(state);
+        self.to.hash(state);
+    }
 }
 
 impl Edge
@@ -133,6 +195,7 @@ impl Edge
             from: self.to,
             to: self.from,
             edge_type: self.edge_type.reflection(),
+            attributes: self.attributes.clone(),
         }
     }
 }
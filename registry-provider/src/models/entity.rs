@@ -1,14 +1,17 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
-use std::fmt::Debug;
 
+use chrono::{DateTime, Utc};
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, ProjectDef, RegistryError, SourceDef,
+    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, EntityLink, ProjectDef, RegistryError,
+    SourceDef,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -230,8 +233,7 @@ where
                     qualified_name.ok_or_else(|| de::Error::missing_field("qualified_name"))?;
                 let properties =
                     properties.ok_or_else(|| de::Error::missing_field("properties"))?;
-                let version =
-                    version.ok_or_else(|| de::Error::missing_field("version"))?;
+                let version = version.ok_or_else(|| de::Error::missing_field("version"))?;
                 Ok(Entity::<Prop> {
                     id,
                     entity_type,
@@ -243,7 +245,14 @@ where
             }
         }
 
-        const FIELDS: &[&str] = &["id", "entity_type", "name", "qualified_name", "properties", "version"];
+        const FIELDS: &[&str] = &[
+            "id",
+            "entity_type",
+            "name",
+            "qualified_name",
+            "properties",
+            "version",
+        ];
         deserializer.deserialize_struct("Entity", FIELDS, EntityVisitor::<Prop> { _t: PhantomData })
     }
 }
@@ -252,11 +261,54 @@ pub trait EntityPropMutator
 where
     Self: Clone + Debug + PartialEq + Eq + crate::fts::ToDocString,
 {
-    fn new_project(definition: &ProjectDef) -> Result<Self, RegistryError>;
-    fn new_source(definition: &SourceDef) -> Result<Self, RegistryError>;
-    fn new_anchor(definition: &AnchorDef) -> Result<Self, RegistryError>;
-    fn new_anchor_feature(definition: &AnchorFeatureDef) -> Result<Self, RegistryError>;
-    fn new_derived_feature(definition: &DerivedFeatureDef) -> Result<Self, RegistryError>;
+    /**
+     * `now` is the time of the mutation, it's taken from the request so all replicas agree
+     */
+    fn new_project(definition: &ProjectDef, now: DateTime<Utc>) -> Result<Self, RegistryError>;
+    fn new_source(definition: &SourceDef, now: DateTime<Utc>) -> Result<Self, RegistryError>;
+    fn new_anchor(definition: &AnchorDef, now: DateTime<Utc>) -> Result<Self, RegistryError>;
+    fn new_anchor_feature(
+        definition: &AnchorFeatureDef,
+        now: DateTime<Utc>,
+    ) -> Result<Self, RegistryError>;
+    fn new_derived_feature(
+        definition: &DerivedFeatureDef,
+        now: DateTime<Utc>,
+    ) -> Result<Self, RegistryError>;
     fn get_version(&self) -> u64;
     fn set_version(&mut self, version: u64);
+    fn get_etag(&self) -> Uuid;
+    /**
+     * Aliases and full names of the feature keys, empty for non-feature entities
+     */
+    fn get_key_aliases(&self) -> Vec<String>;
+    /**
+     * Merge `tags` into existing ones, a tag with empty value will be removed
+     */
+    fn update_tags(&mut self, tags: HashMap<String, String>, etag: Uuid);
+    /**
+     * Value of the tag, `None` if the tag is not set
+     */
+    fn get_tag(&self, key: &str) -> Option<&str>;
+    /**
+     * Replace external links of the entity
+     */
+    fn set_links(&mut self, links: Vec<EntityLink>);
+    /**
+     * Set new name and qualified name, used when the containing project is renamed
+     */
+    fn rename(&mut self, name: &str, qualified_name: &str);
+    /**
+     * Set new id, used when the entity is copied into another project
+     */
+    fn set_id(&mut self, id: Uuid);
+    /**
+     * Set the last modified time, called when connections of the entity changed
+     * `modified_by` is the requestor, the last modifier is kept if it's empty
+     */
+    fn touch(&mut self, modified_by: &str, ts: DateTime<Utc>);
+    /**
+     * Whether both come from the same definition, unlike `eq` this also compares tags
+     */
+    fn same_definition(&self, other: &Self) -> bool;
 }
@@ -26,6 +26,16 @@ pub struct SourceDef {
     pub options: HashMap<String, serde_json::Value>,
     pub event_timestamp_column: Option<String>,
     pub timestamp_format: Option<String>,
+    /**
+     * Connection the table is read from, required by JDBC sources
+     */
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    /**
+     * Table name in the connection, required by JDBC sources
+     */
+    #[serde(default)]
+    pub table: Option<String>,
     pub preprocessing: Option<String>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
@@ -37,7 +47,8 @@ pub struct AnchorDef {
     pub id: Uuid,
     pub name: String,
     pub qualified_name: String,
-    pub source_id: Uuid,
+    #[serde(default)]
+    pub source_ids: HashSet<Uuid>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
 }
@@ -51,6 +62,10 @@ pub struct AnchorFeatureDef {
     pub feature_type: FeatureType,
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
+    #[serde(default)]
+    pub sample_value: Option<String>,
+    #[serde(default)]
+    pub default_value: Option<String>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
 }
@@ -66,6 +81,10 @@ pub struct DerivedFeatureDef {
     pub key: Vec<TypedKey>,
     pub input_anchor_features: HashSet<Uuid>,
     pub input_derived_features: HashSet<Uuid>,
+    #[serde(default)]
+    pub sample_value: Option<String>,
+    #[serde(default)]
+    pub default_value: Option<String>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
 }
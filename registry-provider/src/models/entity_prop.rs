@@ -1,13 +1,13 @@
 use std::{collections::HashMap, fmt::Debug};
 
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
 use crate::{
     AnchorDef, AnchorFeatureAttributes, AnchorFeatureDef, Attributes, DerivedFeatureAttributes,
     DerivedFeatureDef, Entity, EntityPropMutator, EntityType, ProjectDef, RegistryError,
-    SourceAttributes, SourceDef,
+    SourceAttributes, SourceDef, SourceFormat,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,6 +25,55 @@ fn default_created_on() -> DateTime<Utc> {
     Utc::now()
 }
 
+/**
+ * Accept RFC 3339 strings as well as unix seconds in either numeric or string form,
+ * so timestamps written by tools keeping them as stringly-typed numbers are still ordered correctly
+ */
+/**
+ * Written as the unix seconds string older clients expect
+ */
+fn serialize_timestamp<S>(ts: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&ts.timestamp().to_string())
+}
+
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Seconds(i64),
+        Text(String),
+    }
+    let from_seconds = |secs: i64| {
+        Utc.timestamp_opt(secs, 0)
+            .single()
+            .ok_or_else(|| de::Error::custom(format!("Invalid timestamp {}", secs)))
+    };
+    match Timestamp::deserialize(deserializer)? {
+        Timestamp::Seconds(secs) => from_seconds(secs),
+        Timestamp::Text(s) => match s.trim().parse::<i64>() {
+            Ok(secs) => from_seconds(secs),
+            Err(_) => DateTime::parse_from_rfc3339(&s)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(de::Error::custom),
+        },
+    }
+}
+
+/**
+ * Link to an external resource like a dashboard, a document or the lineage in another system
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityLink {
+    pub title: String,
+    pub url: String,
+}
+
 #[derive(Clone, Debug, Eq, Serialize, Deserialize)]
 pub struct EntityProperty {
     pub guid: Uuid,
@@ -39,8 +88,20 @@ pub struct EntityProperty {
     pub version: u64,
     #[serde(default)]
     pub created_by: String,
-    #[serde(default = "default_created_on")]
+    #[serde(default = "default_created_on", alias = "created_at")]
     pub created_on: DateTime<Utc>,
+    #[serde(default)]
+    pub last_modified_by: String,
+    #[serde(
+        default = "default_created_on",
+        serialize_with = "serialize_timestamp",
+        deserialize_with = "deserialize_timestamp"
+    )]
+    pub last_modified_ts: DateTime<Utc>,
+    #[serde(default)]
+    pub etag: Uuid,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<EntityLink>,
     pub attributes: Attributes,
 }
 
@@ -51,7 +112,7 @@ impl PartialEq for EntityProperty {
 }
 
 impl EntityPropMutator for EntityProperty {
-    fn new_project(definition: &ProjectDef) -> Result<Self, RegistryError> {
+    fn new_project(definition: &ProjectDef, now: DateTime<Utc>) -> Result<Self, RegistryError> {
         Ok(EntityProperty {
             guid: definition.id,
             qualified_name: definition.qualified_name.to_owned(),
@@ -63,10 +124,54 @@ impl EntityPropMutator for EntityProperty {
             attributes: Attributes::Project,
             version: 0,
             created_by: definition.created_by.to_owned(),
-            created_on: Utc::now(),
+            created_on: now,
+            last_modified_by: definition.created_by.to_owned(),
+            last_modified_ts: now,
+            etag: definition.id,
+            links: Default::default(),
         })
     }
-    fn new_source(definition: &SourceDef) -> Result<Self, RegistryError> {
+    fn new_source(definition: &SourceDef, now: DateTime<Utc>) -> Result<Self, RegistryError> {
+        if definition.source_type.trim().is_empty() {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Source {} has no type",
+                definition.qualified_name
+            )));
+        }
+        let type_: SourceFormat = definition.source_type.trim().into();
+        if !type_.is_known() {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Source {} has unknown type `{}`",
+                definition.qualified_name, type_
+            )));
+        }
+        if type_ == SourceFormat::Jdbc {
+            for (field, value) in [
+                ("connectionId", &definition.connection_id),
+                ("table", &definition.table),
+            ] {
+                if value
+                    .as_deref()
+                    .map(str::trim)
+                    .unwrap_or_default()
+                    .is_empty()
+                {
+                    return Err(RegistryError::InvalidDefinition(format!(
+                        "JDBC source {} requires `{}`",
+                        definition.qualified_name, field
+                    )));
+                }
+            }
+        }
+        // File sources are read by path, connection and table are ignored
+        let (connection_id, table) = if type_.is_file() {
+            (None, None)
+        } else {
+            (
+                definition.connection_id.to_owned(),
+                definition.table.to_owned(),
+            )
+        };
         Ok(EntityProperty {
             guid: definition.id,
             qualified_name: definition.qualified_name.to_owned(),
@@ -80,14 +185,20 @@ impl EntityPropMutator for EntityProperty {
                 preprocessing: definition.preprocessing.to_owned(),
                 event_timestamp_column: definition.event_timestamp_column.to_owned(),
                 timestamp_format: definition.timestamp_format.to_owned(),
-                type_: definition.source_type.to_owned(),
+                connection_id,
+                table,
+                type_,
             }),
             version: 0,
             created_by: definition.created_by.to_owned(),
-            created_on: Utc::now(),
+            created_on: now,
+            last_modified_by: definition.created_by.to_owned(),
+            last_modified_ts: now,
+            etag: definition.id,
+            links: Default::default(),
         })
     }
-    fn new_anchor(definition: &AnchorDef) -> Result<Self, RegistryError> {
+    fn new_anchor(definition: &AnchorDef, now: DateTime<Utc>) -> Result<Self, RegistryError> {
         Ok(EntityProperty {
             guid: definition.id,
             qualified_name: definition.qualified_name.to_owned(),
@@ -99,10 +210,17 @@ impl EntityPropMutator for EntityProperty {
             attributes: Attributes::Anchor,
             version: 0,
             created_by: definition.created_by.to_owned(),
-            created_on: Utc::now(),
+            created_on: now,
+            last_modified_by: definition.created_by.to_owned(),
+            last_modified_ts: now,
+            etag: definition.id,
+            links: Default::default(),
         })
     }
-    fn new_anchor_feature(definition: &AnchorFeatureDef) -> Result<Self, RegistryError> {
+    fn new_anchor_feature(
+        definition: &AnchorFeatureDef,
+        now: DateTime<Utc>,
+    ) -> Result<Self, RegistryError> {
         Ok(EntityProperty {
             guid: definition.id,
             qualified_name: definition.qualified_name.to_owned(),
@@ -115,13 +233,22 @@ impl EntityPropMutator for EntityProperty {
                 type_: definition.feature_type.to_owned(),
                 transformation: definition.transformation.to_owned(),
                 key: definition.key.to_owned(),
+                sample_value: definition.sample_value.to_owned(),
+                default_value: definition.default_value.to_owned(),
             }),
             version: 0,
             created_by: definition.created_by.to_owned(),
-            created_on: Utc::now(),
+            created_on: now,
+            last_modified_by: definition.created_by.to_owned(),
+            last_modified_ts: now,
+            etag: definition.id,
+            links: Default::default(),
         })
     }
-    fn new_derived_feature(definition: &DerivedFeatureDef) -> Result<Self, RegistryError> {
+    fn new_derived_feature(
+        definition: &DerivedFeatureDef,
+        now: DateTime<Utc>,
+    ) -> Result<Self, RegistryError> {
         Ok(EntityProperty {
             guid: definition.id,
             qualified_name: definition.qualified_name.to_owned(),
@@ -134,10 +261,16 @@ impl EntityPropMutator for EntityProperty {
                 type_: definition.feature_type.to_owned(),
                 transformation: definition.transformation.to_owned(),
                 key: definition.key.to_owned(),
+                sample_value: definition.sample_value.to_owned(),
+                default_value: definition.default_value.to_owned(),
             }),
             version: 0,
             created_by: definition.created_by.to_owned(),
-            created_on: Utc::now(),
+            created_on: now,
+            last_modified_by: definition.created_by.to_owned(),
+            last_modified_ts: now,
+            etag: definition.id,
+            links: Default::default(),
         })
     }
     fn get_version(&self) -> u64 {
@@ -146,6 +279,59 @@ impl EntityPropMutator for EntityProperty {
     fn set_version(&mut self, version: u64) {
         self.version = version;
     }
+    fn get_etag(&self) -> Uuid {
+        self.etag
+    }
+    fn get_key_aliases(&self) -> Vec<String> {
+        let keys = match &self.attributes {
+            Attributes::AnchorFeature(attr) => &attr.key,
+            Attributes::DerivedFeature(attr) => &attr.key,
+            _ => return vec![],
+        };
+        keys.iter()
+            .flat_map(|k| [&k.key_column_alias, &k.full_name])
+            .flatten()
+            .filter(|alias| !alias.is_empty())
+            .cloned()
+            .collect()
+    }
+    fn update_tags(&mut self, tags: HashMap<String, String>, etag: Uuid) {
+        for (k, v) in tags {
+            if v.is_empty() {
+                self.tags.remove(&k);
+            } else {
+                self.tags.insert(k, v);
+            }
+        }
+        self.etag = etag;
+    }
+    fn get_tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+    fn set_links(&mut self, links: Vec<EntityLink>) {
+        self.links = links;
+    }
+    fn rename(&mut self, name: &str, qualified_name: &str) {
+        if self.display_text == self.name {
+            self.display_text = name.to_string();
+        }
+        self.name = name.to_string();
+        self.qualified_name = qualified_name.to_string();
+    }
+    fn set_id(&mut self, id: Uuid) {
+        self.guid = id;
+        self.etag = id;
+    }
+    fn touch(&mut self, modified_by: &str, ts: DateTime<Utc>) {
+        self.last_modified_ts = ts;
+        if !modified_by.is_empty() {
+            self.last_modified_by = modified_by.to_string();
+        }
+    }
+
+    fn same_definition(&self, other: &Self) -> bool {
+        self == other && self.tags == other.tags
+    }
 }
 
 impl From<EntityProperty> for Entity<EntityProperty> {
@@ -173,7 +359,7 @@ mod tests {
 
     #[test]
     fn test_source_def() {
-        let s= r#"{
+        let s = r#"{
             "id": "00000000-0000-0000-0000-000000000000",
                       "qualifiedName": "test",
             "name": "s2",
@@ -190,7 +376,48 @@ mod tests {
             }
           }"#;
         let sd = serde_json::from_str::<crate::SourceDef>(s).unwrap();
-        let ep = crate::EntityProperty::new_source(&sd).unwrap();
+        let ep = crate::EntityProperty::new_source(&sd, chrono::Utc::now()).unwrap();
         println!("{}", serde_json::to_string_pretty(&ep).unwrap());
     }
-}
\ No newline at end of file
+
+    fn with_last_modified(ts: &str) -> crate::EntityProperty {
+        let s = format!(
+            r#"{{
+                "guid": "00000000-0000-0000-0000-000000000000",
+                "name": "p1",
+                "qualified_name": "p1",
+                "status": "ACTIVE",
+                "display_text": "p1",
+                "labels": [],
+                "last_modified_ts": {},
+                "attributes": {{ "typeName": "feathr_workspace_v1" }}
+            }}"#,
+            ts
+        );
+        serde_json::from_str(&s).unwrap()
+    }
+
+    #[test]
+    fn last_modified_ts() {
+        let numeric = with_last_modified("1660000000");
+        let string = with_last_modified(r#""1660000000""#);
+        let rfc3339 = with_last_modified(r#""2022-08-08T23:06:40Z""#);
+        assert_eq!(numeric.last_modified_ts, string.last_modified_ts);
+        assert_eq!(numeric.last_modified_ts, rfc3339.last_modified_ts);
+
+        // Round-trip
+        let json = serde_json::to_string(&string).unwrap();
+        assert!(json.contains(r#""last_modified_ts":"1660000000""#));
+        let p = serde_json::from_str::<crate::EntityProperty>(&json).unwrap();
+        assert_eq!(p.last_modified_ts, string.last_modified_ts);
+
+        // "9" sorts after "10" as strings, but not as timestamps
+        let mut props = vec![with_last_modified(r#""9""#), with_last_modified(r#""10""#)];
+        props.sort_by_key(|p| std::cmp::Reverse(p.last_modified_ts));
+        let secs: Vec<i64> = props
+            .iter()
+            .map(|p| p.last_modified_ts.timestamp())
+            .collect();
+        assert_eq!(secs, vec![10, 9]);
+    }
+}
@@ -16,6 +16,18 @@ pub const ANCHOR_FEATURE_TYPE: &str = "feathr_anchor_feature_v1";
 pub const DERIVED_FEATURE_TYPE: &str = "feathr_derived_feature_v1";
 pub const SOURCE_TYPE: &str = "feathr_source_v1";
 
+/**
+ * Separator between the qualified name of the container and the name of the entity
+ */
+pub const QUALIFIED_NAME_SEPARATOR: &str = "__";
+
+/**
+ * Qualified name of the entity `name` in the container
+ */
+pub fn child_qualified_name(parent_qualified_name: &str, name: &str) -> String {
+    format!("{}{}{}", parent_qualified_name, QUALIFIED_NAME_SEPARATOR, name)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -35,4 +47,26 @@ mod tests {
         let t: FeatureTransformation = serde_json::from_str(s).unwrap();
         println!("{:#?}", t);
     }
+
+    #[test]
+    fn des_source_format() {
+        let f: SourceFormat = serde_json::from_str(r#""PARQUET""#).unwrap();
+        assert_eq!(f, SourceFormat::Parquet);
+        let f: SourceFormat = serde_json::from_str(r#""parquet""#).unwrap();
+        assert_eq!(f, SourceFormat::Parquet);
+        let f: SourceFormat = serde_json::from_str(r#""foo""#).unwrap();
+        assert_eq!(f, SourceFormat::Other("foo".to_string()));
+        assert_eq!(serde_json::to_string(&f).unwrap(), r#""foo""#);
+        assert_eq!(
+            serde_json::to_string(&SourceFormat::Delta).unwrap(),
+            r#""delta""#
+        );
+    }
+
+    #[test]
+    fn known_source_format() {
+        assert!(SourceFormat::from("PARQUET").is_known());
+        assert!(SourceFormat::from("HDFS").is_known());
+        assert!(!SourceFormat::from("parqet").is_known());
+    }
 }
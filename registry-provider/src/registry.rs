@@ -1,11 +1,14 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
 
 use async_trait::async_trait;
 use uuid::Uuid;
 
 use crate::{
-    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, Edge, EdgeType, Entity, EntityPropMutator,
-    EntityType, ProjectDef, RbacRecord, RegistryError, SourceDef, ToDocString,
+    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, Edge, EdgeType, Entity, EntityLink,
+    EntityPropMutator, EntityType, ProjectDef, RbacRecord, RegistryError, SourceDef, ToDocString,
 };
 
 pub fn extract_version(name: &str) -> (&str, Option<u64>) {
@@ -77,6 +80,12 @@ where
         edge_type: EdgeType,
     ) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
 
+    /**
+     * Get all edges with specified connection type starting from the entity
+     * Every edge comes with its reflection, so incoming edges are covered by the reflected type
+     */
+    fn get_edges(&self, uuid: Uuid, edge_type: EdgeType) -> Result<Vec<Edge>, RegistryError>;
+
     /**
      * Traversal graph from `uuid` by following edges with specific edge type
      */
@@ -87,6 +96,50 @@ where
         size_limit: Option<usize>,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError>;
 
+    /**
+     * Same as `bfs`, also tells if the traversal stopped at `size_limit` before all reachable entities were visited
+     * The traversal may also stop early if the provider limits the time it takes
+     */
+    fn bfs_with_limit(
+        &self,
+        uuid: Uuid,
+        edge_type: EdgeType,
+        size_limit: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError>;
+
+    /**
+     * A page of `size` edges from `offset` of the `bfs` traversal, with the entities they reference
+     * The first flag is set if there are more edges after the page, the second one if the provider
+     * limits the time it takes and the traversal ran out of time before the page was filled
+     */
+    fn bfs_page(
+        &self,
+        uuid: Uuid,
+        edge_type: EdgeType,
+        offset: usize,
+        size: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError>;
+
+    /**
+     * Get all features built on the source, directly or via other features
+     * The flag is set if the traversal stopped at `size_limit` or ran out of time
+     */
+    fn get_source_downstream(
+        &self,
+        source_id: Uuid,
+        size_limit: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError>;
+
+    /**
+     * Same as `bfs_page`, over the features built on the source
+     */
+    fn get_source_downstream_page(
+        &self,
+        source_id: Uuid,
+        offset: usize,
+        size: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError>;
+
     /**
      * Get entity ids with FTS
      */
@@ -99,6 +152,17 @@ where
         offset: usize,
     ) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
 
+    /**
+     * Get distinct qualified names of entities whose name starts with `prefix`
+     * Only entities in `scopes` are returned if it's set
+     */
+    fn suggest_names(
+        &self,
+        prefix: &str,
+        scopes: Option<HashSet<Uuid>>,
+        limit: usize,
+    ) -> Result<Vec<String>, RegistryError>;
+
     /**
      * Get all entities and connections between them under a project
      */
@@ -151,6 +215,93 @@ where
 
     async fn delete_entity(&mut self, id: Uuid) -> Result<(), RegistryError>;
 
+    /**
+     * Delete the source, with `force` its consumers are detached first instead of failing
+     */
+    async fn delete_source(&mut self, id: Uuid, force: bool) -> Result<(), RegistryError>;
+
+    /**
+     * Update tags of an entity, fails if `expected_etag` is set and doesn't match the current one
+     */
+    async fn update_tags(
+        &mut self,
+        id: Uuid,
+        tags: HashMap<String, String>,
+        expected_etag: Option<Uuid>,
+        new_etag: Uuid,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Merge `tags` into the project and all entities in it, returns the number of updated entities
+     */
+    async fn update_project_tags(
+        &mut self,
+        project_id: Uuid,
+        tags: HashMap<String, String>,
+        new_etag: Uuid,
+    ) -> Result<usize, RegistryError>;
+
+    /**
+     * Replace external links of an entity
+     */
+    async fn update_links(&mut self, id: Uuid, links: Vec<EntityLink>)
+        -> Result<(), RegistryError>;
+
+    /**
+     * Add and remove input features of a derived feature without recreating it
+     */
+    async fn update_derived_inputs(
+        &mut self,
+        feature_id: Uuid,
+        add: Vec<Uuid>,
+        remove: Vec<Uuid>,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Move an anchor feature under another anchor in the same project
+     */
+    async fn move_anchor_feature(
+        &mut self,
+        feature_id: Uuid,
+        new_anchor_id: Uuid,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Copy the project and all its contents into a new project, new ids are derived from `id_seed`
+     */
+    async fn clone_project(
+        &mut self,
+        src_id: Uuid,
+        new_name: &str,
+        id_seed: Uuid,
+    ) -> Result<(Uuid, u64), RegistryError>;
+
+    /**
+     * Get ids of all features in the project, every feature comes after all its input features
+     */
+    fn topo_order_features(&self, project_id: Uuid) -> Result<Vec<Uuid>, RegistryError>;
+
+    /**
+     * Get all features having a key with the alias or full name
+     */
+    fn get_features_by_key(&self, key: &str) -> Vec<Entity<EntityProp>>;
+
+    /**
+     * Get all deleted entities still retained by the registry
+     */
+    fn list_deleted(&self) -> Vec<Entity<EntityProp>>;
+
+    /**
+     * Get live entities of the type across all projects ordered by id, starting after the id `after`
+     * Also returns the id to continue from if there are more entities after the page
+     */
+    fn list_entities_by_type(
+        &self,
+        entity_type: EntityType,
+        after: Option<Uuid>,
+        size: usize,
+    ) -> (Vec<Entity<EntityProp>>, Option<Uuid>);
+
     // Provided implementations
 
     /**
@@ -166,6 +317,24 @@ where
         }
     }
 
+    /**
+     * Get one entity by its id or qualified name, fails with `WrongEntityType` if it has another type
+     */
+    fn get_typed_entity(
+        &self,
+        id_or_name: &str,
+        expected: EntityType,
+    ) -> Result<Entity<EntityProp>, RegistryError> {
+        let entity = self.get_entity_by_id_or_qualified_name(id_or_name)?;
+        if entity.entity_type != expected {
+            return Err(RegistryError::WrongEntityType(
+                entity.id,
+                entity.entity_type,
+            ));
+        }
+        Ok(entity)
+    }
+
     /**
      * Get entity name
      */
@@ -278,22 +447,14 @@ where
     ) -> Result<Entity<EntityProp>, RegistryError> {
         let versions = self.get_all_versions(qualified_name);
         match version {
-            Some(v) => {
-                versions
-                    .into_iter()
-                    .find(|e| e.version == v)
-                    .ok_or_else(|| RegistryError::EntityNotFound(format!(
-                        "{}:{}",
-                        qualified_name, v
-                    )))
-            }
+            Some(v) => versions
+                .into_iter()
+                .find(|e| e.version == v)
+                .ok_or_else(|| RegistryError::EntityNotFound(format!("{}:{}", qualified_name, v))),
             None => versions
                 .last()
                 .cloned()
-                .ok_or_else(|| RegistryError::EntityNotFound(format!(
-                    "{}:latest",
-                    qualified_name
-                ))),
+                .ok_or_else(|| RegistryError::EntityNotFound(format!("{}:latest", qualified_name))),
         }
     }
 
@@ -11,6 +11,7 @@ create table edges
     from_id   varchar(50) not null,
     to_id     varchar(50) not null,
     edge_type varchar(50) not null,
+    edge_attributes nvarchar(max),
     constraint edges_pk
         primary key (from_id, to_id, edge_type)
 )
@@ -9,7 +9,8 @@ create table edges
 (
     from_id   varchar(50) not null,
     to_id     varchar(50) not null,
-    edge_type varchar(20) not null
+    edge_type varchar(20) not null,
+    edge_attributes text null
 );
 
 create index entity_dep_conn_type_index
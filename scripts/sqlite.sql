@@ -7,6 +7,7 @@ CREATE TABLE edges(
     from_id varchar(50),
     to_id varchar(50),
     edge_type varchar(50),
+    edge_attributes text,
     PRIMARY KEY (from_id, to_id, edge_type)
 );
 create table userroles
@@ -0,0 +1,168 @@
+use std::{collections::HashMap, fmt::Debug, mem::take};
+
+use async_trait::async_trait;
+use log::debug;
+use registry_provider::{Edge, EdgeType, Entity, RbacRecord, RegistryError, ToDocString};
+use uuid::Uuid;
+
+use crate::db_registry::ExternalStorage;
+
+/**
+ * A write held by `BufferedStorage` until the next flush
+ */
+#[derive(Clone, Debug)]
+pub enum PendingWrite<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    AddEntity {
+        id: Uuid,
+        entity: Entity<EntityProp>,
+    },
+    Connect {
+        from_id: Uuid,
+        to_id: Uuid,
+        edge_type: EdgeType,
+        attributes: HashMap<String, String>,
+    },
+}
+
+/**
+ * External storage able to write a batch of changes in one transaction
+ */
+#[async_trait]
+pub trait BatchWriter<EntityProp>: ExternalStorage<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    /**
+     * Write all changes in one transaction, nothing is written if any of them fails
+     */
+    async fn write_batch(
+        &mut self,
+        writes: Vec<PendingWrite<EntityProp>>,
+    ) -> Result<(), RegistryError>;
+}
+
+/**
+ * Holds `add_entity` and `connect` of the wrapped storage and writes them in one batch on `flush`,
+ * so a registry mutation touching many edges costs one round trip instead of one per edge.
+ * Other operations flush the buffer first to keep the order of writes.
+ */
+#[derive(Debug)]
+pub struct BufferedStorage<S, EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    inner: S,
+    pending: Vec<PendingWrite<EntityProp>>,
+}
+
+impl<S, EntityProp> BufferedStorage<S, EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            pending: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S, EntityProp> ExternalStorage<EntityProp> for BufferedStorage<S, EntityProp>
+where
+    S: BatchWriter<EntityProp>,
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString + Send + Sync,
+{
+    async fn add_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError> {
+        self.pending.push(PendingWrite::AddEntity {
+            id,
+            entity: entity.clone(),
+        });
+        Ok(())
+    }
+
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner.update_entity(id, entity).await
+    }
+
+    async fn delete_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner.delete_entity(id, entity).await
+    }
+
+    async fn connect(
+        &mut self,
+        from_id: Uuid,
+        to_id: Uuid,
+        edge_type: EdgeType,
+        attributes: &HashMap<String, String>,
+    ) -> Result<(), RegistryError> {
+        self.pending.push(PendingWrite::Connect {
+            from_id,
+            to_id,
+            edge_type,
+            attributes: attributes.clone(),
+        });
+        Ok(())
+    }
+
+    async fn disconnect(
+        &mut self,
+        from: &Entity<EntityProp>,
+        from_id: Uuid,
+        to: &Entity<EntityProp>,
+        to_id: Uuid,
+        edge_type: EdgeType,
+        edge_id: Uuid,
+    ) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner
+            .disconnect(from, from_id, to, to_id, edge_type, edge_id)
+            .await
+    }
+
+    async fn grant_permission(&mut self, grant: &RbacRecord) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner.grant_permission(grant).await
+    }
+
+    async fn revoke_permission(&mut self, revoke: &RbacRecord) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner.revoke_permission(revoke).await
+    }
+
+    async fn reconcile(
+        &mut self,
+        entities: &[&Entity<EntityProp>],
+        edges: &[&Edge],
+    ) -> Result<(), RegistryError> {
+        self.flush().await?;
+        self.inner.reconcile(entities, edges).await
+    }
+
+    async fn flush(&mut self) -> Result<(), RegistryError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let writes = take(&mut self.pending);
+        debug!("Writing {} buffered changes", writes.len());
+        self.inner.write_batch(writes).await?;
+        self.inner.flush().await
+    }
+}
@@ -1,6 +1,13 @@
-use registry_provider::{EntityProperty, Entity, Edge, RbacRecord};
+use std::{str::FromStr, sync::Arc};
 
-use crate::Registry;
+use petgraph::visit::EdgeRef;
+use registry_provider::{EntityProperty, Entity, Edge, RbacRecord, RegistryError};
+use tokio::sync::RwLock;
+
+use crate::{db_registry::ExternalStorage, Registry};
+
+mod buffered;
+pub(crate) use buffered::*;
 
 #[cfg(feature = "mssql")]
 mod mssql;
@@ -20,6 +27,94 @@ fn get_rbac_table() -> String {
     std::env::var("RBAC_TABLE").unwrap_or_else(|_| "userroles".to_string())
 }
 
+/**
+ * SQL dialect of the statements generated by `dump_sql`
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SqlDialect {
+    /**
+     * Standard SQL, used by PostgreSQL and SQLite
+     */
+    #[default]
+    Ansi,
+    /**
+     * MySQL treats backslashes in string literals as escapes
+     */
+    MySql,
+    /**
+     * SQL Server needs `N'..'` literals to keep non-ASCII text
+     */
+    MsSql,
+}
+
+impl FromStr for SqlDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ansi" | "postgres" | "sqlite" => Ok(SqlDialect::Ansi),
+            "mysql" => Ok(SqlDialect::MySql),
+            "mssql" => Ok(SqlDialect::MsSql),
+            _ => Err(format!("Unknown SQL dialect '{}'", s)),
+        }
+    }
+}
+
+impl SqlDialect {
+    /**
+     * Quote the string as a SQL literal
+     */
+    fn literal(self, s: &str) -> String {
+        let quoted = s.replace('\'', "''");
+        match self {
+            SqlDialect::Ansi => format!("'{}'", quoted),
+            SqlDialect::MySql => format!("'{}'", quoted.replace('\\', "\\\\")),
+            SqlDialect::MsSql => format!("N'{}'", quoted),
+        }
+    }
+}
+
+impl Registry<EntityProperty> {
+    /**
+     * SQL statements inserting all live entities and edges between them, one statement per line,
+     * the output can be used to restore a database of the dialect
+     */
+    pub fn dump_sql(&self, dialect: SqlDialect) -> String {
+        let sql_literal = |s: &str| dialect.literal(s);
+        let entity_table = get_entity_table();
+        let edge_table = get_edge_table();
+        let entities = self
+            .graph
+            .node_weights()
+            .filter(|e| !self.deleted.contains(&e.id))
+            .map(|e| {
+                format!(
+                    "insert into {} (entity_id, entity_content) values ({}, {});",
+                    entity_table,
+                    sql_literal(&e.id.to_string()),
+                    sql_literal(&serde_json::to_string(&e.properties).unwrap_or_default()),
+                )
+            });
+        let edges = self
+            .graph
+            .edge_references()
+            .map(|e| e.weight())
+            .filter(|e| !(self.deleted.contains(&e.from) || self.deleted.contains(&e.to)))
+            .map(|e| {
+                format!(
+                    "insert into {} (from_id, to_id, edge_type, edge_attributes) \
+                    values ({}, {}, {}, {});",
+                    edge_table,
+                    sql_literal(&e.from.to_string()),
+                    sql_literal(&e.to.to_string()),
+                    sql_literal(&format!("{:?}", e.edge_type)),
+                    sql_literal(&serde_json::to_string(&e.attributes).unwrap_or_default()),
+                )
+            });
+        entities.chain(edges).map(|s| format!("{}\n", s)).collect()
+    }
+}
+
 pub fn attach_storage(registry: &mut Registry<EntityProperty>) {
     #[cfg(feature = "mssql")]
     if mssql::validate_condition() {
@@ -32,6 +127,38 @@ pub fn attach_storage(registry: &mut Registry<EntityProperty>) {
     }
 }
 
+/**
+ * Storage of the first configured database, `None` if there is none
+ */
+fn create_storage() -> Option<Arc<RwLock<dyn ExternalStorage<EntityProperty>>>> {
+    #[cfg(feature = "mssql")]
+    if mssql::validate_condition() {
+        return Some(mssql::create_storage());
+    }
+
+    #[cfg(feature = "ossdbms")]
+    if sqlx::validate_condition() {
+        return Some(sqlx::create_storage());
+    }
+    None
+}
+
+/**
+ * Attach the configured database to a running registry and write the current content into it
+ * Returns false if the registry already has an external storage
+ */
+pub async fn reattach_storage(
+    registry: &mut Registry<EntityProperty>,
+) -> Result<bool, RegistryError> {
+    if !registry.external_storage.is_empty() {
+        return Ok(false);
+    }
+    let storage = create_storage().ok_or_else(|| {
+        RegistryError::ExternalStorageError("No database is configured".to_string())
+    })?;
+    registry.attach_external_storage(storage).await
+}
+
 pub async fn load_content(
 ) -> Result<(Vec<Entity<EntityProperty>>, Vec<Edge>, Vec<RbacRecord>), anyhow::Error> {
     #[cfg(feature = "mssql")]
@@ -45,3 +172,67 @@ pub async fn load_content(
     }
     anyhow::bail!("Unable to load registry")
 }
+
+#[cfg(test)]
+mod tests {
+    use registry_provider::{EntityProperty, ProjectDef, RegistryProvider};
+    use uuid::Uuid;
+
+    use crate::Registry;
+
+    use super::SqlDialect;
+
+    #[test]
+    fn literal_per_dialect() {
+        let s = r#"{"owner":"o'brien \"jr\"","city":"Zürich"}"#;
+        assert_eq!(
+            SqlDialect::Ansi.literal(s),
+            r#"'{"owner":"o''brien \"jr\"","city":"Zürich"}'"#
+        );
+        assert_eq!(
+            SqlDialect::MySql.literal(s),
+            r#"'{"owner":"o''brien \\"jr\\"","city":"Zürich"}'"#
+        );
+        assert_eq!(
+            SqlDialect::MsSql.literal(s),
+            r#"N'{"owner":"o''brien \"jr\"","city":"Zürich"}'"#
+        );
+        assert_eq!("MySQL".parse::<SqlDialect>(), Ok(SqlDialect::MySql));
+        assert!("oracle".parse::<SqlDialect>().is_err());
+    }
+
+    #[tokio::test]
+    async fn dump_sql() {
+        let mut r: Registry<EntityProperty> = Default::default();
+        let mut ids = vec![];
+        for name in ["p1", "p2"] {
+            let (id, _) = r
+                .new_project(&ProjectDef {
+                    id: Uuid::new_v4(),
+                    qualified_name: name.to_string(),
+                    created_by: Default::default(),
+                    tags: [("owner".to_string(), "o'brien".to_string())].into(),
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        r.delete_entity_by_id(ids[1]).await.unwrap();
+
+        let sql = r.dump_sql(SqlDialect::Ansi);
+        // Only p1 is live
+        assert_eq!(
+            sql.lines()
+                .filter(|l| l.starts_with("insert into entities"))
+                .count(),
+            1
+        );
+        assert!(sql.contains(&ids[0].to_string()));
+        assert!(!sql.contains(&ids[1].to_string()));
+        assert!(sql.contains("o''brien"));
+        assert!(!sql.contains("o'brien"));
+
+        let sql = r.dump_sql(SqlDialect::MsSql);
+        assert!(sql.lines().all(|l| l.contains("values (N'")));
+    }
+}
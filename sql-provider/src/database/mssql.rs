@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 use bb8::{Pool, PooledConnection};
@@ -17,7 +17,7 @@ use registry_provider::{
 };
 
 use crate::{
-    database::{get_entity_table, get_rbac_table},
+    database::{get_entity_table, get_rbac_table, BatchWriter, BufferedStorage, PendingWrite},
     db_registry::ExternalStorage,
     Registry,
 };
@@ -38,10 +38,18 @@ fn edge_try_from_row(r: Row) -> Result<Edge, tiberius::error::Error> {
     let edge_type: EdgeType = serde_json::from_str::<EdgeType>(&s)
         .ok()
         .ok_or_else(|| tiberius::error::Error::Conversion("".into()))?;
+    let c: Option<&str> = r.get(3);
+    let attributes = c
+        .filter(|s| !s.is_empty())
+        .map(serde_json::from_str)
+        .transpose()
+        .map_err(|e| tiberius::error::Error::Conversion(format!("{:?}", e).into()))?
+        .unwrap_or_default();
     Ok(Edge {
         from,
         to,
         edge_type,
+        attributes,
     })
 }
 
@@ -91,11 +99,11 @@ async fn load_entities(
 async fn load_edges(
     conn: &mut PooledConnection<'static, ConnectionManager>,
 ) -> Result<Vec<Edge>, anyhow::Error> {
-    let edges_table = std::env::var("EDGE_TABLE").unwrap_or_else(|_| "edges".to_string());
+    let edges_table = get_edge_table();
     debug!("Loading edges from {}", edges_table);
     let x: Vec<Edge> = conn
         .simple_query(format!(
-            "SELECT from_id, to_id, edge_type from {}",
+            "SELECT from_id, to_id, edge_type, edge_attributes from {}",
             edges_table
         ))
         .await?
@@ -108,6 +116,32 @@ async fn load_edges(
     Ok(x)
 }
 
+/**
+ * Edge tables created before edges had attributes don't have the `edge_attributes` column,
+ * add it so loading and writing edges work on them
+ */
+async fn migrate_edge_table(
+    conn: &mut PooledConnection<'static, ConnectionManager>,
+) -> Result<(), anyhow::Error> {
+    let edges_table = get_edge_table();
+    let probe = conn
+        .simple_query(format!("SELECT edge_attributes FROM {} WHERE 1 = 0", edges_table))
+        .await;
+    // The stream borrows the connection, drain it before running anything else
+    if let Ok(stream) = probe {
+        if stream.into_results().await.is_ok() {
+            return Ok(());
+        }
+    }
+    warn!("Adding column 'edge_attributes' to edges table '{}'", edges_table);
+    conn.execute(
+        format!("ALTER TABLE {} ADD edge_attributes nvarchar(max)", edges_table),
+        &[],
+    )
+    .await?;
+    Ok(())
+}
+
 async fn load_permissions(
     conn: &mut PooledConnection<'static, ConnectionManager>,
 ) -> Result<Vec<RbacRecord>, anyhow::Error> {
@@ -209,6 +243,7 @@ pub async fn load_content(
 ) -> Result<(Vec<Entity<EntityProperty>>, Vec<Edge>, Vec<RbacRecord>), anyhow::Error> {
     debug!("Loading registry data from database");
     let mut conn = connect().await?;
+    migrate_edge_table(&mut conn).await?;
     let edges = load_edges(&mut conn).await?;
     let entities = load_entities(&mut conn).await?;
     let permissions = load_permissions(&mut conn).await?;
@@ -225,9 +260,11 @@ pub async fn load_content(
 }
 
 pub fn attach_storage(registry: &mut Registry<EntityProperty>) {
-    registry
-        .external_storage
-        .push(Arc::new(RwLock::new(MsSqlStorage::default())));
+    registry.external_storage.push(create_storage());
+}
+
+pub fn create_storage() -> Arc<RwLock<dyn ExternalStorage<EntityProperty>>> {
+    Arc::new(RwLock::new(BufferedStorage::new(MsSqlStorage::default())))
 }
 
 #[derive(Debug)]
@@ -243,6 +280,81 @@ impl MsSqlStorage {
             edge_table: edge_table.to_string(),
         }
     }
+
+    fn insert_entity_sql(&self) -> String {
+        format!(
+            r#"IF NOT EXISTS (SELECT 1 FROM {} WHERE entity_id = @P1)
+            BEGIN
+                INSERT INTO {}
+                (entity_id, entity_content)
+                values
+                (@P1, @P2)
+            END"#,
+            self.entity_table, self.entity_table,
+        )
+    }
+
+    /**
+     * Delete all entity and edge records then insert the given ones, in the caller's transaction
+     */
+    async fn replace_all(
+        &self,
+        conn: &mut PooledConnection<'static, ConnectionManager>,
+        entities: &[&Entity<EntityProperty>],
+        edges: &[&Edge],
+    ) -> Result<(), tiberius::error::Error> {
+        for table in [&self.entity_table, &self.edge_table] {
+            conn.execute(format!("DELETE FROM {}", table), &[]).await?;
+        }
+        for e in entities {
+            conn.execute(
+                format!(
+                    r#"INSERT INTO {}
+                    (entity_id, entity_content)
+                    values
+                    (@P1, @P2)"#,
+                    self.entity_table
+                ),
+                &[
+                    &e.id.to_string(),
+                    &serde_json::to_string_pretty(&e.properties).unwrap(),
+                ],
+            )
+            .await?;
+        }
+        for e in edges {
+            conn.execute(
+                format!(
+                    r#"INSERT INTO {}
+                    (from_id, to_id, edge_type, edge_attributes)
+                    values
+                    (@P1, @P2, @P3, @P4)"#,
+                    self.edge_table
+                ),
+                &[
+                    &e.from.to_string(),
+                    &e.to.to_string(),
+                    &format!("{:?}", e.edge_type),
+                    &serde_json::to_string(&e.attributes).unwrap(),
+                ],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    fn insert_edge_sql(&self) -> String {
+        format!(
+            r#"IF NOT EXISTS (SELECT 1 FROM {} WHERE from_id=@P1 and to_id=@P2 and edge_type=@P3)
+            BEGIN
+                INSERT INTO {}
+                (from_id, to_id, edge_type, edge_attributes)
+                values
+                (@P1, @P2, @P3, @P4)
+            END"#,
+            self.edge_table, self.edge_table
+        )
+    }
 }
 
 impl Default for MsSqlStorage {
@@ -257,20 +369,39 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
         &mut self,
         id: Uuid,
         entity: &Entity<EntityProperty>,
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        conn.execute(
+            self.insert_entity_sql().apply(|s| {
+                debug!("SQL is: {}", s);
+                debug!("Id: {}", &id);
+                debug!("Name: {}", &entity.qualified_name);
+                s
+            }),
+            &[
+                &id.to_string(),
+                &serde_json::to_string_pretty(&entity.properties).unwrap(),
+            ],
+        )
+        .await
+        .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProperty>,
     ) -> Result<(), RegistryError> {
         let mut conn = connect()
             .await
             .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
         conn.execute(
             format!(
-                r#"IF NOT EXISTS (SELECT 1 FROM {} WHERE entity_id = @P1)
-                BEGIN
-                    INSERT INTO {}
-                    (entity_id, entity_content)
-                    values
-                    (@P1, @P2)
-                END"#,
-                self.entity_table, self.entity_table,
+                "UPDATE {} SET entity_content = @P2 WHERE entity_id = @P1",
+                self.entity_table
             )
             .apply(|s| {
                 debug!("SQL is: {}", s);
@@ -313,22 +444,13 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
         from_id: Uuid,
         to_id: Uuid,
         edge_type: EdgeType,
+        attributes: &HashMap<String, String>,
     ) -> Result<(), RegistryError> {
         let mut conn = connect()
             .await
             .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
         conn.execute(
-            format!(
-                r#"IF NOT EXISTS (SELECT 1 FROM {} WHERE from_id=@P1 and to_id=@P2 and edge_type=@P3)
-                BEGIN
-                    INSERT INTO {}
-                    (from_id, to_id, edge_type)
-                    values
-                    (@P1, @P2, @P3)
-                END"#,
-                self.edge_table, self.edge_table
-            )
-            .apply(|s| {
+            self.insert_edge_sql().apply(|s| {
                 debug!("SQL is: {}", s);
                 s
             }),
@@ -336,6 +458,7 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
                 &from_id.to_string(),
                 &to_id.to_string(),
                 &format!("{:?}", edge_type),
+                &serde_json::to_string(attributes).unwrap(),
             ],
         )
         .await
@@ -436,4 +559,87 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
         .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
         Ok(())
     }
+
+    async fn reconcile(
+        &mut self,
+        entities: &[&Entity<EntityProperty>],
+        edges: &[&Edge],
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        debug!(
+            "Replacing all records with {} entities and {} edges",
+            entities.len(),
+            edges.len()
+        );
+        conn.execute("BEGIN TRANSACTION", &[])
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        if let Err(e) = self.replace_all(&mut conn, entities, edges).await {
+            warn!("Failed to replace records, rolling back, error: {:?}", e);
+            conn.execute("ROLLBACK TRANSACTION", &[]).await.log().ok();
+            return Err(RegistryError::ExternalStorageError(format!("{:?}", e)));
+        }
+        conn.execute("COMMIT TRANSACTION", &[])
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BatchWriter<EntityProperty> for MsSqlStorage {
+    async fn write_batch(
+        &mut self,
+        writes: Vec<PendingWrite<EntityProperty>>,
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        debug!("Writing {} changes in one transaction", writes.len());
+        conn.execute("BEGIN TRANSACTION", &[])
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        for write in writes {
+            let result = match write {
+                PendingWrite::AddEntity { id, entity } => {
+                    conn.execute(
+                        self.insert_entity_sql(),
+                        &[
+                            &id.to_string(),
+                            &serde_json::to_string_pretty(&entity.properties).unwrap(),
+                        ],
+                    )
+                    .await
+                }
+                PendingWrite::Connect {
+                    from_id,
+                    to_id,
+                    edge_type,
+                    attributes,
+                } => {
+                    conn.execute(
+                        self.insert_edge_sql(),
+                        &[
+                            &from_id.to_string(),
+                            &to_id.to_string(),
+                            &format!("{:?}", edge_type),
+                            &serde_json::to_string(&attributes).unwrap(),
+                        ],
+                    )
+                    .await
+                }
+            };
+            if let Err(e) = result {
+                warn!("Failed to write batch, rolling back, error: {:?}", e);
+                conn.execute("ROLLBACK TRANSACTION", &[]).await.log().ok();
+                return Err(RegistryError::ExternalStorageError(format!("{:?}", e)));
+            }
+        }
+        conn.execute("COMMIT TRANSACTION", &[])
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
 }
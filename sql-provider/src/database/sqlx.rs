@@ -1,8 +1,8 @@
-use std::{str::FromStr, sync::Arc};
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use log::debug;
+use log::{debug, warn};
 use serde::Deserialize;
 use sqlx::{
     any::AnyKind, pool::PoolConnection, Any, AnyConnection, AnyPool, ConnectOptions, Connection,
@@ -69,10 +69,11 @@ struct EdgeWrapper {
     from_id: String,
     to_id: String,
     edge_type: String,
+    edge_attributes: Option<String>,
 }
 
 async fn load_edges() -> Result<Vec<Edge>, anyhow::Error> {
-    let edges_table = std::env::var("EDGE_TABLE").unwrap_or_else(|_| "edges".to_string());
+    let edges_table = get_edge_table();
     debug!("Loading edges from {}", edges_table);
     let pool = POOL
         .get_or_init(|| async { init_pool().await.ok() })
@@ -80,7 +81,10 @@ async fn load_edges() -> Result<Vec<Edge>, anyhow::Error> {
         .clone()
         .ok_or_else(|| anyhow::Error::msg("Environment variable 'CONNECTION_STR' is not set."))?;
     debug!("SQLx connection pool acquired, connecting to database");
-    let sql = format!("SELECT from_id, to_id, edge_type from {}", edges_table);
+    let sql = format!(
+        "SELECT from_id, to_id, edge_type, edge_attributes from {}",
+        edges_table
+    );
     let rows: Vec<EdgeWrapper> = sqlx::query_as::<_, EdgeWrapper>(&sql)
         .fetch_all(&pool)
         .await?;
@@ -120,10 +124,21 @@ async fn load_edges() -> Result<Vec<Edge>, anyhow::Error> {
                 }
             };
 
+            let attributes = r
+                .edge_attributes
+                .filter(|s| !s.is_empty())
+                .map(|s| serde_json::from_str(&s))
+                .transpose()
+                .map_err(|e| {
+                    anyhow::Error::msg(format!("Failed to parse edge attributes, error {}", e))
+                })?
+                .unwrap_or_default();
+
             Ok(Edge {
                 edge_type,
                 from,
                 to,
+                attributes,
             })
         })
         .collect::<Result<Vec<_>, anyhow::Error>>()?;
@@ -224,7 +239,7 @@ pub async fn load_content(
         debug!("Creating edges table '{}' if not exists", get_edge_table());
         let sql = &format!(
             r#"CREATE TABLE IF NOT EXISTS {}
-            (from_id varchar(50), to_id varchar(50), edge_type varchar(50), PRIMARY KEY (from_id, to_id, edge_type))"#,
+            (from_id varchar(50), to_id varchar(50), edge_type varchar(50), edge_attributes text, PRIMARY KEY (from_id, to_id, edge_type))"#,
             get_edge_table()
         );
         conn.execute(sqlx::query(sql)).await?;
@@ -232,6 +247,8 @@ pub async fn load_content(
         conn.close().await?;
     }
 
+    migrate_edge_table().await?;
+
     debug!("Loading registry data from database");
     let edges = load_edges().await?;
     let entities = load_entities().await?;
@@ -248,6 +265,23 @@ pub async fn load_content(
     ))
 }
 
+/**
+ * Edge tables created before edges had attributes don't have the `edge_attributes` column,
+ * add it so loading and writing edges work on them
+ */
+async fn migrate_edge_table() -> Result<(), anyhow::Error> {
+    let edges_table = get_edge_table();
+    let mut conn = connect().await?;
+    let probe = format!("SELECT edge_attributes FROM {} WHERE 1 = 0", edges_table);
+    if conn.execute(sqlx::query(&probe)).await.is_ok() {
+        return Ok(());
+    }
+    warn!("Adding column 'edge_attributes' to edges table '{}'", edges_table);
+    let sql = format!("ALTER TABLE {} ADD edge_attributes text", edges_table);
+    conn.execute(sqlx::query(&sql)).await?;
+    Ok(())
+}
+
 pub fn validate_condition() -> bool {
     if let Ok(conn_str) = std::env::var("CONNECTION_STR") {
         conn_str
@@ -259,9 +293,11 @@ pub fn validate_condition() -> bool {
 }
 
 pub fn attach_storage(registry: &mut Registry<EntityProperty>) {
-    registry
-        .external_storage
-        .push(Arc::new(RwLock::new(SqlxStorage::default())));
+    registry.external_storage.push(create_storage());
+}
+
+pub fn create_storage() -> Arc<RwLock<dyn ExternalStorage<EntityProperty>>> {
+    Arc::new(RwLock::new(SqlxStorage::default()))
 }
 
 static POOL: OnceCell<Option<AnyPool>> = OnceCell::const_new();
@@ -374,6 +410,37 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
         Ok(())
     }
 
+    /**
+     * Function will be called when an existing entity is changed in place, e.g. renamed
+     * ExternalStorage may need to update the entity record in database, etc
+     */
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProperty>,
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        let sql = match conn.kind() {
+            sqlx::any::AnyKind::Postgres => format!(
+                "UPDATE {} SET entity_content = $1 WHERE entity_id = $2;",
+                self.entity_table
+            ),
+            _ => format!(
+                "UPDATE {} SET entity_content = ? WHERE entity_id = ?;",
+                self.entity_table
+            ),
+        };
+        let query = sqlx::query(&sql)
+            .bind(serde_json::to_string_pretty(&entity.properties).unwrap())
+            .bind(id.to_string());
+        conn.execute(query)
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
     /**
      * Function will be called when an entity is deleted in the graph
      * ExternalStorage may need to remove the entity record from database, etc
@@ -404,7 +471,9 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
         from_id: Uuid,
         to_id: Uuid,
         edge_type: EdgeType,
+        attributes: &HashMap<String, String>,
     ) -> Result<(), RegistryError> {
+        let attributes = serde_json::to_string(attributes).unwrap();
         let mut conn = connect()
             .await
             .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
@@ -413,16 +482,17 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
             sqlx::any::AnyKind::Postgres => {
                 let sql = &format!(
                     r#"INSERT INTO {}
-                    (from_id, to_id, edge_type)
+                    (from_id, to_id, edge_type, edge_attributes)
                     values
-                    ($1, $2, $3)
+                    ($1, $2, $3, $4)
                     ON CONFLICT DO NOTHING;"#,
                     self.edge_table,
                 );
                 let query = sqlx::query(sql)
                     .bind(from_id.to_string())
                     .bind(to_id.to_string())
-                    .bind(format!("{:?}", edge_type));
+                    .bind(format!("{:?}", edge_type))
+                    .bind(attributes.clone());
                 conn.execute(query)
                     .await
                     .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
@@ -430,15 +500,16 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
             sqlx::any::AnyKind::MySql => {
                 let sql = format!(
                     r#"INSERT IGNORE INTO {}
-                    (from_id, to_id, edge_type)
+                    (from_id, to_id, edge_type, edge_attributes)
                     values
-                    (?, ?, ?)"#,
+                    (?, ?, ?, ?)"#,
                     self.edge_table,
                 );
                 let query = sqlx::query(&sql)
                     .bind(from_id.to_string())
                     .bind(to_id.to_string())
-                    .bind(format!("{:?}", edge_type));
+                    .bind(format!("{:?}", edge_type))
+                    .bind(attributes.clone());
                 conn.execute(query)
                     .await
                     .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
@@ -446,15 +517,16 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
             sqlx::any::AnyKind::Sqlite => {
                 let sql = format!(
                     r#"INSERT OR IGNORE INTO {}
-                    (from_id, to_id, edge_type)
+                    (from_id, to_id, edge_type, edge_attributes)
                     values
-                    (?, ?, ?)"#,
+                    (?, ?, ?, ?)"#,
                     self.edge_table,
                 );
                 let query = sqlx::query(&sql)
                     .bind(from_id.to_string())
                     .bind(to_id.to_string())
-                    .bind(format!("{:?}", edge_type));
+                    .bind(format!("{:?}", edge_type))
+                    .bind(attributes.clone());
                 conn.execute(query)
                     .await
                     .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
@@ -550,4 +622,79 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
             .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
         Ok(())
     }
+
+    /**
+     * Replace all entity and edge records in one transaction
+     */
+    async fn reconcile(
+        &mut self,
+        entities: &[&Entity<EntityProperty>],
+        edges: &[&Edge],
+    ) -> Result<(), RegistryError> {
+        let err = |e: sqlx::Error| RegistryError::ExternalStorageError(format!("{:?}", e));
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        let (entity_sql, edge_sql) = match conn.kind() {
+            AnyKind::Postgres => (
+                format!(
+                    "INSERT INTO {} (entity_id, entity_content) values ($1, $2)",
+                    self.entity_table
+                ),
+                format!(
+                    r#"INSERT INTO {}
+                    (from_id, to_id, edge_type, edge_attributes)
+                    values
+                    ($1, $2, $3, $4)"#,
+                    self.edge_table
+                ),
+            ),
+            _ => (
+                format!(
+                    "INSERT INTO {} (entity_id, entity_content) values (?, ?)",
+                    self.entity_table
+                ),
+                format!(
+                    r#"INSERT INTO {}
+                    (from_id, to_id, edge_type, edge_attributes)
+                    values
+                    (?, ?, ?, ?)"#,
+                    self.edge_table
+                ),
+            ),
+        };
+        debug!(
+            "Replacing all records with {} entities and {} edges",
+            entities.len(),
+            edges.len()
+        );
+        // Dropped without commit if any statement fails, which rolls back everything
+        let mut tx = conn.begin().await.map_err(err)?;
+        for table in [&self.entity_table, &self.edge_table] {
+            sqlx::query(&format!("DELETE FROM {}", table))
+                .execute(&mut *tx)
+                .await
+                .map_err(err)?;
+        }
+        for e in entities {
+            sqlx::query(&entity_sql)
+                .bind(e.id.to_string())
+                .bind(serde_json::to_string_pretty(&e.properties).unwrap())
+                .execute(&mut *tx)
+                .await
+                .map_err(err)?;
+        }
+        for e in edges {
+            sqlx::query(&edge_sql)
+                .bind(e.from.to_string())
+                .bind(e.to.to_string())
+                .bind(format!("{:?}", e.edge_type))
+                .bind(serde_json::to_string(&e.attributes).unwrap())
+                .execute(&mut *tx)
+                .await
+                .map_err(err)?;
+        }
+        tx.commit().await.map_err(err)?;
+        Ok(())
+    }
 }
@@ -1,10 +1,13 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common_utils::Blank;
 use itertools::Itertools;
-use log::debug;
+use log::{debug, warn};
 use petgraph::{
     graph::{EdgeIndex, Graph, NodeIndex},
     visit::EdgeRef,
@@ -13,16 +16,28 @@ use petgraph::{
 use registry_provider::*;
 use serde::Deserialize;
 use tokio::sync::RwLock;
+use tokio_stream::{Stream, StreamExt};
 use uuid::Uuid;
 
-use crate::fts::{FtsError, FtsIndex};
+use crate::delta::{Change, Changelog};
+use crate::fts::{FtsError, FtsIndex, TokenizerConfig};
 use crate::rbac_map::RbacMap;
 
 const NODE_CAPACITY: usize = 1000;
 
+// Number of entities indexed between FTS commits when loading data
+const FTS_BATCH_SIZE: usize = 10000;
+
+// Number of entities visited between deadline checks in traversals
+const TRAVERSAL_CLOCK_INTERVAL: usize = 64;
+
 impl From<FtsError> for RegistryError {
     fn from(e: FtsError) -> Self {
-        RegistryError::FtsError(e.to_string())
+        match e {
+            FtsError::InvalidQuery(q) => RegistryError::InvalidQuery(q),
+            FtsError::QueryParseError(e) => RegistryError::InvalidQuery(e.to_string()),
+            e => RegistryError::FtsError(e.to_string()),
+        }
     }
 }
 
@@ -45,6 +60,16 @@ where
         entity: &Entity<EntityProp>,
     ) -> Result<(), RegistryError>;
 
+    /**
+     * Function will be called when an existing entity is changed in place, e.g. renamed
+     * ExternalStorage may need to update the entity record in database, etc
+     */
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError>;
+
     /**
      * Function will be called when an entity is deleted in the graph
      * ExternalStorage may need to remove the entity record from database, etc
@@ -65,6 +90,7 @@ where
         from_id: Uuid,
         to_id: Uuid,
         edge_type: EdgeType,
+        attributes: &HashMap<String, String>,
     ) -> Result<(), RegistryError>;
 
     /**
@@ -85,6 +111,58 @@ where
     async fn grant_permission(&mut self, grant: &RbacRecord) -> Result<(), RegistryError>;
 
     async fn revoke_permission(&mut self, revoke: &RbacRecord) -> Result<(), RegistryError>;
+
+    /**
+     * Function will be called when the storage is attached to a running registry
+     * ExternalStorage must make its records match the entities and edges exactly, existing records
+     * are overwritten and records of entities and edges not in the lists are removed
+     */
+    async fn reconcile(
+        &mut self,
+        entities: &[&Entity<EntityProp>],
+        edges: &[&Edge],
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Function will be called before the registry shuts down
+     * ExternalStorage should write out any buffered changes
+     */
+    async fn flush(&mut self) -> Result<(), RegistryError> {
+        Ok(())
+    }
+}
+
+/**
+ * Progress of a breadth-first traversal, entities and edges are in discovery order
+ */
+struct BfsState {
+    entities: Vec<NodeIndex>,
+    edges: Vec<EdgeIndex>,
+    visited_entities: HashSet<NodeIndex>,
+    visited_edges: HashSet<EdgeIndex>,
+    /**
+     * Position in `entities` of the entity whose neighbors are visited next
+     */
+    cursor: usize,
+}
+
+impl BfsState {
+    fn new(root: NodeIndex) -> Self {
+        Self {
+            entities: vec![root],
+            edges: vec![],
+            visited_entities: HashSet::from([root]),
+            visited_edges: HashSet::new(),
+            cursor: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BfsStop {
+    Finished,
+    Stopped,
+    Deadline,
 }
 
 #[derive(Debug)]
@@ -101,6 +179,12 @@ where
     // Secondary index for nodes, can be used as entry points for all entity GUIDs
     pub(crate) name_id_map: HashMap<String, BTreeMap<u64, Uuid>>,
 
+    // Lowercased qualified names to the qualified names in `name_id_map`, used by case-insensitive lookup
+    pub(crate) name_id_map_ci: HashMap<String, HashSet<String>>,
+
+    // Key aliases and full names to the features with the key
+    pub(crate) alias_id_map: HashMap<String, HashSet<Uuid>>,
+
     pub(crate) deleted: HashSet<Uuid>,
 
     // Besides arbitrary NodeIndex, entry points can be used to start a graph traversal
@@ -112,6 +196,18 @@ where
 
     pub(crate) permission_map: RbacMap,
 
+    // Recent mutations, used to take snapshot deltas
+    pub(crate) changelog: Changelog,
+
+    // Lineage traversals taking longer than this stop early and return partial results
+    pub(crate) traversal_timeout: Option<Duration>,
+
+    // Tag keys that can't be changed or removed once they have a value
+    pub(crate) protected_tags: HashSet<String>,
+
+    // Time of the mutation being applied, taken from the request so all replicas agree
+    pub(crate) clock: Option<DateTime<Utc>>,
+
     // TODO:
     pub external_storage: Vec<Arc<RwLock<dyn ExternalStorage<EntityProp>>>>,
 }
@@ -125,10 +221,16 @@ where
             graph: Default::default(),
             node_id_map: Default::default(),
             name_id_map: Default::default(),
+            name_id_map_ci: Default::default(),
+            alias_id_map: Default::default(),
             deleted: Default::default(),
             entry_points: Default::default(),
             fts_index: Default::default(),
             permission_map: Default::default(),
+            changelog: Default::default(),
+            traversal_timeout: None,
+            protected_tags: Default::default(),
+            clock: None,
             external_storage: Default::default(),
         }
     }
@@ -148,22 +250,38 @@ where
         + Deserialize<'de>,
 {
     pub fn from_content(
-        graph: Graph<Entity<EntityProp>, Edge, Directed>,
+        mut graph: Graph<Entity<EntityProp>, Edge, Directed>,
         deleted: HashSet<Uuid>,
         permissions: Vec<RbacRecord>,
     ) -> Self {
-        let fts_index = FtsIndex::new();
+        normalize_edges(&mut graph);
+        let fts_index = FtsIndex::default();
         let node_id_map = graph
             .node_indices()
             .filter_map(|idx| graph.node_weight(idx).map(|w| (w.id, idx)))
             .collect();
+        // Names of deleted entities may have been taken by new ones
         let name_id_map: HashMap<String, BTreeMap<u64, Uuid>> = graph
             .node_weights()
+            .filter(|w| !deleted.contains(&w.id))
             .map(|w| (&w.qualified_name, (w.version, w.id)))
             .group_by(|v| v.0.to_owned())
             .into_iter()
             .map(|(k, v)| (k, v.map(|v| v.1).collect()))
             .collect();
+        let mut name_id_map_ci: HashMap<String, HashSet<String>> = Default::default();
+        for name in name_id_map.keys() {
+            name_id_map_ci
+                .entry(name.to_lowercase())
+                .or_default()
+                .insert(name.to_owned());
+        }
+        let mut alias_id_map: HashMap<String, HashSet<Uuid>> = Default::default();
+        for w in graph.node_weights().filter(|w| !deleted.contains(&w.id)) {
+            for alias in w.properties.get_key_aliases() {
+                alias_id_map.entry(alias).or_default().insert(w.id);
+            }
+        }
         let entry_points = graph
             .node_indices()
             .filter(|&idx| {
@@ -177,24 +295,73 @@ where
             graph,
             node_id_map,
             name_id_map,
+            name_id_map_ci,
+            alias_id_map,
             deleted,
             entry_points,
             fts_index,
             permission_map: Default::default(),
+            changelog: Default::default(),
+            traversal_timeout: None,
+            protected_tags: Default::default(),
+            clock: None,
             external_storage: Default::default(),
         };
         let ids: Vec<_> = ret.node_id_map.keys().copied().collect();
-
-        ids.into_iter().for_each(|id| {
-            ret.index_entity(id.to_owned(), false).ok();
-        });
-        ret.fts_index.commit().ok();
+        ret.index_entities_bulk(ids.into_iter()).ok();
 
         ret.load_permissions(permissions.into_iter()).ok();
         ret
     }
 }
 
+/**
+ * Make sure every edge has exactly one reflection, edges are deduped by `(from, to, edge_type)`
+ * and missing reflections are added, persisted content may or may not include both directions
+ */
+fn normalize_edges<EntityProp>(graph: &mut Graph<Entity<EntityProp>, Edge, Directed>) {
+    // Snapshots taken by older versions have feature-to-feature `Consumes`/`Produces` edges
+    let legacy: Vec<(EdgeIndex, EdgeType)> = graph
+        .edge_references()
+        .filter_map(|e| {
+            let from = graph.node_weight(e.source())?.entity_type;
+            let to = graph.node_weight(e.target())?.entity_type;
+            let edge_type = e.weight().edge_type.normalize(from, to);
+            (edge_type != e.weight().edge_type).then(|| (e.id(), edge_type))
+        })
+        .collect();
+    for (idx, edge_type) in legacy {
+        if let Some(w) = graph.edge_weight_mut(idx) {
+            w.edge_type = edge_type;
+        }
+    }
+
+    let mut existing: HashSet<(NodeIndex, NodeIndex, EdgeType)> = Default::default();
+    let mut duplicated: Vec<EdgeIndex> = graph
+        .edge_references()
+        .filter(|e| !existing.insert((e.source(), e.target(), e.weight().edge_type)))
+        .map(|e| e.id())
+        .collect();
+    // Removing an edge moves the last one into its index, so remove from the back
+    duplicated.sort_unstable();
+    for idx in duplicated.into_iter().rev() {
+        debug!("Removing duplicated edge {:?}", idx);
+        graph.remove_edge(idx);
+    }
+
+    let missing: Vec<(NodeIndex, NodeIndex, Edge)> = graph
+        .edge_references()
+        .filter(|e| {
+            !existing.contains(&(e.target(), e.source(), e.weight().edge_type.reflection()))
+        })
+        .map(|e| (e.target(), e.source(), e.weight().reflection()))
+        .collect();
+    for (from_idx, to_idx, edge) in missing {
+        debug!("Adding missing reflection {:?}", edge);
+        graph.add_edge(from_idx, to_idx, edge);
+    }
+}
+
 #[allow(dead_code)]
 impl<EntityProp> Registry<EntityProp>
 where
@@ -205,10 +372,16 @@ where
             graph: Graph::new(),
             node_id_map: Default::default(),
             name_id_map: Default::default(),
+            name_id_map_ci: Default::default(),
+            alias_id_map: Default::default(),
             deleted: Default::default(),
             entry_points: Default::default(),
-            fts_index: FtsIndex::new(),
+            fts_index: FtsIndex::default(),
             permission_map: Default::default(),
+            changelog: Default::default(),
+            traversal_timeout: None,
+            protected_tags: Default::default(),
+            clock: None,
             external_storage: Default::default(),
         }
     }
@@ -222,22 +395,36 @@ where
         NI: Iterator<Item = Entity<EntityProp>>,
         EI: Iterator<Item = Edge>,
     {
-        let mut ids: HashSet<Uuid> = Default::default();
+        self.load_data_stream(tokio_stream::iter(entities), tokio_stream::iter(edges))
+            .await
+    }
+
+    /**
+     * Load all entities then all edges from the streams, items are consumed one by one and
+     * entities are indexed `FTS_BATCH_SIZE` at a time, so a big registry can be loaded
+     * without holding the whole dataset in memory
+     * The FTS index is committed once at the end, and rolled back if indexing fails
+     */
+    pub async fn load_data_stream<NS, ES>(
+        &mut self,
+        mut entities: NS,
+        mut edges: ES,
+    ) -> Result<(), RegistryError>
+    where
+        NS: Stream<Item = Entity<EntityProp>> + Unpin,
+        ES: Stream<Item = Edge> + Unpin,
+    {
+        let mut ids: Vec<Uuid> = Default::default();
         self.fts_index.enable(false);
-        for e in entities {
+        while let Some(e) = entities.next().await {
             // Insert and ignore any error. e.g. duplicated entities
+            let id = e.id;
             match self
-                .insert_entity(
-                    e.id,
-                    e.entity_type,
-                    e.name.clone(),
-                    e.qualified_name.clone(),
-                    e.properties.clone(),
-                )
+                .insert_entity(e.id, e.entity_type, e.name, e.qualified_name, e.properties)
                 .await
             {
                 Ok(_) => {
-                    ids.insert(e.id);
+                    ids.push(id);
                 }
                 Err(e) => {
                     debug!("Ignored error '{:?}'", e);
@@ -245,15 +432,23 @@ where
             }
         }
 
-        for e in edges {
-            self.connect(e.from, e.to, e.edge_type).await.ok();
+        while let Some(e) = edges.next().await {
+            self.connect_with_attributes(e.from, e.to, e.edge_type, e.attributes)
+                .await
+                .ok();
         }
 
+        // Scopes of entities come from edges, so indexing can only start after all edges are loaded
         self.fts_index.enable(true);
-        for id in ids {
-            self.index_entity(id, false).ok();
+        self.fts_index.begin()?;
+        let indexed = ids
+            .chunks(FTS_BATCH_SIZE)
+            .try_for_each(|chunk| self.index_entities_bulk(chunk.iter().copied()))
+            .and_then(|_| self.fts_index.commit().map_err(RegistryError::from));
+        if indexed.is_err() {
+            self.rollback_fts();
         }
-        self.fts_index.commit()?;
+        indexed?;
 
         self.entry_points = self
             .graph
@@ -283,10 +478,16 @@ where
             graph: Graph::with_capacity(NODE_CAPACITY * 10, NODE_CAPACITY),
             node_id_map: HashMap::with_capacity(NODE_CAPACITY),
             name_id_map: HashMap::with_capacity(NODE_CAPACITY),
+            name_id_map_ci: HashMap::with_capacity(NODE_CAPACITY),
+            alias_id_map: Default::default(),
             deleted: HashSet::with_capacity(NODE_CAPACITY),
             entry_points: Vec::with_capacity(NODE_CAPACITY),
-            fts_index: FtsIndex::new(),
+            fts_index: FtsIndex::default(),
             permission_map: Default::default(),
+            changelog: Default::default(),
+            traversal_timeout: None,
+            protected_tags: Default::default(),
+            clock: None,
             external_storage: Default::default(),
         };
         ret.batch_load(entities, edges).await?;
@@ -424,11 +625,15 @@ where
             .map(|w| w.to_owned())
     }
 
+    /**
+     * Get entity by qualified name, falls back to case-insensitive match if there is no exact match
+     */
     pub(crate) fn get_entity_by_name(
         &self,
         qualified_name: &str,
         version: Option<u64>,
-    ) -> Option<Entity<EntityProp>> {
+    ) -> Result<Entity<EntityProp>, RegistryError> {
+        let qualified_name = self.resolve_qualified_name(qualified_name)?;
         self.name_id_map
             .get(qualified_name)
             .and_then(|ids| match version {
@@ -436,6 +641,182 @@ where
                 None => ids.keys().max().and_then(|v| ids.get(v)),
             })
             .and_then(|&id| self.get_entity_by_id(id))
+            .ok_or_else(|| RegistryError::EntityNotFound(qualified_name.to_string()))
+    }
+
+    /**
+     * Returns the qualified name as registered, the only case-insensitive match is used if there is no exact match
+     */
+    pub(crate) fn resolve_qualified_name<'a>(
+        &'a self,
+        qualified_name: &'a str,
+    ) -> Result<&'a str, RegistryError> {
+        if self.name_id_map.contains_key(qualified_name) {
+            return Ok(qualified_name);
+        }
+        match self.name_id_map_ci.get(&qualified_name.to_lowercase()) {
+            Some(names) if names.len() == 1 => Ok(names.iter().next().unwrap()),
+            Some(names) if names.len() > 1 => {
+                let mut names: Vec<String> = names.iter().cloned().collect();
+                names.sort();
+                Err(RegistryError::AmbiguousName(
+                    qualified_name.to_string(),
+                    names,
+                ))
+            }
+            _ => Err(RegistryError::EntityNotFound(qualified_name.to_string())),
+        }
+    }
+
+    /**
+     * Register the name in `name_id_map` and `name_id_map_ci`
+     */
+    pub(crate) fn add_name(&mut self, qualified_name: &str, version: u64, id: Uuid) {
+        self.name_id_map
+            .entry(qualified_name.to_string())
+            .or_default()
+            .insert(version, id);
+        self.name_id_map_ci
+            .entry(qualified_name.to_lowercase())
+            .or_default()
+            .insert(qualified_name.to_string());
+    }
+
+    /**
+     * Remove the version from `name_id_map`, the name is dropped from both maps when no version remains
+     */
+    pub(crate) fn remove_name(&mut self, qualified_name: &str, version: u64) {
+        if let Some(versions) = self.name_id_map.get_mut(qualified_name) {
+            versions.remove(&version);
+            if versions.is_empty() {
+                self.name_id_map.remove(qualified_name);
+                let key = qualified_name.to_lowercase();
+                if let Some(names) = self.name_id_map_ci.get_mut(&key) {
+                    names.remove(qualified_name);
+                    if names.is_empty() {
+                        self.name_id_map_ci.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * The name is taken if a live entity holds any version of it, names only held by deleted
+     * entities are free to reuse
+     */
+    fn is_name_taken(&self, qualified_name: &str) -> bool {
+        self.name_id_map
+            .get(qualified_name)
+            .map(|versions| versions.values().any(|id| !self.deleted.contains(id)))
+            .unwrap_or_default()
+    }
+
+    /**
+     * Drop the versions of the name still held by deleted entities before the name is reused
+     */
+    fn release_name(&mut self, qualified_name: &str) {
+        let stale: Vec<u64> = self
+            .name_id_map
+            .get(qualified_name)
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter(|&(_, id)| self.deleted.contains(id))
+                    .map(|(&version, _)| version)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for version in stale {
+            self.remove_name(qualified_name, version);
+        }
+    }
+
+    /**
+     * Entities that have been deleted but are still retained in the graph, ordered by qualified name
+     */
+    pub fn list_deleted(&self) -> Vec<Entity<EntityProp>> {
+        let mut entities: Vec<Entity<EntityProp>> = self
+            .deleted
+            .iter()
+            .filter_map(|id| self.node_id_map.get(id))
+            .filter_map(|&idx| self.graph.node_weight(idx).cloned())
+            .collect();
+        entities
+            .sort_by(|a, b| (&a.qualified_name, a.version).cmp(&(&b.qualified_name, b.version)));
+        entities
+    }
+
+    /**
+     * Live entities of the type ordered by id, the page starts after the id `after`
+     * The id to continue from is returned as well if there are more entities after the page
+     */
+    pub fn list_entities_by_type(
+        &self,
+        entity_type: EntityType,
+        after: Option<Uuid>,
+        size: usize,
+    ) -> (Vec<Entity<EntityProp>>, Option<Uuid>) {
+        let mut entities: Vec<&Entity<EntityProp>> = self
+            .graph
+            .node_weights()
+            .filter(|w| w.entity_type == entity_type && !self.deleted.contains(&w.id))
+            .filter(|w| after.map(|id| w.id > id).unwrap_or(true))
+            .collect();
+        entities.sort_by_key(|w| w.id);
+        let more = entities.len() > size;
+        entities.truncate(size);
+        let next = entities.last().filter(|_| more).map(|w| w.id);
+        (entities.into_iter().cloned().collect(), next)
+    }
+
+    /**
+     * Register key aliases of the entity in `alias_id_map`
+     */
+    pub(crate) fn add_key_aliases(&mut self, id: Uuid) {
+        let aliases = self
+            .get_entity_by_id(id)
+            .map(|e| e.properties.get_key_aliases())
+            .unwrap_or_default();
+        for alias in aliases {
+            self.alias_id_map.entry(alias).or_default().insert(id);
+        }
+    }
+
+    /**
+     * Remove the entity from `alias_id_map`, aliases are dropped when no feature remains
+     */
+    pub(crate) fn remove_key_aliases(&mut self, id: Uuid) {
+        let aliases = self
+            .node_id_map
+            .get(&id)
+            .and_then(|&idx| self.graph.node_weight(idx))
+            .map(|w| w.properties.get_key_aliases())
+            .unwrap_or_default();
+        for alias in aliases {
+            if let Some(ids) = self.alias_id_map.get_mut(&alias) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.alias_id_map.remove(&alias);
+                }
+            }
+        }
+    }
+
+    /**
+     * Features having a key with the alias or full name, ordered by qualified name
+     */
+    pub fn get_features_by_key(&self, key: &str) -> Vec<Entity<EntityProp>> {
+        let mut features: Vec<Entity<EntityProp>> = self
+            .alias_id_map
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter_map(|&id| self.get_entity_by_id(id))
+            .collect();
+        features
+            .sort_by(|a, b| (&a.qualified_name, a.version).cmp(&(&b.qualified_name, b.version)));
+        features
     }
 
     pub(crate) fn get_feature_upstream(
@@ -446,14 +827,16 @@ where
         self.bfs_traversal(
             uuid,
             size_limit,
+            None,
             |w| {
                 !self.deleted.contains(&w.id)
                     && (w.entity_type == EntityType::AnchorFeature
                         || w.entity_type == EntityType::DerivedFeature
                         || w.entity_type == EntityType::Source)
             },
-            |e| e.edge_type == EdgeType::Consumes,
+            |e| EdgeType::Consumes.covers(e.edge_type),
         )
+        .map(|(entities, edges, _)| (entities, edges))
     }
 
     pub(crate) fn get_feature_downstream(
@@ -464,113 +847,383 @@ where
         self.bfs_traversal(
             uuid,
             size_limit,
+            None,
             |w| !self.deleted.contains(&w.id) && w.entity_type == EntityType::DerivedFeature,
-            |e| e.edge_type == EdgeType::Produces,
+            |e| EdgeType::Produces.covers(e.edge_type),
+        )
+        .map(|(entities, edges, _)| (entities, edges))
+    }
+
+    /**
+     * All features built on the source, from anchor features consuming it directly down to
+     * derived features on top of them at any depth
+     */
+    pub(crate) fn get_source_downstream(
+        &self,
+        source_id: Uuid,
+        size_limit: Option<usize>,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError> {
+        self.bfs_traversal(
+            source_id,
+            size_limit,
+            self.traversal_deadline(),
+            |w| {
+                !self.deleted.contains(&w.id)
+                    && (w.entity_type == EntityType::AnchorFeature
+                        || w.entity_type == EntityType::DerivedFeature)
+            },
+            |e| EdgeType::Produces.covers(e.edge_type),
+        )
+    }
+
+    /**
+     * Paged `get_source_downstream`, see `bfs_page`
+     */
+    pub(crate) fn get_source_downstream_page(
+        &self,
+        source_id: Uuid,
+        offset: usize,
+        size: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError> {
+        self.bfs_page(
+            source_id,
+            offset,
+            size,
+            self.traversal_deadline(),
+            |w| {
+                !self.deleted.contains(&w.id)
+                    && (w.entity_type == EntityType::AnchorFeature
+                        || w.entity_type == EntityType::DerivedFeature)
+            },
+            |e| EdgeType::Produces.covers(e.edge_type),
         )
     }
 
+    /**
+     * A page of the edges found by the breadth-first traversal from `uuid`, `offset` and `size`
+     * index into the edges in discovery order, which is the same as in `bfs_traversal`
+     * The traversal starts from the root and stops as soon as the page is filled
+     * Returned entities are the root and the ends of the returned edges, the first flag is set
+     * if there are more edges after the page, the second one if the traversal was still running
+     * at `deadline` so the page may be short
+     */
+    pub(crate) fn bfs_page<FN, FE>(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        size: usize,
+        deadline: Option<Instant>,
+        entity_pred: FN,
+        edge_pred: FE,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError>
+    where
+        FN: Fn(&Entity<EntityProp>) -> bool,
+        FE: Fn(&Edge) -> bool,
+    {
+        let end = offset.saturating_add(size);
+        let mut state = BfsState::new(self.get_idx(uuid)?);
+        // Stop at one more edge after the page, so we know there are more
+        let stop = self.bfs_resume(&mut state, deadline, entity_pred, edge_pred, |s, _| {
+            s.edges.len() > end
+        });
+        let has_more = state.edges.len() > end;
+        let page: Vec<Edge> = state
+            .edges
+            .into_iter()
+            .skip(offset)
+            .take(size)
+            .filter_map(|idx| self.graph.edge_weight(idx).cloned())
+            .collect();
+        let ids: HashSet<Uuid> = std::iter::once(uuid)
+            .chain(page.iter().flat_map(|e| [e.from, e.to]))
+            .collect();
+        Ok((
+            state
+                .entities
+                .into_iter()
+                .filter_map(|idx| self.graph.node_weight(idx))
+                .filter(|e| ids.contains(&e.id))
+                .cloned()
+                .collect(),
+            page,
+            has_more,
+            stop == BfsStop::Deadline,
+        ))
+    }
+
+    /**
+     * Breadth-first traversal from `uuid`, the result contains no duplicates
+     * Entities are returned in breadth-first order, neighbors of the same entity are ordered by their ids,
+     * so the result is stable regardless of the order the edges were inserted
+     * The flag is set if the traversal stopped at `size_limit` before all reachable entities were visited,
+     * or it was still running at `deadline`
+     */
     pub(crate) fn bfs_traversal<FN, FE>(
         &self,
         uuid: Uuid,
         size_limit: Option<usize>,
+        deadline: Option<Instant>,
         entity_pred: FN,
         edge_pred: FE,
-    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError>
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError>
     where
         FN: Fn(&Entity<EntityProp>) -> bool,
         FE: Fn(&Edge) -> bool,
     {
         let size_limit = size_limit.unwrap_or(usize::MAX);
-        let idx = self.get_idx(uuid)?;
-        let mut entities: Vec<NodeIndex> = vec![idx];
-        let mut edges: Vec<EdgeIndex> = vec![];
-        let mut offset: usize = 0;
-        // BFS
-        while entities.len() < size_limit && offset < entities.len() {
-            let idx = entities[offset];
-            let next_edges = self
-                .graph
-                .edges(idx)
-                .filter(|e| edge_pred(e.weight()))
-                .filter(|e| {
-                    self.graph
-                        .node_weight(e.target())
-                        .map(|w| entity_pred(w))
-                        .unwrap_or(false)
-                });
-            for edge in next_edges.take(size_limit - entities.len()) {
-                if !edges.contains(&edge.id()) {
-                    edges.push(edge.id());
-                }
-                if !entities.contains(&edge.target()) {
-                    entities.push(edge.target());
-                }
-            }
-            offset += 1;
-        }
+        let mut state = BfsState::new(self.get_idx(uuid)?);
+        let stop = self.bfs_resume(&mut state, deadline, entity_pred, edge_pred, |s, target| {
+            // There are more reachable entities than the limit
+            s.entities.len() >= size_limit && !s.visited_entities.contains(&target)
+        });
         Ok((
-            entities
+            state
+                .entities
                 .into_iter()
                 .filter_map(|idx| self.graph.node_weight(idx).cloned())
                 .collect(),
-            edges
+            state
+                .edges
                 .into_iter()
                 .filter_map(|idx| self.graph.edge_weight(idx).cloned())
                 .collect(),
+            stop != BfsStop::Finished,
         ))
     }
 
-    pub(crate) async fn new_entity<T1, T2>(
-        &mut self,
-        entity_type: EntityType,
-        name: T1,
-        qualified_name: T2,
-        properties: EntityProp,
-    ) -> Result<Uuid, RegistryError>
+    /**
+     * Continue the breadth-first traversal in `state` until all reachable entities are visited,
+     * `stop` returns true before following an edge to the entity, or `deadline` passes
+     * Neighbors of the same entity are visited in the order of their ids, the entity being
+     * visited when stopped is visited again on resume without repeating what's already found
+     */
+    fn bfs_resume<FN, FE, FS>(
+        &self,
+        state: &mut BfsState,
+        deadline: Option<Instant>,
+        entity_pred: FN,
+        edge_pred: FE,
+        mut stop: FS,
+    ) -> BfsStop
     where
-        T1: ToString,
-        T2: ToString,
+        FN: Fn(&Entity<EntityProp>) -> bool,
+        FE: Fn(&Edge) -> bool,
+        FS: FnMut(&BfsState, NodeIndex) -> bool,
     {
-        let id = Uuid::new_v4();
-        self.insert_entity(id, entity_type, name, qualified_name, properties)
-            .await
+        while state.cursor < state.entities.len() {
+            // Checking the clock for every entity is too costly
+            if state.cursor % TRAVERSAL_CLOCK_INTERVAL == 0
+                && deadline.map(|d| Instant::now() >= d).unwrap_or(false)
+            {
+                return BfsStop::Deadline;
+            }
+            let mut next_edges: Vec<_> = self
+                .graph
+                .edges(state.entities[state.cursor])
+                .filter(|e| edge_pred(e.weight()))
+                .filter(|e| {
+                    self.graph
+                        .node_weight(e.target())
+                        .map(|w| entity_pred(w))
+                        .unwrap_or(false)
+                })
+                .collect();
+            next_edges.sort_by_key(|e| (e.weight().to, e.weight().edge_type));
+            for edge in next_edges {
+                if stop(state, edge.target()) {
+                    return BfsStop::Stopped;
+                }
+                if state.visited_edges.insert(edge.id()) {
+                    state.edges.push(edge.id());
+                }
+                if state.visited_entities.insert(edge.target()) {
+                    state.entities.push(edge.target());
+                }
+            }
+            state.cursor += 1;
+        }
+        BfsStop::Finished
     }
 
-    pub async fn insert_entity<T1, T2>(
-        &mut self,
-        uuid: Uuid,
-        entity_type: EntityType,
-        name: T1,
-        qualified_name: T2,
-        properties: EntityProp,
-    ) -> Result<Uuid, RegistryError>
-    where
-        T1: ToString,
-        T2: ToString,
-    {
-        if self.node_id_map.contains_key(&uuid) {
-            // Id conflict, very unlikely to happen if the UUID is generated correctly
-            return Err(RegistryError::EntityIdExists(uuid));
+    /**
+     * Features in the project in dependency order with Kahn's algorithm over `DerivesFrom` edges
+     * Anchor features come first, features ready at the same time are ordered by qualified name,
+     * inputs outside of the project are not taken into account
+     */
+    pub fn topo_order_features(&self, project_id: Uuid) -> Result<Vec<Uuid>, RegistryError> {
+        let project = self
+            .get_entity_by_id(project_id)
+            .ok_or_else(|| RegistryError::EntityNotFound(project_id.to_string()))?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(
+                project_id,
+                project.entity_type,
+            ));
         }
-
-        if self
-            .name_id_map
-            .get(&qualified_name.to_string())
-            .map(|versions| versions.keys().any(|&v| properties.get_version() == v))
-            .unwrap_or_default()
-        {
-            // Try to create an existing version
-            return Err(RegistryError::EntityNameExists(qualified_name.to_string()));
+        let mut features: Vec<NodeIndex> = self
+            .get_neighbors_idx(self.get_idx(project_id)?, |e| {
+                e.edge_type == EdgeType::Contains
+            })
+            .into_iter()
+            .filter(|&idx| {
+                self.graph
+                    .node_weight(idx)
+                    .map(|w| {
+                        !self.deleted.contains(&w.id)
+                            && matches!(
+                                w.entity_type,
+                                EntityType::AnchorFeature | EntityType::DerivedFeature
+                            )
+                    })
+                    .unwrap_or(false)
+            })
+            .collect();
+        features.sort_by_key(|&idx| {
+            let w = &self.graph[idx];
+            (
+                w.entity_type == EntityType::DerivedFeature,
+                w.qualified_name.clone(),
+            )
+        });
+        let members: HashSet<NodeIndex> = features.iter().copied().collect();
+
+        // Number of unresolved inputs of each feature, and features consuming each feature
+        let mut in_degrees: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut dependents: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+        for &idx in &features {
+            let inputs: HashSet<NodeIndex> = self
+                .get_neighbors_idx(idx, |e| e.edge_type == EdgeType::DerivesFrom)
+                .into_iter()
+                .filter(|i| members.contains(i))
+                .collect();
+            in_degrees.insert(idx, inputs.len());
+            for input in inputs {
+                dependents.entry(input).or_default().push(idx);
+            }
+        }
+
+        let mut ready: Vec<NodeIndex> = features
+            .iter()
+            .copied()
+            .filter(|idx| in_degrees[idx] == 0)
+            .collect();
+        let mut ordered: Vec<NodeIndex> = Vec::with_capacity(features.len());
+        let mut offset = 0;
+        while offset < ready.len() {
+            let idx = ready[offset];
+            ordered.push(idx);
+            let mut next: Vec<NodeIndex> = vec![];
+            for &d in dependents.get(&idx).into_iter().flatten() {
+                let degree = in_degrees.get_mut(&d).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    next.push(d);
+                }
+            }
+            next.sort_by_key(|&idx| self.graph[idx].qualified_name.clone());
+            ready.extend(next);
+            offset += 1;
+        }
+
+        if ordered.len() < features.len() {
+            let ordered: HashSet<NodeIndex> = ordered.into_iter().collect();
+            return Err(RegistryError::CyclicDependency(
+                features
+                    .into_iter()
+                    .filter(|idx| !ordered.contains(idx))
+                    .map(|idx| self.graph[idx].id)
+                    .collect(),
+            ));
+        }
+        Ok(ordered.into_iter().map(|idx| self.graph[idx].id).collect())
+    }
+
+    /**
+     * Check the client-supplied id of an entity being created
+     * A retried creation with the same definition gets the existing entity, a different definition under the same id is a conflict
+     */
+    pub(crate) fn check_existing_id<F>(
+        &self,
+        uuid: Uuid,
+        entity_type: EntityType,
+        same_definition: F,
+    ) -> Result<Option<(Uuid, u64)>, RegistryError>
+    where
+        F: Fn(&Entity<EntityProp>) -> bool,
+    {
+        if !self.node_id_map.contains_key(&uuid) {
+            return Ok(None);
+        }
+        match self.get_entity_by_id(uuid) {
+            Some(e) if e.entity_type == entity_type && same_definition(&e) => {
+                Ok(Some((e.id, e.version)))
+            }
+            _ => Err(RegistryError::EntityIdExists(uuid)),
+        }
+    }
+
+    pub(crate) async fn new_entity<T1, T2>(
+        &mut self,
+        entity_type: EntityType,
+        name: T1,
+        qualified_name: T2,
+        properties: EntityProp,
+    ) -> Result<Uuid, RegistryError>
+    where
+        T1: ToString,
+        T2: ToString,
+    {
+        if name.to_string().is_blank() {
+            return Err(RegistryError::InvalidDefinition(
+                "Name cannot be empty or blank".to_string(),
+            ));
+        }
+        let id = Uuid::new_v4();
+        self.insert_entity(id, entity_type, name, qualified_name, properties)
+            .await
+    }
+
+    pub async fn insert_entity<T1, T2>(
+        &mut self,
+        uuid: Uuid,
+        entity_type: EntityType,
+        name: T1,
+        qualified_name: T2,
+        properties: EntityProp,
+    ) -> Result<Uuid, RegistryError>
+    where
+        T1: ToString,
+        T2: ToString,
+    {
+        if self.node_id_map.contains_key(&uuid) {
+            // Id conflict, very unlikely to happen if the UUID is generated correctly
+            return Err(RegistryError::EntityIdExists(uuid));
+        }
+
+        let qualified_name = qualified_name.to_string();
+        let version = properties.get_version();
+        if let Some(&existing) = self
+            .name_id_map
+            .get(&qualified_name)
+            .and_then(|versions| versions.get(&version))
+        {
+            if !self.deleted.contains(&existing) {
+                // Try to create an existing version
+                return Err(RegistryError::EntityNameExists(qualified_name));
+            }
+            // The name is still held by a deleted entity, release it for the new one
+            self.release_name(&qualified_name);
         }
 
         self.insert_node(
             uuid,
             entity_type,
             name.to_string(),
-            qualified_name.to_string(),
+            qualified_name,
             properties,
         )
         .await?;
+        self.changelog.record(Change::Entity(uuid));
         Ok(uuid)
     }
 
@@ -590,55 +1243,299 @@ where
         Ok(())
     }
 
-    pub async fn delete_entity_by_id(&mut self, uuid: Uuid) -> Result<(), RegistryError> {
-        if self
-            .graph
-            .edges_directed(self.get_idx(uuid)?, Direction::Outgoing)
-            .any(|e| e.weight().edge_type.is_downstream())
+    /**
+     * Index multiple entities and commit once
+     */
+    pub fn index_entities_bulk<I>(&mut self, ids: I) -> Result<(), RegistryError>
+    where
+        I: Iterator<Item = Uuid>,
+    {
+        let docs: Vec<(Entity<EntityProp>, Vec<String>)> = ids
+            .filter_map(|id| {
+                let e = self.get_entity_by_id(id)?;
+                let scopes = self
+                    .get_neighbors(id, EdgeType::BelongsTo)
+                    .ok()?
+                    .iter()
+                    .map(|e| e.id.to_string())
+                    .collect();
+                Some((e, scopes))
+            })
+            .collect();
+        self.fts_index.add_docs_bulk(
+            &docs
+                .iter()
+                .map(|(e, scopes)| (e, scopes.to_owned()))
+                .collect::<Vec<_>>(),
+        )?;
+        Ok(())
+    }
+
+    /**
+     * Discard docs indexed in the current FTS transaction
+     */
+    fn rollback_fts(&mut self) {
+        if let Err(e) = self.fts_index.rollback() {
+            warn!("Failed to roll back FTS index, error: {:?}", e);
+        }
+    }
+
+    /**
+     * Write out buffered changes of all attached external storages
+     * The graph keeps the changes if it fails, the error is surfaced so the storage can be
+     * reconciled, e.g. by detaching and reattaching it
+     */
+    pub async fn flush_external_storage(&self) -> Result<(), RegistryError> {
+        for es in &self.external_storage {
+            es.write().await.flush().await?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Attach the external storage if none is attached, returns false without touching it otherwise
+     * The storage is reconciled with all live entities and edges first, so records changed or
+     * deleted while it was detached are brought in line with the graph,
+     * role assignments are left as is as storages keep them as a log of grants and revocations
+     */
+    pub async fn attach_external_storage(
+        &mut self,
+        storage: Arc<RwLock<dyn ExternalStorage<EntityProp>>>,
+    ) -> Result<bool, RegistryError> {
+        if !self.external_storage.is_empty() {
+            return Ok(false);
+        }
         {
-            // Check if there is anything depends on this entity
-            Err(RegistryError::DeleteInUsed(uuid))
-        } else {
-            let idx = self.get_idx(uuid)?;
-            let edges: HashSet<EdgeIndex> = self
-                .get_neighbors_idx(idx, |_| true)
-                .into_iter()
-                .flat_map(|n| {
-                    self.graph
-                        .edges_connecting(idx, n)
-                        .chain(self.graph.edges_connecting(n, idx))
-                        .map(|e| e.id())
-                })
+            let entities: Vec<&Entity<EntityProp>> = self
+                .graph
+                .node_weights()
+                .filter(|w| !self.deleted.contains(&w.id))
                 .collect();
-            // Call entity#disconnect and update node weights in the graph accordingly
-            for edge in &edges {
-                let (from_idx, to_idx) = self.graph.edge_endpoints(edge.to_owned()).unwrap();
-                let from = self.graph.node_weight(from_idx).unwrap().to_owned();
-                let to = self
-                    .graph
-                    .node_weight(to_idx)
-                    .unwrap()
-                    .to_owned()
-                    .to_owned();
-                if let Some(w) = self.graph.node_weight_mut(from_idx) {
-                    w.properties = from.properties
-                }
-                if let Some(w) = self.graph.node_weight_mut(to_idx) {
-                    w.properties = to.properties
-                }
-            }
-            // Call external_storage#remove_entity
-            if let Some(w) = self.graph.node_weight(idx) {
-                for es in &self.external_storage {
-                    es.write().await.delete_entity(uuid, w).await?;
-                }
+            let edges: Vec<&Edge> = self
+                .graph
+                .edge_weights()
+                .filter(|e| !self.deleted.contains(&e.from) && !self.deleted.contains(&e.to))
+                .collect();
+            let mut es = storage.write().await;
+            es.reconcile(&entities, &edges).await?;
+            es.flush().await?;
+        }
+        self.external_storage.push(storage);
+        Ok(true)
+    }
+
+    /**
+     * Drop everything in the FTS index and re-index all live entities, returns the number of docs in the index
+     */
+    pub fn rebuild_fts(&mut self) -> Result<u64, RegistryError> {
+        self.fts_index.clear()?;
+        let ids: Vec<Uuid> = self
+            .node_id_map
+            .keys()
+            .filter(|id| !self.deleted.contains(id))
+            .copied()
+            .collect();
+        self.index_entities_bulk(ids.into_iter())?;
+        Ok(self.fts_index.num_docs())
+    }
+
+    /**
+     * Lineage traversals stop once they have taken `timeout`, returning what has been found so far
+     */
+    pub fn set_traversal_timeout(&mut self, timeout: Option<Duration>) {
+        self.traversal_timeout = timeout;
+    }
+
+    /**
+     * Tag updates changing or removing these keys are rejected once the keys have values
+     */
+    pub fn set_protected_tags(&mut self, keys: HashSet<String>) {
+        self.protected_tags = keys;
+    }
+
+    /**
+     * Set the time of the mutations applied afterwards, the current time is used if unset
+     */
+    pub fn set_clock(&mut self, clock: Option<DateTime<Utc>>) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock.unwrap_or_else(Utc::now)
+    }
+
+    pub(crate) fn traversal_deadline(&self) -> Option<Instant> {
+        self.traversal_timeout.map(|t| Instant::now() + t)
+    }
+
+    /**
+     * Rebuild the FTS index with the tokenizer, nothing is changed if the index already uses it
+     */
+    pub fn set_fts_tokenizer(&mut self, tokenizer: TokenizerConfig) -> Result<(), RegistryError> {
+        if self.fts_index.tokenizer() == tokenizer {
+            return Ok(());
+        }
+        self.fts_index = FtsIndex::new(tokenizer);
+        let ids: Vec<Uuid> = self.node_id_map.keys().copied().collect();
+        self.index_entities_bulk(ids.into_iter())
+    }
+
+    /**
+     * Ids of entities depending on this one, in any project
+     * Incoming `Consumes`/`DerivesFrom` edges are scanned too, in case the reflection is missing
+     */
+    fn get_dependents(&self, uuid: Uuid) -> Result<Vec<Uuid>, RegistryError> {
+        let idx = self.get_idx(uuid)?;
+        Ok(self
+            .graph
+            .edges_directed(idx, Direction::Outgoing)
+            .filter(|e| e.weight().edge_type.is_downstream())
+            .map(|e| e.target())
+            .chain(
+                self.graph
+                    .edges_directed(idx, Direction::Incoming)
+                    .filter(|e| {
+                        matches!(
+                            e.weight().edge_type,
+                            EdgeType::Consumes | EdgeType::DerivesFrom
+                        )
+                    })
+                    .map(|e| e.source()),
+            )
+            .filter(|&n| n != idx)
+            .map(|n| self.graph[n].id)
+            .filter(|id| !self.deleted.contains(id))
+            .unique()
+            .sorted()
+            .collect())
+    }
+
+    pub async fn delete_entity_by_id(&mut self, uuid: Uuid) -> Result<(), RegistryError> {
+        let dependents = self.get_dependents(uuid)?;
+        if !dependents.is_empty() {
+            // Check if there is anything depends on this entity
+            return Err(RegistryError::DeleteInUsed(uuid, dependents));
+        }
+        let idx = self.get_idx(uuid)?;
+        // Call external_storage#remove_entity
+        if let Some(w) = self.graph.node_weight(idx) {
+            for es in &self.external_storage {
+                es.write().await.delete_entity(uuid, w).await?;
             }
-            self.graph.retain_edges(|_, e| !edges.contains(&e));
-            // Mark deletion, we don't want to invalidate node indices as we have a reversed index
-            self.deleted.insert(uuid);
-            Ok(())
         }
+        let now = self.now();
+        self.remove_entity(idx, uuid, now);
         // TODO: How to deal with FTS?
+        Ok(())
+    }
+
+    /**
+     * Remove the entity and all its edges from the graph, external storages must have been updated
+     */
+    fn remove_entity(&mut self, idx: NodeIndex, uuid: Uuid, now: DateTime<Utc>) {
+        let edges: HashSet<EdgeIndex> = self
+            .get_neighbors_idx(idx, |_| true)
+            .into_iter()
+            .flat_map(|n| {
+                self.graph
+                    .edges_connecting(idx, n)
+                    .chain(self.graph.edges_connecting(n, idx))
+                    .map(|e| e.id())
+            })
+            .collect();
+        // Record removed edges and touch the neighbors, so caches and deltas see the changes
+        let mut neighbors: HashSet<NodeIndex> = HashSet::new();
+        for edge in &edges {
+            let (from_idx, to_idx) = self.graph.edge_endpoints(edge.to_owned()).unwrap();
+            let w = &self.graph[*edge];
+            self.changelog
+                .record(Change::Edge(w.from, w.to, w.edge_type));
+            neighbors.extend([from_idx, to_idx].into_iter().filter(|&n| n != idx));
+        }
+        for n in neighbors {
+            if let Some(w) = self.graph.node_weight_mut(n) {
+                w.properties.touch("", now);
+                let id = w.id;
+                self.changelog.record(Change::Entity(id));
+            }
+        }
+        self.graph.retain_edges(|_, e| !edges.contains(&e));
+        self.remove_key_aliases(uuid);
+        // Mark deletion, we don't want to invalidate node indices as we have a reversed index
+        self.deleted.insert(uuid);
+        self.changelog.record(Change::Deleted(uuid));
+    }
+
+    /**
+     * Delete the source, fails with `DeleteInUsed` if anything consumes it
+     * With `force`, anchors and anchor features consuming the source are detached from it first
+     */
+    pub async fn delete_source(
+        &mut self,
+        source_id: Uuid,
+        force: bool,
+    ) -> Result<(), RegistryError> {
+        let source = self
+            .get_entity_by_id(source_id)
+            .ok_or_else(|| RegistryError::EntityNotFound(source_id.to_string()))?;
+        if source.entity_type != EntityType::Source {
+            return Err(RegistryError::WrongEntityType(
+                source_id,
+                source.entity_type,
+            ));
+        }
+        if !force {
+            return self.delete_entity_by_id(source_id).await;
+        }
+        let idx = self.get_idx(source_id)?;
+        let consumers: Vec<Uuid> = self
+            .graph
+            .edges_directed(idx, Direction::Outgoing)
+            .filter(|e| e.weight().edge_type == EdgeType::Produces)
+            .map(|e| self.graph[e.target()].id)
+            .filter(|id| !self.deleted.contains(id))
+            .unique()
+            .collect();
+        // Everything is checked before any change, so a failure leaves the source attached
+        let dependents: Vec<Uuid> = self
+            .get_dependents(source_id)?
+            .into_iter()
+            .filter(|id| !consumers.contains(id))
+            .collect();
+        if !dependents.is_empty() {
+            return Err(RegistryError::DeleteInUsed(source_id, dependents));
+        }
+        // Storages are written before the graph is changed, a failed write leaves the source and
+        // its consumers as they were
+        let now = self.now();
+        let detached: Vec<Entity<EntityProp>> = consumers
+            .iter()
+            .filter_map(|&id| self.get_entity_by_id(id))
+            .map(|mut e| {
+                e.properties.touch("", now);
+                e
+            })
+            .collect();
+        for storage in &self.external_storage {
+            let mut storage = storage.write().await;
+            for consumer in &detached {
+                storage
+                    .disconnect(
+                        consumer,
+                        consumer.id,
+                        &source,
+                        source_id,
+                        EdgeType::Consumes,
+                        Uuid::default(),
+                    )
+                    .await?;
+                storage.update_entity(consumer.id, consumer).await?;
+            }
+            storage.delete_entity(source_id, &source).await?;
+        }
+        // Removing the source also removes the edges from the consumers and touches them
+        self.remove_entity(idx, source_id, now);
+        Ok(())
     }
 
     pub async fn connect(
@@ -646,9 +1543,24 @@ where
         from: Uuid,
         to: Uuid,
         edge_type: EdgeType,
+    ) -> Result<(), RegistryError> {
+        self.connect_with_attributes(from, to, edge_type, Default::default())
+            .await
+    }
+
+    /**
+     * Connect 2 entities, attributes are attached to the edge and its reflection
+     */
+    pub async fn connect_with_attributes(
+        &mut self,
+        from: Uuid,
+        to: Uuid,
+        edge_type: EdgeType,
+        attributes: HashMap<String, String>,
     ) -> Result<(), RegistryError> {
         let from_idx = self.get_idx(from)?;
         let to_idx = self.get_idx(to)?;
+        let edge_type = self.normalize_edge_type(from_idx, to_idx, edge_type);
         debug!(
             "Connecting '{}' and '{}', edge type: {:?}",
             self.graph
@@ -663,49 +1575,664 @@ where
         );
         for storage in &self.external_storage {
             let storage = storage.clone();
-            storage.write().await.connect(from, to, edge_type).await?;
+            storage
+                .write()
+                .await
+                .connect(from, to, edge_type, &attributes)
+                .await?;
         }
-        match self
-            .graph
-            .edges_connecting(from_idx, to_idx)
-            .find(|e| e.weight().edge_type == edge_type)
-        {
-            Some(e) => {
-                debug!("Connection already exists, {:?}", e);
-            }
-            None => {
-                self.insert_edge(edge_type, from_idx, to_idx, from, to);
-            }
-        };
-        match self
-            .graph
-            .edges_connecting(to_idx, from_idx)
-            .find(|e| e.weight().edge_type == edge_type.reflection())
-        {
-            Some(e) => {
-                debug!("Connection already exists, {:?}", e);
-            }
-            None => {
-                self.insert_edge(edge_type.reflection(), to_idx, from_idx, to, from);
-            }
-        };
+        self.insert_edge_pair(edge_type, from_idx, to_idx, from, to, attributes);
         Ok(())
     }
 
-    pub(crate) fn get_idx(&self, uuid: Uuid) -> Result<NodeIndex, RegistryError> {
-        if self.deleted.contains(&uuid) {
-            return Err(RegistryError::InvalidEntity(uuid));
+    /**
+     * Connect all pairs in one pass
+     * All endpoints are validated before any edge is inserted, nothing is changed if any of them is invalid
+     */
+    pub async fn connect_many(
+        &mut self,
+        edges: Vec<(Uuid, Uuid, EdgeType)>,
+    ) -> Result<(), RegistryError> {
+        let mut invalid: Vec<Uuid> = vec![];
+        let mut indices = Vec::with_capacity(edges.len());
+        for &(from, to, _) in edges.iter() {
+            match (self.get_idx(from), self.get_idx(to)) {
+                (Ok(from_idx), Ok(to_idx)) => indices.push((from_idx, to_idx)),
+                (from_idx, to_idx) => {
+                    for (id, idx) in [(from, from_idx), (to, to_idx)] {
+                        if idx.is_err() && !invalid.contains(&id) {
+                            invalid.push(id);
+                        }
+                    }
+                }
+            }
         }
-        Ok(self
-            .node_id_map
-            .get(&uuid)
-            .ok_or(RegistryError::InvalidEntity(uuid))?
-            .to_owned())
+        if !invalid.is_empty() {
+            debug!("Cannot connect invalid entities {:?}", invalid);
+            return Err(RegistryError::InvalidEntities(invalid));
+        }
+        let edges: Vec<(Uuid, Uuid, EdgeType)> = edges
+            .into_iter()
+            .zip(indices.iter())
+            .map(|((from, to, edge_type), &(from_idx, to_idx))| {
+                (
+                    from,
+                    to,
+                    self.normalize_edge_type(from_idx, to_idx, edge_type),
+                )
+            })
+            .collect();
+        for storage in &self.external_storage {
+            let storage = storage.clone();
+            let mut storage = storage.write().await;
+            for &(from, to, edge_type) in edges.iter() {
+                storage
+                    .connect(from, to, edge_type, &Default::default())
+                    .await?;
+            }
+        }
+        for ((from, to, edge_type), (from_idx, to_idx)) in edges.into_iter().zip(indices) {
+            self.insert_edge_pair(edge_type, from_idx, to_idx, from, to, Default::default());
+        }
+        Ok(())
     }
 
-    pub(crate) fn get_neighbors_idx<F>(&self, idx: NodeIndex, predicate: F) -> Vec<NodeIndex>
-    where
-        F: Fn(&Edge) -> bool,
+    /**
+     * Remove the edge and its reflection, nothing happens if the edge doesn't exist
+     */
+    pub async fn disconnect(
+        &mut self,
+        from: Uuid,
+        to: Uuid,
+        edge_type: EdgeType,
+    ) -> Result<(), RegistryError> {
+        let from_idx = self.get_idx(from)?;
+        let to_idx = self.get_idx(to)?;
+        let reflection = edge_type.reflection();
+        let edges: Vec<EdgeIndex> = self
+            .graph
+            .edges_connecting(from_idx, to_idx)
+            .filter(|e| e.weight().edge_type == edge_type)
+            .chain(
+                self.graph
+                    .edges_connecting(to_idx, from_idx)
+                    .filter(|e| e.weight().edge_type == reflection),
+            )
+            .map(|e| e.id())
+            .collect();
+        if edges.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "Disconnecting '{}' and '{}', edge type: {:?}",
+            from, to, edge_type
+        );
+        let from_entity = self.graph[from_idx].to_owned();
+        let to_entity = self.graph[to_idx].to_owned();
+        for storage in &self.external_storage {
+            let storage = storage.clone();
+            storage
+                .write()
+                .await
+                .disconnect(
+                    &from_entity,
+                    from,
+                    &to_entity,
+                    to,
+                    edge_type,
+                    Uuid::default(),
+                )
+                .await?;
+        }
+        self.graph.retain_edges(|_, e| !edges.contains(&e));
+        self.changelog.record(Change::Edge(from, to, edge_type));
+        Ok(())
+    }
+
+    /**
+     * Add and remove input features of the derived feature, the feature keeps its id and version
+     * Inputs to be added must be features, and they cannot depend on the derived feature itself
+     */
+    pub async fn update_derived_inputs(
+        &mut self,
+        feature_id: Uuid,
+        add: Vec<Uuid>,
+        remove: Vec<Uuid>,
+    ) -> Result<(), RegistryError> {
+        let feature = self
+            .get_entity_by_id(feature_id)
+            .filter(|_| !self.deleted.contains(&feature_id))
+            .ok_or_else(|| RegistryError::EntityNotFound(feature_id.to_string()))?;
+        if feature.entity_type != EntityType::DerivedFeature {
+            return Err(RegistryError::WrongEntityType(
+                feature_id,
+                feature.entity_type,
+            ));
+        }
+        let invalid: Vec<Uuid> = add
+            .iter()
+            .filter(|id| self.deleted.contains(id) || self.get_entity_by_id(**id).is_none())
+            .copied()
+            .collect();
+        if !invalid.is_empty() {
+            return Err(RegistryError::InvalidEntities(invalid));
+        }
+        for &id in &add {
+            let input = self.get_entity_by_id(id).unwrap();
+            if input.entity_type != EntityType::AnchorFeature
+                && input.entity_type != EntityType::DerivedFeature
+            {
+                return Err(RegistryError::WrongEntityType(id, input.entity_type));
+            }
+            // The new input cannot be the feature itself or anything derived from it
+            let (upstream, _) = self.get_feature_upstream(id, None)?;
+            if id == feature_id || upstream.iter().any(|e| e.id == feature_id) {
+                return Err(RegistryError::CyclicDependency(vec![feature_id, id]));
+            }
+        }
+        for id in remove {
+            self.disconnect(feature_id, id, EdgeType::DerivesFrom)
+                .await?;
+        }
+        self.connect_many(
+            add.into_iter()
+                .map(|id| (feature_id, id, EdgeType::DerivesFrom))
+                .collect(),
+        )
+        .await?;
+        self.touch_entity(feature_id, "")?;
+        let feature = self.get_entity_by_id(feature_id).unwrap();
+        for storage in &self.external_storage {
+            let storage = storage.clone();
+            storage
+                .write()
+                .await
+                .update_entity(feature_id, &feature)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Move the anchor feature under another anchor of the same project, the feature keeps its id
+     * and version, is renamed under the new anchor, and consumes the sources of the new anchor
+     * Nothing is changed if the new anchor already has a feature with the same name
+     */
+    pub async fn move_anchor_feature(
+        &mut self,
+        feature_id: Uuid,
+        new_anchor_id: Uuid,
+    ) -> Result<(), RegistryError> {
+        for id in [feature_id, new_anchor_id] {
+            if self.get_entity_by_id(id).is_none() {
+                return Err(RegistryError::EntityNotFound(id.to_string()));
+            }
+        }
+        let feature_type = self.get_entity_by_id(feature_id).unwrap().entity_type;
+        if feature_type != EntityType::AnchorFeature {
+            return Err(RegistryError::WrongEntityType(feature_id, feature_type));
+        }
+        let anchor_type = self.get_entity_by_id(new_anchor_id).unwrap().entity_type;
+        if anchor_type != EntityType::Anchor {
+            return Err(RegistryError::WrongEntityType(new_anchor_id, anchor_type));
+        }
+        if self.get_entity_project_id(feature_id)? != self.get_entity_project_id(new_anchor_id)? {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Anchor [{}] is not in the project of feature [{}]",
+                new_anchor_id, feature_id
+            )));
+        }
+        let old_anchors: Vec<Uuid> = self
+            .get_neighbors(feature_id, EdgeType::BelongsTo)?
+            .into_iter()
+            .filter(|e| e.entity_type == EntityType::Anchor)
+            .map(|e| e.id)
+            .collect();
+        if old_anchors == [new_anchor_id] {
+            return Ok(());
+        }
+        let feature = self.get_entity_by_id(feature_id).unwrap();
+        let anchor = self.get_entity_by_id(new_anchor_id).unwrap();
+        let qualified_name = child_qualified_name(&anchor.qualified_name, &feature.name);
+        if self.is_name_taken(&qualified_name) {
+            return Err(RegistryError::EntityNameExists(qualified_name));
+        }
+        for &id in &old_anchors {
+            self.disconnect(id, feature_id, EdgeType::Contains).await?;
+            self.touch_entity(id, "")?;
+        }
+        // The feature consumes whatever the new anchor consumes
+        for source in self.get_neighbors(feature_id, EdgeType::Consumes)? {
+            self.disconnect(feature_id, source.id, EdgeType::Consumes)
+                .await?;
+        }
+        let sources = self.get_neighbors(new_anchor_id, EdgeType::Consumes)?;
+        self.connect_many(
+            std::iter::once((new_anchor_id, feature_id, EdgeType::Contains))
+                .chain(
+                    sources
+                        .into_iter()
+                        .map(|s| (feature_id, s.id, EdgeType::Consumes)),
+                )
+                .collect(),
+        )
+        .await?;
+        self.touch_entity(new_anchor_id, "")?;
+        self.touch_entity(feature_id, "")?;
+        let idx = self.get_idx(feature_id)?;
+        if let Some(w) = self.graph.node_weight_mut(idx) {
+            w.qualified_name = qualified_name.clone();
+            w.properties.rename(&feature.name, &qualified_name);
+        }
+        self.remove_name(&feature.qualified_name, feature.version);
+        self.release_name(&qualified_name);
+        self.add_name(&qualified_name, feature.version, feature_id);
+        self.changelog.record(Change::Entity(feature_id));
+        self.fts_index.delete_doc(&feature_id.to_string())?;
+        self.index_entity(feature_id, true)?;
+        for id in old_anchors.into_iter().chain([new_anchor_id, feature_id]) {
+            let entity = self.get_entity_by_id(id).unwrap();
+            for storage in &self.external_storage {
+                let storage = storage.clone();
+                storage.write().await.update_entity(id, &entity).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Reject tag updates changing or removing protected tags which already have values,
+     * setting a protected tag for the first time is allowed
+     */
+    fn check_protected_tags(
+        &self,
+        uuid: Uuid,
+        tags: &HashMap<String, String>,
+    ) -> Result<(), RegistryError> {
+        if self.protected_tags.is_empty() {
+            return Ok(());
+        }
+        let entity = self
+            .get_entity_by_id(uuid)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        for (key, value) in tags {
+            if !self.protected_tags.contains(key) {
+                continue;
+            }
+            match entity.properties.get_tag(key) {
+                Some(old) if old != value => {
+                    return Err(RegistryError::ProtectedTag(
+                        key.to_owned(),
+                        entity.qualified_name,
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn update_entity_tags(
+        &mut self,
+        uuid: Uuid,
+        tags: HashMap<String, String>,
+        expected_etag: Option<Uuid>,
+        new_etag: Uuid,
+    ) -> Result<(), RegistryError> {
+        let idx = self.get_idx(uuid)?;
+        self.check_protected_tags(uuid, &tags)?;
+        let entity = self
+            .graph
+            .node_weight_mut(idx)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        if let Some(etag) = expected_etag {
+            if entity.properties.get_etag() != etag {
+                return Err(RegistryError::EtagMismatch(uuid));
+            }
+        }
+        entity.properties.update_tags(tags, new_etag);
+        self.changelog.record(Change::Entity(uuid));
+        if let Some(entity) = self.get_entity_by_id(uuid) {
+            for storage in &self.external_storage {
+                storage.write().await.update_entity(uuid, &entity).await?;
+            }
+        }
+        // Tags are searchable, so the doc is indexed again
+        self.fts_index.delete_doc(&uuid.to_string())?;
+        self.index_entity(uuid, true)
+    }
+
+    /**
+     * Merge `tags` into the project and all live entities it contains, a tag with empty value will be removed
+     * Returns the number of updated entities
+     */
+    pub async fn update_project_tags(
+        &mut self,
+        project_id: Uuid,
+        tags: HashMap<String, String>,
+        new_etag: Uuid,
+    ) -> Result<usize, RegistryError> {
+        let project = self
+            .get_entity_by_id(project_id)
+            .ok_or(RegistryError::InvalidEntity(project_id))?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(
+                project_id,
+                project.entity_type,
+            ));
+        }
+        let ids: Vec<Uuid> = std::iter::once(project_id)
+            .chain(
+                self.get_neighbors(project_id, EdgeType::Contains)?
+                    .into_iter()
+                    .map(|e| e.id),
+            )
+            .filter(|id| !self.deleted.contains(id))
+            .collect();
+        // Checked for all entities before changing any of them
+        for id in ids.iter() {
+            self.check_protected_tags(*id, &tags)?;
+        }
+        for id in ids.iter() {
+            let idx = self.get_idx(*id)?;
+            let entity = self
+                .graph
+                .node_weight_mut(idx)
+                .ok_or(RegistryError::InvalidEntity(*id))?;
+            entity.properties.update_tags(tags.clone(), new_etag);
+            self.changelog.record(Change::Entity(*id));
+        }
+        for id in ids.iter() {
+            if let Some(entity) = self.get_entity_by_id(*id) {
+                for storage in &self.external_storage {
+                    storage.write().await.update_entity(*id, &entity).await?;
+                }
+            }
+            self.fts_index.delete_doc(&id.to_string())?;
+        }
+        let count = ids.len();
+        self.index_entities_bulk(ids.into_iter())?;
+        Ok(count)
+    }
+
+    pub async fn update_entity_links(
+        &mut self,
+        uuid: Uuid,
+        links: Vec<EntityLink>,
+    ) -> Result<(), RegistryError> {
+        let idx = self.get_idx(uuid)?;
+        let entity = self
+            .graph
+            .node_weight_mut(idx)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        entity.properties.set_links(links);
+        self.changelog.record(Change::Entity(uuid));
+        if let Some(entity) = self.get_entity_by_id(uuid) {
+            for storage in &self.external_storage {
+                storage.write().await.update_entity(uuid, &entity).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Bump the last modified time of the entity, used when its connections changed
+     */
+    pub fn touch_entity(&mut self, uuid: Uuid, modified_by: &str) -> Result<(), RegistryError> {
+        let now = self.now();
+        let idx = self.get_idx(uuid)?;
+        let entity = self
+            .graph
+            .node_weight_mut(idx)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        entity.properties.touch(modified_by, now);
+        self.changelog.record(Change::Entity(uuid));
+        Ok(())
+    }
+
+    /**
+     * Rename the project, qualified names of all contained entities are updated to the new prefix
+     */
+    pub async fn rename_project(
+        &mut self,
+        uuid: Uuid,
+        new_name: &str,
+    ) -> Result<(), RegistryError> {
+        let project = self
+            .get_entity_by_id(uuid)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(uuid, project.entity_type));
+        }
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(RegistryError::InvalidDefinition(
+                "Project name cannot be empty".to_string(),
+            ));
+        }
+        if project.qualified_name == new_name {
+            return Ok(());
+        }
+
+        // The project itself and all contained entities, with their new qualified names
+        let old_prefix = format!("{}{}", project.qualified_name, QUALIFIED_NAME_SEPARATOR);
+        let renames: Vec<(Uuid, String)> = std::iter::once((uuid, new_name.to_string()))
+            .chain(
+                self.get_neighbors(uuid, EdgeType::Contains)?
+                    .into_iter()
+                    .map(|e| {
+                        let qualified_name = match e.qualified_name.strip_prefix(&old_prefix) {
+                            Some(suffix) => child_qualified_name(new_name, suffix),
+                            None => e.qualified_name,
+                        };
+                        (e.id, qualified_name)
+                    }),
+            )
+            .collect();
+
+        // Nothing is changed if any of the new names is taken
+        for (_, qualified_name) in renames.iter() {
+            if self.is_name_taken(qualified_name) {
+                return Err(RegistryError::EntityNameExists(qualified_name.to_owned()));
+            }
+        }
+
+        for (id, qualified_name) in renames.iter() {
+            let idx = self.get_idx(*id)?;
+            let entity = self
+                .graph
+                .node_weight_mut(idx)
+                .ok_or(RegistryError::InvalidEntity(*id))?;
+            let old_qualified_name =
+                std::mem::replace(&mut entity.qualified_name, qualified_name.to_owned());
+            if *id == uuid {
+                entity.name = qualified_name.to_owned();
+            }
+            let name = entity.name.clone();
+            entity.properties.rename(&name, qualified_name);
+            let version = entity.version;
+            self.remove_name(&old_qualified_name, version);
+            self.release_name(qualified_name);
+            self.add_name(qualified_name, version, *id);
+            self.changelog.record(Change::Entity(*id));
+        }
+
+        for (id, _) in renames.iter() {
+            if let Some(entity) = self.get_entity_by_id(*id) {
+                for storage in &self.external_storage {
+                    storage.write().await.update_entity(*id, &entity).await?;
+                }
+            }
+            self.fts_index.delete_doc(&id.to_string())?;
+        }
+        self.index_entities_bulk(renames.into_iter().map(|(id, _)| id))?;
+        Ok(())
+    }
+
+    /**
+     * Copy the project and all contained entities into a new project named `new_name`
+     * Ids of the copies are derived from the original ids and `id_seed`, so all replicas get the same ids
+     * Edges between copied entities are rewired to the copies, `Consumes` and `DerivesFrom` edges to other projects are kept
+     * All copies are indexed in one FTS transaction, nothing is indexed if the clone fails
+     */
+    pub async fn clone_project(
+        &mut self,
+        src_id: Uuid,
+        new_name: &str,
+        id_seed: Uuid,
+    ) -> Result<Uuid, RegistryError> {
+        self.fts_index.begin()?;
+        match self.do_clone_project(src_id, new_name, id_seed).await {
+            Ok(id) => {
+                self.fts_index.commit()?;
+                Ok(id)
+            }
+            Err(e) => {
+                self.rollback_fts();
+                Err(e)
+            }
+        }
+    }
+
+    async fn do_clone_project(
+        &mut self,
+        src_id: Uuid,
+        new_name: &str,
+        id_seed: Uuid,
+    ) -> Result<Uuid, RegistryError> {
+        let project = self
+            .get_entity_by_id(src_id)
+            .ok_or(RegistryError::InvalidEntity(src_id))?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(src_id, project.entity_type));
+        }
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(RegistryError::InvalidDefinition(
+                "Project name cannot be empty".to_string(),
+            ));
+        }
+
+        // Older versions go first so the copies get the same version numbers
+        let mut members = self.get_neighbors(src_id, EdgeType::Contains)?;
+        members.sort_by(|a, b| (&a.qualified_name, a.version).cmp(&(&b.qualified_name, b.version)));
+        members.insert(0, project.clone());
+
+        let id_map: HashMap<Uuid, Uuid> = members
+            .iter()
+            .map(|e| (e.id, Uuid::from_u128(e.id.as_u128() ^ id_seed.as_u128())))
+            .collect();
+        let old_prefix = format!("{}{}", project.qualified_name, QUALIFIED_NAME_SEPARATOR);
+        let new_qualified_name = |e: &Entity<EntityProp>| {
+            if e.id == src_id {
+                new_name.to_string()
+            } else {
+                match e.qualified_name.strip_prefix(&old_prefix) {
+                    Some(suffix) => child_qualified_name(new_name, suffix),
+                    None => child_qualified_name(new_name, &e.name),
+                }
+            }
+        };
+
+        // Nothing is changed if any of the new ids or names is taken
+        for e in members.iter() {
+            let new_id = id_map[&e.id];
+            if self.node_id_map.contains_key(&new_id) {
+                return Err(RegistryError::EntityIdExists(new_id));
+            }
+            let qualified_name = new_qualified_name(e);
+            if self.is_name_taken(&qualified_name) {
+                return Err(RegistryError::EntityNameExists(qualified_name));
+            }
+        }
+
+        // Edges are collected before inserting anything, reflections are created by `connect`
+        let mut edges: Vec<(Uuid, Uuid, EdgeType, HashMap<String, String>)> = vec![];
+        for e in members.iter() {
+            let idx = self.get_idx(e.id)?;
+            for edge in self.graph.edges(idx).map(|e| e.weight()) {
+                let to = match (id_map.get(&edge.to), edge.edge_type) {
+                    (
+                        Some(&to),
+                        EdgeType::Contains | EdgeType::Consumes | EdgeType::DerivesFrom,
+                    ) => to,
+                    // External reference
+                    (None, EdgeType::Consumes | EdgeType::DerivesFrom)
+                        if !self.deleted.contains(&edge.to) =>
+                    {
+                        edge.to
+                    }
+                    _ => continue,
+                };
+                edges.push((id_map[&e.id], to, edge.edge_type, edge.attributes.clone()));
+            }
+        }
+
+        for e in members.into_iter() {
+            let new_id = id_map[&e.id];
+            let qualified_name = new_qualified_name(&e);
+            let name = if e.id == src_id {
+                new_name.to_string()
+            } else {
+                e.name
+            };
+            let mut properties = e.properties;
+            properties.set_id(new_id);
+            properties.rename(&name, &qualified_name);
+            self.insert_entity(new_id, e.entity_type, name, qualified_name, properties)
+                .await?;
+        }
+        for (from, to, edge_type, attributes) in edges {
+            self.connect_with_attributes(from, to, edge_type, attributes)
+                .await?;
+        }
+        self.index_entities_bulk(id_map.values().copied())?;
+        Ok(id_map[&src_id])
+    }
+
+    pub(crate) fn get_idx(&self, uuid: Uuid) -> Result<NodeIndex, RegistryError> {
+        if self.deleted.contains(&uuid) {
+            return Err(RegistryError::InvalidEntity(uuid));
+        }
+        Ok(self
+            .node_id_map
+            .get(&uuid)
+            .ok_or(RegistryError::InvalidEntity(uuid))?
+            .to_owned())
+    }
+
+    /**
+     * Get the edge with the type between 2 entities
+     */
+    pub(crate) fn get_edge(&self, from: Uuid, to: Uuid, edge_type: EdgeType) -> Option<Edge> {
+        let from_idx = self.get_idx(from).ok()?;
+        let to_idx = self.get_idx(to).ok()?;
+        self.graph
+            .edges_connecting(from_idx, to_idx)
+            .find(|e| e.weight().edge_type == edge_type)
+            .map(|e| e.weight().to_owned())
+    }
+
+    /**
+     * Get all edges with the type going out of the entity
+     */
+    pub(crate) fn get_outgoing_edges(
+        &self,
+        uuid: Uuid,
+        edge_type: EdgeType,
+    ) -> Result<Vec<Edge>, RegistryError> {
+        let idx = self.get_idx(uuid)?;
+        Ok(self
+            .graph
+            .edges(idx)
+            .filter(|e| e.weight().edge_type == edge_type)
+            .map(|e| e.weight().to_owned())
+            .collect())
+    }
+
+    pub(crate) fn get_neighbors_idx<F>(&self, idx: NodeIndex, predicate: F) -> Vec<NodeIndex>
+    where
+        F: Fn(&Edge) -> bool,
     {
         self.graph
             .edges(idx)
@@ -754,53 +2281,100 @@ where
         }
         let idx = self.graph.add_node(entity);
         self.node_id_map.insert(id, idx);
-        self.name_id_map
-            .entry(qualified_name)
-            .or_default()
-            .insert(version, id);
+        self.add_name(&qualified_name, version, id);
+        self.add_key_aliases(id);
         if entity_type.is_entry_point() {
             self.entry_points.push(idx);
         }
         Ok(idx)
     }
 
-    fn insert_edge(
+    /**
+     * Insert the edge and its reflection, existing ones are kept
+     * Non-empty attributes replace the ones on existing edges
+     */
+    fn insert_edge_pair(
         &mut self,
         edge_type: EdgeType,
         from_idx: NodeIndex,
         to_idx: NodeIndex,
-        from_uuid: Uuid,
-        to_uuid: Uuid,
-    ) -> EdgeIndex {
-        self.graph.add_edge(
-            from_idx,
-            to_idx,
-            Edge {
-                from: from_uuid,
-                to: to_uuid,
-                edge_type,
-            },
-        )
+        from: Uuid,
+        to: Uuid,
+        attributes: HashMap<String, String>,
+    ) {
+        let edge = Edge {
+            from,
+            to,
+            edge_type,
+            attributes,
+        };
+        let reflection = edge.reflection();
+        self.upsert_edge(from_idx, to_idx, edge);
+        self.upsert_edge(to_idx, from_idx, reflection);
+        self.changelog.record(Change::Edge(from, to, edge_type));
     }
 
-    pub(crate) fn to_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
-        Ok(match &r {
-            Resource::NamedEntity(name) => {
-                let id = self.get_entity_id(name)?;
-                let proj_id = self.get_entity_project_id(id)?;
-                Resource::Entity(proj_id)
-            }
-            Resource::Entity(id) => {
-                let proj_id = self.get_entity_project_id(*id)?;
-                Resource::Entity(proj_id)
-            }
-            Resource::Global => Resource::Global,
-        })
+    /**
+     * Map legacy feature-to-feature `Consumes`/`Produces` edges to `DerivesFrom`/`DerivedBy`
+     */
+    fn normalize_edge_type(
+        &self,
+        from_idx: NodeIndex,
+        to_idx: NodeIndex,
+        edge_type: EdgeType,
+    ) -> EdgeType {
+        match (
+            self.graph.node_weight(from_idx),
+            self.graph.node_weight(to_idx),
+        ) {
+            (Some(from), Some(to)) => edge_type.normalize(from.entity_type, to.entity_type),
+            _ => edge_type,
+        }
     }
 
-    pub(crate) fn to_named_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
-        Ok(match &r {
-            Resource::NamedEntity(name) => {
+    fn upsert_edge(&mut self, from_idx: NodeIndex, to_idx: NodeIndex, edge: Edge) {
+        match self
+            .graph
+            .edges_connecting(from_idx, to_idx)
+            .find(|e| e.weight().edge_type == edge.edge_type)
+            .map(|e| e.id())
+        {
+            Some(e) => {
+                debug!("Connection already exists, {:?}", e);
+                if !edge.attributes.is_empty() {
+                    if let Some(w) = self.graph.edge_weight_mut(e) {
+                        w.attributes = edge.attributes;
+                    }
+                }
+            }
+            None => {
+                self.insert_edge(from_idx, to_idx, edge);
+            }
+        };
+    }
+
+    fn insert_edge(&mut self, from_idx: NodeIndex, to_idx: NodeIndex, edge: Edge) -> EdgeIndex {
+        self.graph.add_edge(from_idx, to_idx, edge)
+    }
+
+    pub(crate) fn to_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
+        Ok(match &r {
+            Resource::NamedEntity(name) => {
+                let id = self.get_entity_id(name)?;
+                let proj_id = self.get_entity_project_id(id)?;
+                Resource::Entity(proj_id)
+            }
+            Resource::Entity(id) => {
+                let proj_id = self.get_entity_project_id(*id)?;
+                Resource::Entity(proj_id)
+            }
+            Resource::Global => Resource::Global,
+        })
+    }
+
+    pub(crate) fn to_named_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
+        Ok(match &r {
+            Resource::NamedEntity(name) => {
                 let id = self.get_entity_id(name)?;
                 let proj_id = self.get_entity_project_id(id)?;
                 let proj_name = self
@@ -821,7 +2395,10 @@ where
         })
     }
 
-    pub(crate) async fn do_grant_permission(&mut self, grant: &RbacRecord) -> Result<(), RegistryError> {
+    pub(crate) async fn do_grant_permission(
+        &mut self,
+        grant: &RbacRecord,
+    ) -> Result<(), RegistryError> {
         // Permission already granted, no need to do anything
         if self.check_permission(&grant.credential, &grant.resource, grant.permission)? {
             return Ok(());
@@ -841,6 +2418,7 @@ where
 
         // Update local data structure
         self.permission_map.grant_permission(&grant);
+        self.changelog.record(Change::Permissions);
         Ok(())
     }
 }
@@ -852,11 +2430,13 @@ mod tests {
     use async_trait::async_trait;
     use rand::Rng;
     use registry_provider::*;
+    use serde::Serialize;
     use uuid::Uuid;
 
     use super::*;
+    use crate::database::{BatchWriter, BufferedStorage, PendingWrite};
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
     struct DummyEntityProp;
 
     impl ToDocString for DummyEntityProp {
@@ -869,23 +2449,32 @@ mod tests {
     struct DummyEdgeProp;
 
     impl EntityPropMutator for DummyEntityProp {
-        fn new_project(_definition: &ProjectDef) -> Result<Self, RegistryError> {
+        fn new_project(
+            _definition: &ProjectDef,
+            _now: DateTime<Utc>,
+        ) -> Result<Self, RegistryError> {
             Ok(DummyEntityProp)
         }
 
-        fn new_source(_definition: &SourceDef) -> Result<Self, RegistryError> {
+        fn new_source(_definition: &SourceDef, _now: DateTime<Utc>) -> Result<Self, RegistryError> {
             Ok(DummyEntityProp)
         }
 
-        fn new_anchor(_definition: &AnchorDef) -> Result<Self, RegistryError> {
+        fn new_anchor(_definition: &AnchorDef, _now: DateTime<Utc>) -> Result<Self, RegistryError> {
             Ok(DummyEntityProp)
         }
 
-        fn new_anchor_feature(_definition: &AnchorFeatureDef) -> Result<Self, RegistryError> {
+        fn new_anchor_feature(
+            _definition: &AnchorFeatureDef,
+            _now: DateTime<Utc>,
+        ) -> Result<Self, RegistryError> {
             Ok(DummyEntityProp)
         }
 
-        fn new_derived_feature(_definition: &DerivedFeatureDef) -> Result<Self, RegistryError> {
+        fn new_derived_feature(
+            _definition: &DerivedFeatureDef,
+            _now: DateTime<Utc>,
+        ) -> Result<Self, RegistryError> {
             Ok(DummyEntityProp)
         }
 
@@ -894,6 +2483,32 @@ mod tests {
         }
 
         fn set_version(&mut self, _version: u64) {}
+
+        fn get_etag(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        fn get_key_aliases(&self) -> Vec<String> {
+            vec![]
+        }
+
+        fn update_tags(&mut self, _tags: HashMap<String, String>, _etag: Uuid) {}
+
+        fn get_tag(&self, _key: &str) -> Option<&str> {
+            None
+        }
+
+        fn set_links(&mut self, _links: Vec<EntityLink>) {}
+
+        fn rename(&mut self, _name: &str, _qualified_name: &str) {}
+
+        fn set_id(&mut self, _id: Uuid) {}
+
+        fn touch(&mut self, _modified_by: &str, _ts: DateTime<Utc>) {}
+
+        fn same_definition(&self, other: &Self) -> bool {
+            self == other
+        }
     }
 
     #[derive(Debug)]
@@ -910,6 +2525,15 @@ mod tests {
             Ok(())
         }
 
+        async fn update_entity(
+            &mut self,
+            _id: Uuid,
+            entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            debug!("Updating entity {}", entity.name);
+            Ok(())
+        }
+
         async fn delete_entity(
             &mut self,
             _id: Uuid,
@@ -924,6 +2548,7 @@ mod tests {
             from_id: Uuid,
             to_id: Uuid,
             edge_type: EdgeType,
+            _attributes: &HashMap<String, String>,
         ) -> Result<(), RegistryError> {
             debug!("Adding edge: '{}' '{:?}' '{}'", from_id, edge_type, to_id);
             Ok(())
@@ -952,6 +2577,224 @@ mod tests {
         async fn revoke_permission(&mut self, _revoke: &RbacRecord) -> Result<(), RegistryError> {
             Ok(())
         }
+
+        async fn reconcile(
+            &mut self,
+            entities: &[&Entity<DummyEntityProp>],
+            edges: &[&Edge],
+        ) -> Result<(), RegistryError> {
+            debug!(
+                "Reconciling {} entities and {} edges",
+                entities.len(),
+                edges.len()
+            );
+            Ok(())
+        }
+    }
+
+    /**
+     * Keeps qualified names and edges in memory, inserting keeps existing records like SQL storages
+     */
+    #[derive(Debug, Default)]
+    pub struct MemoryStorage {
+        entities: Arc<std::sync::Mutex<HashMap<Uuid, String>>>,
+        edges: Arc<std::sync::Mutex<HashSet<(Uuid, Uuid, EdgeType)>>>,
+    }
+
+    #[async_trait]
+    impl ExternalStorage<DummyEntityProp> for MemoryStorage {
+        async fn add_entity(
+            &mut self,
+            id: Uuid,
+            entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            self.entities
+                .lock()
+                .unwrap()
+                .entry(id)
+                .or_insert_with(|| entity.qualified_name.clone());
+            Ok(())
+        }
+
+        async fn update_entity(
+            &mut self,
+            id: Uuid,
+            entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            if let Some(name) = self.entities.lock().unwrap().get_mut(&id) {
+                *name = entity.qualified_name.clone();
+            }
+            Ok(())
+        }
+
+        async fn delete_entity(
+            &mut self,
+            id: Uuid,
+            _entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            self.entities.lock().unwrap().remove(&id);
+            Ok(())
+        }
+
+        async fn connect(
+            &mut self,
+            from_id: Uuid,
+            to_id: Uuid,
+            edge_type: EdgeType,
+            _attributes: &HashMap<String, String>,
+        ) -> Result<(), RegistryError> {
+            self.edges
+                .lock()
+                .unwrap()
+                .insert((from_id, to_id, edge_type));
+            Ok(())
+        }
+
+        async fn disconnect(
+            &mut self,
+            _from: &Entity<DummyEntityProp>,
+            from_id: Uuid,
+            _to: &Entity<DummyEntityProp>,
+            to_id: Uuid,
+            edge_type: EdgeType,
+            _edge_id: Uuid,
+        ) -> Result<(), RegistryError> {
+            self.edges
+                .lock()
+                .unwrap()
+                .remove(&(from_id, to_id, edge_type));
+            Ok(())
+        }
+
+        async fn grant_permission(&mut self, _grant: &RbacRecord) -> Result<(), RegistryError> {
+            Ok(())
+        }
+
+        async fn revoke_permission(&mut self, _revoke: &RbacRecord) -> Result<(), RegistryError> {
+            Ok(())
+        }
+
+        async fn reconcile(
+            &mut self,
+            entities: &[&Entity<DummyEntityProp>],
+            edges: &[&Edge],
+        ) -> Result<(), RegistryError> {
+            *self.entities.lock().unwrap() = entities
+                .iter()
+                .map(|e| (e.id, e.qualified_name.clone()))
+                .collect();
+            *self.edges.lock().unwrap() =
+                edges.iter().map(|e| (e.from, e.to, e.edge_type)).collect();
+            Ok(())
+        }
+    }
+
+    /**
+     * Records sizes of written batches and counts writes not going through batches
+     */
+    #[derive(Debug, Default)]
+    pub struct BatchRecorder {
+        batches: Arc<std::sync::Mutex<Vec<usize>>>,
+        direct_writes: Arc<std::sync::Mutex<usize>>,
+        updated: Arc<std::sync::Mutex<Vec<Uuid>>>,
+        fail_batches: bool,
+        fail_direct_writes: bool,
+    }
+
+    impl BatchRecorder {
+        fn record_direct(&self) -> Result<(), RegistryError> {
+            if self.fail_direct_writes {
+                return Err(RegistryError::ExternalStorageError(
+                    "Write failed".to_string(),
+                ));
+            }
+            *self.direct_writes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ExternalStorage<DummyEntityProp> for BatchRecorder {
+        async fn add_entity(
+            &mut self,
+            _id: Uuid,
+            _entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn update_entity(
+            &mut self,
+            id: Uuid,
+            _entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            self.record_direct()?;
+            self.updated.lock().unwrap().push(id);
+            Ok(())
+        }
+
+        async fn delete_entity(
+            &mut self,
+            _id: Uuid,
+            _entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn connect(
+            &mut self,
+            _from_id: Uuid,
+            _to_id: Uuid,
+            _edge_type: EdgeType,
+            _attributes: &HashMap<String, String>,
+        ) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn disconnect(
+            &mut self,
+            _from: &Entity<DummyEntityProp>,
+            _from_id: Uuid,
+            _to: &Entity<DummyEntityProp>,
+            _to_id: Uuid,
+            _edge_type: EdgeType,
+            _edge_id: Uuid,
+        ) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn grant_permission(&mut self, _grant: &RbacRecord) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn revoke_permission(&mut self, _revoke: &RbacRecord) -> Result<(), RegistryError> {
+            self.record_direct()
+        }
+
+        async fn reconcile(
+            &mut self,
+            entities: &[&Entity<DummyEntityProp>],
+            edges: &[&Edge],
+        ) -> Result<(), RegistryError> {
+            *self.direct_writes.lock().unwrap() += entities.len() + edges.len();
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl BatchWriter<DummyEntityProp> for BatchRecorder {
+        async fn write_batch(
+            &mut self,
+            writes: Vec<PendingWrite<DummyEntityProp>>,
+        ) -> Result<(), RegistryError> {
+            if self.fail_batches {
+                return Err(RegistryError::ExternalStorageError(
+                    "Batch failed".to_string(),
+                ));
+            }
+            self.batches.lock().unwrap().push(writes.len());
+            Ok(())
+        }
     }
 
     async fn init() -> Registry<DummyEntityProp> {
@@ -1104,19 +2947,19 @@ mod tests {
         r.connect(idx_prj1, idx_df3, EdgeType::Contains)
             .await
             .unwrap(); // Project1 contains DerivedFeature3
-        r.connect(idx_af1, idx_df1, EdgeType::Produces)
+        r.connect(idx_af1, idx_df1, EdgeType::DerivedBy)
             .await
             .unwrap(); // AnchorFeature1 derives DerivedFeature1
-        r.connect(idx_af2, idx_df2, EdgeType::Produces)
+        r.connect(idx_af2, idx_df2, EdgeType::DerivedBy)
             .await
             .unwrap(); // AnchorFeature2 derives DerivedFeature2
-        r.connect(idx_af3, idx_df2, EdgeType::Produces)
+        r.connect(idx_af3, idx_df2, EdgeType::DerivedBy)
             .await
             .unwrap(); // AnchorFeature3 derives DerivedFeature2
-        r.connect(idx_af4, idx_df3, EdgeType::Produces)
+        r.connect(idx_af4, idx_df3, EdgeType::DerivedBy)
             .await
             .unwrap(); // AnchorFeature4 derives DerivedFeature3
-        r.connect(idx_df2, idx_df3, EdgeType::Produces)
+        r.connect(idx_df2, idx_df3, EdgeType::DerivedBy)
             .await
             .unwrap(); // DerivedFeature2 derives DerivedFeature3
 
@@ -1257,10 +3100,7 @@ mod tests {
             .map(|e| e.id)
             .unwrap();
         let (entities, edges) = r.get_feature_upstream(df2, None).unwrap();
-        let mut upstream_names: Vec<String> = entities
-            .into_iter()
-            .map(|w| w.name)
-            .collect();
+        let mut upstream_names: Vec<String> = entities.into_iter().map(|w| w.name).collect();
         upstream_names.sort();
         assert_eq!(
             upstream_names,
@@ -1288,12 +3128,184 @@ mod tests {
             [
                 "anchor_feature2 Consumes source1",
                 "anchor_feature3 Consumes source1",
-                "derived_feature2 Consumes anchor_feature2",
-                "derived_feature2 Consumes anchor_feature3"
+                "derived_feature2 DerivesFrom anchor_feature2",
+                "derived_feature2 DerivesFrom anchor_feature3"
             ]
         );
     }
 
+    /**
+     * One anchor feature feeds `width` derived features, which are all consumed by one more derived feature
+     */
+    async fn fan_out(width: usize) -> (Registry<DummyEntityProp>, Uuid) {
+        let mut r: Registry<DummyEntityProp> = Registry::new();
+        r.fts_index.enable(false);
+        let hub = r
+            .new_entity(
+                EntityType::AnchorFeature,
+                "hub",
+                "project1__hub",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let sink = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "sink",
+                "project1__sink",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        for i in 0..width {
+            let f = r
+                .new_entity(
+                    EntityType::DerivedFeature,
+                    format!("derived{}", i),
+                    format!("project1__derived{}", i),
+                    DummyEntityProp,
+                )
+                .await
+                .unwrap();
+            r.connect(hub, f, EdgeType::DerivedBy).await.unwrap();
+            r.connect(f, sink, EdgeType::DerivedBy).await.unwrap();
+        }
+        (r, hub)
+    }
+
+    #[tokio::test]
+    async fn bfs_wide_fan_out() {
+        const WIDTH: usize = 500;
+        let (r, hub) = fan_out(WIDTH).await;
+        let (entities, edges) = r.bfs(hub, EdgeType::Produces, None).unwrap();
+        let ids: HashSet<Uuid> = entities.iter().map(|e| e.id).collect();
+        assert_eq!(ids.len(), entities.len());
+        assert_eq!(entities.len(), WIDTH + 2);
+        assert_eq!(edges.len(), WIDTH * 2);
+
+        // Breadth-first, neighbors ordered by id
+        assert_eq!(entities[0].id, hub);
+        assert_eq!(entities.last().unwrap().name, "sink");
+        assert!(entities[1..=WIDTH].windows(2).all(|w| w[0].id < w[1].id));
+
+        // Same result every time
+        let (again, _) = r.bfs(hub, EdgeType::Produces, None).unwrap();
+        assert_eq!(entities, again);
+    }
+
+    #[tokio::test]
+    async fn bfs_truncation() {
+        let (r, hub) = fan_out(50).await;
+        let (entities, _, truncated) = r.bfs_with_limit(hub, EdgeType::Produces, 10).unwrap();
+        assert_eq!(entities.len(), 10);
+        assert!(truncated);
+
+        // Limit is larger than the whole lineage
+        let (entities, _, truncated) = r.bfs_with_limit(hub, EdgeType::Produces, 100).unwrap();
+        assert_eq!(entities.len(), 52);
+        assert!(!truncated);
+
+        // Exactly fits
+        let (_, edges, truncated) = r.bfs_with_limit(hub, EdgeType::Produces, 52).unwrap();
+        assert_eq!(edges.len(), 100);
+        assert!(!truncated);
+    }
+
+    #[tokio::test]
+    async fn bfs_paging() {
+        // 20 entities, the hub, 18 derived features and the sink
+        let (r, hub) = fan_out(18).await;
+        let (_, all_edges) = r.bfs(hub, EdgeType::Produces, None).unwrap();
+        assert_eq!(all_edges.len(), 36);
+
+        let mut paged_edges = vec![];
+        let mut offset = 0;
+        loop {
+            let (entities, edges, has_more, truncated) = r
+                .bfs_page(
+                    hub,
+                    offset,
+                    5,
+                    None,
+                    |_| true,
+                    |e| e.edge_type == EdgeType::DerivedBy,
+                )
+                .unwrap();
+            assert!(edges.len() <= 5);
+            assert!(!truncated);
+            // Only the root and the entities referenced by the edges in the page
+            let ids: HashSet<Uuid> = std::iter::once(hub)
+                .chain(edges.iter().flat_map(|e| [e.from, e.to]))
+                .collect();
+            assert_eq!(entities.len(), ids.len());
+            assert!(entities.iter().all(|e| ids.contains(&e.id)));
+            paged_edges.extend(edges);
+            offset += 5;
+            if !has_more {
+                break;
+            }
+        }
+        assert_eq!(offset, 40);
+        assert_eq!(paged_edges, all_edges);
+    }
+
+    #[tokio::test]
+    async fn bfs_deadline() {
+        const WIDTH: usize = 2000;
+        let (mut r, hub) = fan_out(WIDTH).await;
+        let (entities, _, truncated) = r
+            .bfs_with_limit(hub, EdgeType::Produces, usize::MAX)
+            .unwrap();
+        assert!(!truncated);
+        assert_eq!(entities.len(), WIDTH + 2);
+
+        r.set_traversal_timeout(Some(std::time::Duration::from_nanos(1)));
+        let start = Instant::now();
+        let (entities, _, truncated) = r
+            .bfs_with_limit(hub, EdgeType::Produces, usize::MAX)
+            .unwrap();
+        assert!(truncated);
+        assert!(entities.len() < WIDTH + 2);
+        assert!(start.elapsed().as_millis() < 1000);
+
+        // Paging is cut short as well
+        let (_, edges, _, truncated) = r
+            .bfs_page(
+                hub,
+                WIDTH,
+                10,
+                r.traversal_deadline(),
+                |_| true,
+                |e| e.edge_type == EdgeType::DerivedBy,
+            )
+            .unwrap();
+        assert!(truncated);
+        assert!(edges.is_empty());
+
+        // Traversals other than lineage are never cut short
+        let (entities, _) = r.bfs(hub, EdgeType::Produces, None).unwrap();
+        assert_eq!(entities.len(), WIDTH + 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "benchmark"]
+    async fn bfs_wide_fan_out_benchmark() {
+        const WIDTH: usize = 100000;
+        let (r, hub) = fan_out(WIDTH).await;
+        let start = Instant::now();
+        let (entities, _) = r.bfs(hub, EdgeType::Produces, None).unwrap();
+        let time = Instant::now() - start;
+        println!(
+            "Traversed {} entities in {} ms",
+            entities.len(),
+            time.as_millis()
+        );
+        assert_eq!(entities.len(), WIDTH + 2);
+        // The quadratic scan took minutes with this width
+        assert!(time.as_secs() < 10);
+    }
+
     #[tokio::test]
     #[ignore = "too slow"]
     async fn many_nodes() {
@@ -1364,7 +3376,7 @@ mod tests {
             let count: usize = rng.gen_range(2..10);
             for _ in 0..count {
                 let id = features[rng.gen_range(0..features.len())];
-                r.connect(f, id, EdgeType::Consumes).await.unwrap();
+                r.connect(f, id, EdgeType::DerivesFrom).await.unwrap();
             }
             features.push(f);
             r.connect(f, prj1, EdgeType::BelongsTo).await.unwrap();
@@ -1417,4 +3429,902 @@ mod tests {
         // Now only edges between project1 and source1 remain
         assert_eq!(r.graph.edge_count(), 2);
     }
+
+    #[tokio::test]
+    async fn list_deleted() {
+        let mut r = init().await;
+        assert!(r.list_deleted().is_empty());
+        for name in ["project1__derived_feature3", "project1__derived_feature1"] {
+            let id = r.get_entity_by_name(name, None).unwrap().id;
+            r.delete_entity_by_id(id).await.unwrap();
+        }
+        let deleted: Vec<(String, EntityType)> = r
+            .list_deleted()
+            .into_iter()
+            .map(|e| (e.qualified_name, e.entity_type))
+            .collect();
+        assert_eq!(
+            deleted,
+            vec![
+                (
+                    "project1__derived_feature1".to_string(),
+                    EntityType::DerivedFeature
+                ),
+                (
+                    "project1__derived_feature3".to_string(),
+                    EntityType::DerivedFeature
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_with_cross_project_dependent() {
+        let mut r = init().await;
+        let af = r
+            .new_entity(
+                EntityType::AnchorFeature,
+                "anchor_feature9",
+                "project1__anchor_feature9",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let p2 = r
+            .new_entity(EntityType::Project, "project2", "project2", DummyEntityProp)
+            .await
+            .unwrap();
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature9",
+                "project2__derived_feature9",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        r.connect(p2, df, EdgeType::Contains).await.unwrap();
+        r.connect(df, af, EdgeType::DerivesFrom).await.unwrap();
+
+        match r.delete_entity_by_id(af).await {
+            Err(RegistryError::DeleteInUsed(id, dependents)) => {
+                assert_eq!(id, af);
+                assert_eq!(dependents, vec![df]);
+            }
+            _ => panic!("Deleting an entity in use should fail"),
+        }
+
+        // Still refused when only the incoming edge is there
+        r.graph
+            .retain_edges(|g, e| g[e].edge_type != EdgeType::DerivedBy);
+        let err = r.delete_entity_by_id(af).await.unwrap_err();
+        assert!(err.to_string().contains(&df.to_string()));
+        assert!(r.get_entity_by_id(af).is_some());
+
+        // Nothing depends on it once the dependent is gone
+        r.delete_entity_by_id(df).await.unwrap();
+        r.delete_entity_by_id(af).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn load_data_stream() {
+        let r = init().await;
+        let entities: Vec<Entity<DummyEntityProp>> = r.graph.node_weights().cloned().collect();
+        let edges: Vec<Edge> = r.graph.edge_weights().cloned().collect();
+
+        let mut batched = Registry::<DummyEntityProp>::default();
+        batched
+            .load_data(entities.clone(), edges.clone(), vec![])
+            .await
+            .unwrap();
+        let mut streamed = Registry::<DummyEntityProp>::default();
+        streamed
+            .load_data_stream(tokio_stream::iter(entities), tokio_stream::iter(edges))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&streamed.graph).unwrap(),
+            serde_json::to_value(&batched.graph).unwrap()
+        );
+        assert_eq!(streamed.name_id_map, batched.name_id_map);
+        assert_eq!(streamed.entry_points, batched.entry_points);
+        assert_eq!(streamed.fts_index.num_docs(), batched.fts_index.num_docs());
+    }
+
+    #[tokio::test]
+    async fn blank_name() {
+        let mut r = init().await;
+        for name in ["", " "] {
+            assert!(matches!(
+                r.new_entity(
+                    EntityType::AnchorFeature,
+                    name,
+                    "project1__",
+                    DummyEntityProp
+                )
+                .await,
+                Err(RegistryError::InvalidDefinition(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn attach_external_storage() {
+        let mut r = init().await;
+        r.external_storage.clear();
+        let recorder = BatchRecorder::default();
+        let direct_writes = recorder.direct_writes.clone();
+        assert!(r
+            .attach_external_storage(Arc::new(RwLock::new(recorder)))
+            .await
+            .unwrap());
+        // Existing entities and edges are written on attaching
+        let existing = r.graph.node_count() + r.graph.edge_count();
+        assert_eq!(*direct_writes.lock().unwrap(), existing);
+
+        // Attaching again is a no-op
+        let other = BatchRecorder::default();
+        let other_writes = other.direct_writes.clone();
+        assert!(!r
+            .attach_external_storage(Arc::new(RwLock::new(other)))
+            .await
+            .unwrap());
+        assert_eq!(r.external_storage.len(), 1);
+        assert_eq!(*other_writes.lock().unwrap(), 0);
+
+        // New entities go to the attached storage
+        r.new_entity(
+            EntityType::Source,
+            "source9",
+            "project1__source9",
+            DummyEntityProp,
+        )
+        .await
+        .unwrap();
+        assert_eq!(*direct_writes.lock().unwrap(), existing + 1);
+    }
+
+    #[tokio::test]
+    async fn attach_diverged_storage() {
+        let mut r = init().await;
+        r.external_storage.clear();
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let ghost = Uuid::new_v4();
+        let storage = MemoryStorage::default();
+        let entities = storage.entities.clone();
+        let edges = storage.edges.clone();
+        {
+            // The storage missed a rename, a deletion and some edges while it was detached
+            let mut entities = entities.lock().unwrap();
+            entities.insert(af1, "project1__stale_name".to_string());
+            entities.insert(ghost, "project1__ghost".to_string());
+            let mut edges = edges.lock().unwrap();
+            edges.insert((prj1, ghost, EdgeType::Contains));
+            edges.insert((ghost, prj1, EdgeType::BelongsTo));
+        }
+        assert!(r
+            .attach_external_storage(Arc::new(RwLock::new(storage)))
+            .await
+            .unwrap());
+
+        // The storage holds exactly the live entities and edges of the graph
+        let expected_entities: HashMap<Uuid, String> = r
+            .graph
+            .node_weights()
+            .filter(|w| !r.deleted.contains(&w.id))
+            .map(|w| (w.id, w.qualified_name.clone()))
+            .collect();
+        assert_eq!(*entities.lock().unwrap(), expected_entities);
+        assert_eq!(
+            entities.lock().unwrap()[&af1],
+            "project1__anchor_feature1".to_string()
+        );
+        let expected_edges: HashSet<(Uuid, Uuid, EdgeType)> = r
+            .graph
+            .edge_weights()
+            .filter(|e| !r.deleted.contains(&e.from) && !r.deleted.contains(&e.to))
+            .map(|e| (e.from, e.to, e.edge_type))
+            .collect();
+        assert_eq!(*edges.lock().unwrap(), expected_edges);
+    }
+
+    #[tokio::test]
+    async fn buffered_storage() {
+        let mut r = init().await;
+        let recorder = BatchRecorder::default();
+        let batches = recorder.batches.clone();
+        let direct_writes = recorder.direct_writes.clone();
+        r.external_storage = vec![Arc::new(RwLock::new(BufferedStorage::new(recorder)))];
+
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let inputs: Vec<Uuid> = (1..=4)
+            .map(|i| {
+                r.get_entity_by_name(&format!("project1__anchor_feature{}", i), None)
+                    .unwrap()
+                    .id
+            })
+            .collect();
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature5",
+                "project1__derived_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let edges = std::iter::once((prj1, df, EdgeType::Contains))
+            .chain(inputs.iter().map(|&id| (df, id, EdgeType::DerivesFrom)))
+            .collect();
+        r.connect_many(edges).await.unwrap();
+        assert!(batches.lock().unwrap().is_empty());
+        r.flush_external_storage().await.unwrap();
+
+        // The entity, the containing edge and all inputs go in one batch
+        assert_eq!(*batches.lock().unwrap(), vec![inputs.len() + 2]);
+        assert_eq!(*direct_writes.lock().unwrap(), 0);
+        // Nothing left to flush
+        r.flush_external_storage().await.unwrap();
+        assert_eq!(batches.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_flush_keeps_changes() {
+        let mut r = init().await;
+        r.flush_external_storage().await.unwrap();
+        let recorder = BatchRecorder {
+            fail_batches: true,
+            ..Default::default()
+        };
+        r.external_storage = vec![Arc::new(RwLock::new(BufferedStorage::new(recorder)))];
+        let node_count = r.graph.node_count();
+        let edge_count = r.graph.edge_count();
+
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature5",
+                "project1__derived_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        r.connect_many(vec![
+            (prj1, df, EdgeType::Contains),
+            (df, af1, EdgeType::DerivesFrom),
+        ])
+        .await
+        .unwrap();
+        assert!(matches!(
+            r.flush_external_storage().await,
+            Err(RegistryError::ExternalStorageError(_))
+        ));
+
+        // The graph keeps what has been applied, all replicas apply the same log entry
+        assert!(r.get_entity_by_id(df).is_some());
+        assert_eq!(r.graph.node_count(), node_count + 1);
+        // Both edges come with their reflections
+        assert_eq!(r.graph.edge_count(), edge_count + 4);
+    }
+
+    #[tokio::test]
+    async fn links_are_written_to_storage() {
+        let mut r = init().await;
+        let recorder = BatchRecorder::default();
+        let updated = recorder.updated.clone();
+        r.external_storage = vec![Arc::new(RwLock::new(recorder))];
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+        r.update_entity_links(
+            af1,
+            vec![EntityLink {
+                title: "doc".to_string(),
+                url: "https://example.com".to_string(),
+            }],
+        )
+        .await
+        .unwrap();
+        assert_eq!(*updated.lock().unwrap(), vec![af1]);
+    }
+
+    #[tokio::test]
+    async fn failed_force_delete_keeps_source() {
+        let mut r = init().await;
+        let src1 = r.get_entity_by_name("project1__source1", None).unwrap().id;
+        let consumers = r.get_dependents(src1).unwrap();
+        assert!(!consumers.is_empty());
+        let edge_count = r.graph.edge_count();
+        r.external_storage = vec![Arc::new(RwLock::new(BatchRecorder {
+            fail_direct_writes: true,
+            ..Default::default()
+        }))];
+        assert!(r.delete_source(src1, true).await.is_err());
+
+        // Nothing is detached if the storage fails
+        assert!(r.get_entity_by_id(src1).is_some());
+        assert_eq!(r.get_dependents(src1).unwrap(), consumers);
+        assert_eq!(r.graph.edge_count(), edge_count);
+
+        r.external_storage = vec![Arc::new(RwLock::new(DummyExternalStorage))];
+        r.delete_source(src1, true).await.unwrap();
+        assert!(r.get_entity_by_id(src1).is_none());
+        for id in consumers {
+            assert!(r.get_entity_by_id(id).is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn reflections_on_reload() {
+        let r = init().await;
+        let mut graph = r.graph.clone();
+        // Only forward edges are stored, and one of them twice
+        graph.retain_edges(|g, idx| {
+            matches!(
+                g[idx].edge_type,
+                EdgeType::Contains | EdgeType::Consumes | EdgeType::DerivesFrom
+            )
+        });
+        let (from_idx, to_idx) = graph.edge_endpoints(EdgeIndex::new(0)).unwrap();
+        let edge = graph[EdgeIndex::new(0)].clone();
+        graph.add_edge(from_idx, to_idx, edge);
+
+        let restored = Registry::<DummyEntityProp>::from_content(graph, r.deleted.clone(), vec![]);
+        let edges = |g: &Graph<Entity<DummyEntityProp>, Edge, Directed>| {
+            g.edge_weights()
+                .map(|e| (e.from, e.to, e.edge_type))
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(restored.graph.edge_count(), r.graph.edge_count());
+        assert_eq!(edges(&restored.graph), edges(&r.graph));
+    }
+
+    #[tokio::test]
+    async fn legacy_derived_edges() {
+        let r = init().await;
+        let legacy = |e: &Edge| {
+            let mut e = e.clone();
+            e.edge_type = match e.edge_type {
+                EdgeType::DerivesFrom => EdgeType::Consumes,
+                EdgeType::DerivedBy => EdgeType::Produces,
+                t => t,
+            };
+            e
+        };
+        let edges = |g: &Graph<Entity<DummyEntityProp>, Edge, Directed>| {
+            g.edge_weights()
+                .map(|e| (e.from, e.to, e.edge_type))
+                .collect::<HashSet<_>>()
+        };
+
+        // Snapshot taken by older versions
+        let graph = r.graph.map(|_, n| n.clone(), |_, e| legacy(e));
+        let restored = Registry::<DummyEntityProp>::from_content(graph, r.deleted.clone(), vec![]);
+        assert_eq!(edges(&restored.graph), edges(&r.graph));
+
+        // Content loaded from the database written by older versions
+        let mut loaded = Registry::<DummyEntityProp>::new();
+        loaded
+            .batch_load(
+                r.graph.node_weights().cloned(),
+                r.graph.edge_weights().map(legacy),
+            )
+            .await
+            .unwrap();
+        assert_eq!(edges(&loaded.graph), edges(&r.graph));
+
+        // Source dependencies and feature dependencies are told apart
+        let df2 = loaded
+            .get_entity_by_name("project1__derived_feature2", None)
+            .unwrap()
+            .id;
+        assert!(loaded
+            .get_neighbors(df2, EdgeType::Consumes)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            loaded
+                .get_neighbors(df2, EdgeType::DerivesFrom)
+                .unwrap()
+                .len(),
+            2
+        );
+        let af2 = loaded
+            .get_entity_by_name("project1__anchor_feature2", None)
+            .unwrap()
+            .id;
+        let sources = loaded.get_neighbors(af2, EdgeType::Consumes).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].entity_type, EntityType::Source);
+        assert!(loaded
+            .get_neighbors(af2, EdgeType::DerivesFrom)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn bincode_round_trip() {
+        let mut r = init().await;
+        let id = r
+            .get_entity_by_name("project1__derived_feature3", None)
+            .unwrap()
+            .id;
+        r.delete_entity_by_id(id).await.unwrap();
+        let data = r.to_bincode().unwrap();
+        let restored = Registry::<DummyEntityProp>::from_bincode(&data).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored.graph).unwrap(),
+            serde_json::to_value(&r.graph).unwrap()
+        );
+        assert_eq!(restored.deleted, r.deleted);
+        assert_eq!(restored.name_id_map, r.name_id_map);
+        // Truncated data is rejected instead of panicking
+        assert!(Registry::<DummyEntityProp>::from_bincode(&data[..data.len() / 2]).is_err());
+    }
+
+    #[tokio::test]
+    async fn delta() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+
+        r.set_seq(1);
+        let af = r
+            .new_entity(
+                EntityType::AnchorFeature,
+                "anchor_feature5",
+                "project1__anchor_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        r.connect(prj1, af, EdgeType::Contains).await.unwrap();
+
+        let delta = r.take_delta_since(0).unwrap();
+        assert_eq!(delta.entities.len(), 1);
+        assert_eq!(delta.entities[0].id, af);
+        assert_eq!(
+            delta.edges,
+            vec![Edge {
+                edge_type: EdgeType::Contains,
+                from: prj1,
+                to: af,
+                attributes: Default::default(),
+            }]
+        );
+        assert!(delta.deleted.is_empty());
+
+        // Changes up to seq 1 are covered by the full snapshot
+        r.trim_changelog(1);
+        assert!(r.take_delta_since(0).is_none());
+        assert!(r.take_delta_since(1).unwrap().entities.is_empty());
+
+        // Edges removed along with the deleted entity are kept in the delta
+        r.set_seq(2);
+        r.disconnect(prj1, af, EdgeType::Contains).await.unwrap();
+        r.delete_entity_by_id(af).await.unwrap();
+        let delta = r.take_delta_since(1).unwrap();
+        assert_eq!(delta.deleted, vec![af]);
+        assert!(delta
+            .removed_edges
+            .iter()
+            .any(|e| e.from == prj1 && e.to == af && e.edge_type == EdgeType::Contains));
+
+        // Role assignments are not in deltas, a full snapshot is needed
+        r.set_seq(3);
+        r.do_grant_permission(&RbacRecord {
+            credential: Credential::User("u1".to_string()),
+            resource: Resource::Global,
+            permission: Permission::Read,
+            requestor: Credential::User("admin".to_string()),
+            reason: Default::default(),
+            time: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+        assert!(r.changed_fraction(1).is_none());
+        assert!(r.take_delta_since(1).is_none());
+    }
+
+    #[tokio::test]
+    async fn connect_many() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature5",
+                "project1__derived_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let edge_count = r.graph.edge_count();
+
+        r.connect_many(vec![
+            (prj1, df, EdgeType::Contains),
+            (df, af1, EdgeType::DerivesFrom),
+        ])
+        .await
+        .unwrap();
+
+        // Each connection comes with its reflection
+        assert_eq!(r.graph.edge_count(), edge_count + 4);
+        assert!(r
+            .get_neighbors(df, EdgeType::DerivesFrom)
+            .unwrap()
+            .into_iter()
+            .any(|e| e.id == af1));
+    }
+
+    #[tokio::test]
+    async fn connect_many_invalid() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature5",
+                "project1__derived_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let edge_count = r.graph.edge_count();
+        let missing1 = Uuid::new_v4();
+        let missing2 = Uuid::new_v4();
+
+        let result = r
+            .connect_many(vec![
+                (prj1, df, EdgeType::Contains),
+                (df, missing1, EdgeType::DerivesFrom),
+                (df, missing2, EdgeType::DerivesFrom),
+                (df, missing1, EdgeType::DerivesFrom),
+            ])
+            .await;
+
+        match result {
+            Err(RegistryError::InvalidEntities(ids)) => assert_eq!(ids, vec![missing1, missing2]),
+            _ => panic!("Should fail with invalid entities"),
+        }
+        // Nothing is connected, including the valid pair
+        assert_eq!(r.graph.edge_count(), edge_count);
+    }
+
+    #[tokio::test]
+    async fn stats() {
+        let mut r = init().await;
+        let ids: Vec<Uuid> = r.node_id_map.keys().copied().collect();
+        r.index_entities_bulk(ids.into_iter()).unwrap();
+        let af2_1 = r
+            .get_entity_by_name("project2__anchor_feature2_1", None)
+            .unwrap()
+            .id;
+        r.delete_entity_by_id(af2_1).await.unwrap();
+
+        let stats = r.get_stats();
+        assert_eq!(stats.projects, 2);
+        assert_eq!(stats.deleted, 1);
+        assert_eq!(stats.fts_docs, 16);
+        // The deleted entity is not counted in type totals
+        assert_eq!(stats.entities[&EntityType::Project], 2);
+        assert_eq!(stats.entities[&EntityType::Source], 2);
+        assert_eq!(stats.entities[&EntityType::Anchor], 2);
+        assert_eq!(stats.entities[&EntityType::AnchorFeature], 6);
+        assert_eq!(stats.entities[&EntityType::DerivedFeature], 3);
+        // Every connection comes with its reflection
+        assert_eq!(stats.edges[&EdgeType::Contains], 19);
+        assert_eq!(stats.edges[&EdgeType::BelongsTo], 19);
+        assert_eq!(stats.edges[&EdgeType::Produces], 8);
+        assert_eq!(stats.edges[&EdgeType::Consumes], 8);
+        assert_eq!(stats.edges[&EdgeType::DerivedBy], 5);
+        assert_eq!(stats.edges[&EdgeType::DerivesFrom], 5);
+    }
+
+    #[tokio::test]
+    async fn topo_order_features() {
+        let mut r = init().await;
+        let id = |r: &Registry<DummyEntityProp>, name: &str| {
+            r.get_entity_by_name(&format!("project1__{}", name), None)
+                .unwrap()
+                .id
+        };
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        // DerivedFeature3 now depends on AnchorFeature1, AnchorFeature4 and DerivedFeature2
+        let af1 = id(&r, "anchor_feature1");
+        let df2 = id(&r, "derived_feature2");
+        let df3 = id(&r, "derived_feature3");
+        r.connect(af1, df3, EdgeType::DerivedBy).await.unwrap();
+        let order: Vec<String> = r
+            .topo_order_features(prj1)
+            .unwrap()
+            .into_iter()
+            .map(|id| r.get_entity_by_id(id).unwrap().name)
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "anchor_feature1",
+                "anchor_feature2",
+                "anchor_feature3",
+                "anchor_feature4",
+                "derived_feature1",
+                "derived_feature2",
+                "derived_feature3",
+            ]
+        );
+
+        // DerivedFeature2 and DerivedFeature3 depend on each other
+        r.connect(df3, df2, EdgeType::DerivedBy).await.unwrap();
+        match r.topo_order_features(prj1) {
+            Err(RegistryError::CyclicDependency(mut ids)) => {
+                ids.sort();
+                let mut expected = vec![df2, df3];
+                expected.sort();
+                assert_eq!(ids, expected);
+            }
+            _ => panic!("Should fail with cyclic dependency"),
+        }
+
+        // Only projects have features
+        assert!(matches!(
+            r.topo_order_features(id(&r, "anchor1")),
+            Err(RegistryError::WrongEntityType(_, EntityType::Anchor))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rebuild_fts() {
+        let mut r = init().await;
+        let df1 = r
+            .get_entity_by_name("project1__derived_feature1", None)
+            .unwrap()
+            .id;
+        let search = |r: &Registry<DummyEntityProp>| {
+            r.fts_index
+                .search(
+                    "derived_feature1",
+                    ["DerivedFeature".to_string()].into_iter().collect(),
+                    None,
+                    100,
+                    0,
+                )
+                .unwrap()
+        };
+        let docs = r.rebuild_fts().unwrap();
+        assert_eq!(docs, r.node_id_map.len() as u64);
+        assert!(search(&r).contains(&df1));
+
+        // Corrupt the index
+        r.fts_index.delete_doc(&df1.to_string()).unwrap();
+        r.fts_index.commit().unwrap();
+        assert!(!search(&r).contains(&df1));
+
+        assert_eq!(r.rebuild_fts().unwrap(), docs);
+        assert!(search(&r).contains(&df1));
+        // Rebuilding again doesn't duplicate docs
+        assert_eq!(r.rebuild_fts().unwrap(), docs);
+        assert_eq!(search(&r).iter().filter(|&&id| id == df1).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn rename_project() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+
+        r.rename_project(prj1, "project3").await.unwrap();
+
+        assert!(r.get_entity_by_name("project1", None).is_err());
+        let prj = r.get_entity_by_name("project3", None).unwrap();
+        assert_eq!(prj.id, prj1);
+        assert_eq!(prj.name, "project3");
+        assert_eq!(
+            r.get_entity_by_name("project3__anchor_feature1", None)
+                .unwrap()
+                .id,
+            af1
+        );
+        assert!(r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .is_err());
+        let features = r.get_features_by_project("project3");
+        assert_eq!(features.len(), 7);
+        assert!(features
+            .iter()
+            .all(|f| f.qualified_name.starts_with("project3__")));
+        assert!(r.get_features_by_project("project1").is_empty());
+        // Project2 is untouched
+        assert!(r
+            .get_entity_by_name("project2__anchor_feature2_1", None)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn rename_project_conflict() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+
+        match r.rename_project(prj1, "project2").await {
+            Err(RegistryError::EntityNameExists(name)) => assert_eq!(name, "project2"),
+            _ => panic!("Should fail with name conflict"),
+        }
+        // Nothing is renamed
+        assert_eq!(r.get_features_by_project("project1").len(), 7);
+    }
+
+    #[tokio::test]
+    async fn rename_project_to_deleted_name() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let prj3 = r
+            .new_entity(EntityType::Project, "project3", "project3", DummyEntityProp)
+            .await
+            .unwrap();
+        r.delete_entity_by_id(prj3).await.unwrap();
+
+        // Names of deleted entities are free to reuse, same as creating
+        r.rename_project(prj1, "project3").await.unwrap();
+        assert_eq!(r.get_entity_by_name("project3", None).unwrap().id, prj1);
+    }
+
+    #[tokio::test]
+    async fn clone_project() {
+        let mut r = init().await;
+        let prj1 = r.get_entity_by_name("project1", None).unwrap().id;
+        let src_ids: HashSet<Uuid> = r
+            .get_neighbors(prj1, EdgeType::Contains)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+
+        let prj3 = r
+            .clone_project(prj1, "project3", Uuid::new_v4())
+            .await
+            .unwrap();
+        assert_ne!(prj3, prj1);
+        assert_eq!(r.get_features_by_project("project3").len(), 7);
+        // The source project is untouched
+        assert_eq!(r.get_features_by_project("project1").len(), 7);
+
+        let cloned = r.get_neighbors(prj3, EdgeType::Contains).unwrap();
+        assert_eq!(cloned.len(), src_ids.len());
+        assert!(cloned.iter().all(|e| !src_ids.contains(&e.id)));
+        assert!(cloned
+            .iter()
+            .all(|e| e.qualified_name.starts_with("project3__")));
+
+        // Edges are rewired to the copies
+        let af1 = r
+            .get_entity_by_name("project3__anchor_feature1", None)
+            .unwrap();
+        let af1_consumers = r.get_neighbors(af1.id, EdgeType::DerivedBy).unwrap();
+        assert!(!af1_consumers.is_empty());
+        assert!(af1_consumers.iter().all(|e| !src_ids.contains(&e.id)));
+
+        // Cloning into an existing name fails
+        assert!(r
+            .clone_project(prj1, "project2", Uuid::new_v4())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn case_insensitive_lookup() {
+        let mut r = init().await;
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+
+        // Exact hit
+        assert_eq!(
+            r.get_entity_by_qualified_name("project1__anchor_feature1")
+                .unwrap()
+                .id,
+            af1
+        );
+        // Differing case hit
+        assert_eq!(
+            r.get_entity_by_qualified_name("Project1__Anchor_Feature1")
+                .unwrap()
+                .id,
+            af1
+        );
+        assert_eq!(
+            r.get_entity_id_by_qualified_name("PROJECT1__ANCHOR_FEATURE1")
+                .unwrap(),
+            af1
+        );
+
+        // Creation is still case-sensitive, and the lowercase key becomes ambiguous
+        let af1_upper = r
+            .new_entity(
+                EntityType::AnchorFeature,
+                "Anchor_Feature1",
+                "project1__Anchor_Feature1",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        assert_ne!(af1, af1_upper);
+        assert_eq!(
+            r.get_entity_by_qualified_name("project1__Anchor_Feature1")
+                .unwrap()
+                .id,
+            af1_upper
+        );
+        match r.get_entity_by_qualified_name("PROJECT1__ANCHOR_FEATURE1") {
+            Err(RegistryError::AmbiguousName(_, names)) => assert_eq!(
+                names,
+                vec![
+                    "project1__Anchor_Feature1".to_string(),
+                    "project1__anchor_feature1".to_string()
+                ]
+            ),
+            _ => panic!("Should fail with ambiguous name"),
+        }
+    }
+
+    #[tokio::test]
+    async fn edge_attributes() {
+        let mut r = init().await;
+        let af1 = r
+            .get_entity_by_name("project1__anchor_feature1", None)
+            .unwrap()
+            .id;
+        let df = r
+            .new_entity(
+                EntityType::DerivedFeature,
+                "derived_feature5",
+                "project1__derived_feature5",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        let attributes: HashMap<String, String> = [
+            ("join_key".to_string(), "user_id".to_string()),
+            ("note".to_string(), "daily aggregation".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        r.connect_with_attributes(df, af1, EdgeType::DerivesFrom, attributes.clone())
+            .await
+            .unwrap();
+
+        let data = r.take_snapshot().unwrap();
+        let mut restored = Registry::<DummyEntityProp>::new();
+        restored.load_snapshot(&data).unwrap();
+
+        let edge = restored.get_edge(df, af1, EdgeType::DerivesFrom).unwrap();
+        assert_eq!(edge.attributes, attributes);
+        // The reflection carries the same attributes
+        let edge = restored.get_edge(af1, df, EdgeType::DerivedBy).unwrap();
+        assert_eq!(edge.attributes, attributes);
+        // Edges without attributes are untouched
+        let prj1 = restored.get_entity_by_name("project1", None).unwrap().id;
+        assert!(restored
+            .get_edge(prj1, af1, EdgeType::Contains)
+            .unwrap()
+            .attributes
+            .is_empty());
+    }
 }
@@ -0,0 +1,246 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Debug,
+};
+
+use registry_provider::{
+    Edge, EdgeType, Entity, EntityPropMutator, RegistryError, RegistryProvider, ToDocString,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Registry;
+
+/**
+ * One mutation recorded in the changelog
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Change {
+    Entity(Uuid),
+    Edge(Uuid, Uuid, EdgeType),
+    Deleted(Uuid),
+    // Role assignments are not carried in deltas, a full snapshot is needed after this
+    Permissions,
+}
+
+/**
+ * Mutations in the changelog, keyed by the sequence number they were applied at
+ */
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Changelog {
+    // Changes before `base` have been trimmed, so deltas can only be taken since `base`
+    pub(crate) base: u64,
+    pub(crate) current_seq: u64,
+    pub(crate) changes: BTreeMap<u64, Vec<Change>>,
+}
+
+impl Changelog {
+    pub(crate) fn record(&mut self, change: Change) {
+        self.changes
+            .entry(self.current_seq)
+            .or_default()
+            .push(change);
+    }
+
+    fn since(&self, seq: u64) -> impl Iterator<Item = &Change> {
+        self.changes
+            .range(seq + 1..)
+            .flat_map(|(_, changes)| changes.iter())
+    }
+
+    /**
+     * A delta can be taken since `seq` only if the changelog covers it and permissions are unchanged
+     */
+    fn covers(&self, seq: u64) -> bool {
+        seq >= self.base && !self.since(seq).any(|c| *c == Change::Permissions)
+    }
+}
+
+/**
+ * Entities and edges changed after `since`
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistryDelta<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq,
+{
+    pub since: u64,
+    pub entities: Vec<Entity<EntityProp>>,
+    pub edges: Vec<Edge>,
+    #[serde(default)]
+    pub removed_edges: Vec<Edge>,
+    pub deleted: Vec<Uuid>,
+}
+
+impl<EntityProp> Registry<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync,
+{
+    /**
+     * Set the sequence number of the mutations applied afterwards, usually the Raft log index
+     */
+    pub fn set_seq(&mut self, seq: u64) {
+        self.changelog.current_seq = seq;
+    }
+
+    /**
+     * Drop changes up to and including `seq`, deltas can only be taken since `seq` afterwards
+     * Call this right after taking a full snapshot at `seq`
+     */
+    pub fn trim_changelog(&mut self, seq: u64) {
+        self.changelog.changes = self.changelog.changes.split_off(&(seq + 1));
+        self.changelog.base = seq;
+    }
+
+    /**
+     * Fraction of entities changed after `seq`, `None` if the changelog doesn't cover `seq` or
+     * permissions have changed since
+     */
+    pub fn changed_fraction(&self, seq: u64) -> Option<f64> {
+        if !self.changelog.covers(seq) {
+            return None;
+        }
+        let changed: HashSet<Uuid> = self
+            .changelog
+            .since(seq)
+            .filter_map(|c| match c {
+                Change::Entity(id) | Change::Deleted(id) => Some(*id),
+                Change::Edge(from, _, _) => Some(*from),
+                Change::Permissions => None,
+            })
+            .collect();
+        Some(changed.len() as f64 / self.graph.node_count().max(1) as f64)
+    }
+
+    /**
+     * Ids of entities changed by the mutations applied at `seq`, including both ends of edges
+     */
+    pub fn changed_ids_at(&self, seq: u64) -> HashSet<Uuid> {
+        self.changelog
+            .changes
+            .get(&seq)
+            .into_iter()
+            .flatten()
+            .flat_map(|c| match c {
+                Change::Entity(id) | Change::Deleted(id) => vec![*id],
+                Change::Edge(from, to, _) => vec![*from, *to],
+                Change::Permissions => vec![],
+            })
+            .collect()
+    }
+
+    /**
+     * Collect all entities and edges changed after `seq`, `None` if the changelog doesn't cover `seq`
+     * or permissions have changed since, a full snapshot is needed then
+     */
+    pub fn take_delta_since(&self, seq: u64) -> Option<RegistryDelta<EntityProp>> {
+        if !self.changelog.covers(seq) {
+            return None;
+        }
+        let mut entity_ids: Vec<Uuid> = vec![];
+        let mut edges: Vec<Edge> = vec![];
+        let mut removed_edges: Vec<Edge> = vec![];
+        let mut deleted: Vec<Uuid> = vec![];
+        for change in self.changelog.since(seq) {
+            match change {
+                Change::Entity(id) => {
+                    if !entity_ids.contains(id) {
+                        entity_ids.push(*id)
+                    }
+                }
+                Change::Edge(from, to, edge_type) => match self.get_edge(*from, *to, *edge_type) {
+                    Some(edge) => {
+                        if !edges.contains(&edge) {
+                            edges.push(edge)
+                        }
+                    }
+                    None => {
+                        // The edge has been removed since
+                        let edge = Edge {
+                            edge_type: *edge_type,
+                            from: *from,
+                            to: *to,
+                            attributes: Default::default(),
+                        };
+                        if !removed_edges.contains(&edge) {
+                            removed_edges.push(edge)
+                        }
+                    }
+                },
+                Change::Deleted(id) => {
+                    if !deleted.contains(id) {
+                        deleted.push(*id)
+                    }
+                }
+                Change::Permissions => {}
+            }
+        }
+        Some(RegistryDelta {
+            since: seq,
+            entities: entity_ids
+                .into_iter()
+                .filter_map(|id| self.get_entity_by_id(id))
+                .collect(),
+            edges: edges
+                .into_iter()
+                .filter(|e| !deleted.contains(&e.from) && !deleted.contains(&e.to))
+                .collect(),
+            // Edges of deleted entities must be removed before deleting them
+            removed_edges,
+            deleted,
+        })
+    }
+
+    /**
+     * Fold a delta taken from another registry into this one
+     */
+    pub async fn apply_delta(
+        &mut self,
+        delta: RegistryDelta<EntityProp>,
+    ) -> Result<(), RegistryError> {
+        let mut ids = vec![];
+        for e in delta.entities {
+            let id = e.id;
+            match self.node_id_map.get(&id).copied() {
+                Some(idx) => {
+                    // Entity has been updated
+                    if let Some(w) = self.graph.node_weight(idx) {
+                        if w.qualified_name != e.qualified_name {
+                            // Entity has been renamed
+                            let (old_name, old_version) = (w.qualified_name.clone(), w.version);
+                            self.remove_name(&old_name, old_version);
+                            self.add_name(&e.qualified_name, e.version, id);
+                        }
+                    }
+                    self.remove_key_aliases(id);
+                    if let Some(w) = self.graph.node_weight_mut(idx) {
+                        *w = e;
+                    }
+                    self.add_key_aliases(id);
+                    self.changelog.record(Change::Entity(id));
+                }
+                None => {
+                    self.insert_entity(id, e.entity_type, e.name, e.qualified_name, e.properties)
+                        .await?;
+                }
+            }
+            ids.push(id);
+        }
+        for e in delta.edges {
+            self.connect_with_attributes(e.from, e.to, e.edge_type, e.attributes)
+                .await?;
+        }
+        for e in delta.removed_edges {
+            if self.get_entity(e.from).is_ok() && self.get_entity(e.to).is_ok() {
+                self.disconnect(e.from, e.to, e.edge_type).await?;
+            }
+        }
+        for id in delta.deleted {
+            if self.get_entity(id).is_ok() {
+                self.delete_entity_by_id(id).await?;
+            }
+        }
+        self.index_entities_bulk(ids.into_iter())?;
+        Ok(())
+    }
+}
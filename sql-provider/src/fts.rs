@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{collections::HashSet, fmt::Debug, str::FromStr};
 
 use log::{debug, warn};
 use regex::Regex;
@@ -6,13 +6,17 @@ use registry_provider::*;
 use tantivy::{
     collector::TopDocs,
     doc,
-    query::{BooleanQuery, Query, QueryParser, TermQuery},
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RegexQuery, TermQuery},
     schema::{
         Cardinality, Field, IndexRecordOption, NumericOptions, Schema, TextFieldIndexing, STRING,
         TEXT,
     },
+    tokenizer::{
+        BoxTokenStream, LowerCaser, RemoveLongFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
+    },
     Index, IndexReader, IndexWriter, ReloadPolicy, Term,
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -54,6 +58,383 @@ pub enum FtsError {
 
     #[error(transparent)]
     QueryParseError(#[from] tantivy::query::QueryParserError),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+}
+
+/**
+ * Fields can be used in the query as `field:term`
+ */
+const QUERY_FIELDS: [&str; 4] = ["name", "tags", "type", "description"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum QueryToken {
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+    Word(String),
+}
+
+impl std::fmt::Display for QueryToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryToken::And => f.write_str("'AND'"),
+            QueryToken::Or => f.write_str("'OR'"),
+            QueryToken::Not => f.write_str("'NOT'"),
+            QueryToken::LeftParen => f.write_str("'('"),
+            QueryToken::RightParen => f.write_str("')'"),
+            QueryToken::Word(w) => write!(f, "'{}'", w),
+        }
+    }
+}
+
+/**
+ * Split the query into words, parentheses and operators, quoted text is kept in one word
+ */
+fn tokenize_query(q: &str) -> Result<Vec<QueryToken>, FtsError> {
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut chars = q.chars();
+    let push_word = |word: &mut String, tokens: &mut Vec<QueryToken>| {
+        if !word.is_empty() {
+            tokens.push(match word.as_str() {
+                "AND" => QueryToken::And,
+                "OR" => QueryToken::Or,
+                "NOT" => QueryToken::Not,
+                _ => QueryToken::Word(word.to_owned()),
+            });
+            word.clear();
+        }
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                word.push(c);
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            word.push('"');
+                            break;
+                        }
+                        Some(c) => word.push(c),
+                        None => {
+                            return Err(FtsError::InvalidQuery(format!(
+                                "unterminated quote in '{}'",
+                                word
+                            )))
+                        }
+                    }
+                }
+            }
+            '(' | ')' => {
+                push_word(&mut word, &mut tokens);
+                tokens.push(if c == '(' {
+                    QueryToken::LeftParen
+                } else {
+                    QueryToken::RightParen
+                });
+            }
+            c if c.is_whitespace() => push_word(&mut word, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    push_word(&mut word, &mut tokens);
+    Ok(tokens)
+}
+
+/**
+ * Recursive descent parser of the boolean query DSL
+ * `a b` and `a OR b` match either term, `a AND b` matches both, `NOT a` excludes `a` from the enclosing group,
+ * terms can be restricted to a field with `field:term`
+ */
+struct QueryDslParser<'a> {
+    fts: &'a FtsIndex,
+    tokens: Vec<QueryToken>,
+    pos: usize,
+}
+
+impl<'a> QueryDslParser<'a> {
+    fn parse(fts: &'a FtsIndex, q: &str) -> Result<Box<dyn Query>, FtsError> {
+        let mut parser = Self {
+            fts,
+            tokens: tokenize_query(q)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Ok(Box::new(BooleanQuery::new(vec![])));
+        }
+        let (negated, query) = parser.parse_or()?;
+        if let Some(t) = parser.peek() {
+            return Err(FtsError::InvalidQuery(format!("unexpected {}", t)));
+        }
+        Ok(if negated {
+            exclude(vec![], vec![query])
+        } else {
+            query
+        })
+    }
+
+    fn peek(&self) -> Option<&QueryToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<(bool, Box<dyn Query>), FtsError> {
+        let mut clauses = vec![self.parse_and()?];
+        loop {
+            match self.peek() {
+                None | Some(QueryToken::RightParen) => break,
+                Some(QueryToken::Or) => {
+                    self.pos += 1;
+                }
+                _ => {}
+            }
+            clauses.push(self.parse_and()?);
+        }
+        Ok(combine(Occur::Should, clauses))
+    }
+
+    fn parse_and(&mut self) -> Result<(bool, Box<dyn Query>), FtsError> {
+        let mut clauses = vec![self.parse_unary()?];
+        while self.peek() == Some(&QueryToken::And) {
+            self.pos += 1;
+            clauses.push(self.parse_unary()?);
+        }
+        Ok(combine(Occur::Must, clauses))
+    }
+
+    fn parse_unary(&mut self) -> Result<(bool, Box<dyn Query>), FtsError> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(QueryToken::Not) => {
+                let (negated, query) = self.parse_unary()?;
+                Ok((!negated, query))
+            }
+            Some(QueryToken::LeftParen) => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&QueryToken::RightParen) {
+                    return Err(FtsError::InvalidQuery("missing ')'".to_string()));
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Some(QueryToken::Word(word)) => Ok((false, self.parse_term(&word)?)),
+            Some(t) => Err(FtsError::InvalidQuery(format!(
+                "expecting a term but got {}",
+                t
+            ))),
+            None => Err(FtsError::InvalidQuery(
+                "query ends unexpectedly".to_string(),
+            )),
+        }
+    }
+
+    fn parse_term(&self, word: &str) -> Result<Box<dyn Query>, FtsError> {
+        let (fields, term) = match word.split_once(':') {
+            Some((field, term)) if !field.starts_with('"') => {
+                let field = match field {
+                    "name" => self.fts.name_field,
+                    "tags" => self.fts.tags_field,
+                    "type" => self.fts.type_text_field,
+                    "description" => self.fts.description_field,
+                    _ => {
+                        return Err(FtsError::InvalidQuery(format!(
+                            "unknown field '{}', allowed fields are {}",
+                            field,
+                            QUERY_FIELDS.join(", ")
+                        )))
+                    }
+                };
+                if term.is_empty() {
+                    return Err(FtsError::InvalidQuery(format!(
+                        "missing term after '{}'",
+                        word
+                    )));
+                }
+                (vec![field], term)
+            }
+            _ => (
+                vec![self.fts.name_field, self.fts.id_field, self.fts.body_field],
+                word,
+            ),
+        };
+        let query_parser = QueryParser::for_index(&self.fts.index, fields);
+        Ok(match query_parser.parse_query(term) {
+            Ok(q) => q,
+            Err(e) => {
+                warn!("Invalid term, error: {:?}", e);
+                // Clean all special syntax from the term when tantivy cannot parse it
+                query_parser.parse_query(&self.fts.cleaner.replace_all(term, " "))?
+            }
+        })
+    }
+}
+
+/**
+ * Combine clauses with the occur, negated clauses are excluded from the result
+ * Returns the only clause as is so the negation is kept for the enclosing group
+ */
+fn combine(occur: Occur, mut clauses: Vec<(bool, Box<dyn Query>)>) -> (bool, Box<dyn Query>) {
+    if clauses.len() == 1 {
+        return clauses.remove(0);
+    }
+    let (negated, positive): (Vec<_>, Vec<_>) = clauses.into_iter().partition(|(n, _)| *n);
+    let positive: Vec<Box<dyn Query>> = positive.into_iter().map(|(_, q)| q).collect();
+    let negated: Vec<Box<dyn Query>> = negated.into_iter().map(|(_, q)| q).collect();
+    if positive.is_empty() {
+        return (false, exclude(vec![], negated));
+    }
+    let positive = positive.into_iter().map(|q| (occur, q)).collect();
+    (false, exclude(positive, negated))
+}
+
+/**
+ * Match `positive` but not `negated`, matches everything else if `positive` is empty
+ */
+fn exclude(
+    mut positive: Vec<(Occur, Box<dyn Query>)>,
+    negated: Vec<Box<dyn Query>>,
+) -> Box<dyn Query> {
+    if positive.is_empty() {
+        positive.push((Occur::Must, Box::new(AllQuery)));
+    }
+    positive.extend(negated.into_iter().map(|q| (Occur::MustNot, q)));
+    Box::new(BooleanQuery::new(positive))
+}
+
+/**
+ * Split camel case type name into words, e.g. `DerivedFeature` to `derived feature derivedfeature`
+ */
+fn type_text(t: &str) -> String {
+    let mut words = String::new();
+    for c in t.chars() {
+        if c.is_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        words.extend(c.to_lowercase());
+    }
+    format!("{} {}", words, t.to_lowercase())
+}
+
+/**
+ * Tokenizer used to index names, descriptions and bodies
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerConfig {
+    /**
+     * English stemming tokenizer, splits on all punctuations including underscores
+     */
+    Default,
+    /**
+     * Keeps snake_case identifiers as single terms, followed by their subtokens
+     */
+    Identifier,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        TokenizerConfig::Default
+    }
+}
+
+impl FromStr for TokenizerConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(TokenizerConfig::Default),
+            "identifier" => Ok(TokenizerConfig::Identifier),
+            _ => Err(format!(
+                "unknown tokenizer '{}', allowed values are default, identifier",
+                s
+            )),
+        }
+    }
+}
+
+impl TokenizerConfig {
+    fn tokenizer_name(self) -> &'static str {
+        match self {
+            TokenizerConfig::Default => "en_stem",
+            TokenizerConfig::Identifier => "identifier",
+        }
+    }
+}
+
+/**
+ * Splits text on characters other than alphanumerics and underscores
+ * A word with underscores is emitted as is, then each part between underscores is emitted as well
+ * e.g. `cast_float(fare_amount)` to `cast_float cast float fare_amount fare amount`
+ */
+#[derive(Clone)]
+struct IdentifierTokenizer;
+
+struct IdentifierTokenStream {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Tokenizer for IdentifierTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let mut tokens: Vec<Token> = vec![];
+        let mut push = |offset_from: usize, word: &str| {
+            tokens.push(Token {
+                offset_from,
+                offset_to: offset_from + word.len(),
+                position: tokens.len(),
+                text: word.to_string(),
+                position_length: 1,
+            })
+        };
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let mut start: Option<usize> = None;
+        for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            match (start, is_word_char(c)) {
+                (None, true) => start = Some(i),
+                (Some(from), false) => {
+                    let word = &text[from..i];
+                    if word.chars().any(|c| c.is_alphanumeric()) {
+                        push(from, word);
+                        if word.contains('_') {
+                            let mut offset = from;
+                            for part in word.split('_') {
+                                if !part.is_empty() {
+                                    push(offset, part);
+                                }
+                                offset += part.len() + 1;
+                            }
+                        }
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        BoxTokenStream::from(IdentifierTokenStream { tokens, index: 0 })
+    }
+}
+
+impl TokenStream for IdentifierTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.index < self.tokens.len() {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.index - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.index - 1]
+    }
 }
 
 pub struct FtsIndex {
@@ -67,7 +448,14 @@ pub struct FtsIndex {
     type_field: Field,
     body_field: Field,
     name_score_field: Field,
+    suggest_field: Field,
+    qualified_name_field: Field,
+    tags_field: Field,
+    type_text_field: Field,
+    description_field: Field,
+    tokenizer: TokenizerConfig,
     enabled: bool,
+    in_transaction: bool,
     cleaner: Regex,
 }
 
@@ -82,15 +470,22 @@ impl Debug for FtsIndex {
             .field("type_field", &self.type_field)
             .field("body_field", &self.body_field)
             .field("name_score_field", &self.body_field)
+            .field("suggest_field", &self.suggest_field)
+            .field("qualified_name_field", &self.qualified_name_field)
+            .field("tags_field", &self.tags_field)
+            .field("type_text_field", &self.type_text_field)
+            .field("description_field", &self.description_field)
+            .field("tokenizer", &self.tokenizer)
             .field("enabled", &self.enabled)
+            .field("in_transaction", &self.in_transaction)
             .finish()
     }
 }
 
 impl FtsIndex {
-    pub fn new() -> Self {
+    pub fn new(tokenizer: TokenizerConfig) -> Self {
         let indexing_option = TextFieldIndexing::default()
-            .set_tokenizer("en_stem")
+            .set_tokenizer(tokenizer.tokenizer_name())
             .set_index_option(IndexRecordOption::WithFreqsAndPositions);
         let mut schema_builder = Schema::builder();
         schema_builder.add_text_field("name", TEXT.set_indexing_options(indexing_option.clone()));
@@ -105,6 +500,16 @@ impl FtsIndex {
             "name_score",
             NumericOptions::default().set_fast(Cardinality::SingleValue),
         );
+        // Lowercased names, untokenized so they can be matched by prefix
+        schema_builder.add_text_field("suggest", STRING);
+        schema_builder.add_text_field("qualified_name", STRING.set_stored());
+        // Fields only used by `field:term` in the query
+        schema_builder.add_text_field("tags", TEXT);
+        schema_builder.add_text_field("type_text", TEXT);
+        schema_builder.add_text_field(
+            "description",
+            TEXT.set_indexing_options(indexing_option.clone()),
+        );
         let schema = schema_builder.build();
         let name_field = schema.get_field("name").unwrap();
         let id_field = schema.get_field("id").unwrap();
@@ -112,7 +517,18 @@ impl FtsIndex {
         let type_field = schema.get_field("type").unwrap();
         let body_field = schema.get_field("body").unwrap();
         let name_score_field = schema.get_field("name_score").unwrap();
+        let suggest_field = schema.get_field("suggest").unwrap();
+        let qualified_name_field = schema.get_field("qualified_name").unwrap();
+        let tags_field = schema.get_field("tags").unwrap();
+        let type_text_field = schema.get_field("type_text").unwrap();
+        let description_field = schema.get_field("description").unwrap();
         let index = Index::create_in_ram(schema.clone());
+        index.tokenizers().register(
+            TokenizerConfig::Identifier.tokenizer_name(),
+            TextAnalyzer::from(IdentifierTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser),
+        );
         Self {
             _schema: schema,
             reader: index
@@ -128,7 +544,14 @@ impl FtsIndex {
             type_field,
             body_field,
             name_score_field,
+            suggest_field,
+            qualified_name_field,
+            tags_field,
+            type_text_field,
+            description_field,
+            tokenizer,
             enabled: true,
+            in_transaction: false,
             cleaner: Regex::new(
                 r"([:+\(\)\[\]\{\}])|(\s[aA][nN][dD]\s)|(\s[oO][rR]\s)|(\s[tT][oO]\s)",
             )
@@ -136,28 +559,119 @@ impl FtsIndex {
         }
     }
 
+    pub fn tokenizer(&self) -> TokenizerConfig {
+        self.tokenizer
+    }
+
     #[allow(dead_code)]
     pub fn enable(&mut self, enabled: bool) {
         self.enabled = enabled;
     }
 
+    /**
+     * The writer of the index, opened on first use
+     */
+    fn writer(&mut self) -> Result<&mut IndexWriter, FtsError> {
+        let writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => self.index.writer(30_000_000)?,
+        };
+        Ok(self.writer.insert(writer))
+    }
+
     pub fn add_doc<T: ToDoc>(&mut self, d: &T, scopes: Vec<String>) -> Result<(), FtsError> {
-        if self.writer.is_none() {
-            self.writer = Some(self.index.writer(30_000_000).unwrap());
-        }
-        let doc = doc!(
+        let mut doc = doc!(
             self.name_field => d.get_name(),
             self.id_field => d.get_id(),
             self.scopes_field => scopes.join(" "),
             self.type_field => d.get_type(),
             self.body_field => d.get_body(),
             self.name_score_field => str_score(&d.get_name()),
+            self.qualified_name_field => d.get_qualified_name(),
+            self.tags_field => d.get_tags(),
+            self.type_text_field => type_text(&d.get_type()),
+            self.description_field => d.get_description(),
         );
-        self.writer.as_ref().unwrap().add_document(doc)?;
+        for name in d.get_suggest_names() {
+            doc.add_text(self.suggest_field, name.to_lowercase());
+        }
+        self.writer()?.add_document(doc)?;
         Ok(())
     }
 
+    /**
+     * Add all docs in one transaction, much faster than committing them one by one
+     * Nothing is added if any of them fails
+     * Inside an outer transaction, docs are left for the outer `commit` or `rollback`
+     */
+    pub fn add_docs_bulk<T: ToDoc>(&mut self, docs: &[(&T, Vec<String>)]) -> Result<(), FtsError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.in_transaction {
+            return docs
+                .iter()
+                .try_for_each(|(d, scopes)| self.add_doc(*d, scopes.to_owned()));
+        }
+        self.begin()?;
+        let result = docs
+            .iter()
+            .try_for_each(|(d, scopes)| self.add_doc(*d, scopes.to_owned()))
+            .and_then(|_| self.commit());
+        if result.is_err() {
+            if let Err(e) = self.rollback() {
+                warn!("Failed to roll back FTS index, error: {:?}", e);
+            }
+        }
+        result
+    }
+
+    /**
+     * Start a transaction, `index` doesn't commit until `commit` is called,
+     * and `rollback` discards everything since the start
+     */
+    pub fn begin(&mut self) -> Result<(), FtsError> {
+        self.writer()?;
+        self.in_transaction = true;
+        Ok(())
+    }
+
+    /**
+     * Discard all uncommitted changes and end the transaction
+     */
+    pub fn rollback(&mut self) -> Result<(), FtsError> {
+        self.in_transaction = false;
+        if let Some(mut writer) = self.writer.take() {
+            writer.rollback()?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Remove the doc with the id, the deletion is visible after next commit
+     */
+    pub fn delete_doc(&mut self, id: &str) -> Result<(), FtsError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let term = Term::from_field_text(self.id_field, id);
+        self.writer()?.delete_term(term);
+        Ok(())
+    }
+
+    /**
+     * Remove all docs and commit
+     */
+    pub fn clear(&mut self) -> Result<(), FtsError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.writer()?.delete_all_documents()?;
+        self.commit()
+    }
+
     pub fn commit(&mut self) -> Result<(), FtsError> {
+        self.in_transaction = false;
         if let Some(writer) = &mut self.writer {
             writer.commit()?;
         }
@@ -174,10 +688,17 @@ impl FtsIndex {
             return Ok(());
         }
         self.add_doc(doc, scopes)?;
-        self.commit()?;
+        // Docs added in a transaction are committed all together
+        if !self.in_transaction {
+            self.commit()?;
+        }
         Ok(())
     }
 
+    pub fn num_docs(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
     pub fn search(
         &self,
         q: &str,
@@ -188,20 +709,7 @@ impl FtsIndex {
     ) -> Result<Vec<Uuid>, FtsError> {
         //
         let searcher = self.reader.searcher();
-        let query_parser = QueryParser::for_index(
-            &self.index,
-            vec![self.name_field, self.id_field, self.body_field],
-        );
-        let parsed_q: Box<dyn Query> = match query_parser.parse_query(q) {
-            Ok(q) => q,
-            Err(e) => {
-                warn!("Invalid query, error: {:?}", e);
-                // Tantivy query parser may return error
-                // @see https://github.com/quickwit-oss/tantivy/issues/5
-                // Clean all special syntax from the query string when it happens
-                query_parser.parse_query(&self.cleaner.replace_all(q, " "))?
-            }
-        };
+        let parsed_q: Box<dyn Query> = QueryDslParser::parse(self, q)?;
         let query = if types.is_empty() {
             match scope {
                 Some(id) => Box::new(BooleanQuery::intersection(vec![
@@ -262,9 +770,72 @@ impl FtsIndex {
     }
 }
 
+impl FtsIndex {
+    /**
+     * Get distinct qualified names of the docs whose name starts with `prefix`, case-insensitive
+     * Only docs in `scopes` are returned if it's set, a doc is in the scope if the doc or its container has the id
+     */
+    pub fn suggest(
+        &self,
+        prefix: &str,
+        scopes: Option<HashSet<String>>,
+        limit: usize,
+    ) -> Result<Vec<String>, FtsError> {
+        if prefix.is_empty() || limit == 0 {
+            return Ok(vec![]);
+        }
+        let searcher = self.reader.searcher();
+        let prefix_query: Box<dyn Query> = Box::new(RegexQuery::from_pattern(
+            &format!("{}.*", regex::escape(&prefix.to_lowercase())),
+            self.suggest_field,
+        )?);
+        let query: Box<dyn Query> = match scopes {
+            Some(scopes) => {
+                let scope_queries = scopes
+                    .iter()
+                    .flat_map(|s| {
+                        [self.scopes_field, self.id_field].map(|f| -> Box<dyn Query> {
+                            Box::new(TermQuery::new(
+                                Term::from_field_text(f, s),
+                                IndexRecordOption::Basic,
+                            ))
+                        })
+                    })
+                    .collect();
+                Box::new(BooleanQuery::intersection(vec![
+                    prefix_query,
+                    Box::new(BooleanQuery::union(scope_queries)),
+                ]))
+            }
+            None => prefix_query,
+        };
+        // Multiple versions share the same qualified name, fetch more to fill the limit after dedup
+        let top_docs = searcher.search(
+            &query,
+            &TopDocs::with_limit(limit * 4).order_by_u64_field(self.name_score_field),
+        )?;
+        let mut names: Vec<String> = vec![];
+        for (_, addr) in top_docs {
+            let name = searcher
+                .doc(addr)?
+                .get_first(self.qualified_name_field)
+                .and_then(|v| v.as_text().map(ToString::to_string));
+            if let Some(name) = name {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            if names.len() >= limit {
+                break;
+            }
+        }
+        Ok(names)
+    }
+}
+
 impl Default for FtsIndex {
     fn default() -> Self {
-        Self::new()
+        Self::new(Default::default())
     }
 }
 
@@ -298,11 +869,17 @@ mod tests {
         fn get_body(&self) -> String {
             self.body.to_owned()
         }
+        fn get_suggest_names(&self) -> Vec<String> {
+            vec![self.name.to_owned()]
+        }
+        fn get_qualified_name(&self) -> String {
+            self.name.to_owned()
+        }
     }
     #[test]
     fn scoped_search() {
         init_logger();
-        let mut fts = FtsIndex::new();
+        let mut fts = FtsIndex::default();
         let mut docs: HashMap<Uuid, A> = HashMap::new();
         for i in 1..11 {
             let id = Uuid::new_v4();
@@ -336,6 +913,287 @@ mod tests {
         }
     }
 
+    #[test]
+    fn suggest() {
+        init_logger();
+        let mut fts = FtsIndex::default();
+        for (i, name) in ["f_location_avg_fare", "f_location_max_fare", "f_trip_distance"]
+            .iter()
+            .enumerate()
+        {
+            let scopes = vec![format!("scope-{}", i % 2)];
+            let a = A {
+                name: name.to_string(),
+                id: Uuid::new_v4().to_string(),
+                scopes: scopes.clone(),
+                type_: "SomeType".to_string(),
+                body: Default::default(),
+            };
+            fts.add_doc(&a, scopes).unwrap();
+        }
+        fts.commit().unwrap();
+
+        let names = fts.suggest("f_loc", None, 10).unwrap();
+        assert!(names.contains(&"f_location_avg_fare".to_string()));
+        assert!(names.contains(&"f_location_max_fare".to_string()));
+        assert_eq!(names.len(), 2);
+        // Case-insensitive
+        assert_eq!(fts.suggest("F_LOC", None, 10).unwrap().len(), 2);
+        assert_eq!(fts.suggest("f_loc", None, 1).unwrap().len(), 1);
+        assert!(fts.suggest("unrelated", None, 10).unwrap().is_empty());
+        // Only `f_location_avg_fare` is in `scope-0`
+        assert_eq!(
+            fts.suggest("f_loc", Some(set!["scope-0".to_string()]), 10)
+                .unwrap(),
+            vec!["f_location_avg_fare".to_string()]
+        );
+    }
+
+    fn expression_index(tokenizer: TokenizerConfig) -> (FtsIndex, HashMap<String, Uuid>) {
+        let mut fts = FtsIndex::new(tokenizer);
+        let mut ids = HashMap::new();
+        for (name, body) in [
+            ("f_trip_fare", "cast_float(fare_amount)"),
+            ("f_trip_total", "fare amount of the trip"),
+        ] {
+            let id = Uuid::new_v4();
+            let a = A {
+                name: name.to_string(),
+                id: id.to_string(),
+                scopes: vec![],
+                type_: "AnchorFeature".to_string(),
+                body: body.to_string(),
+            };
+            fts.add_doc(&a, vec![]).unwrap();
+            ids.insert(name.to_string(), id);
+        }
+        fts.commit().unwrap();
+        (fts, ids)
+    }
+
+    #[test]
+    fn default_tokenizer() {
+        init_logger();
+        let (fts, _) = expression_index(TokenizerConfig::Default);
+        // `fare_amount` is split into `fare` and `amount`, which also appear in plain text
+        let found = fts
+            .search("fare_amount", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn identifier_tokenizer() {
+        init_logger();
+        let (fts, ids) = expression_index(TokenizerConfig::Identifier);
+        // `fare_amount` is kept as a single term
+        let found = fts
+            .search("fare_amount", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found, vec![ids["f_trip_fare"]]);
+        // Subtokens are still searchable
+        let found = fts.search("fare", HashSet::new(), None, 10, 0).unwrap();
+        assert_eq!(found.len(), 2);
+        let found = fts.search("float", HashSet::new(), None, 10, 0).unwrap();
+        assert_eq!(found, vec![ids["f_trip_fare"]]);
+    }
+
+    fn dsl_index() -> (FtsIndex, HashMap<String, Uuid>) {
+        let mut fts = FtsIndex::default();
+        let mut ids = HashMap::new();
+        for (name, type_) in [
+            ("trip distance", "AnchorFeature"),
+            ("trip fare", "DerivedFeature"),
+            ("location fare", "DerivedFeature"),
+        ] {
+            let id = Uuid::new_v4();
+            let a = A {
+                name: name.to_string(),
+                id: id.to_string(),
+                scopes: vec![],
+                type_: type_.to_string(),
+                body: Default::default(),
+            };
+            fts.add_doc(&a, vec![]).unwrap();
+            ids.insert(name.to_string(), id);
+        }
+        fts.commit().unwrap();
+        (fts, ids)
+    }
+
+    #[test]
+    fn dsl_and() {
+        init_logger();
+        let (fts, ids) = dsl_index();
+        let found = fts
+            .search("name:trip AND type:derived", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found, vec![ids["trip fare"]]);
+        // Without operator terms are combined with OR
+        let found = fts
+            .search("name:distance location", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn dsl_not() {
+        init_logger();
+        let (fts, ids) = dsl_index();
+        let found = fts
+            .search("fare NOT name:location", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found, vec![ids["trip fare"]]);
+        let found = fts
+            .search("NOT type:anchorfeature", HashSet::new(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(!found.contains(&ids["trip distance"]));
+    }
+
+    #[test]
+    fn dsl_invalid() {
+        let (fts, _) = dsl_index();
+        for q in ["name:trip AND", "(name:trip", "name:trip)", "team:fraud", "name:", "\"trip"] {
+            assert!(matches!(
+                fts.search(q, HashSet::new(), None, 10, 0),
+                Err(FtsError::InvalidQuery(_))
+            ));
+        }
+    }
+
+    fn gen_docs(n: usize) -> Vec<(A, Vec<String>)> {
+        (0..n)
+            .map(|i| {
+                let scopes = vec![format!("scope-{}", i % 2), format!("scope-{}", i % 5)];
+                (
+                    A {
+                        name: format!("some name{}", i),
+                        id: Uuid::new_v4().to_string(),
+                        scopes: scopes.clone(),
+                        type_: format!("SomeType{}", i % 2),
+                        body: format!("This is the body of name{}", i),
+                    },
+                    scopes,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bulk_parity() {
+        init_logger();
+        let docs = gen_docs(50);
+        let mut incremental = FtsIndex::default();
+        for (d, scopes) in &docs {
+            incremental.index(d, scopes.clone()).unwrap();
+        }
+        let mut bulk = FtsIndex::default();
+        bulk.add_docs_bulk(
+            &docs
+                .iter()
+                .map(|(d, s)| (d, s.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        for (q, types, scope) in [
+            ("body", HashSet::new(), None),
+            ("name1", HashSet::new(), None),
+            ("body", set!["SomeType1".to_string()], Some("scope-2".to_string())),
+        ] {
+            assert_eq!(
+                incremental
+                    .search(q, types.clone(), scope.clone(), 100, 0)
+                    .unwrap(),
+                bulk.search(q, types, scope, 100, 0).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn transaction_parity() {
+        init_logger();
+        let docs = gen_docs(50);
+        let mut per_doc = FtsIndex::default();
+        for (d, scopes) in &docs {
+            per_doc.index(d, scopes.clone()).unwrap();
+        }
+        let mut batched = FtsIndex::default();
+        batched.begin().unwrap();
+        for (d, scopes) in &docs {
+            batched.index(d, scopes.clone()).unwrap();
+        }
+        // Nothing is visible before the commit
+        assert_eq!(batched.num_docs(), 0);
+        batched.commit().unwrap();
+        assert_eq!(batched.num_docs(), per_doc.num_docs());
+        for (q, types, scope) in [
+            ("body", HashSet::new(), None),
+            ("name1", HashSet::new(), None),
+            ("body", set!["SomeType1".to_string()], Some("scope-2".to_string())),
+        ] {
+            assert_eq!(
+                per_doc.search(q, types.clone(), scope.clone(), 100, 0).unwrap(),
+                batched.search(q, types, scope, 100, 0).unwrap()
+            );
+        }
+
+        // Rolled back docs are discarded, committed ones are kept
+        batched.begin().unwrap();
+        for (d, scopes) in gen_docs(10).iter() {
+            batched.index(d, scopes.clone()).unwrap();
+        }
+        batched.rollback().unwrap();
+        batched.commit().unwrap();
+        assert_eq!(batched.num_docs(), 50);
+        // Out of the transaction, docs are committed immediately
+        let (d, scopes) = &gen_docs(1)[0];
+        batched.index(d, scopes.clone()).unwrap();
+        assert_eq!(batched.num_docs(), 51);
+    }
+
+    #[test]
+    fn bulk_in_transaction() {
+        init_logger();
+        let docs = gen_docs(20);
+        let docs: Vec<_> = docs.iter().map(|(d, s)| (d, s.clone())).collect();
+        let mut fts = FtsIndex::default();
+        // Bulk adding inside a transaction is left to the outer commit or rollback
+        fts.begin().unwrap();
+        fts.add_docs_bulk(&docs[..10]).unwrap();
+        assert_eq!(fts.num_docs(), 0);
+        fts.rollback().unwrap();
+        assert_eq!(fts.num_docs(), 0);
+
+        fts.begin().unwrap();
+        fts.add_docs_bulk(&docs[..10]).unwrap();
+        fts.add_docs_bulk(&docs[10..]).unwrap();
+        fts.commit().unwrap();
+        assert_eq!(fts.num_docs(), 20);
+    }
+
+    #[test]
+    #[ignore = "too slow"]
+    fn bulk_many_docs() {
+        let docs = gen_docs(10000);
+        let start = std::time::Instant::now();
+        let mut fts = FtsIndex::default();
+        fts.add_docs_bulk(
+            &docs
+                .iter()
+                .map(|(d, s)| (d, s.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        println!("Took {} ms", start.elapsed().as_millis());
+        assert_eq!(
+            fts.search("body", HashSet::new(), None, 20000, 0)
+                .unwrap()
+                .len(),
+            10000
+        );
+    }
+
     #[test]
     fn cleaner() {
         let cleaner =
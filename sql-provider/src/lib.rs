@@ -1,23 +1,30 @@
 mod database;
 mod db_registry;
+mod delta;
 mod fts;
 mod rbac_map;
 mod serdes;
+mod stats;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 use async_trait::async_trait;
-pub use database::{attach_storage, load_content};
+pub use database::{attach_storage, load_content, reattach_storage, SqlDialect};
 pub use db_registry::Registry;
+pub use delta::RegistryDelta;
+pub use fts::TokenizerConfig;
 use log::{debug, warn};
 use registry_provider::{
     extract_version, AnchorDef, AnchorFeatureDef, Credential, DerivedFeatureDef, Edge, EdgeType,
-    Entity, EntityPropMutator, EntityType, Permission, ProjectDef, RbacError, RbacProvider,
-    RbacRecord, RegistryError, RegistryProvider, Resource, SourceDef, ToDocString,
+    Entity, EntityLink, EntityPropMutator, EntityType, Permission, ProjectDef, RbacError,
+    RbacProvider, RbacRecord, RegistryError, RegistryProvider, Resource, SourceDef, ToDocString,
 };
+pub use stats::RegistryStats;
 use uuid::Uuid;
 
+use crate::delta::Change;
+
 #[async_trait]
 impl<EntityProp> RegistryProvider<EntityProp> for Registry<EntityProp>
 where
@@ -68,7 +75,6 @@ where
     ) -> Result<Entity<EntityProp>, RegistryError> {
         let (qualified_name, version) = extract_version(qualified_name);
         self.get_entity_by_name(qualified_name, version)
-            .ok_or_else(|| RegistryError::EntityNotFound(qualified_name.to_string()))
     }
 
     /**
@@ -90,6 +96,7 @@ where
      */
     fn get_entity_id_by_qualified_name(&self, qualified_name: &str) -> Result<Uuid, RegistryError> {
         let (qualified_name, version) = extract_version(qualified_name);
+        let qualified_name = self.resolve_qualified_name(qualified_name)?;
         self.name_id_map
             .get(qualified_name)
             .and_then(|ids| match version {
@@ -116,6 +123,13 @@ where
             .collect())
     }
 
+    /**
+     * Get all edges with specified connection type starting from the entity
+     */
+    fn get_edges(&self, uuid: Uuid, edge_type: EdgeType) -> Result<Vec<Edge>, RegistryError> {
+        self.get_outgoing_edges(uuid, edge_type)
+    }
+
     /**
      * Traversal graph from `uuid` by following edges with specific edge type
      */
@@ -125,7 +139,64 @@ where
         edge_type: EdgeType,
         size_limit: Option<usize>,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError> {
-        self.bfs_traversal(uuid, size_limit, |_| true, |e| e.edge_type == edge_type)
+        self.bfs_traversal(
+            uuid,
+            size_limit,
+            None,
+            |_| true,
+            |e| edge_type.covers(e.edge_type),
+        )
+        .map(|(entities, edges, _)| (entities, edges))
+    }
+
+    fn bfs_with_limit(
+        &self,
+        uuid: Uuid,
+        edge_type: EdgeType,
+        size_limit: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError> {
+        self.bfs_traversal(
+            uuid,
+            Some(size_limit),
+            self.traversal_deadline(),
+            |_| true,
+            |e| edge_type.covers(e.edge_type),
+        )
+    }
+
+    fn bfs_page(
+        &self,
+        uuid: Uuid,
+        edge_type: EdgeType,
+        offset: usize,
+        size: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError> {
+        Registry::bfs_page(
+            self,
+            uuid,
+            offset,
+            size,
+            self.traversal_deadline(),
+            |_| true,
+            |e| edge_type.covers(e.edge_type),
+        )
+    }
+
+    fn get_source_downstream(
+        &self,
+        source_id: Uuid,
+        size_limit: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool), RegistryError> {
+        Registry::get_source_downstream(self, source_id, Some(size_limit))
+    }
+
+    fn get_source_downstream_page(
+        &self,
+        source_id: Uuid,
+        offset: usize,
+        size: usize,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, bool, bool), RegistryError> {
+        Registry::get_source_downstream_page(self, source_id, offset, size)
     }
 
     /**
@@ -154,6 +225,22 @@ where
             .collect())
     }
 
+    /**
+     * Get entity name suggestions with FTS
+     */
+    fn suggest_names(
+        &self,
+        prefix: &str,
+        scopes: Option<HashSet<Uuid>>,
+        limit: usize,
+    ) -> Result<Vec<String>, RegistryError> {
+        Ok(self.fts_index.suggest(
+            prefix,
+            scopes.map(|s| s.into_iter().map(|id| id.to_string()).collect()),
+            limit,
+        )?)
+    }
+
     /**
      * Get all entities and connections between them under a project
      */
@@ -169,7 +256,12 @@ where
     // Create new project
     async fn new_project(&mut self, definition: &ProjectDef) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
-        let mut prop = EntityProp::new_project(definition)?;
+        let mut prop = EntityProp::new_project(definition, self.now())?;
+        if let Some(existing) = self.check_existing_id(definition.id, EntityType::Project, |e| {
+            e.properties.same_definition(&prop)
+        })? {
+            return Ok(existing);
+        }
         match self.get_all_versions(&definition.qualified_name).last() {
             // It makes no sense to create a new version of a project
             Some(e) => Ok((e.id, e.version)),
@@ -185,6 +277,7 @@ where
                     )
                     .await?;
                 self.index_entity(project_id, true)?;
+                self.flush_external_storage().await?;
                 Ok((project_id, 1))
             }
         }
@@ -197,7 +290,12 @@ where
         definition: &SourceDef,
     ) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
-        let mut prop = EntityProp::new_source(definition)?;
+        let mut prop = EntityProp::new_source(definition, self.now())?;
+        if let Some(existing) = self.check_existing_id(definition.id, EntityType::Source, |e| {
+            e.properties.same_definition(&prop)
+        })? {
+            return Ok(existing);
+        }
 
         for v in self.get_all_versions(&definition.qualified_name) {
             if v.properties == prop {
@@ -221,8 +319,10 @@ where
 
         self.connect(project_id, source_id, EdgeType::Contains)
             .await?;
+        self.touch_entity(project_id, &definition.created_by)?;
 
         self.index_entity(source_id, true)?;
+        self.flush_external_storage().await?;
         Ok((source_id, version))
     }
 
@@ -232,14 +332,24 @@ where
         project_id: Uuid,
         definition: &AnchorDef,
     ) -> Result<(Uuid, u64), RegistryError> {
-        if self.get_entity_by_id(definition.source_id).is_none() {
-            debug!(
-                "Source {} not found, cannot create anchor",
-                definition.source_id
-            );
-            return Err(RegistryError::EntityNotFound(
-                definition.source_id.to_string(),
-            ));
+        for &source_id in &definition.source_ids {
+            if self.get_entity_by_id(source_id).is_none() {
+                debug!("Source {} not found, cannot create anchor", source_id);
+                return Err(RegistryError::EntityNotFound(source_id.to_string()));
+            }
+        }
+
+        let mut prop = EntityProp::new_anchor(definition, self.now())?;
+        if let Some(existing) = self.check_existing_id(definition.id, EntityType::Anchor, |e| {
+            let sources: HashSet<Uuid> = self
+                .get_neighbors(e.id, EdgeType::Consumes)
+                .expect("Data inconsistency detected")
+                .into_iter()
+                .map(|s| s.id)
+                .collect();
+            e.properties.same_definition(&prop) && definition.source_ids == sources
+        })? {
+            return Ok(existing);
         }
 
         if let Some(e) = self
@@ -251,12 +361,13 @@ where
                     e.id, e.qualified_name
                 );
                 // We only check source for conflicts as the anchor is always empty when it's just created
-                let source = self
+                let sources: HashSet<Uuid> = self
                     .get_neighbors(e.id, EdgeType::Consumes)
-                    .expect("Data inconsistency detected");
-                // An anchor has exactly one source
-                assert!(source.len() == 1, "Data inconsistency detected");
-                definition.source_id == source[0].id
+                    .expect("Data inconsistency detected")
+                    .into_iter()
+                    .map(|s| s.id)
+                    .collect();
+                definition.source_ids == sources
             })
         {
             // Found existing anchor with same name and source
@@ -264,7 +375,6 @@ where
         }
 
         // Create new version
-        let mut prop = EntityProp::new_anchor(definition)?;
         let version = self.get_next_version_number(&definition.qualified_name);
         prop.set_version(version);
 
@@ -280,11 +390,15 @@ where
 
         self.connect(project_id, anchor_id, EdgeType::Contains)
             .await?;
+        self.touch_entity(project_id, &definition.created_by)?;
 
-        self.connect(anchor_id, definition.source_id, EdgeType::Consumes)
-            .await?;
+        for &source_id in &definition.source_ids {
+            self.connect(anchor_id, source_id, EdgeType::Consumes)
+                .await?;
+        }
 
         self.index_entity(anchor_id, true)?;
+        self.flush_external_storage().await?;
         Ok((anchor_id, version))
     }
 
@@ -296,7 +410,14 @@ where
         definition: &AnchorFeatureDef,
     ) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
-        let mut prop = EntityProp::new_anchor_feature(definition)?;
+        let mut prop = EntityProp::new_anchor_feature(definition, self.now())?;
+        if let Some(existing) =
+            self.check_existing_id(definition.id, EntityType::AnchorFeature, |e| {
+                e.properties.same_definition(&prop)
+            })?
+        {
+            return Ok(existing);
+        }
 
         if let Some(e) = self
             .get_all_versions(&definition.qualified_name)
@@ -332,6 +453,9 @@ where
 
         self.connect(anchor_id, feature_id, EdgeType::Contains)
             .await?;
+        // Both containers have a new member
+        self.touch_entity(project_id, &definition.created_by)?;
+        self.touch_entity(anchor_id, &definition.created_by)?;
 
         // Anchor feature also consumes source of the anchor
         let sources = self.get_neighbors(anchor_id, EdgeType::Consumes)?;
@@ -340,6 +464,7 @@ where
         }
 
         self.index_entity(feature_id, true)?;
+        self.flush_external_storage().await?;
         Ok((feature_id, version))
     }
 
@@ -349,6 +474,7 @@ where
         project_id: Uuid,
         definition: &DerivedFeatureDef,
     ) -> Result<(Uuid, u64), RegistryError> {
+        // An input listed as both anchor and derived feature gets only one edge
         let input: HashSet<Uuid> = definition
             .input_anchor_features
             .iter()
@@ -356,6 +482,13 @@ where
             .copied()
             .collect();
 
+        if input.contains(&definition.id) {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Derived feature {} cannot be its own input",
+                definition.qualified_name
+            )));
+        }
+
         for id in input.iter() {
             if self.get_entity_by_id(*id).is_none() {
                 debug!(
@@ -366,7 +499,20 @@ where
             }
         }
 
-        let mut prop = EntityProp::new_derived_feature(definition)?;
+        let mut prop = EntityProp::new_derived_feature(definition, self.now())?;
+        if let Some(existing) =
+            self.check_existing_id(definition.id, EntityType::DerivedFeature, |e| {
+                let upstream: HashSet<Uuid> = self
+                    .get_neighbors(e.id, EdgeType::DerivesFrom)
+                    .expect("Data inconsistency detected")
+                    .into_iter()
+                    .map(|e| e.id)
+                    .collect();
+                upstream == input && e.properties.same_definition(&prop)
+            })?
+        {
+            return Ok(existing);
+        }
 
         if let Some(e) = self
             .get_all_versions(&definition.qualified_name)
@@ -378,7 +524,7 @@ where
                 );
                 // Check if input features in the def are same as existing one
                 let upstream: HashSet<Uuid> = self
-                    .get_neighbors(e.id, EdgeType::Consumes)
+                    .get_neighbors(e.id, EdgeType::DerivesFrom)
                     .expect("Data inconsistency detected")
                     .into_iter()
                     .map(|e| e.id)
@@ -401,18 +547,21 @@ where
             )
             .await?;
 
-        self.connect(project_id, feature_id, EdgeType::Contains)
-            .await?;
-
-        for &id in definition
-            .input_anchor_features
-            .iter()
-            .chain(definition.input_derived_features.iter())
-        {
-            self.connect(feature_id, id, EdgeType::Consumes).await?;
-        }
+        let mut input: Vec<Uuid> = input.into_iter().collect();
+        input.sort();
+        let edges = std::iter::once((project_id, feature_id, EdgeType::Contains))
+            .chain(
+                input
+                    .into_iter()
+                    .map(|id| (feature_id, id, EdgeType::DerivesFrom)),
+            )
+            .collect();
+        self.connect_many(edges).await?;
+        self.touch_entity(project_id, &definition.created_by)?;
 
         self.index_entity(feature_id, true)?;
+        // Buffered writes of this entity and its edges go to the external storage in one batch
+        self.flush_external_storage().await?;
         Ok((feature_id, version))
     }
 
@@ -420,6 +569,92 @@ where
         self.delete_entity_by_id(id).await
     }
 
+    async fn delete_source(&mut self, id: Uuid, force: bool) -> Result<(), RegistryError> {
+        Registry::delete_source(self, id, force).await?;
+        self.flush_external_storage().await
+    }
+
+    async fn update_tags(
+        &mut self,
+        id: Uuid,
+        tags: HashMap<String, String>,
+        expected_etag: Option<Uuid>,
+        new_etag: Uuid,
+    ) -> Result<(), RegistryError> {
+        self.update_entity_tags(id, tags, expected_etag, new_etag)
+            .await?;
+        self.flush_external_storage().await
+    }
+
+    async fn update_project_tags(
+        &mut self,
+        project_id: Uuid,
+        tags: HashMap<String, String>,
+        new_etag: Uuid,
+    ) -> Result<usize, RegistryError> {
+        Registry::update_project_tags(self, project_id, tags, new_etag).await
+    }
+
+    async fn update_links(
+        &mut self,
+        id: Uuid,
+        links: Vec<EntityLink>,
+    ) -> Result<(), RegistryError> {
+        self.update_entity_links(id, links).await?;
+        self.flush_external_storage().await
+    }
+
+    async fn update_derived_inputs(
+        &mut self,
+        feature_id: Uuid,
+        add: Vec<Uuid>,
+        remove: Vec<Uuid>,
+    ) -> Result<(), RegistryError> {
+        Registry::update_derived_inputs(self, feature_id, add, remove).await?;
+        self.flush_external_storage().await
+    }
+
+    async fn move_anchor_feature(
+        &mut self,
+        feature_id: Uuid,
+        new_anchor_id: Uuid,
+    ) -> Result<(), RegistryError> {
+        Registry::move_anchor_feature(self, feature_id, new_anchor_id).await?;
+        self.flush_external_storage().await
+    }
+
+    async fn clone_project(
+        &mut self,
+        src_id: Uuid,
+        new_name: &str,
+        id_seed: Uuid,
+    ) -> Result<(Uuid, u64), RegistryError> {
+        let id = Registry::clone_project(self, src_id, new_name, id_seed).await?;
+        self.flush_external_storage().await?;
+        Ok((id, self.get_entity(id)?.version))
+    }
+
+    fn topo_order_features(&self, project_id: Uuid) -> Result<Vec<Uuid>, RegistryError> {
+        Registry::topo_order_features(self, project_id)
+    }
+
+    fn get_features_by_key(&self, key: &str) -> Vec<Entity<EntityProp>> {
+        Registry::get_features_by_key(self, key)
+    }
+
+    fn list_deleted(&self) -> Vec<Entity<EntityProp>> {
+        Registry::list_deleted(self)
+    }
+
+    fn list_entities_by_type(
+        &self,
+        entity_type: EntityType,
+        after: Option<Uuid>,
+        size: usize,
+    ) -> (Vec<Entity<EntityProp>>, Option<Uuid>) {
+        Registry::list_entities_by_type(self, entity_type, after, size)
+    }
+
     fn get_all_versions(&self, qualified_name: &str) -> Vec<Entity<EntityProp>> {
         let (qualified_name, _version) = extract_version(qualified_name);
         match self.name_id_map.get(qualified_name) {
@@ -492,8 +727,8 @@ where
                 Resource::NamedEntity(name) => match name.parse::<Uuid>() {
                     Ok(id) => Resource::Entity(id),
                     Err(_) => Resource::Entity(match self.get_entity_by_name(name, None) {
-                        Some(e) => e.id,
-                        None => {
+                        Ok(e) => e.id,
+                        Err(_) => {
                             warn!("Entity {} not found, skipped", name);
                             continue;
                         }
@@ -561,6 +796,7 @@ where
 
         // Update local data structure
         self.permission_map.grant_permission(&grant);
+        self.changelog.record(Change::Permissions);
         Ok(())
     }
 
@@ -594,6 +830,7 @@ where
 
         // Update local data structure
         self.permission_map.revoke_permission(&revoke);
+        self.changelog.record(Change::Permissions);
         Ok(())
     }
 }
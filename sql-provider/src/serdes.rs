@@ -1,12 +1,16 @@
-use registry_provider::{ToDocString, SerializableRegistry, EntityPropMutator};
+use petgraph::graph::{Graph, NodeIndex};
+use registry_provider::{
+    Edge, Entity, EntityPropMutator, EntityType, RbacRecord, SerializableRegistry, ToDocString,
+};
 use serde::{
-    de::{self, MapAccess, SeqAccess, Visitor},
+    de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor},
     ser::SerializeStruct,
     Deserialize, Serialize,
 };
 use std::{fmt::Debug, marker::PhantomData};
+use uuid::Uuid;
 
-use crate::Registry;
+use crate::{Registry, RegistryDelta};
 
 impl<EntityProp> Serialize for Registry<EntityProp>
 where
@@ -159,4 +163,183 @@ EntityProp: Clone
         *self = serde_json::from_slice::<'de, Self>(data).unwrap();
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Entity in the bincode snapshot
+ * Properties use untagged enums and skipped fields which bincode cannot decode, so they're kept as JSON
+ */
+#[derive(Serialize, Deserialize)]
+struct BinaryEntity {
+    id: Uuid,
+    entity_type: EntityType,
+    name: String,
+    qualified_name: String,
+    version: u64,
+    properties: Vec<u8>,
+}
+
+impl BinaryEntity {
+    fn new<EntityProp>(e: &Entity<EntityProp>) -> Result<Self, bincode::Error>
+    where
+        EntityProp: Clone + Debug + PartialEq + Eq + Serialize,
+    {
+        Ok(Self {
+            id: e.id,
+            entity_type: e.entity_type,
+            name: e.name.to_owned(),
+            qualified_name: e.qualified_name.to_owned(),
+            version: e.version,
+            properties: serde_json::to_vec(&e.properties).map_err(json_error)?,
+        })
+    }
+
+    fn into_entity<EntityProp>(self) -> Result<Entity<EntityProp>, bincode::Error>
+    where
+        EntityProp: Clone + Debug + PartialEq + Eq + DeserializeOwned,
+    {
+        Ok(Entity {
+            id: self.id,
+            entity_type: self.entity_type,
+            name: self.name,
+            qualified_name: self.qualified_name,
+            version: self.version,
+            properties: serde_json::from_slice(&self.properties).map_err(json_error)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryRegistry {
+    // In the order of node indices
+    entities: Vec<BinaryEntity>,
+    // Node indices of the endpoints and the edge
+    edges: Vec<(u32, u32, Edge)>,
+    deleted: Vec<Uuid>,
+    permissions: Vec<u8>,
+}
+
+fn json_error(e: serde_json::Error) -> bincode::Error {
+    Box::new(bincode::ErrorKind::Custom(e.to_string()))
+}
+
+impl<EntityProp> Registry<EntityProp>
+where
+    EntityProp: Clone
+        + Debug
+        + PartialEq
+        + Eq
+        + EntityPropMutator
+        + ToDocString
+        + Send
+        + Sync
+        + Serialize
+        + DeserializeOwned,
+{
+    /**
+     * Serialize the registry in the compact bincode format, it contains the same content as the JSON format
+     */
+    pub fn to_bincode(&self) -> Result<Vec<u8>, bincode::Error> {
+        let entities = self
+            .graph
+            .node_weights()
+            .map(BinaryEntity::new)
+            .collect::<Result<_, bincode::Error>>()?;
+        let edges = self
+            .graph
+            .raw_edges()
+            .iter()
+            .map(|e| {
+                (
+                    e.source().index() as u32,
+                    e.target().index() as u32,
+                    e.weight.to_owned(),
+                )
+            })
+            .collect();
+        let permissions = serde_json::to_vec(&self.permission_map.iter().collect::<Vec<_>>())
+            .map_err(json_error)?;
+        bincode::serialize(&BinaryRegistry {
+            entities,
+            edges,
+            deleted: self.deleted.iter().copied().collect(),
+            permissions,
+        })
+    }
+
+    /**
+     * Deserialize the registry from the output of `to_bincode`
+     */
+    pub fn from_bincode(data: &[u8]) -> Result<Self, bincode::Error> {
+        let content: BinaryRegistry = bincode::deserialize(data)?;
+        let mut graph = Graph::with_capacity(content.entities.len(), content.edges.len());
+        for e in content.entities {
+            graph.add_node(e.into_entity()?);
+        }
+        for (from, to, edge) in content.edges {
+            if from as usize >= graph.node_count() || to as usize >= graph.node_count() {
+                return Err(Box::new(bincode::ErrorKind::Custom(format!(
+                    "Edge {}->{} refers to a missing node",
+                    from, to
+                ))));
+            }
+            graph.add_edge(NodeIndex::new(from as usize), NodeIndex::new(to as usize), edge);
+        }
+        let permissions: Vec<RbacRecord> =
+            serde_json::from_slice(&content.permissions).map_err(json_error)?;
+        Ok(Self::from_content(
+            graph,
+            content.deleted.into_iter().collect(),
+            permissions,
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryDelta {
+    since: u64,
+    entities: Vec<BinaryEntity>,
+    edges: Vec<Edge>,
+    removed_edges: Vec<Edge>,
+    deleted: Vec<Uuid>,
+}
+
+impl<EntityProp> RegistryDelta<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + Serialize + DeserializeOwned,
+{
+    /**
+     * Serialize the delta in the same compact bincode format as the registry
+     */
+    pub fn to_bincode(&self) -> Result<Vec<u8>, bincode::Error> {
+        bincode::serialize(&BinaryDelta {
+            since: self.since,
+            entities: self
+                .entities
+                .iter()
+                .map(BinaryEntity::new)
+                .collect::<Result<_, bincode::Error>>()?,
+            edges: self.edges.to_owned(),
+            removed_edges: self.removed_edges.to_owned(),
+            deleted: self.deleted.to_owned(),
+        })
+    }
+
+    /**
+     * Deserialize the delta from the output of `to_bincode`
+     */
+    pub fn from_bincode(data: &[u8]) -> Result<Self, bincode::Error> {
+        let content: BinaryDelta = bincode::deserialize(data)?;
+        Ok(Self {
+            since: content.since,
+            entities: content
+                .entities
+                .into_iter()
+                .map(BinaryEntity::into_entity)
+                .collect::<Result<_, bincode::Error>>()?,
+            edges: content.edges,
+            removed_edges: content.removed_edges,
+            deleted: content.deleted,
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use registry_provider::{EdgeType, EntityPropMutator, EntityType, ToDocString};
+use serde::{Deserialize, Serialize};
+
+use crate::Registry;
+
+/**
+ * Counts of what's in the registry
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryStats {
+    /// Number of live entities of each type, deleted entities are not included
+    pub entities: HashMap<EntityType, usize>,
+    /// Number of edges of each type between live entities
+    pub edges: HashMap<EdgeType, usize>,
+    pub projects: usize,
+    pub deleted: usize,
+    pub fts_docs: u64,
+}
+
+impl<EntityProp> Registry<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync,
+{
+    pub fn get_stats(&self) -> RegistryStats {
+        let mut stats = RegistryStats {
+            deleted: self.deleted.len(),
+            fts_docs: self.fts_index.num_docs(),
+            ..Default::default()
+        };
+        for e in self
+            .graph
+            .node_weights()
+            .filter(|e| !self.deleted.contains(&e.id))
+        {
+            *stats.entities.entry(e.entity_type).or_default() += 1;
+            if e.entity_type == EntityType::Project {
+                stats.projects += 1;
+            }
+        }
+        for e in self
+            .graph
+            .edge_weights()
+            .filter(|e| !self.deleted.contains(&e.from) && !self.deleted.contains(&e.to))
+        {
+            *stats.edges.entry(e.edge_type).or_default() += 1;
+        }
+        stats
+    }
+}